@@ -2,10 +2,15 @@
 //!
 //! This module implements a background service that:
 //! - Periodically scans the network
-//! - Uploads results to Cartographer Cloud
+//! - Uploads results to Cartographer Cloud, queueing them on disk while it is unreachable
+//! - Keeps the device inventory (first/last seen, history) up to date
+//! - Sends local alerts for device changes (`[notifications]`)
 //! - Handles graceful shutdown via SIGTERM/SIGINT
 
 use anyhow::Result;
+use cartographer_core::inventory::Inventory;
+use cartographer_core::cloud::{Delivery, Outbox};
+use cartographer_core::notifications::{self, Notifier};
 use cartographer_core::{auth, cloud, scanner};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
@@ -46,9 +51,28 @@ pub async fn run_daemon(interval_minutes: u64, foreground: bool) -> Result<()> {
     // Create cloud client (reuse for all requests)
     let client = cloud::CloudClient::new();
 
+    // Hear DHCP requests between scans; each scan attaches the fingerprints
+    scanner::start_dhcp_listener();
+
+    // Devices seen across scans and restarts
+    let mut inventory = Inventory::open_or_in_memory();
+    tracing::info!("Device inventory: {} known devices", inventory.len());
+
+    // Uploads that fail wait here and are replayed in order
+    let mut outbox = Outbox::open(cloud::load_outbox_config());
+    if !outbox.is_empty() {
+        tracing::info!("Upload queue: {} uploads waiting", outbox.len());
+    }
+
+    // Alerts go out locally, whether or not the upload succeeds
+    let notifier = notifications::load_notifier_or_disabled();
+    if notifier.is_active() {
+        tracing::info!("Alerts: sending to {}", notifier.sink_names().join(", "));
+    }
+
     // Run initial scan immediately
     tracing::info!("Running initial scan...");
-    if let Err(e) = run_scan_and_upload(&client).await {
+    if let Err(e) = run_scan_and_upload(&client, &mut inventory, &notifier, &mut outbox).await {
         tracing::error!("Initial scan failed: {}", e);
     }
 
@@ -57,6 +81,9 @@ pub async fn run_daemon(interval_minutes: u64, foreground: bool) -> Result<()> {
     // Skip the first tick since we just ran
     scan_interval.tick().await;
 
+    // Replay queued uploads between scans (the outbox backs off after failures)
+    let mut retry_interval = interval(Duration::from_secs(60));
+
     // Main daemon loop
     loop {
         tokio::select! {
@@ -70,7 +97,7 @@ pub async fn run_daemon(interval_minutes: u64, foreground: bool) -> Result<()> {
                 // Check if still authenticated
                 match auth::check_auth().await {
                     Ok(status) if status.authenticated => {
-                        if let Err(e) = run_scan_and_upload(&client).await {
+                        if let Err(e) = run_scan_and_upload(&client, &mut inventory, &notifier, &mut outbox).await {
                             tracing::error!("Scan failed: {}", e);
                         }
                     }
@@ -85,6 +112,11 @@ pub async fn run_daemon(interval_minutes: u64, foreground: bool) -> Result<()> {
                     }
                 }
             }
+            _ = retry_interval.tick() => {
+                if !outbox.is_empty() && outbox.is_due() {
+                    outbox.replay(&client).await;
+                }
+            }
             _ = tokio::signal::ctrl_c() => {
                 tracing::info!("Received Ctrl+C, shutting down");
                 break;
@@ -92,6 +124,7 @@ pub async fn run_daemon(interval_minutes: u64, foreground: bool) -> Result<()> {
         }
     }
 
+    scanner::stop_dhcp_listener();
     tracing::info!("Daemon stopped");
     Ok(())
 }
@@ -128,8 +161,13 @@ fn setup_signal_handlers(shutdown: Arc<AtomicBool>) {
     }
 }
 
-/// Run a network scan and upload results to cloud
-async fn run_scan_and_upload(client: &cloud::CloudClient) -> Result<()> {
+/// Run a network scan, record it in the inventory, alert on changes and upload results to cloud
+async fn run_scan_and_upload(
+    client: &cloud::CloudClient,
+    inventory: &mut Inventory,
+    notifier: &Notifier,
+    outbox: &mut Outbox,
+) -> Result<()> {
     let start = std::time::Instant::now();
 
     tracing::info!("Starting network scan...");
@@ -144,10 +182,30 @@ async fn run_scan_and_upload(client: &cloud::CloudClient) -> Result<()> {
         scan_duration.as_secs_f64()
     );
 
-    // Upload to cloud
+    let randomized_macs = scanner::load_validated_scan_config()?.randomized_macs;
+    let changes = inventory.merge(
+        scan_result.devices.clone(),
+        &scan_result.network_info,
+        &randomized_macs,
+        chrono::Utc::now(),
+    );
+    for change in &changes {
+        tracing::info!("Change: {}", change);
+    }
+    if let Err(e) = inventory.save() {
+        tracing::warn!("Failed to save device inventory: {:#}", e);
+    }
+    notifier.notify(&changes).await;
+
+    // Upload to cloud, or queue until it is reachable
     tracing::debug!("Uploading results to cloud...");
-    client.upload_scan_result(&scan_result).await?;
-    tracing::info!("Results synced to cloud");
+    let upload = cloud::CloudClient::scan_upload(&scan_result)?;
+    match outbox.send(client, upload).await? {
+        Delivery::Sent => tracing::info!("Results synced to cloud"),
+        Delivery::Queued(depth) => {
+            tracing::warn!("Cloud unreachable, results queued ({} uploads waiting)", depth)
+        }
+    }
 
     Ok(())
 }
@@ -9,7 +9,8 @@
 mod daemon;
 
 use anyhow::Result;
-use cartographer_core::{auth, cloud, scanner};
+use cartographer_core::inventory::{self, Inventory};
+use cartographer_core::{auth, cloud, notifications, scanner};
 use clap::{Parser, Subcommand, ValueEnum};
 
 #[derive(Parser)]
@@ -83,6 +84,23 @@ pub enum Commands {
 
     /// Show configuration paths and settings
     Config,
+
+    /// Show the device inventory kept by the daemon
+    Devices {
+        /// Also list the addresses, MACs and hostnames each device has used
+        #[arg(long)]
+        history: bool,
+    },
+
+    /// Show device changes seen by the daemon (new, offline, back, IP/MAC/hostname)
+    Changes {
+        /// How far back: a duration (30m, 24h, 7d), a date (2026-03-01) or an RFC 3339 time
+        #[arg(long, default_value = "24h")]
+        since: String,
+    },
+
+    /// Send a test alert to every sink in the [notifications] config
+    TestAlerts,
 }
 
 #[tokio::main]
@@ -108,6 +126,9 @@ async fn main() -> Result<()> {
             daemon::run_daemon(interval, foreground).await
         }
         Commands::Config => cmd_config(&cli).await,
+        Commands::Devices { history } => cmd_devices(&cli, history),
+        Commands::Changes { ref since } => cmd_changes(&cli, since),
+        Commands::TestAlerts => cmd_test_alerts(&cli).await,
     }
 }
 
@@ -206,36 +227,125 @@ async fn cmd_scan(cli: &Cli, upload: bool) -> Result<()> {
 
     let scan_result = scanner::scan_network_with_progress(progress_callback).await?;
 
+    let expiring = expiring_certificates(&scan_result.devices);
+
     match cli.format {
         OutputFormat::Text => {
             println!();
             println!("Found {} devices:", scan_result.devices.len());
-            println!();
-            for device in &scan_result.devices {
-                let hostname = device.hostname.as_deref().unwrap_or("-");
-                let vendor = device.vendor.as_deref().unwrap_or("");
-                let time_str = device
-                    .response_time_ms
-                    .map(|t| format!("{:.1}ms", t))
-                    .unwrap_or_else(|| "-".to_string());
-
-                if vendor.is_empty() {
-                    println!("  {:15} {:>8}  {}", device.ip, time_str, hostname);
-                } else {
-                    println!("  {:15} {:>8}  {} ({})", device.ip, time_str, hostname, vendor);
+            for target in &scan_result.network_info.targets {
+                println!();
+                println!("{} on {}:", target.subnet, target.interface);
+                for device in scan_result
+                    .devices
+                    .iter()
+                    .filter(|d| d.subnet.as_deref() == Some(target.subnet.as_str()))
+                {
+                    let hostname = match (&device.hostname, device.hostname_source) {
+                        (Some(name), Some(source)) => format!("{} [{}]", name, source),
+                        (Some(name), None) => name.clone(),
+                        (None, _) => "-".to_string(),
+                    };
+                    let vendor = match device.vendor.as_deref() {
+                        Some(vendor) => vendor,
+                        None if device.randomized_mac => "randomized MAC",
+                        None => "",
+                    };
+                    let time_str = device
+                        .response_time_ms
+                        .map(|t| format!("{:.1}ms", t))
+                        .unwrap_or_else(|| "-".to_string());
+
+                    let ip = device.primary_ip();
+                    if vendor.is_empty() {
+                        println!("  {:15} {:>8}  {}", ip, time_str, hostname);
+                    } else {
+                        println!("  {:15} {:>8}  {} ({})", ip, time_str, hostname, vendor);
+                    }
+                    for address in device.addresses.iter().filter(|a| a.as_str() != ip) {
+                        println!("  {:15} {:>8}  {}", "", "", address);
+                    }
+                    if let Some(device_type) = &device.device_type {
+                        println!(
+                            "  {:15} {:>8}  type: {}",
+                            "",
+                            "",
+                            describe_type(device_type, device)
+                        );
+                    }
+                    if !device.open_ports.is_empty() {
+                        let ports: Vec<String> =
+                            device.open_ports.iter().map(|p| p.to_string()).collect();
+                        println!("  {:15} {:>8}  ports: {}", "", "", ports.join(", "));
+                    }
+                    if let Some(model) = &device.model {
+                        println!("  {:15} {:>8}  model: {}", "", "", model);
+                    }
+                    if let (Some(state), Some(interface)) =
+                        (&device.neighbor_state, &device.interface)
+                    {
+                        println!("  {:15} {:>8}  neighbor: {} on {}", "", "", state, interface);
+                    }
+                    if let Some(os) = &device.os_guess {
+                        println!(
+                            "  {:15} {:>8}  os: {} from {}",
+                            "",
+                            "",
+                            os,
+                            os.evidence.join(", ")
+                        );
+                    }
+                    if !device.advertised_services.is_empty() {
+                        let advertised: Vec<String> = device
+                            .advertised_services
+                            .iter()
+                            .map(|s| format!("{} ({}:{})", s.name, s.service_type, s.port))
+                            .collect();
+                        println!("  {:15} {:>8}  mdns: {}", "", "", advertised.join(", "));
+                    }
+                    if let Some(upnp) = &device.upnp {
+                        println!("  {:15} {:>8}  upnp: {}", "", "", describe_upnp(upnp));
+                    }
+                    if let Some(snmp) = &device.snmp {
+                        println!("  {:15} {:>8}  snmp: {}", "", "", describe_snmp(snmp));
+                    }
+                    if let Some(dhcp) = &device.dhcp {
+                        println!("  {:15} {:>8}  dhcp: {}", "", "", describe_dhcp(dhcp));
+                    }
+                    for service in &device.services {
+                        println!("  {:15} {:>8}  {}", "", "", describe_service(service));
+                    }
                 }
             }
+
+            if !scan_result.topology.is_empty() {
+                println!();
+                println!("Switch ports (LLDP/CDP):");
+                for edge in &scan_result.topology {
+                    println!("  {}", describe_edge(edge));
+                }
+            }
+
+            if !expiring.is_empty() {
+                println!();
+                println!("Certificates expiring within {} days:", CERT_EXPIRY_WARNING_DAYS);
+                for cert in &expiring {
+                    println!("  {}:{}  {}", cert.ip, cert.port, cert.describe());
+                }
+            }
+
+            if let Some(hint) = scanner::privileges::arp_sweep_instructions(&scan_result.capabilities) {
+                println!();
+                println!("{}", hint);
+            }
         }
         OutputFormat::Json => {
             if !upload {
                 println!("{}", serde_json::json!({
                     "devices": scan_result.devices,
-                    "network_info": {
-                        "interface": scan_result.network_info.interface,
-                        "subnet": scan_result.network_info.subnet,
-                        "gateway_ip": scan_result.network_info.gateway_ip,
-                        "local_ip": scan_result.network_info.local_ip,
-                    },
+                    "network_info": scan_result.network_info,
+                    "topology": scan_result.topology,
+                    "expiring_certificates": expiring,
                     "uploaded": false,
                 }));
             }
@@ -263,12 +373,9 @@ async fn cmd_scan(cli: &Cli, upload: bool) -> Result<()> {
                             OutputFormat::Json => {
                                 println!("{}", serde_json::json!({
                                     "devices": scan_result.devices,
-                                    "network_info": {
-                                        "interface": scan_result.network_info.interface,
-                                        "subnet": scan_result.network_info.subnet,
-                                        "gateway_ip": scan_result.network_info.gateway_ip,
-                                        "local_ip": scan_result.network_info.local_ip,
-                                    },
+                                    "network_info": scan_result.network_info,
+                                    "topology": scan_result.topology,
+                                    "expiring_certificates": expiring,
                                     "uploaded": true,
                                     "network_name": status.network_name,
                                 }));
@@ -310,8 +417,187 @@ async fn cmd_scan(cli: &Cli, upload: bool) -> Result<()> {
     Ok(())
 }
 
+/// Certificates closer than this to expiry are flagged in scan output
+const CERT_EXPIRY_WARNING_DAYS: i64 = 30;
+
+/// A TLS certificate that is expired or expires soon
+#[derive(serde::Serialize)]
+struct ExpiringCertificate {
+    ip: String,
+    port: u16,
+    subject: String,
+    not_after: Option<chrono::DateTime<chrono::Utc>>,
+    days_left: i64,
+}
+
+impl ExpiringCertificate {
+    fn describe(&self) -> String {
+        let when = if self.days_left < 0 {
+            format!("expired {} days ago", -self.days_left)
+        } else {
+            format!("expires in {} days", self.days_left)
+        };
+        format!("{} ({})", self.subject, when)
+    }
+}
+
+fn expiring_certificates(devices: &[scanner::Device]) -> Vec<ExpiringCertificate> {
+    let now = chrono::Utc::now();
+    devices
+        .iter()
+        .flat_map(|d| d.services.iter().map(move |s| (d, s)))
+        .filter_map(|(device, service)| {
+            let cert = service.tls_certificate.as_ref()?;
+            if !cert.expires_within(CERT_EXPIRY_WARNING_DAYS, now) {
+                return None;
+            }
+            Some(ExpiringCertificate {
+                ip: device.primary_ip().to_string(),
+                port: service.port,
+                subject: cert.subject.clone(),
+                not_after: cert.not_after,
+                days_left: cert.days_until_expiry(now)?,
+            })
+        })
+        .collect()
+}
+
+/// One-line summary of a service: banner, web server and title, certificate
+fn describe_service(service: &scanner::ServiceInfo) -> String {
+    let mut parts = vec![format!("{}:", service.port)];
+    if let Some(banner) = &service.banner {
+        parts.push(banner.clone());
+    }
+    if let Some(server) = &service.http_server {
+        parts.push(server.clone());
+    }
+    if let Some(title) = &service.http_title {
+        parts.push(format!("\"{}\"", title));
+    }
+    if let Some(cert) = &service.tls_certificate {
+        let expiry = cert
+            .not_after
+            .map(|t| format!(", expires {}", t.format("%Y-%m-%d")))
+            .unwrap_or_default();
+        let flag = if cert.expires_within(CERT_EXPIRY_WARNING_DAYS, chrono::Utc::now()) {
+            " [EXPIRING]"
+        } else {
+            ""
+        };
+        parts.push(format!("cert {}{}{}", cert.subject, expiry, flag));
+    }
+    parts.join(" ")
+}
+
+/// "eth0 -> core-sw1 port Gi1/0/12 (mgmt 10.0.0.2) [lldp]"
+fn describe_edge(edge: &scanner::TopologyEdge) -> String {
+    let neighbor = edge.system_name.as_deref().unwrap_or(&edge.chassis_id);
+    let mut text = format!(
+        "{} -> {} port {}",
+        edge.local_interface, neighbor, edge.port_id
+    );
+    if let Some(description) = &edge.port_description
+        && description != &edge.port_id
+    {
+        text.push_str(&format!(" \"{}\"", description));
+    }
+    if let Some(address) = &edge.management_address {
+        text.push_str(&format!(" (mgmt {})", address));
+    }
+    format!("{} [{}]", text, edge.protocol)
+}
+
+/// "macOS, vendor class MSFT 5.0, options 1,121,3,6,15,..."
+fn describe_type(device_type: &str, device: &scanner::Device) -> String {
+    let mut text = device_type.to_string();
+    if !device.tags.is_empty() {
+        text.push_str(&format!(" [{}]", device.tags.join(", ")));
+    }
+    if let Some(classification) = &device.classification {
+        text.push_str(&format!(" by {}", classification));
+    }
+    text
+}
+
+fn describe_dhcp(dhcp: &scanner::DhcpFingerprint) -> String {
+    let mut details = vec![dhcp
+        .os_family
+        .map(|f| f.to_string())
+        .unwrap_or_else(|| "unknown OS".to_string())];
+    if let Some(vendor_class) = &dhcp.vendor_class {
+        details.push(format!("vendor class {}", vendor_class));
+    }
+    if let Some(client_id) = &dhcp.client_id {
+        details.push(format!("client id {}", client_id));
+    }
+    if !dhcp.parameter_list.is_empty() {
+        details.push(format!("options {}", dhcp.parameter_list_string()));
+    }
+    details.join(", ")
+}
+
+/// "sysName - sysDescr (location, up 3d 4h, 24 interfaces) [v2c]"
+fn describe_snmp(snmp: &scanner::SnmpInfo) -> String {
+    let mut text = snmp.sys_name.clone().unwrap_or_else(|| "unnamed".to_string());
+    if let Some(descr) = &snmp.sys_descr {
+        // sysDescr is often several lines; the first says enough
+        let first_line = descr.lines().next().unwrap_or_default();
+        text.push_str(&format!(" - {}", first_line));
+    }
+
+    let mut details: Vec<String> = snmp.sys_location.iter().cloned().collect();
+    if let Some(uptime) = snmp.uptime_seconds {
+        let days = uptime / 86_400;
+        let hours = uptime % 86_400 / 3600;
+        details.push(if days > 0 {
+            format!("up {}d {}h", days, hours)
+        } else {
+            format!("up {}h {}m", hours, uptime % 3600 / 60)
+        });
+    }
+    if !snmp.interfaces.is_empty() {
+        let up = snmp
+            .interfaces
+            .iter()
+            .filter(|i| i.oper_status.as_deref() == Some("up"))
+            .count();
+        details.push(format!("{} interfaces, {} up", snmp.interfaces.len(), up));
+    }
+    if !details.is_empty() {
+        text.push_str(&format!(" ({})", details.join(", ")));
+    }
+    format!("{} [{}]", text, snmp.version)
+}
+
+/// "Friendly Name (Manufacturer Model, serial X)" from a UPnP description
+fn describe_upnp(upnp: &scanner::UpnpDevice) -> String {
+    let mut details: Vec<String> = upnp.manufacturer.iter().cloned().collect();
+    if let Some(model) = upnp.model() {
+        details.push(model);
+    }
+    let mut details = details.join(" ");
+    if let Some(serial) = &upnp.serial_number {
+        if !details.is_empty() {
+            details.push_str(", ");
+        }
+        details.push_str(&format!("serial {}", serial));
+    }
+
+    let name = upnp
+        .friendly_name
+        .clone()
+        .or_else(|| upnp.server.clone())
+        .unwrap_or_else(|| "unnamed".to_string());
+    if details.is_empty() {
+        name
+    } else {
+        format!("{} ({})", name, details)
+    }
+}
+
 async fn cmd_status(cli: &Cli) -> Result<()> {
     let auth_status = auth::check_auth().await?;
+    let queue = cloud::Outbox::open(cloud::load_outbox_config()).stats();
 
     match cli.format {
         OutputFormat::Text => {
@@ -324,6 +610,7 @@ async fn cmd_status(cli: &Cli) -> Result<()> {
                 );
                 println!();
                 println!("Storage: {}", auth::get_credential_storage_info());
+                println!("Upload queue: {}", describe_queue(&queue));
             } else {
                 println!("Status: Not connected");
                 println!();
@@ -337,6 +624,7 @@ async fn cmd_status(cli: &Cli) -> Result<()> {
                 "network_id": auth_status.network_id,
                 "network_name": auth_status.network_name,
                 "storage_info": auth::get_credential_storage_info(),
+                "upload_queue": queue,
             }));
         }
     }
@@ -344,6 +632,18 @@ async fn cmd_status(cli: &Cli) -> Result<()> {
     Ok(())
 }
 
+fn describe_queue(queue: &cloud::OutboxStats) -> String {
+    match queue.oldest {
+        Some(oldest) if queue.pending > 0 => format!(
+            "{} uploads waiting ({:.1} MB), oldest from {}",
+            queue.pending,
+            queue.bytes as f64 / (1024.0 * 1024.0),
+            oldest.format("%Y-%m-%d %H:%M UTC")
+        ),
+        _ => "empty".to_string(),
+    }
+}
+
 async fn cmd_disconnect(cli: &Cli) -> Result<()> {
     // Check if connected
     let auth_status = auth::check_auth().await?;
@@ -362,6 +662,11 @@ async fn cmd_disconnect(cli: &Cli) -> Result<()> {
 
     auth::delete_credentials().await?;
 
+    // Queued uploads belong to the network we just left
+    if let Err(e) = cloud::Outbox::open(cloud::load_outbox_config()).clear() {
+        tracing::warn!("Failed to clear upload queue: {:#}", e);
+    }
+
     match cli.format {
         OutputFormat::Text => {
             println!("Disconnected from '{}'",
@@ -379,9 +684,150 @@ async fn cmd_disconnect(cli: &Cli) -> Result<()> {
     Ok(())
 }
 
+fn cmd_devices(cli: &Cli, history: bool) -> Result<()> {
+    let inventory = Inventory::open()?;
+
+    match cli.format {
+        OutputFormat::Text => {
+            if inventory.is_empty() {
+                println!("No devices yet. 'cartographer daemon' records every device it finds.");
+                return Ok(());
+            }
+            let online = inventory.records().iter().filter(|r| r.online).count();
+            println!(
+                "{} devices ({} online, {} offline)",
+                inventory.len(),
+                online,
+                inventory.len() - online
+            );
+            println!();
+            for record in inventory.records() {
+                let device = &record.device;
+                let name = device.hostname.as_deref().unwrap_or("-");
+                let state = if record.online { "online" } else { "offline" };
+                match device.vendor.as_deref() {
+                    Some(vendor) => println!(
+                        "  {:15} {:>8}  {} ({})",
+                        device.primary_ip(),
+                        state,
+                        name,
+                        vendor
+                    ),
+                    None => println!("  {:15} {:>8}  {}", device.primary_ip(), state, name),
+                }
+                println!(
+                    "  {:15} {:>8}  first seen {}, last seen {}",
+                    "",
+                    "",
+                    record.first_seen.format("%Y-%m-%d %H:%M"),
+                    record.last_seen.format("%Y-%m-%d %H:%M")
+                );
+                if history {
+                    for (label, sightings) in [
+                        ("ip", &record.addresses),
+                        ("mac", &record.macs),
+                        ("hostname", &record.hostnames),
+                    ] {
+                        for sighting in sightings {
+                            println!(
+                                "  {:15} {:>8}  {}: {} ({} to {})",
+                                "",
+                                "",
+                                label,
+                                sighting.value,
+                                sighting.first_seen.format("%Y-%m-%d %H:%M"),
+                                sighting.last_seen.format("%Y-%m-%d %H:%M")
+                            );
+                        }
+                    }
+                }
+            }
+        }
+        OutputFormat::Json => {
+            println!("{}", serde_json::json!({
+                "inventory_file": inventory.path(),
+                "devices": inventory.records(),
+            }));
+        }
+    }
+
+    Ok(())
+}
+
+fn cmd_changes(cli: &Cli, since: &str) -> Result<()> {
+    let since = inventory::changes::parse_since(since, chrono::Utc::now())?;
+    let changes = Inventory::open()?.changes_since(since)?;
+
+    match cli.format {
+        OutputFormat::Text => {
+            if changes.is_empty() {
+                println!("No device changes since {}.", since.format("%Y-%m-%d %H:%M UTC"));
+                return Ok(());
+            }
+            for change in &changes {
+                println!("{}  {}", change.at.format("%Y-%m-%d %H:%M:%S"), change);
+            }
+        }
+        OutputFormat::Json => {
+            println!("{}", serde_json::json!({
+                "since": since,
+                "changes": changes,
+            }));
+        }
+    }
+
+    Ok(())
+}
+
+async fn cmd_test_alerts(cli: &Cli) -> Result<()> {
+    let notifier = notifications::load_notifier()?;
+    let results = notifier.test().await;
+    let failed = results.iter().filter(|(_, result)| result.is_err()).count();
+
+    match cli.format {
+        OutputFormat::Text => {
+            if results.is_empty() {
+                println!(
+                    "No alert sinks configured. Add [[notifications.sinks]] to {}",
+                    cloud::config::get_config_file_path_string()
+                );
+                return Ok(());
+            }
+            for (sink, result) in &results {
+                match result {
+                    Ok(()) => println!("  ok      {}", sink),
+                    Err(e) => println!("  failed  {}: {:#}", sink, e),
+                }
+            }
+        }
+        OutputFormat::Json => {
+            let results: Vec<_> = results
+                .iter()
+                .map(|(sink, result)| serde_json::json!({
+                    "sink": sink,
+                    "ok": result.is_ok(),
+                    "error": result.as_ref().err().map(|e| format!("{:#}", e)),
+                }))
+                .collect();
+            println!("{}", serde_json::json!({ "results": results }));
+        }
+    }
+
+    if failed > 0 {
+        anyhow::bail!("{} of {} sinks failed", failed, results.len());
+    }
+    Ok(())
+}
+
 async fn cmd_config(cli: &Cli) -> Result<()> {
     let cloud_config = cloud::load_cloud_config();
     let config_path = cloud::config::get_config_file_path_string();
+    // Defaults while the [scan] section is invalid; the problems are listed below
+    let scan_config = scanner::load_validated_scan_config().unwrap_or_default();
+    let issues = scanner::config::scan_config_issues()?;
+    let rules = scanner::classify::load_rules(&scan_config.classify);
+    let vendors = scanner::oui::load_database(&scan_config.oui);
+    let notifier = notifications::load_notifier();
 
     match cli.format {
         OutputFormat::Text => {
@@ -393,6 +839,18 @@ async fn cmd_config(cli: &Cli) -> Result<()> {
             println!("Dashboard URL:    {}", cloud_config.dashboard_url);
             println!("Credential store: {}", auth::get_credential_storage_info());
             println!();
+            print_scan_config(&scan_config);
+            print_rules(&scan_config.classify, &rules);
+            print_vendors(&scan_config.oui, &vendors);
+            print_alerts(&notifier);
+            if !issues.is_empty() {
+                println!();
+                println!("Config problems (scans will fail until fixed):");
+                for issue in &issues {
+                    println!("  {}", issue);
+                }
+            }
+            println!();
             println!("Environment variables:");
             println!("  CARTOGRAPHER_CLOUD_URL - Override API endpoint");
             println!();
@@ -407,9 +865,207 @@ async fn cmd_config(cli: &Cli) -> Result<()> {
                 "api_source": format!("{}", cloud_config.source),
                 "dashboard_url": cloud_config.dashboard_url,
                 "credential_storage": auth::get_credential_storage_info(),
+                "scan": scan_config,
+                "issues": issues,
+                "classification_rules": match &rules {
+                    Ok(rules) => serde_json::json!({
+                        "builtin": rules.builtin_count(),
+                        "user": rules.user_count(),
+                        "user_file": rules.user_file(),
+                    }),
+                    Err(e) => serde_json::json!({
+                        "builtin": scanner::Ruleset::builtin().builtin_count(),
+                        "error": format!("{:#}", e),
+                    }),
+                },
+                "oui_database": match &vendors {
+                    Ok(vendors) => serde_json::json!({
+                        "builtin": scanner::oui::builtin_source(),
+                        "registry": vendors.registry_source(),
+                        "custom": vendors.custom_count(),
+                    }),
+                    Err(e) => serde_json::json!({
+                        "builtin": scanner::oui::builtin_source(),
+                        "error": format!("{:#}", e),
+                    }),
+                },
+                "notifications": match &notifier {
+                    Ok(notifier) => serde_json::json!({
+                        "enabled": notifier.is_enabled(),
+                        "sinks": notifier.sink_names(),
+                        "rules": notifier.rule_count(),
+                    }),
+                    Err(e) => serde_json::json!({
+                        "error": format!("{:#}", e),
+                    }),
+                },
             }));
         }
     }
 
     Ok(())
 }
+
+fn print_scan_config(config: &scanner::ScanConfig) {
+    let list = |items: &[String], empty: &str| {
+        if items.is_empty() {
+            empty.to_string()
+        } else {
+            items.join(", ")
+        }
+    };
+
+    println!("Scan settings:");
+    println!("  Interfaces:     {}", list(&config.interfaces, "all (non-loopback, up)"));
+    println!("  Include:        {}", list(&config.include, "interface subnets"));
+    println!("  Exclude:        {}", list(&config.exclude, "none"));
+    println!("  Max hosts:      {}", config.max_hosts);
+    println!(
+        "  Ping:           {} engine, {}/s, {} retries, {}ms timeout",
+        config.ping.engine, config.ping.rate_limit, config.ping.retries, config.ping.timeout_ms
+    );
+    println!(
+        "  ARP sweep:      {}, {}/s, {} retries, {}ms timeout",
+        if config.arp_sweep.enabled { "enabled" } else { "disabled" },
+        config.arp_sweep.rate_limit,
+        config.arp_sweep.retries,
+        config.arp_sweep.timeout_ms
+    );
+    println!(
+        "  mDNS:           {}, {}ms window",
+        if config.mdns.enabled { "enabled" } else { "disabled" },
+        config.mdns.window_ms
+    );
+    let mut resolvers = vec!["system"];
+    if config.resolvers.netbios {
+        resolvers.push("NetBIOS");
+    }
+    if config.resolvers.llmnr {
+        resolvers.push("LLMNR");
+    }
+    println!("  Name lookups:   {}", resolvers.join(", "));
+    println!(
+        "  SSDP/UPnP:      {}, {}ms window, {}ms per description",
+        if config.ssdp.enabled { "enabled" } else { "disabled" },
+        config.ssdp.window_ms,
+        config.ssdp.fetch_timeout_ms
+    );
+    println!(
+        "  LLDP/CDP:       {}, {}ms window",
+        if config.lldp.enabled { "enabled" } else { "disabled" },
+        config.lldp.window_ms
+    );
+    println!(
+        "  DHCP listener:  {}",
+        if config.dhcp.enabled { "enabled" } else { "disabled" }
+    );
+    let mut credentials = Vec::new();
+    if let Some(v3) = &config.snmp.v3 {
+        credentials.push(format!("v3 user {}", v3.username));
+    }
+    // Community strings are secrets; only count them
+    if !config.snmp.communities.is_empty() {
+        credentials.push(format!("{} v2c communities", config.snmp.communities.len()));
+    }
+    println!(
+        "  SNMP:           {}, {}, {}ms timeout, {} retries",
+        if config.snmp.enabled { "enabled" } else { "disabled" },
+        credentials.join(" then "),
+        config.snmp.timeout_ms,
+        config.snmp.retries
+    );
+    let ports = if config.ports.is_empty() {
+        "top 100 ports".to_string()
+    } else {
+        let list: Vec<String> = config.ports.iter().map(|p| p.to_string()).collect();
+        format!("ports {}", list.join(", "))
+    };
+    println!(
+        "  Port scan:      {}, {}, {} concurrent, {}ms timeout",
+        if config.port_scan.enabled { "enabled" } else { "disabled" },
+        ports,
+        config.port_scan.concurrency,
+        config.port_scan.timeout_ms
+    );
+    println!(
+        "  Services:       {}, {} devices at once, {}ms per port, {}ms per device",
+        if config.services.enabled { "enabled" } else { "disabled" },
+        config.services.concurrency,
+        config.services.timeout_ms,
+        config.services.device_budget_ms
+    );
+}
+
+fn print_vendors(
+    config: &scanner::config::OuiConfig,
+    vendors: &Result<scanner::oui::Database>,
+) {
+    let vendors = match vendors {
+        Ok(vendors) => vendors,
+        Err(e) => {
+            println!(
+                "  MAC vendors:    {}; registry files and custom prefixes ignored: {:#}",
+                scanner::oui::builtin_source(),
+                e
+            );
+            return;
+        }
+    };
+    match vendors.registry_source() {
+        Some(source) => println!("  MAC vendors:    {}", source),
+        None => println!(
+            "  MAC vendors:    {} (no registry files in {})",
+            scanner::oui::builtin_source(),
+            scanner::oui::registry_dir(config)
+                .map(|p| p.display().to_string())
+                .unwrap_or_else(|| "the data directory".to_string())
+        ),
+    }
+    if vendors.custom_count() > 0 {
+        println!("  Custom OUIs:    {}", vendors.custom_count());
+    }
+}
+
+fn print_alerts(notifier: &Result<notifications::Notifier>) {
+    match notifier {
+        Ok(notifier) if notifier.sink_names().is_empty() => {
+            println!("  Alerts:         no sinks configured")
+        }
+        Ok(notifier) => println!(
+            "  Alerts:         {}, sinks {}, {}",
+            if notifier.is_enabled() { "enabled" } else { "disabled" },
+            notifier.sink_names().join(", "),
+            match notifier.rule_count() {
+                0 => "every event to every sink".to_string(),
+                1 => "1 rule".to_string(),
+                n => format!("{} rules", n),
+            }
+        ),
+        Err(e) => println!("  Alerts:         none sent: {:#}", e),
+    }
+}
+
+fn print_rules(config: &scanner::config::ClassifyConfig, rules: &Result<scanner::Ruleset>) {
+    match rules {
+        Ok(rules) => match rules.user_file() {
+            Some(path) => println!(
+                "  Device rules:   {} built-in, {} from {}",
+                rules.builtin_count(),
+                rules.user_count(),
+                path.display()
+            ),
+            None => println!(
+                "  Device rules:   {} built-in (no {})",
+                rules.builtin_count(),
+                scanner::classify::user_rules_path(config)
+                    .map(|p| p.display().to_string())
+                    .unwrap_or_else(|| "rules.toml".to_string())
+            ),
+        },
+        Err(e) => println!(
+            "  Device rules:   {} built-in; user rules ignored: {:#}",
+            scanner::Ruleset::builtin().builtin_count(),
+            e
+        ),
+    }
+}
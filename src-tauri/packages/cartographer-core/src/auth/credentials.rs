@@ -43,6 +43,18 @@ pub struct AuthStatus {
     pub user_email: Option<String>,
     pub network_id: Option<String>,
     pub network_name: Option<String>,
+    /// Account-level agent settings, only present right after a login completes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agent_settings: Option<AgentSettings>,
+}
+
+/// Account-level agent settings delivered by the cloud alongside the access token
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AgentSettings {
+    /// Minimum interval between automatic full scans (manual scans are exempt)
+    pub automatic_full_scan_min_interval_seconds: Option<u64>,
+    /// Interval between background health checks
+    pub health_poll_interval_seconds: Option<u64>,
 }
 
 /// Get the cartographer config directory
@@ -411,6 +423,7 @@ pub async fn check_auth() -> Result<AuthStatus> {
                     user_email: Some(creds.user_email),
                     network_id: Some(creds.network_id),
                     network_name: Some(creds.network_name),
+                    agent_settings: None,
                 })
             }
             Ok(TokenVerifyResult::Invalid) => {
@@ -421,6 +434,7 @@ pub async fn check_auth() -> Result<AuthStatus> {
                     user_email: None,
                     network_id: None,
                     network_name: None,
+                    agent_settings: None,
                 })
             }
             Ok(TokenVerifyResult::NetworkError(reason)) => {
@@ -434,6 +448,7 @@ pub async fn check_auth() -> Result<AuthStatus> {
                     user_email: Some(creds.user_email),
                     network_id: Some(creds.network_id),
                     network_name: Some(creds.network_name),
+                    agent_settings: None,
                 })
             }
             Err(e) => {
@@ -443,6 +458,7 @@ pub async fn check_auth() -> Result<AuthStatus> {
                     user_email: Some(creds.user_email),
                     network_id: Some(creds.network_id),
                     network_name: Some(creds.network_name),
+                    agent_settings: None,
                 })
             }
         }
@@ -452,6 +468,7 @@ pub async fn check_auth() -> Result<AuthStatus> {
             user_email: None,
             network_id: None,
             network_name: None,
+            agent_settings: None,
         })
     }
 }
@@ -3,7 +3,7 @@
 //! This module implements the device flow for authenticating CLI/headless agents
 //! without requiring a browser on the same machine.
 
-use crate::auth::credentials::{save_credentials, Credentials};
+use crate::auth::credentials::{save_credentials, AgentSettings, Credentials};
 use crate::cloud::CloudClient;
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
@@ -104,6 +104,11 @@ pub async fn poll_for_login(
                 let network_id = token_resp.network_id;
                 let network_name = token_resp.network_name;
                 let user_email = token_resp.user_email;
+                let agent_settings = AgentSettings {
+                    automatic_full_scan_min_interval_seconds: token_resp
+                        .automatic_full_scan_min_interval_seconds,
+                    health_poll_interval_seconds: token_resp.health_poll_interval_seconds,
+                };
 
                 let creds = Credentials {
                     access_token: token_resp.access_token,
@@ -126,6 +131,7 @@ pub async fn poll_for_login(
                     user_email: Some(user_email),
                     network_id: Some(network_id),
                     network_name: Some(network_name),
+                    agent_settings: Some(agent_settings),
                 });
             }
             Ok(None) => {
@@ -1,14 +1,32 @@
 //! HTTP client for Cartographer cloud API.
-
-use super::config::{load_cloud_config, CloudEndpointConfig};
-use crate::scanner::{Device, ScanResult};
+//!
+//! Uploads, device code requests and network lookups go through
+//! [`CloudClient::send_with_retry`], which retries connection errors,
+//! timeouts, 429 and 5xx responses per `[cloud.retry]`: after the server's
+//! Retry-After when it sends one, otherwise after a random delay of up to
+//! `base_delay_ms * 2^attempt` ("full jitter"). Scan and health uploads carry
+//! an `Idempotency-Key` that stays the same across retries and outbox
+//! replays, so the server can drop a request it has already counted.
+//! Token polling, command polling and command claims/results are not retried
+//! here; their loops already repeat them.
+
+use super::commands::{ClaimResponse, PollResponse, ResultReport, ResultResponse};
+use super::config::{load_cloud_config, load_retry_config, CloudEndpointConfig, RetryConfig};
+use crate::scanner::{
+    AdvertisedService, Classification, Device, DhcpFingerprint, HostnameSource, OpenPort, OsGuess,
+    ScanResult, ServiceInfo, SnmpInfo, TopologyEdge, UpnpDevice,
+};
 use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use rand::Rng;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use std::time::Duration;
 
 #[derive(Debug, Clone)]
 pub struct CloudClient {
     config: CloudEndpointConfig,
+    retry: RetryConfig,
     http_client: Arc<reqwest::Client>,
 }
 
@@ -31,7 +49,11 @@ impl CloudClient {
                 .build()
                 .expect("Failed to create HTTP client"),
         );
-        Self { config, http_client }
+        Self {
+            config,
+            retry: load_retry_config(),
+            http_client,
+        }
     }
 
     /// Create a CloudClient with a custom configuration
@@ -43,7 +65,22 @@ impl CloudClient {
                 .build()
                 .expect("Failed to create HTTP client"),
         );
-        Self { config, http_client }
+        Self {
+            config,
+            retry: RetryConfig::default(),
+            http_client,
+        }
+    }
+
+    /// Use a different retry policy
+    pub fn with_retry(mut self, retry: RetryConfig) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// The retry policy for failed requests
+    pub fn retry_config(&self) -> &RetryConfig {
+        &self.retry
     }
 
     /// Get the base API URL
@@ -60,9 +97,7 @@ impl CloudClient {
         let url = format!("{}/agent/device-code", self.config.api_url);
 
         let resp = self
-            .http_client
-            .post(&url)
-            .send()
+            .send_with_retry(|| self.http_client.post(&url))
             .await
             .context("Failed to request device code")?;
 
@@ -151,68 +186,142 @@ impl CloudClient {
     ///
     /// Requires authentication token from stored credentials.
     pub async fn upload_scan_result(&self, scan_result: &ScanResult) -> Result<()> {
-        let creds = crate::auth::load_credentials()
-            .await
-            .context("Failed to load credentials")?
-            .ok_or_else(|| anyhow::anyhow!("Not authenticated"))?;
-
-        let url = format!("{}/agent/sync", self.config.api_url);
-
-        let gateway_ip = scan_result.network_info.gateway_ip.as_deref();
+        self.send_upload(&Self::scan_upload(scan_result)?).await
+    }
 
-        tracing::info!(
-            "Uploading {} devices to cloud (network: {}, gateway: {:?})",
-            scan_result.devices.len(),
-            creds.network_name,
-            gateway_ip
-        );
+    /// Build the sync request for a scan, stamped with the current time, to
+    /// send now or queue in the outbox.
+    pub fn scan_upload(scan_result: &ScanResult) -> Result<Upload> {
+        let created_at = Utc::now();
+        let gateway_ips = scan_result.network_info.gateway_ips();
+        let primary = scan_result.network_info.primary();
 
         let payload = SyncRequest {
-            timestamp: chrono::Utc::now().to_rfc3339(),
+            timestamp: created_at.to_rfc3339(),
             scan_duration_ms: None,
             devices: scan_result
                 .devices
                 .iter()
-                .map(|d| ScanDevice {
-                    ip: d.ip.clone(),
-                    mac: d.mac.clone(),
-                    response_time_ms: d.response_time_ms,
-                    hostname: d.hostname.clone(),
-                    is_gateway: gateway_ip.map_or(false, |gw| gw == d.ip),
-                    vendor: d.vendor.clone(),
-                    device_type: d.device_type.clone(),
-                })
+                .map(|d| ScanDevice::from_device(d, &gateway_ips))
                 .collect(),
             network_info: Some(NetworkInfo {
-                subnet: Some(scan_result.network_info.subnet.clone()),
-                interface: Some(scan_result.network_info.interface.clone()),
+                subnet: primary.map(|t| t.subnet.clone()),
+                interface: primary.map(|t| t.interface.clone()),
+                targets: scan_result
+                    .network_info
+                    .targets
+                    .iter()
+                    .map(|t| TargetInfo {
+                        subnet: t.subnet.clone(),
+                        interface: t.interface.clone(),
+                        gateway_ip: t.gateway_ip.clone(),
+                    })
+                    .collect(),
             }),
+            topology: scan_result.topology.clone(),
         };
 
+        Ok(Upload::new(
+            UploadKind::Scan,
+            created_at,
+            serde_json::to_value(&payload).context("Failed to serialize scan")?,
+        ))
+    }
+
+    /// Send a scan or health check upload, new or replayed from the outbox.
+    ///
+    /// A server error status comes back as a [`ServerError`].
+    pub async fn send_upload(&self, upload: &Upload) -> Result<()> {
+        let creds = crate::auth::load_credentials()
+            .await
+            .context("Failed to load credentials")?
+            .ok_or_else(|| anyhow::anyhow!("Not authenticated"))?;
+
+        if upload.kind == UploadKind::Scan {
+            tracing::info!(
+                "Uploading {} devices to cloud (network: {}, scanned {})",
+                upload.payload["devices"].as_array().map_or(0, |d| d.len()),
+                creds.network_name,
+                upload.created_at.format("%Y-%m-%d %H:%M:%S UTC")
+            );
+        }
+
+        self.post_upload(&creds.access_token, upload).await?;
+
+        if upload.kind == UploadKind::Scan {
+            tracing::info!("Scan uploaded successfully");
+        }
+        Ok(())
+    }
+
+    pub(super) async fn post_upload(&self, token: &str, upload: &Upload) -> Result<()> {
+        let url = format!("{}/{}", self.config.api_url, upload.kind.path());
         let resp = self
-            .http_client
-            .post(&url)
-            .bearer_auth(&creds.access_token)
-            .json(&payload)
-            .send()
+            .send_with_retry(|| {
+                self.http_client
+                    .post(&url)
+                    .bearer_auth(token)
+                    .header("Idempotency-Key", &upload.idempotency_key)
+                    .json(&upload.payload)
+            })
             .await
-            .context("Failed to upload scan")?;
+            .with_context(|| format!("Failed to upload {}", upload.kind))?;
 
         if !resp.status().is_success() {
             let status = resp.status();
             let body = resp.text().await.unwrap_or_default();
-            tracing::error!("Sync failed: {} - {}", status, body);
-            return Err(anyhow::anyhow!(
-                "Server returned error: {} - {}",
-                status,
-                body
-            ));
+            if upload.kind == UploadKind::Scan {
+                tracing::error!("Sync failed: {} - {}", status, body);
+            }
+            return Err(ServerError { status, body }.into());
         }
-
-        tracing::info!("Scan uploaded successfully");
         Ok(())
     }
 
+    /// Send a request, retrying connection errors, timeouts, 429 and 5xx
+    /// responses per the retry policy.
+    ///
+    /// `build` makes a fresh request for every attempt. The last response is
+    /// returned whatever its status; a Retry-After longer than
+    /// `max_delay_ms` returns it straight away.
+    pub async fn send_with_retry(
+        &self,
+        build: impl Fn() -> reqwest::RequestBuilder,
+    ) -> reqwest::Result<reqwest::Response> {
+        let mut attempt = 0;
+        loop {
+            let result = build().send().await;
+            let retry_after = match &result {
+                Ok(resp) if is_retryable(resp.status()) => retry_after(resp.headers()),
+                Err(e) if e.is_connect() || e.is_timeout() => None,
+                _ => return result,
+            };
+            if attempt >= self.retry.max_retries {
+                return result;
+            }
+            let delay = match retry_after {
+                Some(delay) if delay > Duration::from_millis(self.retry.max_delay_ms) => {
+                    return result;
+                }
+                Some(delay) => delay,
+                None => self.retry.backoff_delay(attempt),
+            };
+            let reason = match &result {
+                Ok(resp) => resp.status().to_string(),
+                Err(e) => e.to_string(),
+            };
+            attempt += 1;
+            tracing::debug!(
+                "Request failed ({}), retry {} of {} in {:?}",
+                reason,
+                attempt,
+                self.retry.max_retries,
+                delay
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+
     /// Legacy function - upload devices without network info
     pub async fn upload_scan(&self, devices: &[Device]) -> Result<()> {
         let creds = crate::auth::load_credentials()
@@ -233,25 +342,19 @@ impl CloudClient {
             scan_duration_ms: None,
             devices: devices
                 .iter()
-                .map(|d| ScanDevice {
-                    ip: d.ip.clone(),
-                    mac: d.mac.clone(),
-                    response_time_ms: d.response_time_ms,
-                    hostname: d.hostname.clone(),
-                    is_gateway: false,
-                    vendor: d.vendor.clone(),
-                    device_type: d.device_type.clone(),
-                })
+                .map(|d| ScanDevice::from_device(d, &[]))
                 .collect(),
             network_info: None,
+            topology: Vec::new(),
         };
 
         let resp = self
-            .http_client
-            .post(&url)
-            .bearer_auth(&creds.access_token)
-            .json(&payload)
-            .send()
+            .send_with_retry(|| {
+                self.http_client
+                    .post(&url)
+                    .bearer_auth(&creds.access_token)
+                    .json(&payload)
+            })
             .await
             .context("Failed to upload scan")?;
 
@@ -279,10 +382,7 @@ impl CloudClient {
         let url = format!("{}/agent/network", self.config.api_url);
 
         let resp = self
-            .http_client
-            .get(&url)
-            .bearer_auth(&creds.access_token)
-            .send()
+            .send_with_retry(|| self.http_client.get(&url).bearer_auth(&creds.access_token))
             .await
             .context("Failed to get network info")?;
 
@@ -297,15 +397,15 @@ impl CloudClient {
 
     /// Upload health check results to the cloud.
     pub async fn upload_health_check(&self, results: &[DeviceHealthResult]) -> Result<()> {
-        let creds = crate::auth::load_credentials()
-            .await
-            .context("Failed to load credentials")?
-            .ok_or_else(|| anyhow::anyhow!("Not authenticated"))?;
-
-        let url = format!("{}/agent/health", self.config.api_url);
+        self.send_upload(&Self::health_upload(results)?).await
+    }
 
+    /// Build the health check request, stamped with the current time, to
+    /// send now or queue in the outbox.
+    pub fn health_upload(results: &[DeviceHealthResult]) -> Result<Upload> {
+        let created_at = Utc::now();
         let payload = HealthCheckRequest {
-            timestamp: chrono::Utc::now().to_rfc3339(),
+            timestamp: created_at.to_rfc3339(),
             results: results
                 .iter()
                 .map(|r| HealthCheckResultPayload {
@@ -316,38 +416,111 @@ impl CloudClient {
                 .collect(),
         };
 
+        Ok(Upload::new(
+            UploadKind::Health,
+            created_at,
+            serde_json::to_value(&payload).context("Failed to serialize health check")?,
+        ))
+    }
+
+    /// Open the cloud dashboard in the default browser.
+    #[cfg(feature = "browser")]
+    pub async fn open_dashboard(&self) -> Result<()> {
+        let creds = crate::auth::load_credentials()
+            .await
+            .context("Failed to load credentials")?
+            .ok_or_else(|| anyhow::anyhow!("Not authenticated"))?;
+
+        let url = format!("{}/app/network/{}", self.config.dashboard_url, creds.network_id);
+        webbrowser::open(&url).context("Failed to open dashboard in browser")
+    }
+
+    /// Long-poll the cloud for pending agent commands.
+    ///
+    /// The server holds the request open for up to `timeout_secs` and returns
+    /// as soon as a command is queued.
+    pub async fn poll_commands(&self, token: &str, timeout_secs: u64) -> Result<PollResponse> {
+        let url = format!(
+            "{}/agent/commands/poll?timeout={}",
+            self.config.api_url, timeout_secs
+        );
+
+        let resp = self
+            .http_client
+            .get(&url)
+            .bearer_auth(token)
+            .timeout(std::time::Duration::from_secs(timeout_secs + 5))
+            .send()
+            .await
+            .context("Failed to poll for commands")?;
+
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body = resp.text().await.unwrap_or_default();
+            return Err(anyhow::anyhow!("Poll failed: {} - {}", status, body));
+        }
+
+        resp.json::<PollResponse>()
+            .await
+            .context("Failed to parse poll response")
+    }
+
+    /// Claim a pending command so no other agent executes it.
+    pub async fn claim_command(&self, token: &str, command_id: i64) -> Result<ClaimResponse> {
+        let url = format!(
+            "{}/agent/commands/{}/claim",
+            self.config.api_url, command_id
+        );
+
         let resp = self
             .http_client
             .post(&url)
-            .bearer_auth(&creds.access_token)
-            .json(&payload)
+            .bearer_auth(token)
             .send()
             .await
-            .context("Failed to upload health check")?;
+            .context("Failed to claim command")?;
 
         if !resp.status().is_success() {
             let status = resp.status();
             let body = resp.text().await.unwrap_or_default();
-            return Err(anyhow::anyhow!(
-                "Server returned error: {} - {}",
-                status,
-                body
-            ));
+            return Err(anyhow::anyhow!("Claim failed: {} - {}", status, body));
         }
 
-        Ok(())
+        resp.json::<ClaimResponse>()
+            .await
+            .context("Failed to parse claim response")
     }
 
-    /// Open the cloud dashboard in the default browser.
-    #[cfg(feature = "browser")]
-    pub async fn open_dashboard(&self) -> Result<()> {
-        let creds = crate::auth::load_credentials()
+    /// Report the outcome of an executed command.
+    pub async fn report_command_result(
+        &self,
+        token: &str,
+        command_id: i64,
+        report: &ResultReport,
+    ) -> Result<ResultResponse> {
+        let url = format!(
+            "{}/agent/commands/{}/result",
+            self.config.api_url, command_id
+        );
+
+        let resp = self
+            .http_client
+            .post(&url)
+            .bearer_auth(token)
+            .json(report)
+            .send()
             .await
-            .context("Failed to load credentials")?
-            .ok_or_else(|| anyhow::anyhow!("Not authenticated"))?;
+            .context("Failed to report command result")?;
 
-        let url = format!("{}/app/network/{}", self.config.dashboard_url, creds.network_id);
-        webbrowser::open(&url).context("Failed to open dashboard in browser")
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body = resp.text().await.unwrap_or_default();
+            return Err(anyhow::anyhow!("Report failed: {} - {}", status, body));
+        }
+
+        resp.json::<ResultResponse>()
+            .await
+            .context("Failed to parse result response")
     }
 }
 
@@ -357,6 +530,116 @@ impl Default for CloudClient {
     }
 }
 
+/// What an upload carries
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UploadKind {
+    /// Sync request from a network scan
+    Scan,
+    /// Health check results
+    Health,
+}
+
+impl UploadKind {
+    fn path(self) -> &'static str {
+        match self {
+            UploadKind::Scan => "agent/sync",
+            UploadKind::Health => "agent/health",
+        }
+    }
+}
+
+impl std::fmt::Display for UploadKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UploadKind::Scan => write!(f, "scan"),
+            UploadKind::Health => write!(f, "health check"),
+        }
+    }
+}
+
+/// A scan or health check request body, built when the data was collected
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Upload {
+    pub kind: UploadKind,
+    /// When the scan or health check ran (the payload's timestamp)
+    pub created_at: DateTime<Utc>,
+    /// Sent as the Idempotency-Key header, the same on every attempt
+    #[serde(default = "new_idempotency_key")]
+    pub idempotency_key: String,
+    pub payload: serde_json::Value,
+}
+
+impl Upload {
+    /// An upload with a fresh idempotency key
+    pub fn new(kind: UploadKind, created_at: DateTime<Utc>, payload: serde_json::Value) -> Self {
+        Self {
+            kind,
+            created_at,
+            idempotency_key: new_idempotency_key(),
+            payload,
+        }
+    }
+}
+
+/// A random UUID (version 4) string
+fn new_idempotency_key() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
+impl RetryConfig {
+    /// Delay before retry `attempt + 1`: uniformly random between zero and
+    /// `base_delay_ms * 2^attempt`, capped at `max_delay_ms`
+    pub fn backoff_delay(&self, attempt: u32) -> Duration {
+        let ceiling = self
+            .base_delay_ms
+            .saturating_mul(1u64 << attempt.min(32))
+            .min(self.max_delay_ms);
+        Duration::from_millis(rand::thread_rng().gen_range(0..=ceiling))
+    }
+}
+
+/// Statuses worth sending the same request again for
+fn is_retryable(status: reqwest::StatusCode) -> bool {
+    status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+}
+
+/// The Retry-After header, in seconds or as an HTTP date
+fn retry_after(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+    let value = headers
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        (at.with_timezone(&Utc) - Utc::now())
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
+
+/// The server answered an upload with an error status
+#[derive(Debug, thiserror::Error)]
+#[error("Server returned error: {status} - {body}")]
+pub struct ServerError {
+    pub status: reqwest::StatusCode,
+    pub body: String,
+}
+
+impl ServerError {
+    /// Whether sending the same request again cannot succeed (the server
+    /// rejected the payload), as opposed to an outage, a rate limit or an
+    /// expired token
+    pub fn is_permanent(&self) -> bool {
+        self.status.is_client_error() && !matches!(self.status.as_u16(), 401 | 403 | 408 | 429)
+    }
+}
+
 /// Result of token verification attempt
 #[derive(Debug, Clone)]
 pub enum TokenVerifyResult {
@@ -397,6 +680,10 @@ pub struct TokenResponse {
     pub network_id: String,
     pub network_name: String,
     pub user_email: String,
+    /// Account-level minimum interval between automatic full scans
+    pub automatic_full_scan_min_interval_seconds: Option<u64>,
+    /// Account-level health check polling interval
+    pub health_poll_interval_seconds: Option<u64>,
 }
 
 #[derive(Debug, Serialize)]
@@ -405,24 +692,94 @@ struct SyncRequest {
     scan_duration_ms: Option<u64>,
     devices: Vec<ScanDevice>,
     network_info: Option<NetworkInfo>,
+    /// Switch ports the agent host is attached to (LLDP/CDP)
+    topology: Vec<TopologyEdge>,
 }
 
 #[derive(Debug, Serialize)]
 struct NetworkInfo {
+    /// Subnet and interface of the primary (default route) target
     subnet: Option<String>,
     interface: Option<String>,
+    /// Every subnet covered by the scan
+    targets: Vec<TargetInfo>,
+}
+
+#[derive(Debug, Serialize)]
+struct TargetInfo {
+    subnet: String,
+    interface: String,
+    gateway_ip: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 struct ScanDevice {
+    /// Primary address (IPv4 when known)
     ip: String,
+    /// All IPv4 and IPv6 addresses of the device
+    addresses: Vec<String>,
     mac: Option<String>,
+    /// The MAC is random and may change; match the device by other signals
+    randomized_mac: bool,
     response_time_ms: Option<f64>,
     hostname: Option<String>,
+    /// Resolver that produced the hostname (dns, mdns, netbios, llmnr, ...)
+    hostname_source: Option<HostnameSource>,
     is_gateway: bool,
     vendor: Option<String>,
     device_type: Option<String>,
+    /// Classification rule that set the device type, and what it matched
+    classification: Option<Classification>,
+    /// Tags of every matching classification rule
+    tags: Vec<String>,
+    /// Interface and subnet the device was seen on
+    interface: Option<String>,
+    subnet: Option<String>,
+    /// Open TCP ports (empty unless the port scan is enabled)
+    open_ports: Vec<OpenPort>,
+    /// Banners and TLS certificates read from open ports
+    services: Vec<ServiceInfo>,
+    /// Model and services advertised over mDNS
+    model: Option<String>,
+    advertised_services: Vec<AdvertisedService>,
+    /// UPnP description (friendly name, manufacturer, model, serial)
+    upnp: Option<UpnpDevice>,
+    /// SNMP system group and interface table
+    snmp: Option<SnmpInfo>,
+    /// Hostname, vendor class, parameter list and OS family from DHCP
+    dhcp: Option<DhcpFingerprint>,
+    /// OS family, confidence and the signals behind it
+    os_guess: Option<OsGuess>,
+}
+
+impl ScanDevice {
+    fn from_device(d: &Device, gateway_ips: &[&str]) -> Self {
+        Self {
+            ip: d.primary_ip().to_string(),
+            addresses: d.addresses.clone(),
+            mac: d.mac.clone(),
+            randomized_mac: d.randomized_mac,
+            response_time_ms: d.response_time_ms,
+            hostname: d.hostname.clone(),
+            hostname_source: d.hostname_source,
+            is_gateway: gateway_ips.iter().any(|gw| d.has_address(gw)),
+            vendor: d.vendor.clone(),
+            device_type: d.device_type.clone(),
+            classification: d.classification.clone(),
+            tags: d.tags.clone(),
+            interface: d.interface.clone(),
+            subnet: d.subnet.clone(),
+            open_ports: d.open_ports.clone(),
+            services: d.services.clone(),
+            model: d.model.clone(),
+            advertised_services: d.advertised_services.clone(),
+            upnp: d.upnp.clone(),
+            snmp: d.snmp.clone(),
+            dhcp: d.dhcp.clone(),
+            os_guess: d.os_guess.clone(),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -452,3 +809,121 @@ struct HealthCheckResultPayload {
     reachable: bool,
     response_time_ms: Option<f64>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::config::ConfigSource;
+    use crate::mock_server::mock_server;
+
+    fn client(api_url: &str, max_retries: u32) -> CloudClient {
+        CloudClient::with_config(CloudEndpointConfig {
+            api_url: api_url.to_string(),
+            dashboard_url: api_url.to_string(),
+            source: ConfigSource::Default,
+        })
+        .with_retry(RetryConfig {
+            max_retries,
+            base_delay_ms: 10,
+            max_delay_ms: 1000,
+        })
+    }
+
+    fn upload() -> Upload {
+        Upload::new(
+            UploadKind::Health,
+            Utc::now(),
+            serde_json::json!({"results": []}),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_upload_retries_with_same_idempotency_key() {
+        let (url, requests) =
+            mock_server(vec![(503, ""), (429, "Retry-After: 0\r\n"), (200, "")]).await;
+        let upload = upload();
+        client(&url, 3).post_upload("token", &upload).await.unwrap();
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 3);
+        let key = format!("idempotency-key: {}", upload.idempotency_key);
+        for request in requests.iter().map(|r| &r.head) {
+            assert!(request.starts_with("post /agent/health "), "{}", request);
+            assert!(request.contains(&key), "{}", request);
+            assert!(
+                request.contains("authorization: bearer token"),
+                "{}",
+                request
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_upload_gives_up() {
+        // Out of retries: the last error status is returned
+        let (url, requests) = mock_server(vec![(500, ""), (502, "")]).await;
+        let error = client(&url, 1)
+            .post_upload("token", &upload())
+            .await
+            .unwrap_err();
+        assert_eq!(error.downcast_ref::<ServerError>().unwrap().status, 502);
+        assert_eq!(requests.lock().unwrap().len(), 2);
+
+        // Client errors are not retried
+        let (url, requests) = mock_server(vec![(400, "")]).await;
+        let error = client(&url, 3)
+            .post_upload("token", &upload())
+            .await
+            .unwrap_err();
+        assert!(error.downcast_ref::<ServerError>().unwrap().is_permanent());
+        assert_eq!(requests.lock().unwrap().len(), 1);
+
+        // Nor is a rate limit that outlasts max_delay_ms
+        let (url, requests) = mock_server(vec![(429, "Retry-After: 3600\r\n")]).await;
+        assert!(
+            client(&url, 3)
+                .post_upload("token", &upload())
+                .await
+                .is_err()
+        );
+        assert_eq!(requests.lock().unwrap().len(), 1);
+
+        // Nothing listening: connection errors are retried, then reported
+        let closed = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", closed.local_addr().unwrap());
+        drop(closed);
+        assert!(
+            client(&url, 2)
+                .post_upload("token", &upload())
+                .await
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_retry_delays() {
+        let retry = RetryConfig {
+            max_retries: 5,
+            base_delay_ms: 100,
+            max_delay_ms: 1000,
+        };
+        for attempt in 0..40 {
+            let ceiling = (100u64 << attempt.min(10)).min(1000);
+            assert!(retry.backoff_delay(attempt) <= Duration::from_millis(ceiling));
+        }
+
+        let mut headers = reqwest::header::HeaderMap::new();
+        assert_eq!(retry_after(&headers), None);
+        headers.insert("retry-after", "120".parse().unwrap());
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(120)));
+        headers.insert(
+            "retry-after",
+            "Wed, 21 Oct 2015 07:28:00 GMT".parse().unwrap(),
+        );
+        assert_eq!(retry_after(&headers), Some(Duration::ZERO));
+
+        let key = upload().idempotency_key;
+        assert_eq!(uuid::Uuid::parse_str(&key).unwrap().get_version_num(), 4);
+        assert_ne!(key, upload().idempotency_key);
+    }
+}
@@ -1,3 +1,8 @@
+//! Cloud command types for the agent long-poll command channel.
+//!
+//! The cloud queues commands (e.g. `scan_network`, `health_check`) for an agent,
+//! which polls for them, claims each one, executes it and reports the result.
+
 use serde::{Deserialize, Serialize};
 
 /// A command received during long-poll.
@@ -5,7 +5,8 @@
 //! 2. Config file (~/.config/cartographer/config.toml)
 //! 3. Default values
 
-use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
 
@@ -30,6 +31,54 @@ struct CloudConfig {
     api_url: Option<String>,
     /// Dashboard URL for browser links (e.g., "https://your-instance.example.com")
     dashboard_url: Option<String>,
+    /// Limits of the queue of uploads waiting to be retried
+    #[serde(default)]
+    outbox: OutboxConfig,
+    /// Retries of failed requests
+    #[serde(default)]
+    retry: RetryConfig,
+}
+
+/// Retries of failed cloud requests (`[cloud.retry]`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetryConfig {
+    /// Extra attempts after a connection error, timeout, 429 or 5xx (0 = none)
+    pub max_retries: u32,
+    /// Delay cap of the first retry; doubles with every attempt
+    pub base_delay_ms: u64,
+    /// Longest delay between attempts. A Retry-After asking for longer ends
+    /// the retries.
+    pub max_delay_ms: u64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay_ms: 500,
+            max_delay_ms: 30_000,
+        }
+    }
+}
+
+/// Limits of the upload outbox (`[cloud.outbox]`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutboxConfig {
+    /// Total size of queued uploads; the oldest are dropped beyond it (0 = no limit)
+    pub max_size_mb: u64,
+    /// Queued uploads older than this are dropped (0 = no limit)
+    pub max_age_hours: u64,
+}
+
+impl Default for OutboxConfig {
+    fn default() -> Self {
+        Self {
+            max_size_mb: 50,
+            max_age_hours: 7 * 24,
+        }
+    }
 }
 
 /// Runtime cloud configuration
@@ -64,39 +113,69 @@ impl std::fmt::Display for ConfigSource {
     }
 }
 
-/// Get the path to the configuration file
-fn get_config_file_path() -> Option<PathBuf> {
+/// Get the directory holding config.toml and the other agent files
+pub(crate) fn get_config_dir_path() -> Option<PathBuf> {
     dirs::config_dir()
         .or_else(|| dirs::home_dir().map(|h| h.join(".config")))
-        .map(|p| p.join("cartographer").join("config.toml"))
+        .map(|p| p.join("cartographer"))
+}
+
+/// Get the directory holding agent data (the same one the desktop app uses)
+pub(crate) fn get_data_dir_path() -> Option<PathBuf> {
+    dirs::data_local_dir()
+        .or_else(dirs::data_dir)
+        .map(|p| p.join("cartographer-agent"))
+}
+
+/// Get the path to the configuration file
+fn get_config_file_path() -> Option<PathBuf> {
+    get_config_dir_path().map(|p| p.join("config.toml"))
 }
 
 /// Load configuration from the config file
 fn load_config_file() -> Option<ConfigFile> {
-    let path = get_config_file_path()?;
+    read_config_file()
+}
 
-    if !path.exists() {
-        return None;
-    }
+/// Read and parse the config file into any section layout.
+///
+/// Returns `None` if the file is missing or invalid (a warning is logged).
+pub(crate) fn read_config_file<T: DeserializeOwned>() -> Option<T> {
+    let (path, content) = match read_config_source() {
+        Ok(source) => source?,
+        Err(e) => {
+            tracing::warn!("{}", e);
+            return None;
+        }
+    };
 
-    match fs::read_to_string(&path) {
-        Ok(content) => match toml::from_str(&content) {
-            Ok(config) => {
-                tracing::debug!("Loaded config from {:?}", path);
-                Some(config)
-            }
-            Err(e) => {
-                tracing::warn!("Failed to parse config file {:?}: {}", path, e);
-                None
-            }
-        },
+    match toml::from_str(&content) {
+        Ok(config) => {
+            tracing::debug!("Loaded config from {:?}", path);
+            Some(config)
+        }
         Err(e) => {
-            tracing::warn!("Failed to read config file {:?}: {}", path, e);
+            tracing::warn!("Failed to parse config file {:?}: {}", path, e);
             None
         }
     }
 }
 
+/// Read the raw config file. Returns `Ok(None)` if there is no config file.
+pub(crate) fn read_config_source() -> anyhow::Result<Option<(PathBuf, String)>> {
+    let Some(path) = get_config_file_path() else {
+        return Ok(None);
+    };
+
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let content = fs::read_to_string(&path)
+        .map_err(|e| anyhow::anyhow!("Failed to read config file {:?}: {}", path, e))?;
+    Ok(Some((path, content)))
+}
+
 /// Load cloud endpoint configuration with priority:
 /// 1. Environment variable (CARTOGRAPHER_CLOUD_URL)
 /// 2. Config file (~/.config/cartographer/config.toml)
@@ -166,6 +245,22 @@ pub fn load_cloud_config() -> CloudEndpointConfig {
     }
 }
 
+/// Load the `[cloud.outbox]` limits, falling back to defaults
+pub fn load_outbox_config() -> OutboxConfig {
+    load_config_file()
+        .and_then(|config| config.cloud)
+        .map(|cloud| cloud.outbox)
+        .unwrap_or_default()
+}
+
+/// Load the `[cloud.retry]` settings, falling back to defaults
+pub fn load_retry_config() -> RetryConfig {
+    load_config_file()
+        .and_then(|config| config.cloud)
+        .map(|cloud| cloud.retry)
+        .unwrap_or_default()
+}
+
 /// Get the path to the config file for documentation purposes
 pub fn get_config_file_path_string() -> String {
     get_config_file_path()
@@ -185,6 +280,182 @@ pub fn generate_example_config() -> String {
 
 # Dashboard URL for browser links (optional, derived from api_url if not set)
 # dashboard_url = "https://your-instance.example.com"
+
+[cloud.outbox]
+# Scans and health checks that fail to upload are queued on disk and
+# replayed in order once the cloud is reachable again. Oldest uploads are
+# dropped beyond these limits (0 = no limit)
+# max_size_mb = 50
+# max_age_hours = 168
+
+[cloud.retry]
+# Connection errors, timeouts, 429 and 5xx responses are retried after a
+# random delay of up to base_delay_ms, doubling per attempt up to
+# max_delay_ms (or after the server's Retry-After)
+# max_retries = 3
+# base_delay_ms = 500
+# max_delay_ms = 30000
+
+[scan]
+# Only scan these interfaces. Default: every non-loopback interface that is up
+# interfaces = ["eth0"]
+
+# IPv4 subnets to scan instead of the interfaces' own subnets. Subnets not
+# attached to this machine are pinged through the default route.
+# include = ["10.20.0.0/24"]
+
+# Subnets or single addresses that are never probed or reported
+# exclude = ["10.20.0.50", "10.20.0.128/28"]
+
+# Maximum number of addresses swept per scan
+# max_hosts = 4096
+
+# TCP ports probed by the port scan. Default: the 100 most common ports
+# ports = [22, 80, 443, 502, 8080]
+
+[scan.ping]
+# ICMP engine: "auto" (datagram socket, then raw socket, then system ping),
+# "datagram", "raw" or "subprocess"
+# engine = "auto"
+
+# Maximum echo requests per second (0 = unlimited)
+# rate_limit = 500
+
+# Extra attempts for hosts that did not answer
+# retries = 1
+
+# Reply timeout per attempt, in milliseconds
+# timeout_ms = 1000
+
+[scan.arp_sweep]
+# Active ARP sweep (Linux, needs CAP_NET_RAW); finds hosts that drop ICMP
+# enabled = true
+# rate_limit = 1000
+# retries = 1
+# timeout_ms = 500
+
+[scan.mdns]
+# Browse mDNS/DNS-SD advertisements (printers, Chromecasts, HomeKit, NAS)
+# enabled = true
+# window_ms = 3000
+
+[scan.ssdp]
+# Find UPnP devices (routers, TVs, media servers) and read their descriptions
+# enabled = true
+# window_ms = 3000
+# fetch_timeout_ms = 2000
+
+[scan.lldp]
+# Listen for LLDP/CDP announcements from switches (Linux, needs CAP_NET_RAW)
+# enabled = true
+# window_ms = 30000
+
+[scan.dhcp]
+# Fingerprint DHCP requests heard while the agent runs (Linux, needs CAP_NET_RAW)
+# enabled = true
+
+[scan.resolvers]
+# Ask hosts without a PTR record for their NetBIOS and LLMNR names
+# netbios = true
+# llmnr = true
+
+[scan.snmp]
+# Read sysName, sysDescr, uptime and interface tables (off by default)
+# enabled = false
+# communities = ["public"]
+# timeout_ms = 1000
+# retries = 1
+# concurrency = 32
+
+# [scan.snmp.v3]
+# Tried before the communities
+# username = "monitor"
+# auth_protocol = "sha"
+# auth_password = "..."
+# privacy_protocol = "aes"
+# privacy_password = "..."
+
+[scan.port_scan]
+# TCP connect scan of discovered devices (off by default)
+# enabled = false
+# concurrency = 256
+# timeout_ms = 500
+
+[scan.services]
+# Read SSH banners, HTTP server/title and TLS certificates of open ports
+# (runs after the port scan)
+# enabled = true
+# concurrency = 32
+# timeout_ms = 1500
+# device_budget_ms = 5000
+
+[scan.randomized_macs]
+# Phones and laptops pick random MAC addresses. Link a new one to a known
+# device with the same hostname, DHCP client id or mDNS name, and forget
+# offline ones after ghost_ttl_hours (0 = never)
+# link_by_identity = true
+# ghost_ttl_hours = 24
+
+[scan.classify]
+# Extra device type rules, checked ahead of the built-in ones. Default:
+# rules.toml next to this file, if it exists. Each [[rule]] sets a device
+# type and tags when all of its conditions match, e.g.:
+#   [[rule]]
+#   name = "lab-plcs"
+#   device_type = "iot"
+#   tags = ["ot"]
+#   priority = 50
+#   hostname = '^plc-[0-9]+$'
+#   ports = [502]
+# rules_file = "/etc/cartographer/rules.toml"
+
+[scan.oui]
+# MAC vendor names. The agent ships a built-in copy of the IEEE registry;
+# drop newer MA-L/MA-M/MA-S CSV files (oui.csv, mam.csv, oui36.csv from
+# standards-oui.ieee.org) into registry_dir to use them instead. Default:
+# the "oui" folder in the agent data directory.
+# registry_dir = "/var/lib/cartographer/oui"
+#
+# In-house hardware: MAC prefix (6 to 12 hex digits) = vendor name,
+# checked before the registry; the longest matching prefix wins
+# [scan.oui.custom]
+# "F4:A6:E5" = "Meridian Test Rig"
+# "70:B3:D5:F2:C" = "Lab Sensor"
+
+[notifications]
+# Local alerts on device changes, sent even when cloud sync is down.
+# Event types: new, offline, back, ip_changed, mac_changed, hostname_changed
+# enabled = true
+#
+# [[notifications.sinks]]
+# name = "ops-webhook"
+# type = "webhook"
+# url = "https://hooks.example.com/services/T000/B000"
+# headers = { Authorization = "Bearer ..." }
+# JSON body with {{message}}, {{kind}}, {{ip}}, {{mac}}, {{hostname}},
+# {{vendor}}, {{tags}}, {{previous}}, {{current}}, {{device_id}}, {{at}}.
+# Default: the event as JSON
+# template = '{"text": "Cartographer: {{message}}"}'
+#
+# [[notifications.sinks]]
+# name = "syslog"
+# type = "syslog"
+# RFC 5424 over udp://host:port, tcp://host:port or unix:///dev/log
+# address = "udp://10.0.0.5:514"
+# facility = "local0"
+#
+# [[notifications.sinks]]
+# name = "pager"
+# type = "command"
+# Gets the event as JSON on stdin and CARTOGRAPHER_* environment variables
+# command = ["/usr/local/bin/page-oncall", "--team", "network"]
+#
+# Which events go where. Without rules, every event goes to every sink
+# [[notifications.rules]]
+# events = ["new"]
+# tags = ["ot"]
+# exclude_tags = ["guest"]
+# sinks = ["pager", "syslog"]
 "#
     .to_string()
 }
@@ -1,9 +1,20 @@
 //! Cloud synchronization module.
 //!
-//! Provides HTTP client for communicating with the Cartographer cloud API.
+//! Provides HTTP client for communicating with the Cartographer cloud API,
+//! and the outbox that queues uploads while the cloud is unreachable.
 
 mod client;
+pub mod commands;
 pub mod config;
+pub mod outbox;
 
-pub use client::{CloudClient, DeviceCodeResponse, TokenResponse, TokenVerifyResult};
-pub use config::{load_cloud_config, CloudEndpointConfig, ConfigSource};
+pub use client::{
+    CloudClient, DeviceCodeResponse, DeviceHealthResult, ServerError, TokenResponse,
+    TokenVerifyResult, Upload, UploadKind,
+};
+pub use commands::{ClaimResponse, PendingCommand, PollResponse, ResultReport, ResultResponse};
+pub use config::{
+    load_cloud_config, load_outbox_config, load_retry_config, CloudEndpointConfig, ConfigSource,
+    OutboxConfig, RetryConfig,
+};
+pub use outbox::{Delivery, Outbox, OutboxStats};
@@ -0,0 +1,517 @@
+//! Durable queue of uploads that could not be sent
+//!
+//! A scan or health check upload that fails (no uplink, cloud down, expired
+//! token) is written to the `outbox` folder of the agent data directory,
+//! one file per upload, named after its creation time so that a directory
+//! listing gives the replay order. Later uploads queue behind it until the
+//! outbox has been replayed, oldest first, so the cloud receives them in
+//! the order they happened. Failed replays back off exponentially.
+//!
+//! The outbox is capped by total size and by age (`[cloud.outbox]`); the
+//! oldest uploads are dropped first. Uploads the server rejects outright
+//! (a 4xx other than 401, 403, 408 and 429) are dropped instead of
+//! blocking the queue.
+
+use super::client::{CloudClient, ServerError, Upload, UploadKind};
+use super::config::OutboxConfig;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// First retry delay after a failed send
+const BACKOFF_BASE_SECS: u64 = 30;
+/// Longest retry delay
+const BACKOFF_MAX_SECS: u64 = 30 * 60;
+
+/// What happened to an upload handed to the outbox
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delivery {
+    Sent,
+    /// Queued for a later replay; holds the queue depth
+    Queued(usize),
+}
+
+/// Queue depth for status displays
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutboxStats {
+    pub pending: usize,
+    pub bytes: u64,
+    pub oldest: Option<DateTime<Utc>>,
+}
+
+struct Entry {
+    path: PathBuf,
+    created_at: DateTime<Utc>,
+    bytes: u64,
+}
+
+pub struct Outbox {
+    dir: PathBuf,
+    config: OutboxConfig,
+    failures: u32,
+    retry_at: Option<Instant>,
+}
+
+impl Outbox {
+    /// The outbox in the agent data directory (or the temp directory when
+    /// there is none)
+    pub fn open(config: OutboxConfig) -> Self {
+        let dir = super::config::get_data_dir_path()
+            .unwrap_or_else(|| std::env::temp_dir().join("cartographer-agent"))
+            .join("outbox");
+        Self::open_at(dir, config)
+    }
+
+    pub fn open_at(dir: impl Into<PathBuf>, config: OutboxConfig) -> Self {
+        Self {
+            dir: dir.into(),
+            config,
+            failures: 0,
+            retry_at: None,
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn stats(&self) -> OutboxStats {
+        let entries = self.entries();
+        OutboxStats {
+            pending: entries.len(),
+            bytes: entries.iter().map(|e| e.bytes).sum(),
+            oldest: entries.first().map(|e| e.created_at),
+        }
+    }
+
+    /// Whether the backoff after the last failure has passed
+    pub fn is_due(&self) -> bool {
+        self.retry_at.is_none_or(|at| Instant::now() >= at)
+    }
+
+    /// Send an upload, or queue it if the outbox is still waiting to replay
+    /// older ones or the send fails. Errors only when the upload is lost:
+    /// the server rejected it or it could not be written to disk.
+    pub async fn send(&mut self, client: &CloudClient, upload: Upload) -> Result<Delivery> {
+        if !self.is_empty() {
+            self.replay(client).await;
+        }
+        if self.is_empty() {
+            match client.send_upload(&upload).await {
+                Ok(()) => {
+                    self.failures = 0;
+                    self.retry_at = None;
+                    return Ok(Delivery::Sent);
+                }
+                Err(e) if is_rejected(&e) => return Err(e),
+                Err(e) => {
+                    tracing::warn!("Failed to upload {}, queueing it: {:#}", upload.kind, e);
+                    self.record_failure();
+                }
+            }
+        }
+        self.push(&upload)?;
+        Ok(Delivery::Queued(self.len()))
+    }
+
+    /// Replay queued uploads, oldest first, if the backoff has passed.
+    /// Stops at the first one that fails. Returns how many were sent.
+    pub async fn replay(&mut self, client: &CloudClient) -> usize {
+        self.replay_with(async |upload| client.send_upload(upload).await)
+            .await
+    }
+
+    async fn replay_with(&mut self, mut send: impl AsyncFnMut(&Upload) -> Result<()>) -> usize {
+        if !self.is_due() {
+            return 0;
+        }
+        let mut sent = 0;
+        for entry in self.prune() {
+            let upload = match read_upload(&entry.path) {
+                Ok(upload) => upload,
+                Err(e) => {
+                    tracing::warn!("Dropping unreadable queued upload: {:#}", e);
+                    remove(&entry.path);
+                    continue;
+                }
+            };
+            match send(&upload).await {
+                Ok(()) => {
+                    remove(&entry.path);
+                    sent += 1;
+                    self.failures = 0;
+                    self.retry_at = None;
+                }
+                Err(e) if is_rejected(&e) => {
+                    tracing::warn!(
+                        "Server rejected queued {} from {}, dropping it: {:#}",
+                        upload.kind,
+                        upload.created_at,
+                        e
+                    );
+                    remove(&entry.path);
+                }
+                Err(e) => {
+                    self.record_failure();
+                    tracing::debug!("Replay of queued {} failed: {:#}", upload.kind, e);
+                    break;
+                }
+            }
+        }
+        if sent > 0 {
+            tracing::info!(
+                "Replayed {} queued uploads ({} still queued)",
+                sent,
+                self.len()
+            );
+        }
+        sent
+    }
+
+    /// Queue an upload without trying to send it
+    pub fn push(&mut self, upload: &Upload) -> Result<()> {
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create outbox {}", self.dir.display()))?;
+        let content = serde_json::to_vec(upload)?;
+
+        let micros = upload.created_at.timestamp_micros().max(0);
+        let kind = match upload.kind {
+            UploadKind::Scan => "scan",
+            UploadKind::Health => "health",
+        };
+        let mut seq = 0;
+        let path = loop {
+            let path = self
+                .dir
+                .join(format!("{:020}-{:02}-{}.json", micros, seq, kind));
+            if !path.exists() {
+                break path;
+            }
+            seq += 1;
+        };
+        let tmp_path = path.with_extension("tmp");
+        std::fs::write(&tmp_path, &content)
+            .with_context(|| format!("Failed to write {}", tmp_path.display()))?;
+        std::fs::rename(&tmp_path, &path)
+            .with_context(|| format!("Failed to queue upload {}", path.display()))?;
+
+        self.prune();
+        Ok(())
+    }
+
+    /// Delete every queued upload (on disconnect: they belong to the old
+    /// network)
+    pub fn clear(&mut self) -> Result<()> {
+        if self.dir.exists() {
+            std::fs::remove_dir_all(&self.dir)
+                .with_context(|| format!("Failed to delete outbox {}", self.dir.display()))?;
+        }
+        self.failures = 0;
+        self.retry_at = None;
+        Ok(())
+    }
+
+    /// Drop uploads past the age cap, then the oldest ones until the queue
+    /// fits the size cap. Returns what is left, oldest first.
+    fn prune(&self) -> Vec<Entry> {
+        let mut entries = self.entries();
+        let max_age = Duration::hours(self.config.max_age_hours as i64);
+        let cutoff = Utc::now() - max_age;
+        let max_bytes = self.config.max_size_mb.saturating_mul(1024 * 1024);
+
+        let mut total: u64 = entries.iter().map(|e| e.bytes).sum();
+        let mut dropped = 0;
+        entries.retain(|entry| {
+            let expired = self.config.max_age_hours > 0 && entry.created_at < cutoff;
+            let over = max_bytes > 0 && total > max_bytes;
+            if expired || over {
+                remove(&entry.path);
+                total -= entry.bytes;
+                dropped += 1;
+                return false;
+            }
+            true
+        });
+        if dropped > 0 {
+            tracing::warn!(
+                "Upload queue over its limits ({} MB, {} hours): dropped {} oldest uploads",
+                self.config.max_size_mb,
+                self.config.max_age_hours,
+                dropped
+            );
+        }
+        entries
+    }
+
+    /// Queued uploads, oldest first
+    fn entries(&self) -> Vec<Entry> {
+        let Ok(dir) = std::fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        let mut entries: Vec<Entry> = dir
+            .flatten()
+            .filter_map(|item| {
+                let path = item.path();
+                if path.extension().is_none_or(|e| e != "json") {
+                    return None;
+                }
+                let micros: i64 = path
+                    .file_name()?
+                    .to_str()?
+                    .split('-')
+                    .next()?
+                    .parse()
+                    .ok()?;
+                Some(Entry {
+                    created_at: Utc.timestamp_micros(micros).single()?,
+                    bytes: item.metadata().map(|m| m.len()).unwrap_or(0),
+                    path,
+                })
+            })
+            .collect();
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        entries
+    }
+
+    fn record_failure(&mut self) {
+        self.failures = self.failures.saturating_add(1);
+        let delay = BACKOFF_BASE_SECS
+            .saturating_mul(1 << (self.failures - 1).min(16))
+            .min(BACKOFF_MAX_SECS);
+        self.retry_at = Some(Instant::now() + std::time::Duration::from_secs(delay));
+    }
+}
+
+fn is_rejected(error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<ServerError>()
+        .is_some_and(|e| e.is_permanent())
+}
+
+fn read_upload(path: &Path) -> Result<Upload> {
+    let content =
+        std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    serde_json::from_slice(&content).with_context(|| format!("Invalid upload {}", path.display()))
+}
+
+fn remove(path: &Path) {
+    if let Err(e) = std::fs::remove_file(path) {
+        tracing::warn!("Failed to delete {}: {}", path.display(), e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cloud::config::{CloudEndpointConfig, ConfigSource, RetryConfig};
+    use crate::mock_server::mock_server;
+
+    fn upload(kind: UploadKind, created_at: DateTime<Utc>, filler: usize) -> Upload {
+        Upload::new(
+            kind,
+            created_at,
+            serde_json::json!({
+                "timestamp": created_at.to_rfc3339(),
+                "filler": "x".repeat(filler),
+            }),
+        )
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "cartographer-outbox-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_queue_order_and_caps() {
+        let dir = temp_dir("caps");
+        let config = OutboxConfig {
+            max_size_mb: 1,
+            max_age_hours: 24,
+        };
+        let mut outbox = Outbox::open_at(&dir, config);
+        let now = Utc::now();
+
+        // Queued out of order, replayed by creation time
+        outbox
+            .push(&upload(UploadKind::Health, now - Duration::minutes(5), 10))
+            .unwrap();
+        outbox
+            .push(&upload(UploadKind::Scan, now - Duration::minutes(10), 10))
+            .unwrap();
+        outbox
+            .push(&upload(UploadKind::Scan, now - Duration::minutes(10), 10))
+            .unwrap();
+        let kinds: Vec<UploadKind> = outbox
+            .entries()
+            .iter()
+            .map(|e| read_upload(&e.path).unwrap().kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            [UploadKind::Scan, UploadKind::Scan, UploadKind::Health]
+        );
+        assert_eq!(
+            outbox.stats().oldest.unwrap().timestamp(),
+            (now - Duration::minutes(10)).timestamp()
+        );
+
+        // Too old
+        outbox
+            .push(&upload(UploadKind::Scan, now - Duration::hours(25), 10))
+            .unwrap();
+        assert_eq!(outbox.len(), 3);
+
+        // Over 1 MB: the oldest go first
+        outbox
+            .push(&upload(UploadKind::Scan, now, 700 * 1024))
+            .unwrap();
+        outbox
+            .push(&upload(
+                UploadKind::Scan,
+                now + Duration::seconds(1),
+                700 * 1024,
+            ))
+            .unwrap();
+        let stats = outbox.stats();
+        assert_eq!(stats.pending, 1);
+        assert!(stats.bytes <= 1024 * 1024);
+
+        outbox.clear().unwrap();
+        assert!(outbox.is_empty());
+    }
+
+    #[test]
+    fn test_backoff() {
+        let mut outbox = Outbox::open_at(temp_dir("backoff"), OutboxConfig::default());
+        assert!(outbox.is_due());
+        outbox.record_failure();
+        assert!(!outbox.is_due());
+        for _ in 0..40 {
+            outbox.record_failure();
+        }
+        let wait = outbox.retry_at.unwrap() - Instant::now();
+        assert!(wait <= std::time::Duration::from_secs(BACKOFF_MAX_SECS));
+    }
+
+    /// Queue uploads a minute apart, oldest first
+    fn queued(name: &str, count: i64) -> (Outbox, Vec<Upload>) {
+        let mut outbox = Outbox::open_at(temp_dir(name), OutboxConfig::default());
+        let start = Utc::now() - Duration::hours(1);
+        let uploads: Vec<Upload> = (0..count)
+            .map(|i| upload(UploadKind::Health, start + Duration::minutes(i), 10))
+            .collect();
+        for upload in &uploads {
+            outbox.push(upload).unwrap();
+        }
+        (outbox, uploads)
+    }
+
+    /// Replay against `url` with a fixed token and no client-side retries
+    async fn replay(outbox: &mut Outbox, url: &str) -> usize {
+        let client = CloudClient::with_config(CloudEndpointConfig {
+            api_url: url.to_string(),
+            dashboard_url: url.to_string(),
+            source: ConfigSource::Default,
+        })
+        .with_retry(RetryConfig {
+            max_retries: 0,
+            ..Default::default()
+        });
+        outbox
+            .replay_with(async |upload| client.post_upload("token", upload).await)
+            .await
+    }
+
+    fn keys(outbox: &Outbox) -> Vec<String> {
+        outbox
+            .entries()
+            .iter()
+            .map(|e| read_upload(&e.path).unwrap().idempotency_key)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_replay_stops_at_first_failure() {
+        let (mut outbox, uploads) = queued("replay-order", 3);
+        let (url, requests) = mock_server(vec![(200, ""), (503, "")]).await;
+
+        assert_eq!(replay(&mut outbox, &url).await, 1);
+        let requests = requests.lock().unwrap().clone();
+        assert_eq!(requests.len(), 2);
+        for (request, upload) in requests.iter().zip(&uploads) {
+            assert!(
+                request.head.contains(&upload.idempotency_key),
+                "{}",
+                request.head
+            );
+        }
+
+        // The failed upload and the one behind it stay, in order
+        assert_eq!(
+            keys(&outbox),
+            [
+                uploads[1].idempotency_key.clone(),
+                uploads[2].idempotency_key.clone()
+            ]
+        );
+        assert!(!outbox.is_due());
+        assert_eq!(replay(&mut outbox, &url).await, 0);
+        outbox.clear().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_replay_drops_rejected_and_unreadable() {
+        let (mut outbox, uploads) = queued("replay-drop", 2);
+        // Oldest of all, and not an upload
+        let garbage = outbox.dir().join(format!("{:020}-00-scan.json", 1));
+        std::fs::write(&garbage, b"{not json").unwrap();
+        assert_eq!(outbox.len(), 3);
+
+        let (url, requests) = mock_server(vec![(422, ""), (200, "")]).await;
+        assert_eq!(replay(&mut outbox, &url).await, 1);
+        assert!(outbox.is_empty());
+        assert!(!garbage.exists());
+
+        // The unreadable file was never sent; the rejected one did not
+        // block the one behind it
+        let requests = requests.lock().unwrap().clone();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[0].head.contains(&uploads[0].idempotency_key));
+        assert!(requests[1].head.contains(&uploads[1].idempotency_key));
+        assert!(outbox.is_due());
+    }
+
+    #[tokio::test]
+    async fn test_replay_success_resets_backoff() {
+        let (mut outbox, _) = queued("replay-backoff", 2);
+        let (url, _) = mock_server(vec![(500, ""), (200, ""), (200, "")]).await;
+
+        assert_eq!(replay(&mut outbox, &url).await, 0);
+        assert_eq!(outbox.failures, 1);
+        assert!(!outbox.is_due());
+
+        // Pretend the backoff has passed
+        outbox.retry_at = Some(Instant::now());
+        assert_eq!(replay(&mut outbox, &url).await, 2);
+        assert_eq!(outbox.failures, 0);
+        assert_eq!(outbox.retry_at, None);
+        assert!(outbox.is_empty());
+    }
+}
@@ -0,0 +1,330 @@
+//! Device change events
+//!
+//! Merging a scan or a health check into the inventory reports what changed
+//! since the last one: a new device, a device that went offline or came
+//! back, and a new IP address, MAC address or hostname. The events are kept
+//! in `inventory.changes.jsonl` next to the inventory, newest last, trimmed
+//! to the most recent `MAX_EVENTS`.
+
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::Path;
+
+/// Events kept in the change log
+const MAX_EVENTS: usize = 10_000;
+
+/// What changed about a device
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    /// First time the device was seen
+    New,
+    /// Not found by a scan, or not answering a health check
+    Offline,
+    /// Seen again after being offline
+    Back,
+    IpChanged,
+    MacChanged,
+    HostnameChanged,
+}
+
+impl std::fmt::Display for ChangeKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChangeKind::New => write!(f, "new"),
+            ChangeKind::Offline => write!(f, "offline"),
+            ChangeKind::Back => write!(f, "back"),
+            ChangeKind::IpChanged => write!(f, "ip_changed"),
+            ChangeKind::MacChanged => write!(f, "mac_changed"),
+            ChangeKind::HostnameChanged => write!(f, "hostname_changed"),
+        }
+    }
+}
+
+/// A change to one device
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangeEvent {
+    pub at: DateTime<Utc>,
+    pub kind: ChangeKind,
+    /// Inventory id of the device
+    pub device_id: String,
+    /// The device's primary IP address after the change
+    pub ip: String,
+    pub hostname: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mac: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vendor: Option<String>,
+    /// Classification tags of the device, for alert rules
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Old value of a changed IP, MAC or hostname
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous: Option<String>,
+    /// New value of a changed IP, MAC or hostname
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub current: Option<String>,
+}
+
+impl std::fmt::Display for ChangeEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match &self.hostname {
+            Some(hostname) => format!("{} ({})", self.ip, hostname),
+            None => self.ip.clone(),
+        };
+        let previous = self.previous.as_deref().unwrap_or("-");
+        let current = self.current.as_deref().unwrap_or("-");
+        match self.kind {
+            ChangeKind::New => write!(f, "new device {}", name),
+            ChangeKind::Offline => write!(f, "{} went offline", name),
+            ChangeKind::Back => write!(f, "{} is back online", name),
+            ChangeKind::IpChanged => write!(f, "{} changed IP from {}", name, previous),
+            ChangeKind::MacChanged => {
+                write!(f, "{} changed MAC from {} to {}", name, previous, current)
+            }
+            ChangeKind::HostnameChanged => {
+                write!(f, "{} changed hostname from {}", name, previous)
+            }
+        }
+    }
+}
+
+/// Append events to the change log, trimming it once it holds twice
+/// `MAX_EVENTS`
+pub fn append(path: &Path, events: &[ChangeEvent]) -> Result<()> {
+    if events.is_empty() {
+        return Ok(());
+    }
+    let mut buffer = Vec::new();
+    for event in events {
+        serde_json::to_writer(&mut buffer, event)?;
+        buffer.push(b'\n');
+    }
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).context("Failed to create data directory")?;
+    }
+    let mut file = super::store::open_append(path)
+        .with_context(|| format!("Failed to open change log {}", path.display()))?;
+    file.write_all(&buffer)
+        .with_context(|| format!("Failed to write change log {}", path.display()))?;
+    drop(file);
+
+    let size = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    // Cheap check first: events are a few hundred bytes each
+    if size > (MAX_EVENTS * 2 * 128) as u64 {
+        let all = read(path)?;
+        if all.len() > MAX_EVENTS * 2 {
+            trim(path, &all[all.len() - MAX_EVENTS..])?;
+        }
+    }
+    Ok(())
+}
+
+/// Events at or after `since`, oldest first
+pub fn read_since(path: &Path, since: DateTime<Utc>) -> Result<Vec<ChangeEvent>> {
+    Ok(read(path)?.into_iter().filter(|e| e.at >= since).collect())
+}
+
+/// Delete the change log
+pub fn clear(path: &Path) -> Result<()> {
+    if path.exists() {
+        std::fs::remove_file(path)
+            .with_context(|| format!("Failed to delete change log {}", path.display()))?;
+    }
+    Ok(())
+}
+
+fn read(path: &Path) -> Result<Vec<ChangeEvent>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read change log {}", path.display()))?;
+    // Lines cut short by a crash are skipped
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+fn trim(path: &Path, keep: &[ChangeEvent]) -> Result<()> {
+    let mut buffer = Vec::new();
+    for event in keep {
+        serde_json::to_writer(&mut buffer, event)?;
+        buffer.push(b'\n');
+    }
+    let tmp_path = path.with_extension("jsonl.tmp");
+    std::fs::write(&tmp_path, &buffer)
+        .with_context(|| format!("Failed to write {}", tmp_path.display()))?;
+    std::fs::rename(&tmp_path, path)
+        .with_context(|| format!("Failed to replace change log {}", path.display()))
+}
+
+/// Parse a `--since` value: a duration back from `now` ("30m", "24h", "7d"),
+/// a date ("2026-03-01", midnight UTC) or an RFC 3339 timestamp
+pub fn parse_since(value: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>> {
+    let value = value.trim();
+    if let Ok(at) = DateTime::parse_from_rfc3339(value) {
+        return Ok(at.with_timezone(&Utc));
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc());
+    }
+
+    let split = value.len().saturating_sub(1);
+    let (amount, unit) = (value.get(..split), value.get(split..));
+    let amount = amount.and_then(|a| a.parse::<i64>().ok());
+    let unit_secs = match unit {
+        Some("s") => 1,
+        Some("m") => 60,
+        Some("h") => 3600,
+        Some("d") => 86_400,
+        Some("w") => 7 * 86_400,
+        _ => 0,
+    };
+    let Some(amount) = amount.filter(|a| *a >= 0 && unit_secs > 0) else {
+        bail!(
+            "'{}' is not a duration (30m, 24h, 7d), date (2026-03-01) or RFC 3339 time",
+            value
+        );
+    };
+    amount
+        .checked_mul(unit_secs)
+        .and_then(chrono::Duration::try_seconds)
+        .and_then(|ago| now.checked_sub_signed(ago))
+        .with_context(|| format!("'{}' is too far back", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_log(name: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "cartographer-{}-{}.changes.jsonl",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    fn event(at: DateTime<Utc>, kind: ChangeKind, ip: &str) -> ChangeEvent {
+        ChangeEvent {
+            at,
+            kind,
+            device_id: format!("ip:{}", ip),
+            ip: ip.to_string(),
+            hostname: None,
+            mac: None,
+            vendor: None,
+            tags: Vec::new(),
+            previous: None,
+            current: None,
+        }
+    }
+
+    #[test]
+    fn test_append_and_read_since() {
+        let path = temp_log("change-log");
+        let now = Utc::now();
+        let mut renamed = event(now, ChangeKind::HostnameChanged, "192.168.1.20");
+        renamed.hostname = Some("workstation".to_string());
+        renamed.vendor = Some("Dell".to_string());
+        renamed.tags = vec!["workstation".to_string()];
+        renamed.previous = Some("desktop".to_string());
+        renamed.current = Some("workstation".to_string());
+
+        assert!(read_since(&path, now).unwrap().is_empty());
+        append(&path, &[]).unwrap();
+        assert!(
+            !path.exists(),
+            "nothing to write should not create the file"
+        );
+
+        let old = event(
+            now - chrono::Duration::days(2),
+            ChangeKind::New,
+            "192.168.1.20",
+        );
+        append(&path, std::slice::from_ref(&old)).unwrap();
+        append(&path, std::slice::from_ref(&renamed)).unwrap();
+        // A crash in the middle of a write leaves half a line behind
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap();
+        file.write_all(b"{\"at\":\"2026-").unwrap();
+        drop(file);
+        // ... which must not swallow the next event
+        let back = event(
+            now + chrono::Duration::seconds(1),
+            ChangeKind::Back,
+            "192.168.1.30",
+        );
+        append(&path, std::slice::from_ref(&back)).unwrap();
+
+        let all = read_since(&path, now - chrono::Duration::days(7)).unwrap();
+        assert_eq!(all, [old, renamed.clone(), back.clone()]);
+        assert_eq!(read_since(&path, now).unwrap(), [renamed, back.clone()]);
+        assert_eq!(
+            read_since(&path, now + chrono::Duration::seconds(1)).unwrap(),
+            [back]
+        );
+        assert!(
+            read_since(&path, now + chrono::Duration::seconds(2))
+                .unwrap()
+                .is_empty()
+        );
+
+        clear(&path).unwrap();
+        assert!(!path.exists());
+        clear(&path).unwrap();
+    }
+
+    #[test]
+    fn test_append_trims_to_newest() {
+        let path = temp_log("change-log-trim");
+        let start = Utc::now();
+        let events: Vec<ChangeEvent> = (0..MAX_EVENTS * 2 + 1)
+            .map(|i| {
+                let mut e = event(
+                    start + chrono::Duration::seconds(i as i64),
+                    ChangeKind::Offline,
+                    "192.168.1.30",
+                );
+                e.hostname = Some("a-device-with-a-longish-hostname.lan".to_string());
+                e
+            })
+            .collect();
+        append(&path, &events).unwrap();
+
+        let kept = read_since(&path, start).unwrap();
+        assert_eq!(kept.len(), MAX_EVENTS);
+        assert_eq!(kept.last(), events.last());
+        assert_eq!(kept[0], events[events.len() - MAX_EVENTS]);
+        assert!(!path.with_extension("jsonl.tmp").exists());
+        clear(&path).unwrap();
+    }
+
+    #[test]
+    fn test_parse_since() {
+        let now = DateTime::parse_from_rfc3339("2026-03-10T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let at = |s: &str| parse_since(s, now).unwrap().to_rfc3339();
+
+        assert_eq!(at("24h"), "2026-03-09T12:00:00+00:00");
+        assert_eq!(at("7d"), "2026-03-03T12:00:00+00:00");
+        assert_eq!(at("30m"), "2026-03-10T11:30:00+00:00");
+        assert_eq!(at("2026-03-01"), "2026-03-01T00:00:00+00:00");
+        assert_eq!(at("2026-03-05T08:00:00+01:00"), "2026-03-05T07:00:00+00:00");
+        for bad in ["", "yesterday", "5x", "-3h", "h", "9999999999999w"] {
+            assert!(parse_since(bad, now).is_err(), "{} should be rejected", bad);
+        }
+    }
+}
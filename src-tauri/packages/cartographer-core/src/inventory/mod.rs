@@ -0,0 +1,1011 @@
+//! Local device inventory
+//!
+//! Keeps every device the agent has seen across scans and restarts, keyed by
+//! a stable id assigned when the device first appears: when it was first and
+//! last seen, the addresses, MACs and hostnames it has used, and when it went
+//! offline and came back. Scan results are merged in by IP, then MAC, then
+//! identity (hostname, DHCP client id, mDNS name) for devices with random
+//! MACs, so a device keeps its record through DHCP churn and MAC rotation.
+//!
+//! The inventory lives in `inventory.jsonl` in the agent data directory, an
+//! append-only journal (see `store`), and what changed in each merge goes to
+//! the change log next to it (see `changes`). Both the CLI daemon and the
+//! desktop app's scheduler keep their device list here.
+
+pub mod changes;
+mod store;
+
+pub use changes::{ChangeEvent, ChangeKind};
+
+use crate::scanner::config::RandomizedMacConfig;
+use crate::scanner::{Device, NetworkInfo, identity, oui};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use store::Journal;
+
+const INVENTORY_FILE: &str = "inventory.jsonl";
+
+/// Addresses, MACs and hostnames kept per device, most recent first
+const MAX_SIGHTINGS: usize = 32;
+
+/// Online/offline transitions kept per device
+const MAX_TRANSITIONS: usize = 64;
+
+/// A device in the inventory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InventoryRecord {
+    /// Assigned when the device first appeared; never changes
+    pub id: String,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    /// Found by the last scan or answering the last health check
+    pub online: bool,
+    /// The device as last seen (`response_time_ms` is `None` while offline)
+    pub device: Device,
+    /// IP addresses the device has used
+    #[serde(default)]
+    pub addresses: Vec<Sighting>,
+    /// MAC addresses the device has used
+    #[serde(default)]
+    pub macs: Vec<Sighting>,
+    /// Hostnames the device has had
+    #[serde(default)]
+    pub hostnames: Vec<Sighting>,
+    /// Online/offline changes, oldest first
+    #[serde(default)]
+    pub transitions: Vec<Transition>,
+}
+
+/// A value (address, MAC, hostname) and when the device was seen with it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Sighting {
+    pub value: String,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+}
+
+/// The device went online or offline
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Transition {
+    pub at: DateTime<Utc>,
+    pub online: bool,
+}
+
+/// Device inventory, loaded from and saved to the journal file
+#[derive(Default)]
+pub struct Inventory {
+    records: Vec<InventoryRecord>,
+    journal: Option<Journal>,
+    /// Ids of records changed since the last save
+    changed: BTreeSet<String>,
+    /// Ids of records forgotten since the last save
+    removed: Vec<String>,
+    changes_path: Option<PathBuf>,
+    /// Change events not yet written to the change log
+    pending_changes: Vec<ChangeEvent>,
+}
+
+/// Path of the inventory file in the agent data directory
+pub fn default_path() -> Option<PathBuf> {
+    crate::cloud::config::get_data_dir_path().map(|d| d.join(INVENTORY_FILE))
+}
+
+impl Inventory {
+    /// An inventory that is never saved
+    pub fn in_memory() -> Self {
+        Self::default()
+    }
+
+    /// Load the inventory from the agent data directory
+    pub fn open() -> Result<Self> {
+        let path = default_path().context("Could not find data directory")?;
+        Self::open_at(&path)
+    }
+
+    /// Load the inventory from a journal file (created on first save)
+    pub fn open_at(path: &Path) -> Result<Self> {
+        let (journal, records) = Journal::open(path)?;
+        tracing::debug!(
+            "Loaded inventory {}: {} devices",
+            path.display(),
+            records.len()
+        );
+        Ok(Self {
+            records,
+            journal: Some(journal),
+            changes_path: Some(path.with_extension("changes.jsonl")),
+            ..Default::default()
+        })
+    }
+
+    /// Load the inventory, or start an unsaved one if the file cannot be read
+    pub fn open_or_in_memory() -> Self {
+        Self::open().unwrap_or_else(|e| {
+            tracing::warn!("{:#}; keeping the device inventory in memory only", e);
+            Self::in_memory()
+        })
+    }
+
+    /// Journal file, if the inventory is saved
+    pub fn path(&self) -> Option<&Path> {
+        self.journal.as_ref().map(Journal::path)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// All records, in the order the devices first appeared
+    pub fn records(&self) -> &[InventoryRecord] {
+        &self.records
+    }
+
+    /// Current device list: online devices and offline ones still kept
+    pub fn devices(&self) -> Vec<Device> {
+        self.records.iter().map(|r| r.device.clone()).collect()
+    }
+
+    /// Saved change events at or after `since`, oldest first
+    pub fn changes_since(&self, since: DateTime<Utc>) -> Result<Vec<ChangeEvent>> {
+        let mut events = match &self.changes_path {
+            Some(path) => changes::read_since(path, since)?,
+            None => Vec::new(),
+        };
+        events.extend(
+            self.pending_changes
+                .iter()
+                .filter(|e| e.at >= since)
+                .cloned(),
+        );
+        Ok(events)
+    }
+
+    /// Merge a scan into the inventory.
+    ///
+    /// Each scanned device is matched to a record by IP first, then MAC,
+    /// then (with `link_by_identity`) by hostname, DHCP client id or mDNS
+    /// name when one side has a random MAC. Matched devices keep the health
+    /// data, hostname, vendor and type the scan did not find; unmatched ones
+    /// get a new record. Records no scanned device matched are marked
+    /// offline, except that records outside the scanned subnets are dropped
+    /// (VPN, containers, virtual adapters) and records with random MACs are
+    /// forgotten after `ghost_ttl_hours` offline.
+    ///
+    /// Returns what changed: new devices, devices gone offline or back, and
+    /// changed IPs, MACs and hostnames. They are saved with the inventory.
+    pub fn merge(
+        &mut self,
+        devices: Vec<Device>,
+        network_info: &NetworkInfo,
+        config: &RandomizedMacConfig,
+        now: DateTime<Utc>,
+    ) -> Vec<ChangeEvent> {
+        let mut matched = vec![false; self.records.len()];
+        let mut events = Vec::new();
+
+        for mut device in devices {
+            device.last_seen = Some(now);
+            match self.find_match(&device, &matched, config) {
+                Some(index) => {
+                    matched[index] = true;
+                    let record = &mut self.records[index];
+                    preserve_missing(&mut device, &record.device);
+                    let old = std::mem::replace(&mut record.device, device);
+                    record.last_seen = now;
+                    observe(record, now);
+                    if set_online(record, true, now) {
+                        events.push(change(record, ChangeKind::Back, now, None, None));
+                    }
+                    events.extend(field_changes(record, &old, now));
+                    self.changed.insert(record.id.clone());
+                }
+                None => {
+                    let id = self.new_id(&device);
+                    tracing::info!("New device {} ({})", device.primary_ip(), id);
+                    let mut record = InventoryRecord {
+                        id,
+                        first_seen: now,
+                        last_seen: now,
+                        online: true,
+                        device,
+                        addresses: Vec::new(),
+                        macs: Vec::new(),
+                        hostnames: Vec::new(),
+                        transitions: vec![Transition {
+                            at: now,
+                            online: true,
+                        }],
+                    };
+                    observe(&mut record, now);
+                    events.push(change(&record, ChangeKind::New, now, None, None));
+                    self.changed.insert(record.id.clone());
+                    self.records.push(record);
+                }
+            }
+        }
+
+        let Self {
+            records,
+            changed,
+            removed,
+            ..
+        } = self;
+        let mut index = 0;
+        records.retain_mut(|record| {
+            let was_matched = matched.get(index).copied().unwrap_or(true);
+            index += 1;
+            if was_matched {
+                return true;
+            }
+
+            // Old random MACs of devices that have since picked new ones
+            if identity::is_expired_ghost(&record.device, config, now) {
+                tracing::info!(
+                    "Forgetting device {} ({:?}): randomized MAC offline for over {}h",
+                    record.device.primary_ip(),
+                    record.device.mac,
+                    config.ghost_ttl_hours
+                );
+                removed.push(record.id.clone());
+                return false;
+            }
+
+            // Drop out-of-subnet devices instead of keeping them as offline
+            if let Some(Ok(ip)) = record.device.ipv4().map(|a| a.parse::<std::net::IpAddr>())
+                && !network_info.contains(ip)
+            {
+                tracing::info!(
+                    "Dropping out-of-subnet device {} (not in {})",
+                    record.device.primary_ip(),
+                    network_info.subnets().join(", ")
+                );
+                removed.push(record.id.clone());
+                return false;
+            }
+
+            record.device.response_time_ms = None;
+            if set_online(record, false, now) {
+                tracing::info!(
+                    "Device {} not found in scan, marking as offline",
+                    record.device.primary_ip()
+                );
+                events.push(change(record, ChangeKind::Offline, now, None, None));
+            }
+            changed.insert(record.id.clone());
+            true
+        });
+        changed.retain(|id| !removed.contains(id));
+
+        self.pending_changes.extend(events.iter().cloned());
+        events
+    }
+
+    /// Record health check results: (IP, response time, `None` if
+    /// unreachable). Returns the devices that went offline or came back.
+    pub fn record_health(
+        &mut self,
+        results: &[(String, Option<f64>)],
+        now: DateTime<Utc>,
+    ) -> Vec<ChangeEvent> {
+        let mut events = Vec::new();
+        for (ip, response_time) in results {
+            let Some(record) = self.records.iter_mut().find(|r| r.device.has_address(ip)) else {
+                continue;
+            };
+            record.device.response_time_ms = *response_time;
+            if response_time.is_some() {
+                record.last_seen = now;
+                record.device.last_seen = Some(now);
+            }
+            if set_online(record, response_time.is_some(), now) {
+                let kind = if record.online {
+                    ChangeKind::Back
+                } else {
+                    ChangeKind::Offline
+                };
+                events.push(change(record, kind, now, None, None));
+            }
+            self.changed.insert(record.id.clone());
+        }
+        self.pending_changes.extend(events.iter().cloned());
+        events
+    }
+
+    /// Add devices saved before the inventory existed, keeping their last
+    /// seen time as the first one known
+    pub fn import(&mut self, devices: Vec<Device>, now: DateTime<Utc>) {
+        for device in devices {
+            let seen = device.last_seen.unwrap_or(now);
+            let online = device.response_time_ms.is_some();
+            let mut record = InventoryRecord {
+                id: self.new_id(&device),
+                first_seen: seen,
+                last_seen: seen,
+                online,
+                device,
+                addresses: Vec::new(),
+                macs: Vec::new(),
+                hostnames: Vec::new(),
+                transitions: vec![Transition { at: seen, online }],
+            };
+            observe(&mut record, seen);
+            self.changed.insert(record.id.clone());
+            self.records.push(record);
+        }
+    }
+
+    /// Forget every device and delete the journal file and change log
+    pub fn clear(&mut self) -> Result<()> {
+        self.records.clear();
+        self.changed.clear();
+        self.removed.clear();
+        self.pending_changes.clear();
+        if let Some(path) = &self.changes_path {
+            changes::clear(path)?;
+        }
+        match &mut self.journal {
+            Some(journal) => journal.delete(),
+            None => Ok(()),
+        }
+    }
+
+    /// Write the changes since the last save to the journal, and the
+    /// change events to the change log
+    pub fn save(&mut self) -> Result<()> {
+        let Some(journal) = &mut self.journal else {
+            self.changed.clear();
+            self.removed.clear();
+            self.pending_changes.clear();
+            return Ok(());
+        };
+
+        if journal.needs_compaction(self.records.len()) {
+            journal.compact(&self.records)?;
+        } else {
+            let changed = self.records.iter().filter(|r| self.changed.contains(&r.id));
+            journal.append(changed, &self.removed)?;
+        }
+        self.changed.clear();
+        self.removed.clear();
+
+        if let Some(path) = &self.changes_path {
+            changes::append(path, &self.pending_changes)?;
+        }
+        self.pending_changes.clear();
+        Ok(())
+    }
+
+    fn find_match(
+        &self,
+        device: &Device,
+        matched: &[bool],
+        config: &RandomizedMacConfig,
+    ) -> Option<usize> {
+        let unmatched = || {
+            self.records
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| !matched.get(*i).copied().unwrap_or(true))
+        };
+
+        // IP first; a different MAC on the same IP is a replaced NIC
+        if let Some((index, record)) =
+            unmatched().find(|(_, r)| device.addresses.iter().any(|a| r.device.has_address(a)))
+        {
+            if let Some(new_mac) = &device.mac
+                && record.device.mac.as_deref().map(normalize_mac) != Some(normalize_mac(new_mac))
+            {
+                tracing::info!(
+                    "Device {} MAC changed from {:?} to {}",
+                    device.primary_ip(),
+                    record.device.mac,
+                    new_mac
+                );
+            }
+            return Some(index);
+        }
+
+        let new_mac = device.mac.as_deref()?;
+        // Same MAC on a new IP: DHCP churn
+        if let Some((index, record)) = unmatched().find(|(_, r)| {
+            r.device.mac.as_deref().map(normalize_mac) == Some(normalize_mac(new_mac))
+        }) {
+            tracing::info!(
+                "Device MAC {} moved from IP {} to {}",
+                new_mac,
+                record.device.primary_ip(),
+                device.primary_ip()
+            );
+            return Some(index);
+        }
+
+        // A phone or laptop may have picked a new random MAC
+        if !config.link_by_identity {
+            return None;
+        }
+        let (found, key) = identity::find_same_device(device, unmatched().map(|(_, r)| &r.device))?;
+        let index = self
+            .records
+            .iter()
+            .position(|r| std::ptr::eq(&r.device, found))?;
+        tracing::info!(
+            "Device {} ({}) is {} ({:?}) with a new MAC, linked by {}",
+            device.primary_ip(),
+            new_mac,
+            found.primary_ip(),
+            found.mac,
+            key
+        );
+        Some(index)
+    }
+
+    /// "mac:aa:bb:cc:dd:ee:ff" for vendor-assigned MACs, else "ip:..."; a
+    /// suffix keeps it unique
+    fn new_id(&self, device: &Device) -> String {
+        let base = match device.mac.as_deref() {
+            Some(mac) if !oui::is_randomized_mac(mac) => format!("mac:{}", normalize_mac(mac)),
+            _ => format!("ip:{}", device.primary_ip()),
+        };
+        let taken = |id: &str| self.records.iter().any(|r| r.id == id);
+        if !taken(&base) {
+            return base;
+        }
+        (2..)
+            .map(|n| format!("{}#{}", base, n))
+            .find(|id| !taken(id))
+            .unwrap_or(base)
+    }
+}
+
+/// Keep what the previous sighting knew and this scan did not find
+fn preserve_missing(device: &mut Device, old: &Device) {
+    if (device.response_time_ms.is_none() || device.response_time_ms == Some(0.0))
+        && old.response_time_ms.is_some()
+    {
+        device.response_time_ms = old.response_time_ms;
+    }
+    if device.hostname.is_none() {
+        device.hostname = old.hostname.clone();
+    }
+    if device.vendor.is_none() {
+        device.vendor = old.vendor.clone();
+    }
+    if device.device_type.is_none() {
+        device.device_type = old.device_type.clone();
+    }
+}
+
+/// Add the device's current addresses, MAC and hostname to its history
+fn observe(record: &mut InventoryRecord, now: DateTime<Utc>) {
+    for address in &record.device.addresses {
+        note(&mut record.addresses, address, now);
+    }
+    if let Some(mac) = &record.device.mac {
+        note(&mut record.macs, &normalize_mac(mac), now);
+    }
+    if let Some(hostname) = &record.device.hostname {
+        note(&mut record.hostnames, hostname, now);
+    }
+}
+
+fn note(sightings: &mut Vec<Sighting>, value: &str, now: DateTime<Utc>) {
+    match sightings.iter_mut().find(|s| s.value == value) {
+        Some(sighting) => sighting.last_seen = sighting.last_seen.max(now),
+        None => sightings.push(Sighting {
+            value: value.to_string(),
+            first_seen: now,
+            last_seen: now,
+        }),
+    }
+    sightings.sort_by_key(|s| std::cmp::Reverse(s.last_seen));
+    sightings.truncate(MAX_SIGHTINGS);
+}
+
+/// Returns whether the device went online or offline
+fn set_online(record: &mut InventoryRecord, online: bool, now: DateTime<Utc>) -> bool {
+    if record.online == online {
+        return false;
+    }
+    record.online = online;
+    record.transitions.push(Transition { at: now, online });
+    if record.transitions.len() > MAX_TRANSITIONS {
+        let excess = record.transitions.len() - MAX_TRANSITIONS;
+        record.transitions.drain(..excess);
+    }
+    true
+}
+
+fn change(
+    record: &InventoryRecord,
+    kind: ChangeKind,
+    now: DateTime<Utc>,
+    previous: Option<String>,
+    current: Option<String>,
+) -> ChangeEvent {
+    ChangeEvent {
+        at: now,
+        kind,
+        device_id: record.id.clone(),
+        ip: record.device.primary_ip().to_string(),
+        hostname: record.device.hostname.clone(),
+        mac: record.device.mac.clone(),
+        vendor: record.device.vendor.clone(),
+        tags: record.device.tags.clone(),
+        previous,
+        current,
+    }
+}
+
+/// IP, MAC and hostname changes between the old and the updated device
+fn field_changes(record: &InventoryRecord, old: &Device, now: DateTime<Utc>) -> Vec<ChangeEvent> {
+    let new = &record.device;
+    let mut events = Vec::new();
+    if !old.primary_ip().is_empty() && old.primary_ip() != new.primary_ip() {
+        events.push(change(
+            record,
+            ChangeKind::IpChanged,
+            now,
+            Some(old.primary_ip().to_string()),
+            Some(new.primary_ip().to_string()),
+        ));
+    }
+    if let (Some(old_mac), Some(new_mac)) = (&old.mac, &new.mac)
+        && normalize_mac(old_mac) != normalize_mac(new_mac)
+    {
+        events.push(change(
+            record,
+            ChangeKind::MacChanged,
+            now,
+            Some(normalize_mac(old_mac)),
+            Some(normalize_mac(new_mac)),
+        ));
+    }
+    if let (Some(old_name), Some(new_name)) = (&old.hostname, &new.hostname)
+        && !old_name.eq_ignore_ascii_case(new_name)
+    {
+        events.push(change(
+            record,
+            ChangeKind::HostnameChanged,
+            now,
+            Some(old_name.clone()),
+            Some(new_name.clone()),
+        ));
+    }
+    events
+}
+
+/// "AA-BB-CC-DD-EE-FF" -> "aa:bb:cc:dd:ee:ff"
+fn normalize_mac(mac: &str) -> String {
+    let hex: String = mac
+        .chars()
+        .filter(|c| c.is_ascii_hexdigit())
+        .collect::<String>()
+        .to_lowercase();
+    hex.as_bytes()
+        .chunks(2)
+        .map(|chunk| std::str::from_utf8(chunk).unwrap_or(""))
+        .collect::<Vec<&str>>()
+        .join(":")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::ScanTarget;
+    use chrono::Duration;
+
+    fn device(ip: &str, mac: &str, hostname: Option<&str>) -> Device {
+        Device {
+            addresses: vec![ip.to_string()],
+            mac: Some(mac.to_string()),
+            randomized_mac: oui::is_randomized_mac(mac),
+            hostname: hostname.map(String::from),
+            response_time_ms: Some(1.5),
+            ..Default::default()
+        }
+    }
+
+    fn lan() -> NetworkInfo {
+        NetworkInfo {
+            targets: vec![ScanTarget {
+                interface: "eth0".to_string(),
+                subnet: "192.168.1.0/24".to_string(),
+                gateway_ip: Some("192.168.1.1".to_string()),
+                local_ip: Some("192.168.1.10".to_string()),
+                ipv6_prefixes: Vec::new(),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_merge_tracks_history() {
+        let config = RandomizedMacConfig::default();
+        let t0 = Utc::now() - Duration::hours(3);
+        let t1 = t0 + Duration::hours(1);
+        let t2 = t1 + Duration::hours(1);
+        let mut inventory = Inventory::in_memory();
+
+        let events = inventory.merge(
+            vec![
+                device("192.168.1.20", "3C:52:82:1A:2B:3C", Some("desktop")),
+                device("192.168.1.30", "00:17:f2:00:00:01", None),
+            ],
+            &lan(),
+            &config,
+            t0,
+        );
+        assert_eq!(inventory.len(), 2);
+        assert!(events.iter().all(|e| e.kind == ChangeKind::New));
+        assert_eq!(events.len(), 2);
+        assert_eq!(inventory.records()[0].id, "mac:3c:52:82:1a:2b:3c");
+
+        // The desktop got a new lease and the Mac went away
+        let mut moved = device("192.168.1.21", "3c-52-82-1a-2b-3c", None);
+        moved.response_time_ms = None;
+        let events = inventory.merge(vec![moved], &lan(), &config, t1);
+        let kinds: Vec<ChangeKind> = events.iter().map(|e| e.kind).collect();
+        assert_eq!(kinds, [ChangeKind::IpChanged, ChangeKind::Offline]);
+        assert_eq!(events[0].previous.as_deref(), Some("192.168.1.20"));
+        assert_eq!(events[0].current.as_deref(), Some("192.168.1.21"));
+        assert_eq!(
+            events[0].to_string(),
+            "192.168.1.21 (desktop) changed IP from 192.168.1.20"
+        );
+
+        let desktop = &inventory.records()[0];
+        assert_eq!(desktop.first_seen, t0);
+        assert_eq!(desktop.last_seen, t1);
+        assert_eq!(desktop.device.hostname.as_deref(), Some("desktop"));
+        assert_eq!(desktop.device.response_time_ms, Some(1.5));
+        let ips: Vec<&str> = desktop.addresses.iter().map(|s| s.value.as_str()).collect();
+        assert_eq!(ips, ["192.168.1.21", "192.168.1.20"]);
+        assert_eq!(desktop.macs.len(), 1);
+
+        let mac = &inventory.records()[1];
+        assert!(!mac.online);
+        assert_eq!(mac.last_seen, t0);
+        assert_eq!(mac.device.response_time_ms, None);
+
+        // ... and came back
+        let events = inventory.record_health(&[("192.168.1.30".to_string(), Some(2.0))], t2);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind, ChangeKind::Back);
+        assert_eq!(events[0].device_id, "mac:00:17:f2:00:00:01");
+        let mac = &inventory.records()[1];
+        assert!(mac.online);
+        assert_eq!(mac.last_seen, t2);
+        let changes: Vec<bool> = mac.transitions.iter().map(|t| t.online).collect();
+        assert_eq!(changes, [true, false, true]);
+    }
+
+    fn kinds(events: &[ChangeEvent]) -> Vec<ChangeKind> {
+        events.iter().map(|e| e.kind).collect()
+    }
+
+    #[test]
+    fn test_change_new_device() {
+        let config = RandomizedMacConfig::default();
+        let now = Utc::now();
+        let mut inventory = Inventory::in_memory();
+        let mut printer = device("192.168.1.20", "00:1b:a9:00:00:01", Some("printer"));
+        printer.vendor = Some("Brother".to_string());
+        printer.tags = vec!["printer".to_string()];
+
+        let events = inventory.merge(vec![printer.clone()], &lan(), &config, now);
+        assert_eq!(kinds(&events), [ChangeKind::New]);
+        let event = &events[0];
+        assert_eq!(event.at, now);
+        assert_eq!(event.device_id, "mac:00:1b:a9:00:00:01");
+        assert_eq!(event.ip, "192.168.1.20");
+        assert_eq!(event.mac.as_deref(), Some("00:1b:a9:00:00:01"));
+        assert_eq!(event.vendor.as_deref(), Some("Brother"));
+        assert_eq!(event.tags, ["printer"]);
+        assert_eq!(event.previous, None);
+        assert_eq!(event.to_string(), "new device 192.168.1.20 (printer)");
+
+        // Seeing it again is not news
+        let events = inventory.merge(vec![printer], &lan(), &config, now);
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn test_change_offline_and_back() {
+        let config = RandomizedMacConfig::default();
+        let t0 = Utc::now() - Duration::hours(2);
+        let t1 = t0 + Duration::hours(1);
+        let mut inventory = Inventory::in_memory();
+        let tv = device("192.168.1.30", "00:17:f2:00:00:01", Some("tv"));
+        inventory.merge(vec![tv.clone()], &lan(), &config, t0);
+
+        let events = inventory.merge(Vec::new(), &lan(), &config, t1);
+        assert_eq!(kinds(&events), [ChangeKind::Offline]);
+        assert_eq!(events[0].to_string(), "192.168.1.30 (tv) went offline");
+        // Still gone: reported once
+        assert!(inventory.merge(Vec::new(), &lan(), &config, t1).is_empty());
+
+        let events = inventory.merge(vec![tv], &lan(), &config, t1);
+        assert_eq!(kinds(&events), [ChangeKind::Back]);
+        assert_eq!(events[0].to_string(), "192.168.1.30 (tv) is back online");
+
+        // Health checks report the same transitions
+        let unreachable = [("192.168.1.30".to_string(), None)];
+        let events = inventory.record_health(&unreachable, t1);
+        assert_eq!(kinds(&events), [ChangeKind::Offline]);
+        assert!(inventory.record_health(&unreachable, t1).is_empty());
+        let events = inventory.record_health(&[("192.168.1.30".to_string(), Some(3.0))], t1);
+        assert_eq!(kinds(&events), [ChangeKind::Back]);
+        // Unknown addresses are ignored
+        assert!(
+            inventory
+                .record_health(&[("192.168.1.99".to_string(), None)], t1)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_change_ip() {
+        let config = RandomizedMacConfig::default();
+        let now = Utc::now();
+        let mut inventory = Inventory::in_memory();
+        inventory.merge(
+            vec![device("192.168.1.20", "3c:52:82:1a:2b:3c", Some("desktop"))],
+            &lan(),
+            &config,
+            now,
+        );
+
+        let moved = device("192.168.1.25", "3C-52-82-1A-2B-3C", Some("desktop"));
+        let events = inventory.merge(vec![moved], &lan(), &config, now);
+        assert_eq!(kinds(&events), [ChangeKind::IpChanged]);
+        assert_eq!(events[0].device_id, "mac:3c:52:82:1a:2b:3c");
+        assert_eq!(events[0].ip, "192.168.1.25");
+        assert_eq!(events[0].previous.as_deref(), Some("192.168.1.20"));
+        assert_eq!(events[0].current.as_deref(), Some("192.168.1.25"));
+        assert_eq!(inventory.len(), 1);
+    }
+
+    #[test]
+    fn test_change_mac() {
+        let config = RandomizedMacConfig::default();
+        let now = Utc::now();
+        let mut inventory = Inventory::in_memory();
+        inventory.merge(
+            vec![device("192.168.1.5", "00:11:32:00:00:01", Some("nas"))],
+            &lan(),
+            &config,
+            now,
+        );
+
+        // Same address, replaced NIC
+        let replaced = device("192.168.1.5", "00:11:32:00:00:02", Some("nas"));
+        let events = inventory.merge(vec![replaced], &lan(), &config, now);
+        assert_eq!(kinds(&events), [ChangeKind::MacChanged]);
+        assert_eq!(events[0].previous.as_deref(), Some("00:11:32:00:00:01"));
+        assert_eq!(events[0].current.as_deref(), Some("00:11:32:00:00:02"));
+        assert_eq!(
+            events[0].to_string(),
+            "192.168.1.5 (nas) changed MAC from 00:11:32:00:00:01 to 00:11:32:00:00:02"
+        );
+
+        // Only the notation differs
+        let same = device("192.168.1.5", "00-11-32-00-00-02", Some("nas"));
+        assert!(inventory.merge(vec![same], &lan(), &config, now).is_empty());
+    }
+
+    #[test]
+    fn test_change_hostname() {
+        let config = RandomizedMacConfig::default();
+        let now = Utc::now();
+        let mut inventory = Inventory::in_memory();
+        inventory.merge(
+            vec![device("192.168.1.20", "3c:52:82:1a:2b:3c", Some("desktop"))],
+            &lan(),
+            &config,
+            now,
+        );
+
+        let renamed = device("192.168.1.20", "3c:52:82:1a:2b:3c", Some("workstation"));
+        let events = inventory.merge(vec![renamed], &lan(), &config, now);
+        assert_eq!(kinds(&events), [ChangeKind::HostnameChanged]);
+        assert_eq!(events[0].hostname.as_deref(), Some("workstation"));
+        assert_eq!(events[0].previous.as_deref(), Some("desktop"));
+        assert_eq!(events[0].current.as_deref(), Some("workstation"));
+        assert_eq!(
+            events[0].to_string(),
+            "192.168.1.20 (workstation) changed hostname from desktop"
+        );
+
+        // Case differences and scans that found no name are not changes
+        for hostname in [Some("WORKSTATION"), None] {
+            let device = device("192.168.1.20", "3c:52:82:1a:2b:3c", hostname);
+            assert!(
+                inventory
+                    .merge(vec![device], &lan(), &config, now)
+                    .is_empty()
+            );
+        }
+        assert_eq!(inventory.records()[0].hostnames.len(), 3);
+    }
+
+    #[test]
+    fn test_change_vendor() {
+        let config = RandomizedMacConfig::default();
+        let now = Utc::now();
+        let mut inventory = Inventory::in_memory();
+        let mut camera = device("192.168.1.60", "00:12:34:00:00:01", None);
+        camera.vendor = Some("Axis".to_string());
+        inventory.merge(vec![camera], &lan(), &config, now);
+
+        // A better vendor lookup is not a device change on its own
+        let mut camera = device("192.168.1.60", "00:12:34:00:00:01", None);
+        camera.vendor = Some("Axis Communications AB".to_string());
+        assert!(
+            inventory
+                .merge(vec![camera], &lan(), &config, now)
+                .is_empty()
+        );
+
+        // Events carry the vendor the scan found, or the one it had before
+        let mut moved = device("192.168.1.61", "00:12:34:00:00:01", None);
+        moved.vendor = None;
+        let events = inventory.merge(vec![moved], &lan(), &config, now);
+        assert_eq!(kinds(&events), [ChangeKind::IpChanged]);
+        assert_eq!(events[0].vendor.as_deref(), Some("Axis Communications AB"));
+    }
+
+    #[test]
+    fn test_merge_drops_ghosts_and_other_subnets() {
+        let config = RandomizedMacConfig::default();
+        let t0 = Utc::now() - Duration::hours(30);
+        let mut inventory = Inventory::in_memory();
+        inventory.merge(
+            vec![
+                device("192.168.1.40", "a6:3f:0e:91:7d:22", Some("janes-phone")),
+                device("10.8.0.5", "00:17:f2:00:00:02", None),
+            ],
+            &lan(),
+            &config,
+            t0,
+        );
+
+        // Same phone with its next random MAC and a new lease
+        let rotated = device("192.168.1.41", "5e:11:22:33:44:55", Some("Janes-Phone"));
+        let events = inventory.merge(vec![rotated], &lan(), &config, t0 + Duration::hours(1));
+        let kinds: Vec<ChangeKind> = events.iter().map(|e| e.kind).collect();
+        assert_eq!(kinds, [ChangeKind::IpChanged, ChangeKind::MacChanged]);
+        assert_eq!(inventory.len(), 1, "VPN peer should be dropped");
+        let phone = &inventory.records()[0];
+        assert_eq!(phone.first_seen, t0);
+        assert_eq!(phone.macs.len(), 2);
+
+        // Gone for longer than ghost_ttl_hours
+        inventory.merge(Vec::new(), &lan(), &config, Utc::now());
+        assert!(inventory.is_empty());
+    }
+
+    #[test]
+    fn test_save_and_reload() {
+        let path = std::env::temp_dir().join(format!(
+            "cartographer-inventory-test-{}.jsonl",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        let config = RandomizedMacConfig::default();
+        let now = Utc::now();
+
+        let mut inventory = Inventory::open_at(&path).unwrap();
+        for minute in 0..300 {
+            inventory.merge(
+                vec![device("192.168.1.20", "3c:52:82:1a:2b:3c", Some("desktop"))],
+                &lan(),
+                &config,
+                now + Duration::minutes(minute),
+            );
+            inventory.save().unwrap();
+        }
+        inventory.merge(
+            vec![device("192.168.1.50", "00:17:f2:00:00:03", None)],
+            &lan(),
+            &config,
+            now + Duration::minutes(300),
+        );
+        inventory.save().unwrap();
+
+        // A crash in the middle of a write leaves half a line behind
+        let mut content = std::fs::read_to_string(&path).unwrap();
+        content.push_str("{\"op\":\"put\",\"record\":{\"id\":");
+        std::fs::write(&path, content).unwrap();
+
+        let reloaded = Inventory::open_at(&path).unwrap();
+        assert_eq!(reloaded.len(), 2);
+        let changes = reloaded.changes_since(now).unwrap();
+        let kinds: Vec<ChangeKind> = changes.iter().map(|e| e.kind).collect();
+        assert_eq!(
+            kinds,
+            [ChangeKind::New, ChangeKind::New, ChangeKind::Offline]
+        );
+        assert_eq!(
+            reloaded
+                .changes_since(now + Duration::minutes(300))
+                .unwrap()
+                .len(),
+            2
+        );
+        let desktop = &reloaded.records()[0];
+        assert_eq!(desktop.first_seen, now);
+        assert_eq!(desktop.last_seen, now + Duration::minutes(299));
+        assert!(!desktop.online);
+
+        let mut reloaded = reloaded;
+        reloaded.clear().unwrap();
+        assert!(!path.exists());
+        assert!(reloaded.changes_since(now).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_changes_since() {
+        let path = std::env::temp_dir().join(format!(
+            "cartographer-changes-since-test-{}.jsonl",
+            std::process::id()
+        ));
+        let changes_path = path.with_extension("changes.jsonl");
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&changes_path);
+        let config = RandomizedMacConfig::default();
+        let now = Utc::now();
+        let desktop = || device("192.168.1.20", "3c:52:82:1a:2b:3c", Some("desktop"));
+
+        // Saved by the daemon over the last few days
+        let mut inventory = Inventory::open_at(&path).unwrap();
+        inventory.merge(vec![desktop()], &lan(), &config, now - Duration::days(3));
+        inventory.merge(Vec::new(), &lan(), &config, now - Duration::hours(30));
+        inventory.save().unwrap();
+        inventory.merge(vec![desktop()], &lan(), &config, now - Duration::hours(2));
+        inventory.save().unwrap();
+        assert!(changes_path.exists());
+
+        // Not saved yet
+        inventory.merge(Vec::new(), &lan(), &config, now);
+
+        let since = |value: &str| {
+            let since = changes::parse_since(value, now).unwrap();
+            kinds(&inventory.changes_since(since).unwrap())
+        };
+        assert_eq!(
+            since("7d"),
+            [
+                ChangeKind::New,
+                ChangeKind::Offline,
+                ChangeKind::Back,
+                ChangeKind::Offline
+            ]
+        );
+        assert_eq!(since("24h"), [ChangeKind::Back, ChangeKind::Offline]);
+        assert_eq!(since("0s"), [ChangeKind::Offline]);
+        let tomorrow = (now + Duration::days(1)).format("%Y-%m-%d").to_string();
+        assert!(since(&tomorrow).is_empty());
+
+        // What `cartographer changes` reads back after the daemon saved
+        inventory.save().unwrap();
+        let reloaded = Inventory::open_at(&path).unwrap();
+        let events = reloaded
+            .changes_since(changes::parse_since("24h", now).unwrap())
+            .unwrap();
+        assert_eq!(kinds(&events), [ChangeKind::Back, ChangeKind::Offline]);
+        assert_eq!(events[0].at, now - Duration::hours(2));
+
+        let mut reloaded = reloaded;
+        reloaded.clear().unwrap();
+        assert!(!changes_path.exists());
+    }
+}
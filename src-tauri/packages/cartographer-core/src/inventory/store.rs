@@ -0,0 +1,222 @@
+//! Append-only journal backing the inventory
+//!
+//! Every save appends one JSON line per changed record (`put`) or forgotten
+//! record (`remove`), so a crash mid-write loses at most the last line: the
+//! next save ends that partial line before appending its own.
+//! Loading replays the lines in order. Once the journal holds several times
+//! more lines than there are records, it is rewritten with one `put` per
+//! record and swapped in with a rename.
+
+use super::InventoryRecord;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Journals shorter than this are never compacted
+const COMPACT_MIN_LINES: usize = 256;
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum Entry {
+    Put { record: Box<InventoryRecord> },
+    Remove { id: String },
+}
+
+/// Serialized form of a borrowed record, so saving does not clone
+#[derive(Serialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum EntryRef<'a> {
+    Put { record: &'a InventoryRecord },
+    Remove { id: &'a str },
+}
+
+pub(super) struct Journal {
+    path: PathBuf,
+    lines: usize,
+}
+
+impl Journal {
+    /// Open the journal at `path` and replay it (a missing file is empty)
+    pub(super) fn open(path: &Path) -> Result<(Self, Vec<InventoryRecord>)> {
+        let mut journal = Self {
+            path: path.to_path_buf(),
+            lines: 0,
+        };
+        if !path.exists() {
+            return Ok((journal, Vec::new()));
+        }
+
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read inventory {}", path.display()))?;
+        let mut records: Vec<InventoryRecord> = Vec::new();
+        let mut skipped = 0;
+        for line in content.lines().filter(|l| !l.trim().is_empty()) {
+            journal.lines += 1;
+            match serde_json::from_str::<Entry>(line) {
+                Ok(Entry::Put { record }) => match records.iter_mut().find(|r| r.id == record.id) {
+                    Some(existing) => *existing = *record,
+                    None => records.push(*record),
+                },
+                Ok(Entry::Remove { id }) => records.retain(|r| r.id != id),
+                // A line cut short by a crash, or written by a newer agent
+                Err(_) => skipped += 1,
+            }
+        }
+        if skipped > 0 {
+            tracing::warn!(
+                "Inventory {}: skipped {} unreadable lines",
+                path.display(),
+                skipped
+            );
+        }
+        Ok((journal, records))
+    }
+
+    pub(super) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append changed and removed records
+    pub(super) fn append<'a>(
+        &mut self,
+        puts: impl IntoIterator<Item = &'a InventoryRecord>,
+        removes: &[String],
+    ) -> Result<()> {
+        let mut buffer = Vec::new();
+        let mut lines = 0;
+        for record in puts {
+            serde_json::to_writer(&mut buffer, &EntryRef::Put { record })?;
+            buffer.push(b'\n');
+            lines += 1;
+        }
+        for id in removes {
+            serde_json::to_writer(&mut buffer, &EntryRef::Remove { id })?;
+            buffer.push(b'\n');
+            lines += 1;
+        }
+        if lines == 0 {
+            return Ok(());
+        }
+
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir).context("Failed to create inventory directory")?;
+        }
+        let mut file = open_append(&self.path)
+            .with_context(|| format!("Failed to open inventory {}", self.path.display()))?;
+        file.write_all(&buffer)
+            .and_then(|_| file.sync_data())
+            .with_context(|| format!("Failed to write inventory {}", self.path.display()))?;
+        self.lines += lines;
+        Ok(())
+    }
+
+    /// Whether the journal has grown enough to be rewritten
+    pub(super) fn needs_compaction(&self, records: usize) -> bool {
+        self.lines > COMPACT_MIN_LINES.max(records * 4)
+    }
+
+    /// Rewrite the journal with one line per record
+    pub(super) fn compact(&mut self, records: &[InventoryRecord]) -> Result<()> {
+        let mut buffer = Vec::new();
+        for record in records {
+            serde_json::to_writer(&mut buffer, &EntryRef::Put { record })?;
+            buffer.push(b'\n');
+        }
+
+        let tmp_path = self.path.with_extension("jsonl.tmp");
+        std::fs::write(&tmp_path, &buffer)
+            .with_context(|| format!("Failed to write {}", tmp_path.display()))?;
+        std::fs::rename(&tmp_path, &self.path)
+            .with_context(|| format!("Failed to replace inventory {}", self.path.display()))?;
+        tracing::debug!(
+            "Compacted inventory from {} to {} lines",
+            self.lines,
+            records.len()
+        );
+        self.lines = records.len();
+        Ok(())
+    }
+
+    /// Delete the journal file
+    pub(super) fn delete(&mut self) -> Result<()> {
+        if self.path.exists() {
+            std::fs::remove_file(&self.path)
+                .with_context(|| format!("Failed to delete inventory {}", self.path.display()))?;
+        }
+        self.lines = 0;
+        Ok(())
+    }
+}
+
+/// Open `path` for appending, first ending a line cut short by a crash so
+/// the next line is not glued onto it
+pub(super) fn open_append(path: &Path) -> std::io::Result<std::fs::File> {
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .read(true)
+        .append(true)
+        .open(path)?;
+    if file.metadata()?.len() > 0 {
+        let mut last = [0u8];
+        file.seek(SeekFrom::End(-1))?;
+        file.read_exact(&mut last)?;
+        if last[0] != b'\n' {
+            file.write_all(b"\n")?;
+        }
+    }
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn record(id: &str) -> InventoryRecord {
+        let now = Utc::now();
+        InventoryRecord {
+            id: id.to_string(),
+            first_seen: now,
+            last_seen: now,
+            online: true,
+            device: Default::default(),
+            addresses: Vec::new(),
+            macs: Vec::new(),
+            hostnames: Vec::new(),
+            transitions: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_append_after_torn_line() {
+        let path = std::env::temp_dir().join(format!(
+            "cartographer-journal-test-{}.jsonl",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+
+        let (mut journal, _) = Journal::open(&path).unwrap();
+        journal.append([&record("a")], &[]).unwrap();
+        // A crash in the middle of a write leaves half a line behind
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap();
+        file.write_all(b"{\"op\":\"put\",\"record\":{\"id\":")
+            .unwrap();
+        drop(file);
+
+        let (mut journal, records) = Journal::open(&path).unwrap();
+        assert_eq!(records.len(), 1);
+        journal.append([&record("b"), &record("c")], &[]).unwrap();
+        journal.append([], &["a".to_string()]).unwrap();
+
+        let (_, records) = Journal::open(&path).unwrap();
+        let ids: Vec<&str> = records.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, ["b", "c"]);
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(content.lines().count(), 5);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -3,6 +3,8 @@
 //! This crate provides the core functionality for Cartographer agents:
 //! - Network scanning (ARP, ping sweep, hostname resolution)
 //! - Cloud synchronization (device code auth, scan upload)
+//! - Device inventory (first/last seen and history across scans)
+//! - Local alerts on device changes (webhook, syslog, command)
 //! - Credential management (keyring with file fallback)
 //!
 //! # Features
@@ -43,9 +45,19 @@
 
 pub mod auth;
 pub mod cloud;
+pub mod inventory;
+pub mod notifications;
 pub mod scanner;
 
+#[cfg(test)]
+mod mock_server;
+
 // Re-export commonly used types
-pub use auth::{AuthStatus, Credentials, LoginFlowStarted, LoginUrlEvent};
+pub use auth::{AgentSettings, AuthStatus, Credentials, LoginFlowStarted, LoginUrlEvent};
 pub use cloud::{CloudClient, CloudEndpointConfig, ConfigSource, TokenVerifyResult};
-pub use scanner::{Device, NetworkInfo, ScanCapabilities, ScanProgress, ScanResult, ScanStage};
+pub use scanner::{
+    AdvertisedService, Classification, Device, DhcpFingerprint, HostnameSource, NetworkInfo,
+    OpenPort, OsFamily, OsGuess, ScanCapabilities, ScanProgress, ScanResult, ScanStage,
+    ScanTarget, ServiceInfo, SnmpInfo, SnmpInterface, TcpFingerprint, TlsCertificate,
+    TopologyEdge, UpnpDevice,
+};
@@ -0,0 +1,68 @@
+//! Scripted HTTP server for the cloud client, outbox and webhook tests
+
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+/// A request the mock server received
+#[derive(Debug, Clone)]
+pub(crate) struct MockRequest {
+    /// Request line and headers, lowercased
+    pub head: String,
+    pub body: String,
+}
+
+/// A local HTTP server answering one connection per scripted response
+/// (status, extra headers) and recording each request
+pub(crate) async fn mock_server(
+    responses: Vec<(u16, &'static str)>,
+) -> (String, Arc<Mutex<Vec<MockRequest>>>) {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = format!("http://{}", listener.local_addr().unwrap());
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let seen = requests.clone();
+    tokio::spawn(async move {
+        for (status, headers) in responses {
+            // A client that hangs up early does not use up a response
+            let (mut stream, request) = loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                if let Some(request) = read_request(&mut stream).await {
+                    break (stream, request);
+                }
+            };
+            seen.lock().unwrap().push(request);
+
+            let response = format!(
+                "HTTP/1.1 {} Mock\r\n{}Content-Length: 2\r\nConnection: close\r\n\r\n{{}}",
+                status, headers
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+            stream.shutdown().await.unwrap();
+        }
+    });
+    (address, requests)
+}
+
+/// Read one request, or `None` if the connection closes first
+async fn read_request(stream: &mut TcpStream) -> Option<MockRequest> {
+    let mut request = Vec::new();
+    let mut buffer = [0u8; 4096];
+    let head_end = loop {
+        if let Some(end) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+            break end + 4;
+        }
+        let n = stream.read(&mut buffer).await.ok().filter(|n| *n > 0)?;
+        request.extend_from_slice(&buffer[..n]);
+    };
+    let head = String::from_utf8_lossy(&request[..head_end]).to_lowercase();
+    let length = head
+        .lines()
+        .find_map(|l| l.strip_prefix("content-length:"))
+        .map_or(0, |v| v.trim().parse::<usize>().unwrap());
+    while request.len() < head_end + length {
+        let n = stream.read(&mut buffer).await.ok().filter(|n| *n > 0)?;
+        request.extend_from_slice(&buffer[..n]);
+    }
+    let body = String::from_utf8_lossy(&request[head_end..]).into_owned();
+    Some(MockRequest { head, body })
+}
@@ -0,0 +1,187 @@
+//! Command sink: runs a local program once per event
+//!
+//! The program gets the event as JSON on stdin and its fields as
+//! environment variables (`CARTOGRAPHER_KIND`, `CARTOGRAPHER_IP`,
+//! `CARTOGRAPHER_MAC`, `CARTOGRAPHER_MESSAGE`, ...). It is run directly,
+//! not through a shell, and killed if it outlives the timeout. A non-zero
+//! exit status counts as a failed delivery.
+
+use super::{CommandConfig, event_fields};
+use crate::inventory::ChangeEvent;
+use anyhow::{Context, Result, bail};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+
+pub(super) struct Command {
+    program: String,
+    args: Vec<String>,
+    timeout: Duration,
+}
+
+impl Command {
+    pub(super) fn new(config: &CommandConfig) -> Result<Self> {
+        let Some((program, args)) = config.command.split_first() else {
+            bail!("command is empty");
+        };
+        if program.trim().is_empty() {
+            bail!("command has no program");
+        }
+        Ok(Self {
+            program: program.clone(),
+            args: args.to_vec(),
+            timeout: Duration::from_millis(config.timeout_ms),
+        })
+    }
+
+    pub(super) async fn send(&self, event: &ChangeEvent) -> Result<()> {
+        let mut command = tokio::process::Command::new(&self.program);
+        command
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        for (name, value) in event_fields(event) {
+            command.env(format!("CARTOGRAPHER_{}", name.to_uppercase()), value);
+        }
+        let mut child = command
+            .spawn()
+            .with_context(|| format!("Failed to run {}", self.program))?;
+
+        let input = serde_json::to_vec(event)?;
+        let run = async {
+            if let Some(mut stdin) = child.stdin.take() {
+                // Programs that ignore stdin may exit before reading it
+                let _ = stdin.write_all(&input).await;
+            }
+            child.wait_with_output().await
+        };
+        let output = tokio::time::timeout(self.timeout, run)
+            .await
+            .with_context(|| format!("{} did not finish in time", self.program))??;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let detail = stderr.lines().next().unwrap_or("").trim();
+            bail!("{} exited with {}: {}", self.program, output.status, detail);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::notifications::sample_event;
+
+    fn command(script: &str) -> Command {
+        command_with_timeout(script, 5000)
+    }
+
+    fn command_with_timeout(script: &str, timeout_ms: u64) -> Command {
+        Command::new(&CommandConfig {
+            name: "hook".to_string(),
+            command: vec!["sh".to_string(), "-c".to_string(), script.to_string()],
+            timeout_ms,
+        })
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_command_environment() {
+        let out = std::env::temp_dir().join(format!(
+            "cartographer-command-env-test-{}",
+            std::process::id()
+        ));
+        let script = format!(
+            "env | grep '^CARTOGRAPHER_' | sort > '{0}.env' && cat > '{0}.json'",
+            out.display()
+        );
+        let mut event = sample_event();
+        event.kind = crate::inventory::ChangeKind::HostnameChanged;
+        event.tags = vec!["ot".to_string(), "plc".to_string()];
+        event.previous = Some("old-name".to_string());
+        event.current = Some("cartographer-test".to_string());
+        command(&script).send(&event).await.unwrap();
+
+        let env = std::fs::read_to_string(out.with_extension("env")).unwrap();
+        let expected = [
+            format!("CARTOGRAPHER_AT={}", event.at.to_rfc3339()),
+            "CARTOGRAPHER_CURRENT=cartographer-test".to_string(),
+            "CARTOGRAPHER_DEVICE_ID=mac:02:00:00:00:00:01".to_string(),
+            "CARTOGRAPHER_HOSTNAME=cartographer-test".to_string(),
+            "CARTOGRAPHER_IP=192.0.2.10".to_string(),
+            "CARTOGRAPHER_KIND=hostname_changed".to_string(),
+            "CARTOGRAPHER_MAC=02:00:00:00:00:01".to_string(),
+            "CARTOGRAPHER_MESSAGE=192.0.2.10 (cartographer-test) changed hostname from old-name"
+                .to_string(),
+            "CARTOGRAPHER_PREVIOUS=old-name".to_string(),
+            "CARTOGRAPHER_TAGS=ot,plc".to_string(),
+            "CARTOGRAPHER_VENDOR=".to_string(),
+        ];
+        assert_eq!(env.lines().collect::<Vec<_>>(), expected);
+
+        let stdin = std::fs::read_to_string(out.with_extension("json")).unwrap();
+        let sent: ChangeEvent = serde_json::from_str(&stdin).unwrap();
+        assert_eq!(sent, event);
+
+        let _ = std::fs::remove_file(out.with_extension("env"));
+        let _ = std::fs::remove_file(out.with_extension("json"));
+    }
+
+    #[tokio::test]
+    async fn test_command_failures() {
+        let event = sample_event();
+
+        // The first stderr line explains a non-zero exit
+        let error = command("echo 'no route to pager' >&2; echo 'retrying' >&2; exit 2")
+            .send(&event)
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("exit status: 2"), "{}", error);
+        assert!(error.ends_with("no route to pager"), "{}", error);
+
+        // Killed by a signal
+        assert!(command("kill -9 $$").send(&event).await.is_err());
+
+        let error = command_with_timeout("sleep 5", 100)
+            .send(&event)
+            .await
+            .unwrap_err();
+        assert!(
+            error.to_string().contains("did not finish in time"),
+            "{}",
+            error
+        );
+
+        let missing = Command::new(&CommandConfig {
+            name: "missing".to_string(),
+            command: vec!["/nonexistent/cartographer-hook".to_string()],
+            timeout_ms: 5000,
+        })
+        .unwrap();
+        let error = missing.send(&event).await.unwrap_err();
+        assert!(error.to_string().starts_with("Failed to run"), "{}", error);
+
+        let blank = CommandConfig {
+            name: "blank".to_string(),
+            command: vec![" ".to_string(), "--flag".to_string()],
+            timeout_ms: 5000,
+        };
+        assert!(Command::new(&blank).is_err());
+        let empty = CommandConfig {
+            name: "empty".to_string(),
+            command: Vec::new(),
+            timeout_ms: 5000,
+        };
+        assert!(Command::new(&empty).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_run_command() {
+        let check = r#"test "$CARTOGRAPHER_KIND" = new && grep -q '"ip":"192.0.2.10"'"#;
+        command(check).send(&sample_event()).await.unwrap();
+    }
+}
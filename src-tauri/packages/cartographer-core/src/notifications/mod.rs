@@ -0,0 +1,550 @@
+//! Local alerts for device changes
+//!
+//! Change events from the inventory (a new device, one going offline, a new
+//! IP address...) are sent to the sinks configured in the `[notifications]`
+//! section of config.toml, without going through the cloud:
+//!
+//! ```toml
+//! [notifications]
+//! enabled = true
+//!
+//! [[notifications.sinks]]
+//! name = "ops-webhook"
+//! type = "webhook"             # JSON POST
+//! url = "https://hooks.example.com/T000/B000"
+//! headers = { Authorization = "Bearer ..." }
+//! template = '{"text": "{{message}}"}'   # default: the event as JSON
+//! timeout_ms = 5000
+//!
+//! [[notifications.sinks]]
+//! name = "syslog"
+//! type = "syslog"              # RFC 5424
+//! address = "udp://10.0.0.5:514"         # or tcp://host:port, unix:///dev/log
+//! facility = "local0"
+//!
+//! [[notifications.sinks]]
+//! name = "pager"
+//! type = "command"             # event JSON on stdin, CARTOGRAPHER_* variables
+//! command = ["/usr/local/bin/page-oncall", "--team", "network"]
+//!
+//! [[notifications.rules]]
+//! events = ["new"]             # default: every event type
+//! tags = ["ot"]                # devices with any of these tags (default: all)
+//! exclude_tags = ["guest"]
+//! sinks = ["pager", "syslog"]  # default: every sink
+//! ```
+//!
+//! An event goes to every sink named by a rule it matches. Without rules,
+//! every event goes to every sink.
+
+mod command;
+mod syslog;
+mod webhook;
+
+use crate::inventory::{ChangeEvent, ChangeKind};
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Default time allowed for one delivery
+const DEFAULT_TIMEOUT_MS: u64 = 5000;
+
+fn default_timeout_ms() -> u64 {
+    DEFAULT_TIMEOUT_MS
+}
+
+/// The `[notifications]` section
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NotificationConfig {
+    /// Send alerts at all
+    pub enabled: bool,
+    pub sinks: Vec<SinkConfig>,
+    pub rules: Vec<AlertRule>,
+}
+
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            sinks: Vec::new(),
+            rules: Vec::new(),
+        }
+    }
+}
+
+/// Where alerts are sent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SinkConfig {
+    Webhook(WebhookConfig),
+    Syslog(SyslogConfig),
+    Command(CommandConfig),
+}
+
+impl SinkConfig {
+    pub fn name(&self) -> &str {
+        match self {
+            SinkConfig::Webhook(c) => &c.name,
+            SinkConfig::Syslog(c) => &c.name,
+            SinkConfig::Command(c) => &c.name,
+        }
+    }
+}
+
+/// HTTP POST of a JSON body
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookConfig {
+    pub name: String,
+    pub url: String,
+    /// Extra request headers (e.g. Authorization)
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// JSON body with `{{field}}` placeholders (default: the event as JSON)
+    pub template: Option<String>,
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+/// RFC 5424 message to a syslog server or the local socket
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SyslogConfig {
+    pub name: String,
+    /// "udp://host:port", "tcp://host:port" or "unix:///dev/log" (port 514
+    /// when omitted)
+    pub address: String,
+    /// Facility name: "daemon", "user", "local0" to "local7", ...
+    #[serde(default = "syslog::default_facility")]
+    pub facility: String,
+    #[serde(default = "syslog::default_app_name")]
+    pub app_name: String,
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+/// Local program run once per event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CommandConfig {
+    pub name: String,
+    /// Program and arguments (no shell)
+    pub command: Vec<String>,
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+/// Which events go to which sinks
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AlertRule {
+    /// Event types (empty = all)
+    pub events: Vec<ChangeKind>,
+    /// Match devices with any of these tags (empty = all devices)
+    pub tags: Vec<String>,
+    /// Skip devices with any of these tags
+    pub exclude_tags: Vec<String>,
+    /// Sink names (empty = all sinks)
+    pub sinks: Vec<String>,
+}
+
+impl AlertRule {
+    pub fn matches(&self, event: &ChangeEvent) -> bool {
+        let has_tag = |tags: &[String]| {
+            tags.iter()
+                .any(|t| event.tags.iter().any(|e| e.eq_ignore_ascii_case(t)))
+        };
+        (self.events.is_empty() || self.events.contains(&event.kind))
+            && (self.tags.is_empty() || has_tag(&self.tags))
+            && !has_tag(&self.exclude_tags)
+    }
+
+    fn routes_to(&self, sink: &str) -> bool {
+        self.sinks.is_empty() || self.sinks.iter().any(|s| s == sink)
+    }
+}
+
+enum Sink {
+    Webhook(webhook::Webhook),
+    Syslog(syslog::Syslog),
+    Command(command::Command),
+}
+
+impl Sink {
+    fn new(config: &SinkConfig) -> Result<Self> {
+        Ok(match config {
+            SinkConfig::Webhook(c) => Sink::Webhook(webhook::Webhook::new(c)?),
+            SinkConfig::Syslog(c) => Sink::Syslog(syslog::Syslog::new(c)?),
+            SinkConfig::Command(c) => Sink::Command(command::Command::new(c)?),
+        })
+    }
+
+    async fn send(&self, event: &ChangeEvent) -> Result<()> {
+        match self {
+            Sink::Webhook(s) => s.send(event).await,
+            Sink::Syslog(s) => s.send(event).await,
+            Sink::Command(s) => s.send(event).await,
+        }
+    }
+}
+
+/// Sends change events to the configured sinks
+pub struct Notifier {
+    enabled: bool,
+    sinks: Vec<(String, Sink)>,
+    rules: Vec<AlertRule>,
+}
+
+impl Notifier {
+    /// Check the settings and set up the sinks
+    pub fn new(config: &NotificationConfig) -> Result<Self> {
+        let mut sinks: Vec<(String, Sink)> = Vec::new();
+        for sink in &config.sinks {
+            let name = sink.name().trim();
+            if name.is_empty() {
+                bail!("Every sink needs a name");
+            }
+            if sinks.iter().any(|(n, _)| n == name) {
+                bail!("Sink '{}' is defined twice", name);
+            }
+            let built = Sink::new(sink).with_context(|| format!("Sink '{}'", name))?;
+            sinks.push((name.to_string(), built));
+        }
+        for (index, rule) in config.rules.iter().enumerate() {
+            if let Some(unknown) = rule
+                .sinks
+                .iter()
+                .find(|s| !sinks.iter().any(|(n, _)| n == *s))
+            {
+                bail!("Rule {} sends to unknown sink '{}'", index + 1, unknown);
+            }
+        }
+        Ok(Self {
+            enabled: config.enabled,
+            sinks,
+            rules: config.rules.clone(),
+        })
+    }
+
+    /// A notifier that sends nothing
+    pub fn disabled() -> Self {
+        Self {
+            enabled: false,
+            sinks: Vec::new(),
+            rules: Vec::new(),
+        }
+    }
+
+    /// Whether events would be sent anywhere
+    pub fn is_active(&self) -> bool {
+        self.enabled && !self.sinks.is_empty()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn sink_names(&self) -> Vec<&str> {
+        self.sinks.iter().map(|(name, _)| name.as_str()).collect()
+    }
+
+    pub fn rule_count(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Names of the sinks an event goes to
+    pub fn sinks_for(&self, event: &ChangeEvent) -> Vec<&str> {
+        self.sinks
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .filter(|name| {
+                self.rules.is_empty()
+                    || self
+                        .rules
+                        .iter()
+                        .any(|r| r.matches(event) && r.routes_to(name))
+            })
+            .collect()
+    }
+
+    /// Send events to the sinks their rules select. Sinks are served in
+    /// parallel, each getting its events in order; failures are logged.
+    pub async fn notify(&self, events: &[ChangeEvent]) {
+        if !self.is_active() || events.is_empty() {
+            return;
+        }
+        let deliveries = self.sinks.iter().map(|(name, sink)| async move {
+            for event in events {
+                if !self.sinks_for(event).contains(&name.as_str()) {
+                    continue;
+                }
+                match sink.send(event).await {
+                    Ok(()) => tracing::debug!("Alert sent to {}: {}", name, event),
+                    Err(e) => tracing::warn!("Alert to {} failed: {:#}", name, e),
+                }
+            }
+        });
+        futures::future::join_all(deliveries).await;
+    }
+
+    /// Send a sample event to every sink, ignoring rules and `enabled`
+    pub async fn test(&self) -> Vec<(String, Result<()>)> {
+        let event = sample_event();
+        let deliveries = self
+            .sinks
+            .iter()
+            .map(|(name, sink)| async { (name.clone(), sink.send(&event).await) });
+        futures::future::join_all(deliveries).await
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ConfigFile {
+    #[serde(default)]
+    notifications: NotificationConfig,
+}
+
+/// Load the `[notifications]` section and set up its sinks (no config file
+/// means no sinks).
+pub fn load_notifier() -> Result<Notifier> {
+    let Some((path, content)) = crate::cloud::config::read_config_source()? else {
+        return Notifier::new(&NotificationConfig::default());
+    };
+    let file: ConfigFile = toml::from_str(&content)
+        .with_context(|| format!("Invalid [notifications] settings in {}", path.display()))?;
+    Notifier::new(&file.notifications)
+        .with_context(|| format!("Invalid [notifications] settings in {}", path.display()))
+}
+
+/// Like `load_notifier`, but logs a problem and sends nothing instead of
+/// failing
+pub fn load_notifier_or_disabled() -> Notifier {
+    match load_notifier() {
+        Ok(notifier) => notifier,
+        Err(e) => {
+            tracing::warn!("Alerts disabled: {:#}", e);
+            Notifier::disabled()
+        }
+    }
+}
+
+/// Event fields by name, for webhook templates and command variables
+fn event_fields(event: &ChangeEvent) -> Vec<(&'static str, String)> {
+    let text = |value: &Option<String>| value.clone().unwrap_or_default();
+    vec![
+        ("at", event.at.to_rfc3339()),
+        ("kind", event.kind.to_string()),
+        ("device_id", event.device_id.clone()),
+        ("ip", event.ip.clone()),
+        ("hostname", text(&event.hostname)),
+        ("mac", text(&event.mac)),
+        ("vendor", text(&event.vendor)),
+        ("tags", event.tags.join(",")),
+        ("previous", text(&event.previous)),
+        ("current", text(&event.current)),
+        ("message", event.to_string()),
+    ]
+}
+
+/// Made-up event for `Notifier::test` and template checks
+fn sample_event() -> ChangeEvent {
+    ChangeEvent {
+        at: chrono::Utc::now(),
+        kind: ChangeKind::New,
+        device_id: "mac:02:00:00:00:00:01".to_string(),
+        ip: "192.0.2.10".to_string(),
+        hostname: Some("cartographer-test".to_string()),
+        mac: Some("02:00:00:00:00:01".to_string()),
+        vendor: None,
+        tags: vec!["test".to_string()],
+        previous: None,
+        current: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(kind: ChangeKind, tags: &[&str]) -> ChangeEvent {
+        ChangeEvent {
+            kind,
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            ..sample_event()
+        }
+    }
+
+    #[test]
+    fn test_rules_route_events_to_sinks() {
+        let file: ConfigFile = toml::from_str(
+            r#"
+[[notifications.sinks]]
+name = "syslog"
+type = "syslog"
+address = "udp://127.0.0.1:514"
+
+[[notifications.sinks]]
+name = "pager"
+type = "command"
+command = ["true"]
+
+[[notifications.rules]]
+events = ["new"]
+tags = ["OT"]
+sinks = ["pager"]
+
+[[notifications.rules]]
+exclude_tags = ["guest"]
+sinks = ["syslog"]
+"#,
+        )
+        .unwrap();
+        let notifier = Notifier::new(&file.notifications).unwrap();
+        assert!(notifier.is_active());
+
+        let new_plc = event(ChangeKind::New, &["ot"]);
+        assert_eq!(notifier.sinks_for(&new_plc), ["syslog", "pager"]);
+        assert_eq!(
+            notifier.sinks_for(&event(ChangeKind::Offline, &["ot"])),
+            ["syslog"]
+        );
+        assert!(
+            notifier
+                .sinks_for(&event(ChangeKind::New, &["guest"]))
+                .is_empty()
+        );
+
+        // No rules: everything goes everywhere
+        let mut config = file.notifications.clone();
+        config.rules.clear();
+        let notifier = Notifier::new(&config).unwrap();
+        assert_eq!(notifier.sinks_for(&event(ChangeKind::Back, &[])).len(), 2);
+    }
+
+    #[test]
+    fn test_rule_filters_by_event_type() {
+        let rule = AlertRule {
+            events: vec![ChangeKind::Offline, ChangeKind::Back],
+            ..Default::default()
+        };
+        assert!(rule.matches(&event(ChangeKind::Offline, &[])));
+        assert!(rule.matches(&event(ChangeKind::Back, &["ot"])));
+        for kind in [
+            ChangeKind::New,
+            ChangeKind::IpChanged,
+            ChangeKind::MacChanged,
+            ChangeKind::HostnameChanged,
+        ] {
+            assert!(
+                !rule.matches(&event(kind, &[])),
+                "{} should not match",
+                kind
+            );
+        }
+
+        // No event types: every one
+        let any = AlertRule::default();
+        assert!(any.matches(&event(ChangeKind::HostnameChanged, &[])));
+    }
+
+    #[test]
+    fn test_rule_filters_by_tag() {
+        let rule = AlertRule {
+            tags: vec!["ot".to_string(), "camera".to_string()],
+            exclude_tags: vec!["Lab".to_string()],
+            ..Default::default()
+        };
+        assert!(rule.matches(&event(ChangeKind::New, &["OT"])));
+        assert!(rule.matches(&event(ChangeKind::New, &["printer", "camera"])));
+        assert!(!rule.matches(&event(ChangeKind::New, &["printer"])));
+        assert!(
+            !rule.matches(&event(ChangeKind::New, &[])),
+            "untagged devices do not match a tag filter"
+        );
+        assert!(
+            !rule.matches(&event(ChangeKind::New, &["ot", "lab"])),
+            "exclude_tags wins over tags"
+        );
+
+        let all_but_guests = AlertRule {
+            exclude_tags: vec!["guest".to_string()],
+            ..Default::default()
+        };
+        assert!(all_but_guests.matches(&event(ChangeKind::New, &[])));
+        assert!(!all_but_guests.matches(&event(ChangeKind::New, &["Guest"])));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_notify_sends_only_matching_events() {
+        let log = std::env::temp_dir().join(format!(
+            "cartographer-notify-test-{}.log",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&log);
+        let append = format!(
+            r#"echo "$CARTOGRAPHER_KIND $CARTOGRAPHER_TAGS" >> '{}'"#,
+            log.display()
+        );
+        let config = NotificationConfig {
+            enabled: true,
+            sinks: vec![SinkConfig::Command(CommandConfig {
+                name: "log".to_string(),
+                command: vec!["sh".to_string(), "-c".to_string(), append],
+                timeout_ms: 5000,
+            })],
+            rules: vec![AlertRule {
+                events: vec![ChangeKind::New, ChangeKind::Offline],
+                tags: vec!["ot".to_string()],
+                ..Default::default()
+            }],
+        };
+        let notifier = Notifier::new(&config).unwrap();
+
+        notifier
+            .notify(&[
+                event(ChangeKind::New, &["ot"]),
+                event(ChangeKind::New, &["printer"]),
+                event(ChangeKind::Back, &["ot"]),
+                event(ChangeKind::Offline, &["ot", "plc"]),
+            ])
+            .await;
+        assert_eq!(
+            std::fs::read_to_string(&log).unwrap(),
+            "new ot\noffline ot,plc\n"
+        );
+
+        // Disabled: nothing is sent
+        let notifier = Notifier::new(&NotificationConfig {
+            enabled: false,
+            ..config
+        })
+        .unwrap();
+        notifier.notify(&[event(ChangeKind::New, &["ot"])]).await;
+        assert_eq!(std::fs::read_to_string(&log).unwrap().lines().count(), 2);
+        let _ = std::fs::remove_file(&log);
+    }
+
+    #[test]
+    fn test_invalid_settings_are_rejected() {
+        let parse = |toml: &str| {
+            toml::from_str::<ConfigFile>(toml)
+                .map_err(anyhow::Error::from)
+                .and_then(|f| Notifier::new(&f.notifications))
+        };
+
+        let unknown_sink = "[[notifications.rules]]\nsinks = [\"nowhere\"]\n";
+        assert!(parse(unknown_sink).is_err());
+        let twice =
+            "[[notifications.sinks]]\nname = \"a\"\ntype = \"command\"\ncommand = [\"true\"]\n"
+                .repeat(2);
+        assert!(parse(&twice).is_err());
+        let bad_event = "[[notifications.rules]]\nevents = [\"exploded\"]\n";
+        assert!(parse(bad_event).is_err());
+        let typo =
+            "[[notifications.sinks]]\nname = \"a\"\ntype = \"command\"\ncomand = [\"true\"]\n";
+        assert!(parse(typo).is_err());
+        assert!(!parse("").unwrap().is_active());
+    }
+}
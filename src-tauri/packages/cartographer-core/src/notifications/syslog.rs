@@ -0,0 +1,333 @@
+//! Syslog sink: RFC 5424 messages over UDP, TCP or a local unix socket
+//!
+//! Each event is one message. The MSGID is the event type and the device
+//! details travel as structured data, e.g.
+//!
+//! ```text
+//! <28>1 2026-03-10T12:00:00.000000Z nms01 cartographer 4242 new [device@32473 id="mac:..." ip="192.168.1.20" mac="..."] new device 192.168.1.20
+//! ```
+//!
+//! TCP uses octet-counting framing (RFC 6587). 32473 is the private
+//! enterprise number set aside for examples (RFC 5612); collectors only
+//! need the SD-ID to be well formed.
+
+use super::SyslogConfig;
+use crate::inventory::{ChangeEvent, ChangeKind};
+use anyhow::{Context, Result, bail};
+use chrono::SecondsFormat;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+
+const DEFAULT_PORT: u16 = 514;
+
+const FACILITIES: &[&str] = &[
+    "kern", "user", "mail", "daemon", "auth", "syslog", "lpr", "news", "uucp", "cron", "authpriv",
+    "ftp", "ntp", "audit", "alert", "clock", "local0", "local1", "local2", "local3", "local4",
+    "local5", "local6", "local7",
+];
+
+pub(super) fn default_facility() -> String {
+    "daemon".to_string()
+}
+
+pub(super) fn default_app_name() -> String {
+    "cartographer".to_string()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Target {
+    Udp(String),
+    Tcp(String),
+    Unix(std::path::PathBuf),
+}
+
+pub(super) struct Syslog {
+    target: Target,
+    facility: u8,
+    app_name: String,
+    hostname: String,
+    timeout: Duration,
+}
+
+impl Syslog {
+    pub(super) fn new(config: &SyslogConfig) -> Result<Self> {
+        let facility = FACILITIES
+            .iter()
+            .position(|f| f.eq_ignore_ascii_case(config.facility.trim()))
+            .with_context(|| format!("Unknown syslog facility '{}'", config.facility))?;
+        let app_name = config.app_name.trim();
+        if app_name.is_empty() || app_name.len() > 48 || !is_header_text(app_name) {
+            bail!("app_name must be 1 to 48 printable ASCII characters without spaces");
+        }
+        Ok(Self {
+            target: parse_address(&config.address)?,
+            facility: facility as u8,
+            app_name: app_name.to_string(),
+            hostname: local_hostname(),
+            timeout: Duration::from_millis(config.timeout_ms),
+        })
+    }
+
+    pub(super) async fn send(&self, event: &ChangeEvent) -> Result<()> {
+        let message = self.format(event, std::process::id());
+        tokio::time::timeout(self.timeout, self.deliver(message.as_bytes()))
+            .await
+            .context("Timed out")?
+    }
+
+    async fn deliver(&self, message: &[u8]) -> Result<()> {
+        match &self.target {
+            Target::Udp(address) => {
+                let remote = tokio::net::lookup_host(address)
+                    .await?
+                    .next()
+                    .with_context(|| format!("{} did not resolve", address))?;
+                let local = if remote.is_ipv4() {
+                    "0.0.0.0:0"
+                } else {
+                    "[::]:0"
+                };
+                let socket = tokio::net::UdpSocket::bind(local).await?;
+                socket
+                    .send_to(message, remote)
+                    .await
+                    .with_context(|| format!("Failed to send to {}", address))?;
+            }
+            Target::Tcp(address) => {
+                let mut stream = tokio::net::TcpStream::connect(address)
+                    .await
+                    .with_context(|| format!("Failed to connect to {}", address))?;
+                let mut frame = format!("{} ", message.len()).into_bytes();
+                frame.extend_from_slice(message);
+                stream.write_all(&frame).await?;
+                stream.shutdown().await?;
+            }
+            #[cfg(unix)]
+            Target::Unix(path) => {
+                let socket = tokio::net::UnixDatagram::unbound()?;
+                socket
+                    .send_to(message, path)
+                    .await
+                    .with_context(|| format!("Failed to send to {}", path.display()))?;
+            }
+            #[cfg(not(unix))]
+            Target::Unix(_) => bail!("Unix sockets are not available on this platform"),
+        }
+        Ok(())
+    }
+
+    fn format(&self, event: &ChangeEvent, pid: u32) -> String {
+        let severity = match event.kind {
+            ChangeKind::New => 4,
+            ChangeKind::Offline | ChangeKind::MacChanged => 5,
+            _ => 6,
+        };
+        let mut params = vec![("id", event.device_id.clone()), ("ip", event.ip.clone())];
+        for (name, value) in [
+            ("mac", &event.mac),
+            ("hostname", &event.hostname),
+            ("vendor", &event.vendor),
+            ("previous", &event.previous),
+            ("current", &event.current),
+        ] {
+            if let Some(value) = value {
+                params.push((name, value.clone()));
+            }
+        }
+        if !event.tags.is_empty() {
+            params.push(("tags", event.tags.join(",")));
+        }
+        let data: String = params
+            .iter()
+            .map(|(name, value)| format!(" {}=\"{}\"", name, escape_param(value)))
+            .collect();
+
+        format!(
+            "<{}>1 {} {} {} {} {} [device@32473{}] {}",
+            self.facility as u32 * 8 + severity,
+            event.at.to_rfc3339_opts(SecondsFormat::Micros, true),
+            self.hostname,
+            self.app_name,
+            pid,
+            event.kind,
+            data,
+            event
+        )
+    }
+}
+
+/// "udp://host:port", "tcp://host:port" or "unix:///path"
+fn parse_address(address: &str) -> Result<Target> {
+    let address = address.trim();
+    let Some((scheme, rest)) = address.split_once("://") else {
+        bail!(
+            "'{}' needs a scheme: udp://host:port, tcp://host:port or unix:///path",
+            address
+        );
+    };
+    if rest.is_empty() {
+        bail!("'{}' has no host or path", address);
+    }
+    let with_port = || {
+        let has_port = match rest.strip_prefix('[') {
+            Some(v6) => v6.contains("]:"),
+            None => rest.contains(':'),
+        };
+        if has_port {
+            rest.to_string()
+        } else {
+            format!("{}:{}", rest, DEFAULT_PORT)
+        }
+    };
+    match scheme.to_ascii_lowercase().as_str() {
+        "udp" => Ok(Target::Udp(with_port())),
+        "tcp" => Ok(Target::Tcp(with_port())),
+        "unix" => Ok(Target::Unix(rest.into())),
+        other => bail!("Unknown syslog transport '{}' (udp, tcp or unix)", other),
+    }
+}
+
+/// Escape `"`, `\` and `]` in an SD-PARAM value
+fn escape_param(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '"' | '\\' | ']') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// PRINTUSASCII, as required for header fields
+fn is_header_text(value: &str) -> bool {
+    value.bytes().all(|b| (33..=126).contains(&b))
+}
+
+/// This machine's name for the HOSTNAME field ("-" when unknown)
+fn local_hostname() -> String {
+    #[cfg(unix)]
+    let name = {
+        let mut buffer = [0u8; 256];
+        // SAFETY: the buffer outlives the call and its length is passed
+        let rc = unsafe { libc::gethostname(buffer.as_mut_ptr().cast(), buffer.len()) };
+        let end = buffer.iter().position(|b| *b == 0).unwrap_or(buffer.len());
+        (rc == 0).then(|| String::from_utf8_lossy(&buffer[..end]).into_owned())
+    };
+    #[cfg(not(unix))]
+    let name = std::env::var("COMPUTERNAME").ok();
+
+    name.filter(|n| !n.is_empty() && n.len() <= 255 && is_header_text(n))
+        .unwrap_or_else(|| "-".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notifications::sample_event;
+
+    fn syslog(address: &str) -> Syslog {
+        Syslog::new(&SyslogConfig {
+            name: "syslog".to_string(),
+            address: address.to_string(),
+            facility: "local0".to_string(),
+            app_name: default_app_name(),
+            timeout_ms: 2000,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_format_message() {
+        let mut sink = syslog("udp://127.0.0.1");
+        sink.hostname = "nms01".to_string();
+        let mut event = sample_event();
+        event.at = chrono::DateTime::parse_from_rfc3339("2026-03-10T12:00:00Z")
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+        event.hostname = Some("plc \"7\"".to_string());
+
+        assert_eq!(
+            sink.format(&event, 4242),
+            "<132>1 2026-03-10T12:00:00.000000Z nms01 cartographer 4242 new \
+             [device@32473 id=\"mac:02:00:00:00:00:01\" ip=\"192.0.2.10\" \
+             mac=\"02:00:00:00:00:01\" hostname=\"plc \\\"7\\\"\" tags=\"test\"] \
+             new device 192.0.2.10 (plc \"7\")"
+        );
+    }
+
+    #[test]
+    fn test_parse_address() {
+        assert_eq!(
+            parse_address("udp://10.0.0.5").unwrap(),
+            Target::Udp("10.0.0.5:514".to_string())
+        );
+        assert_eq!(
+            parse_address("TCP://[2001:db8::1]:6514").unwrap(),
+            Target::Tcp("[2001:db8::1]:6514".to_string())
+        );
+        assert_eq!(
+            parse_address("unix:///dev/log").unwrap(),
+            Target::Unix("/dev/log".into())
+        );
+        for bad in ["10.0.0.5:514", "http://10.0.0.5", "udp://"] {
+            assert!(parse_address(bad).is_err(), "{} should be rejected", bad);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_send_udp_and_tcp() {
+        let udp = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let sink = syslog(&format!("udp://{}", udp.local_addr().unwrap()));
+        sink.send(&sample_event()).await.unwrap();
+        let mut buffer = [0u8; 1024];
+        let len = udp.recv(&mut buffer).await.unwrap();
+        assert!(buffer[..len].starts_with(b"<132>1 "));
+
+        let tcp = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let sink = syslog(&format!("tcp://{}", tcp.local_addr().unwrap()));
+        let event = sample_event();
+        let (sent, accepted) = tokio::join!(sink.send(&event), tcp.accept());
+        sent.unwrap();
+        let mut received = String::new();
+        tokio::io::AsyncReadExt::read_to_string(&mut accepted.unwrap().0, &mut received)
+            .await
+            .unwrap();
+        let (length, message) = received.split_once(' ').unwrap();
+        assert_eq!(length.parse::<usize>().unwrap(), message.len());
+        assert!(message.starts_with("<132>1 "));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_send_unix_socket() {
+        let path = std::env::temp_dir().join(format!(
+            "cartographer-syslog-test-{}.sock",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        let log = tokio::net::UnixDatagram::bind(&path).unwrap();
+        let mut sink = syslog(&format!("unix://{}", path.display()));
+        sink.hostname = "nms01".to_string();
+        let event = sample_event();
+
+        sink.send(&event).await.unwrap();
+        let mut buffer = [0u8; 1024];
+        let len = log.recv(&mut buffer).await.unwrap();
+        // One datagram per message, without TCP framing
+        assert_eq!(
+            std::str::from_utf8(&buffer[..len]).unwrap(),
+            sink.format(&event, std::process::id())
+        );
+
+        // Nobody listening
+        drop(log);
+        std::fs::remove_file(&path).unwrap();
+        let error = sink.send(&event).await.unwrap_err();
+        assert!(
+            format!("{:#}", error).contains("Failed to send to"),
+            "{:#}",
+            error
+        );
+    }
+}
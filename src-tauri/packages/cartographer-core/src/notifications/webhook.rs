@@ -0,0 +1,195 @@
+//! Webhook sink: POSTs each event as JSON
+//!
+//! The body is the event itself unless the sink has a template, a JSON
+//! document with `{{field}}` placeholders (`{{message}}`, `{{ip}}`,
+//! `{{mac}}`, ...; see `event_fields`). Values are escaped for use inside
+//! JSON strings, so a template for a chat webhook looks like
+//! `{"text": "Cartographer: {{message}}"}`.
+
+use super::{WebhookConfig, event_fields, sample_event};
+use crate::inventory::ChangeEvent;
+use anyhow::{Context, Result, bail};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use std::time::Duration;
+
+pub(super) struct Webhook {
+    client: reqwest::Client,
+    url: reqwest::Url,
+    template: Option<String>,
+}
+
+impl Webhook {
+    pub(super) fn new(config: &WebhookConfig) -> Result<Self> {
+        let url = reqwest::Url::parse(config.url.trim())
+            .with_context(|| format!("'{}' is not a URL", config.url))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            bail!("Webhook URL must be http or https, not {}", url.scheme());
+        }
+
+        let mut headers = HeaderMap::new();
+        for (name, value) in &config.headers {
+            let name = HeaderName::from_bytes(name.as_bytes())
+                .with_context(|| format!("Invalid header name '{}'", name))?;
+            let value = HeaderValue::from_str(value)
+                .with_context(|| format!("Invalid value for header {}", name))?;
+            headers.insert(name, value);
+        }
+
+        if let Some(template) = &config.template {
+            let body = render(template, &sample_event())?;
+            serde_json::from_str::<serde_json::Value>(&body)
+                .context("Template does not produce valid JSON")?;
+        }
+
+        let client = reqwest::Client::builder()
+            .default_headers(headers)
+            .timeout(Duration::from_millis(config.timeout_ms))
+            .build()
+            .context("Failed to create HTTP client")?;
+        Ok(Self {
+            client,
+            url,
+            template: config.template.clone(),
+        })
+    }
+
+    pub(super) async fn send(&self, event: &ChangeEvent) -> Result<()> {
+        let body = match &self.template {
+            Some(template) => render(template, event)?,
+            None => default_body(event)?,
+        };
+        self.client
+            .post(self.url.clone())
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body)
+            .send()
+            .await
+            .with_context(|| format!("POST to {} failed", self.url.host_str().unwrap_or("?")))?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+/// The event as JSON, with the human-readable message added
+fn default_body(event: &ChangeEvent) -> Result<String> {
+    let mut body = serde_json::to_value(event)?;
+    if let Some(object) = body.as_object_mut() {
+        object.insert("message".to_string(), event.to_string().into());
+    }
+    Ok(body.to_string())
+}
+
+/// Fill in a template's `{{field}}` placeholders; unknown fields are errors
+fn render(template: &str, event: &ChangeEvent) -> Result<String> {
+    let fields = event_fields(event);
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            bail!("Unclosed '{{{{' in template");
+        };
+        let key = after[..end].trim();
+        let Some((_, value)) = fields.iter().find(|(name, _)| *name == key) else {
+            bail!("Unknown template field '{}'", key);
+        };
+        // Escaped as the inside of a JSON string
+        let quoted = serde_json::to_string(value)?;
+        out.push_str(&quoted[1..quoted.len() - 1]);
+        rest = &after[end + 2..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_server::mock_server;
+
+    #[test]
+    fn test_render_template() {
+        let mut event = sample_event();
+        event.hostname = Some("lab \"PLC\"".to_string());
+        let body = render(r#"{"text": "{{message}}", "mac": "{{ mac }}"}"#, &event).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["text"], "new device 192.0.2.10 (lab \"PLC\")");
+        assert_eq!(json["mac"], "02:00:00:00:00:01");
+
+        assert!(render("{{serial}}", &event).is_err());
+        assert!(render("{{message", &event).is_err());
+
+        let config = |template: &str| WebhookConfig {
+            name: "hook".to_string(),
+            url: "https://hooks.example.com/alert".to_string(),
+            headers: Default::default(),
+            template: Some(template.to_string()),
+            timeout_ms: 1000,
+        };
+        assert!(Webhook::new(&config(r#"{"text": "{{message}}"}"#)).is_ok());
+        assert!(Webhook::new(&config(r#"{"text": {{message}}}"#)).is_err());
+    }
+
+    fn webhook(url: &str, template: Option<&str>) -> Webhook {
+        Webhook::new(&WebhookConfig {
+            name: "hook".to_string(),
+            url: url.to_string(),
+            headers: [("Authorization".to_string(), "Bearer abc".to_string())].into(),
+            template: template.map(String::from),
+            timeout_ms: 5000,
+        })
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_send_custom_template() {
+        let mut event = sample_event();
+        event.kind = crate::inventory::ChangeKind::IpChanged;
+        event.tags = vec!["ot".to_string(), "plc".to_string()];
+        event.previous = Some("192.0.2.9".to_string());
+        event.current = Some("192.0.2.10".to_string());
+        let template = r#"{"text": "Cartographer: {{message}}", "kind": "{{kind}}", "from": "{{previous}}", "to": "{{current}}", "tags": "{{tags}}", "vendor": "{{vendor}}"}"#;
+
+        let (url, requests) = mock_server(vec![(200, "")]).await;
+        let hook = webhook(&format!("{}/hook", url), Some(template));
+        hook.send(&event).await.unwrap();
+        let request = requests.lock().unwrap()[0].clone();
+        assert!(request.head.starts_with("post /hook "), "{}", request.head);
+        assert!(request.head.contains("authorization: bearer abc"));
+        assert!(request.head.contains("content-type: application/json"));
+
+        let json: serde_json::Value = serde_json::from_str(&request.body).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "text": "Cartographer: 192.0.2.10 (cartographer-test) changed IP from 192.0.2.9",
+                "kind": "ip_changed",
+                "from": "192.0.2.9",
+                "to": "192.0.2.10",
+                "tags": "ot,plc",
+                "vendor": "",
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_send_default_body() {
+        let event = sample_event();
+        let (url, requests) = mock_server(vec![(200, "")]).await;
+        webhook(&url, None).send(&event).await.unwrap();
+        let body = requests.lock().unwrap()[0].body.clone();
+
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["kind"], "new");
+        assert_eq!(json["deviceId"], "mac:02:00:00:00:00:01");
+        assert_eq!(json["message"], "new device 192.0.2.10 (cartographer-test)");
+        let sent: ChangeEvent = serde_json::from_value(json).unwrap();
+        assert_eq!(sent, event);
+
+        // Error statuses are failed deliveries
+        let (url, requests) = mock_server(vec![(500, "")]).await;
+        assert!(webhook(&url, None).send(&event).await.is_err());
+        assert_eq!(requests.lock().unwrap().len(), 1);
+    }
+}
@@ -1,6 +1,13 @@
-//! ARP table scanning using system commands
+//! ARP and IPv6 neighbor table scanning
+//!
+//! Linux reads the kernel neighbor table natively (see `neighbor`);
+//! Windows parses `arp -a` / `netsh` and macOS `arp -a` / `ndp -an` output.
 
-use super::{hidden_command, Device};
+#[cfg(any(target_os = "windows", target_os = "macos"))]
+use super::hidden_command;
+#[cfg(target_os = "linux")]
+use super::neighbor;
+use super::Device;
 use anyhow::Result;
 
 /// Get devices from the system ARP table
@@ -54,12 +61,8 @@ fn get_arp_table_windows() -> Result<Vec<Device>> {
 
                 if mac.contains('-') && mac.len() == 17 {
                     devices_by_ip.entry(ip.to_string()).or_insert_with(|| Device {
-                        ip: ip.to_string(),
                         mac: Some(mac.replace('-', ":")),
-                        response_time_ms: None,
-                        hostname: None,
-                        vendor: None,
-                        device_type: None,
+                        ..Device::new(ip)
                     });
                 }
             }
@@ -71,40 +74,27 @@ fn get_arp_table_windows() -> Result<Vec<Device>> {
 
 #[cfg(target_os = "linux")]
 fn get_arp_table_linux() -> Result<Vec<Device>> {
-    let output = hidden_command("arp").args(["-n"]).output()?;
-
-    let output_str = String::from_utf8_lossy(&output.stdout);
-    let mut devices = Vec::new();
-
-    for line in output_str.lines().skip(1) {
-        let line = line.trim();
-        if line.is_empty() {
-            continue;
-        }
-
-        let parts: Vec<&str> = line.split_whitespace().collect();
-        if parts.len() >= 3 {
-            let ip = parts[0];
-            let mac = parts[2];
-
-            if ip.parse::<std::net::IpAddr>().is_ok() && mac.contains(':') && mac.len() == 17 {
-                if mac == "(incomplete)" || mac == "00:00:00:00:00:00" {
-                    continue;
-                }
+    Ok(neighbor::read_neighbor_table()?
+        .into_iter()
+        .filter(|e| e.ip.parse::<std::net::Ipv4Addr>().is_ok())
+        .filter_map(neighbor_device)
+        .collect())
+}
 
-                devices.push(Device {
-                    ip: ip.to_string(),
-                    mac: Some(mac.to_string()),
-                    response_time_ms: None,
-                    hostname: None,
-                    vendor: None,
-                    device_type: None,
-                });
-            }
-        }
+/// Device for a resolved neighbor table entry, keeping its interface and
+/// state; unresolved entries are skipped
+#[cfg(target_os = "linux")]
+fn neighbor_device(entry: neighbor::NeighborEntry) -> Option<Device> {
+    if !entry.state.is_resolved() {
+        tracing::trace!("Skipping {} neighbor {} on {}", entry.state, entry.ip, entry.interface);
+        return None;
     }
-
-    Ok(devices)
+    Some(Device {
+        mac: Some(entry.mac?),
+        interface: Some(entry.interface),
+        neighbor_state: Some(entry.state),
+        ..Device::new(entry.ip)
+    })
 }
 
 #[cfg(target_os = "macos")]
@@ -135,12 +125,8 @@ fn get_arp_table_macos() -> Result<Vec<Device>> {
                             }
 
                             devices.push(Device {
-                                ip: ip.to_string(),
                                 mac: Some(mac.to_string()),
-                                response_time_ms: None,
-                                hostname: None,
-                                vendor: None,
-                                device_type: None,
+                                ..Device::new(ip)
                             });
                         }
                     }
@@ -151,3 +137,179 @@ fn get_arp_table_macos() -> Result<Vec<Device>> {
 
     Ok(devices)
 }
+
+/// Get IPv6 neighbors seen on any of the given interfaces, reading the
+/// neighbor table once. Each device's `interface` is the one it was seen on.
+///
+/// Link-local and global addresses are returned as separate devices; the
+/// scanner merges them with IPv4 entries by MAC address.
+pub async fn get_ipv6_neighbors(interfaces: &[&str]) -> Result<Vec<Device>> {
+    #[cfg(target_os = "windows")]
+    {
+        get_ipv6_neighbors_windows(interfaces)
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        get_ipv6_neighbors_linux(interfaces)
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        get_ipv6_neighbors_macos(interfaces)
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+    {
+        let _ = interfaces;
+        Ok(Vec::new())
+    }
+}
+
+/// Whether an IPv6 neighbor address is a unicast host worth reporting
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+fn is_ipv6_host(ip: &std::net::Ipv6Addr) -> bool {
+    !ip.is_multicast() && !ip.is_unspecified() && !ip.is_loopback()
+}
+
+#[cfg(target_os = "linux")]
+fn get_ipv6_neighbors_linux(interfaces: &[&str]) -> Result<Vec<Device>> {
+    Ok(neighbor::read_neighbor_table()?
+        .into_iter()
+        .filter(|e| {
+            e.ip.parse::<std::net::Ipv6Addr>().is_ok_and(|ip| is_ipv6_host(&ip))
+                && interfaces.contains(&e.interface.as_str())
+        })
+        .filter_map(neighbor_device)
+        .collect())
+}
+
+#[cfg(target_os = "macos")]
+fn get_ipv6_neighbors_macos(interfaces: &[&str]) -> Result<Vec<Device>> {
+    let output = hidden_command("ndp").args(["-an"]).output()?;
+
+    let output_str = String::from_utf8_lossy(&output.stdout);
+    let mut devices = Vec::new();
+
+    // Columns: Neighbor, Linklayer Address, Netif, Expire, St, Flgs, Prbs
+    for line in output_str.lines().skip(1) {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.len() < 3 || !interfaces.contains(&parts[2]) {
+            continue;
+        }
+
+        // Link-local neighbors carry a zone suffix ("fe80::1%en0")
+        let ip = parts[0].split('%').next().unwrap_or_default();
+        let mac = parts[1];
+
+        match ip.parse::<std::net::Ipv6Addr>() {
+            Ok(addr) if is_ipv6_host(&addr) => {}
+            _ => continue,
+        }
+
+        if mac == "(incomplete)" || !mac.contains(':') {
+            continue;
+        }
+
+        devices.push(Device {
+            mac: Some(mac.to_string()),
+            interface: Some(parts[2].to_string()),
+            ..Device::new(ip)
+        });
+    }
+
+    Ok(devices)
+}
+
+#[cfg(target_os = "windows")]
+fn get_ipv6_neighbors_windows(interfaces: &[&str]) -> Result<Vec<Device>> {
+    let output = hidden_command("netsh")
+        .args(["interface", "ipv6", "show", "neighbors"])
+        .output()?;
+
+    let output_str = String::from_utf8_lossy(&output.stdout);
+    let mut devices = Vec::new();
+    let mut in_interface: Option<&str> = None;
+
+    for line in output_str.lines() {
+        let line = line.trim();
+
+        // Section headers look like "Interface 12: Wi-Fi"; `interfaces` come
+        // from ipconfig ("Wireless LAN adapter Wi-Fi").
+        if let Some(rest) = line.strip_prefix("Interface ") {
+            let name = rest.split_once(':').map(|(_, n)| n.trim()).unwrap_or("");
+            in_interface = interfaces
+                .iter()
+                .copied()
+                .find(|interface| !name.is_empty() && interface.ends_with(name));
+            continue;
+        }
+
+        let Some(interface) = in_interface else {
+            continue;
+        };
+
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.len() < 3 || parts[2].starts_with("Unreachable") || parts[2] == "Incomplete" {
+            continue;
+        }
+
+        let ip = parts[0];
+        let mac = parts[1];
+
+        match ip.parse::<std::net::Ipv6Addr>() {
+            Ok(addr) if is_ipv6_host(&addr) => {}
+            _ => continue,
+        }
+
+        if mac.contains('-') && mac.len() == 17 && mac != "00-00-00-00-00-00" {
+            devices.push(Device {
+                mac: Some(mac.replace('-', ":")),
+                interface: Some(interface.to_string()),
+                ..Device::new(ip)
+            });
+        }
+    }
+
+    Ok(devices)
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use neighbor::{NeighborEntry, NeighborState};
+
+    fn entry(ip: &str, mac: Option<&str>, state: NeighborState) -> NeighborEntry {
+        NeighborEntry {
+            ip: ip.to_string(),
+            mac: mac.map(String::from),
+            interface: "eth0".to_string(),
+            state,
+        }
+    }
+
+    #[test]
+    fn test_neighbor_device() {
+        let device = neighbor_device(entry(
+            "192.168.1.20",
+            Some("3c:52:82:1a:2b:3c"),
+            NeighborState::Stale,
+        ))
+        .unwrap();
+        assert_eq!(device.primary_ip(), "192.168.1.20");
+        assert_eq!(device.mac.as_deref(), Some("3c:52:82:1a:2b:3c"));
+        assert_eq!(device.interface.as_deref(), Some("eth0"));
+        assert_eq!(device.neighbor_state, Some(NeighborState::Stale));
+
+        assert!(neighbor_device(entry("192.168.1.77", None, NeighborState::Failed)).is_none());
+        assert!(
+            neighbor_device(entry(
+                "192.168.1.78",
+                Some("3c:52:82:1a:2b:3d"),
+                NeighborState::Incomplete
+            ))
+            .is_none()
+        );
+        assert!(neighbor_device(entry("192.168.1.79", None, NeighborState::Reachable)).is_none());
+    }
+}
@@ -0,0 +1,415 @@
+//! Active ARP request sweep (Linux, AF_PACKET)
+//!
+//! Hosts that drop ICMP (firewalled Windows machines, many phones) still
+//! have to answer ARP. With `CAP_NET_RAW` we broadcast a who-has request
+//! for every address in the subnet and collect the replies.
+
+use super::config::ArpSweepConfig;
+use anyhow::Result;
+use std::net::Ipv4Addr;
+
+const ETH_HEADER_LEN: usize = 14;
+const ARP_PACKET_LEN: usize = 28;
+/// Minimum Ethernet payload is 46 bytes; pad the 42-byte request to 60
+const FRAME_LEN: usize = 60;
+const ETHERTYPE_ARP: u16 = 0x0806;
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ARP_HTYPE_ETHERNET: u16 = 1;
+const ARP_OP_REQUEST: u16 = 1;
+const ARP_OP_REPLY: u16 = 2;
+
+/// Build a broadcast ARP "who-has `target`" request frame.
+pub fn build_arp_request(src_mac: [u8; 6], src_ip: Ipv4Addr, target: Ipv4Addr) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(FRAME_LEN);
+    // Ethernet header
+    frame.extend_from_slice(&[0xff; 6]);
+    frame.extend_from_slice(&src_mac);
+    frame.extend_from_slice(&ETHERTYPE_ARP.to_be_bytes());
+    // ARP payload
+    frame.extend_from_slice(&ARP_HTYPE_ETHERNET.to_be_bytes());
+    frame.extend_from_slice(&ETHERTYPE_IPV4.to_be_bytes());
+    frame.push(6); // hardware address length
+    frame.push(4); // protocol address length
+    frame.extend_from_slice(&ARP_OP_REQUEST.to_be_bytes());
+    frame.extend_from_slice(&src_mac);
+    frame.extend_from_slice(&src_ip.octets());
+    frame.extend_from_slice(&[0; 6]);
+    frame.extend_from_slice(&target.octets());
+    frame.resize(FRAME_LEN, 0);
+    frame
+}
+
+/// Parse an Ethernet frame carrying an ARP reply, returning the sender's
+/// IPv4 address and MAC.
+pub fn parse_arp_reply(frame: &[u8]) -> Option<(Ipv4Addr, [u8; 6])> {
+    if frame.len() < ETH_HEADER_LEN + ARP_PACKET_LEN {
+        return None;
+    }
+    if u16::from_be_bytes([frame[12], frame[13]]) != ETHERTYPE_ARP {
+        return None;
+    }
+
+    let arp = &frame[ETH_HEADER_LEN..];
+    let htype = u16::from_be_bytes([arp[0], arp[1]]);
+    let ptype = u16::from_be_bytes([arp[2], arp[3]]);
+    let op = u16::from_be_bytes([arp[6], arp[7]]);
+    if htype != ARP_HTYPE_ETHERNET || ptype != ETHERTYPE_IPV4 || arp[4] != 6 || arp[5] != 4 {
+        return None;
+    }
+    if op != ARP_OP_REPLY {
+        return None;
+    }
+
+    let mut mac = [0u8; 6];
+    mac.copy_from_slice(&arp[8..14]);
+    let ip = Ipv4Addr::new(arp[14], arp[15], arp[16], arp[17]);
+    Some((ip, mac))
+}
+
+/// Format a MAC as lowercase "aa:bb:cc:dd:ee:ff"
+fn format_mac(mac: &[u8; 6]) -> String {
+    mac.iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+/// Whether an AF_PACKET socket can be opened (root or `CAP_NET_RAW`).
+pub fn can_arp_sweep() -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        match packet::open_socket() {
+            Ok(_) => true,
+            Err(e) => {
+                tracing::debug!("ARP sweep unavailable: {}", e);
+                false
+            }
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        false
+    }
+}
+
+/// Sweep the given hosts with ARP requests on `interface`.
+/// Returns responding hosts with their MAC and round-trip time in ms.
+pub async fn arp_sweep(
+    interface: &str,
+    local_ip: Ipv4Addr,
+    targets: Vec<Ipv4Addr>,
+    config: &ArpSweepConfig,
+) -> Result<Vec<(Ipv4Addr, String, f64)>> {
+    #[cfg(target_os = "linux")]
+    {
+        let interface = interface.to_string();
+        let config = config.clone();
+        tokio::task::spawn_blocking(move || {
+            packet::sweep_blocking(&interface, local_ip, &targets, &config)
+        })
+        .await
+        .map_err(|e| anyhow::anyhow!("ARP sweep task panicked: {}", e))?
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = (interface, local_ip, targets, config);
+        Err(anyhow::anyhow!("ARP sweep is only supported on Linux"))
+    }
+}
+
+/// AF_PACKET socket helpers, shared with the LLDP/CDP capture
+#[cfg(target_os = "linux")]
+pub(super) mod packet {
+    use super::super::is_scan_cancelled;
+    use super::*;
+    use anyhow::Context;
+    use std::collections::HashMap;
+    use std::io;
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+    use std::time::{Duration, Instant};
+
+    fn arp_protocol() -> u16 {
+        (libc::ETH_P_ARP as u16).to_be()
+    }
+
+    pub(super) fn open_socket() -> io::Result<OwnedFd> {
+        let raw = unsafe {
+            libc::socket(
+                libc::AF_PACKET,
+                libc::SOCK_RAW | libc::SOCK_CLOEXEC | libc::SOCK_NONBLOCK,
+                arp_protocol() as libc::c_int,
+            )
+        };
+        if raw < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(unsafe { OwnedFd::from_raw_fd(raw) })
+    }
+
+    /// Read the interface MAC from sysfs.
+    pub(in crate::scanner) fn interface_mac(interface: &str) -> Result<[u8; 6]> {
+        let path = format!("/sys/class/net/{}/address", interface);
+        let text =
+            std::fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path))?;
+
+        let octets: Vec<u8> = text
+            .trim()
+            .split(':')
+            .filter_map(|o| u8::from_str_radix(o, 16).ok())
+            .collect();
+        octets
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("Interface {} has no Ethernet address", interface))
+    }
+
+    /// Bind to `interface` for frames of `protocol` (network byte order).
+    /// Returns the interface index.
+    pub(in crate::scanner) fn bind_to_interface(
+        fd: &OwnedFd,
+        interface: &str,
+        protocol: u16,
+    ) -> Result<u32> {
+        let name = std::ffi::CString::new(interface).context("Invalid interface name")?;
+        let ifindex = unsafe { libc::if_nametoindex(name.as_ptr()) };
+        if ifindex == 0 {
+            return Err(io::Error::last_os_error())
+                .with_context(|| format!("Unknown interface {}", interface));
+        }
+
+        let mut addr: libc::sockaddr_ll = unsafe { std::mem::zeroed() };
+        addr.sll_family = libc::AF_PACKET as u16;
+        addr.sll_protocol = protocol;
+        addr.sll_ifindex = ifindex as i32;
+
+        let result = unsafe {
+            libc::bind(
+                fd.as_raw_fd(),
+                &addr as *const libc::sockaddr_ll as *const libc::sockaddr,
+                std::mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t,
+            )
+        };
+        if result < 0 {
+            return Err(io::Error::last_os_error())
+                .with_context(|| format!("Failed to bind to {}", interface));
+        }
+        Ok(ifindex)
+    }
+
+    pub(in crate::scanner) fn wait_readable(fd: &OwnedFd, timeout: Duration) -> bool {
+        let mut pfd = libc::pollfd {
+            fd: fd.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let timeout_ms = timeout.as_micros().div_ceil(1000).min(i32::MAX as u128) as i32;
+        unsafe { libc::poll(&mut pfd, 1, timeout_ms) > 0 }
+    }
+
+    pub(in crate::scanner) fn setsockopt<T>(
+        fd: &OwnedFd,
+        level: i32,
+        name: i32,
+        value: &T,
+    ) -> io::Result<()> {
+        let result = unsafe {
+            libc::setsockopt(
+                fd.as_raw_fd(),
+                level,
+                name,
+                value as *const T as *const libc::c_void,
+                std::mem::size_of::<T>() as libc::socklen_t,
+            )
+        };
+        if result < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    struct SweepState {
+        index: HashMap<Ipv4Addr, usize>,
+        sent_at: Vec<Option<Instant>>,
+        replies: Vec<Option<([u8; 6], f64)>>,
+        answered: usize,
+    }
+
+    impl SweepState {
+        fn drain(&mut self, fd: &OwnedFd, buf: &mut [u8]) {
+            loop {
+                let received = unsafe {
+                    libc::recv(
+                        fd.as_raw_fd(),
+                        buf.as_mut_ptr() as *mut libc::c_void,
+                        buf.len(),
+                        0,
+                    )
+                };
+                if received <= 0 {
+                    return;
+                }
+
+                let Some((ip, mac)) = parse_arp_reply(&buf[..received as usize]) else {
+                    continue;
+                };
+                let Some(&i) = self.index.get(&ip) else {
+                    continue;
+                };
+                if let (None, Some(sent)) = (self.replies[i], self.sent_at[i]) {
+                    self.replies[i] = Some((mac, sent.elapsed().as_secs_f64() * 1000.0));
+                    self.answered += 1;
+                }
+            }
+        }
+    }
+
+    pub(super) fn sweep_blocking(
+        interface: &str,
+        local_ip: Ipv4Addr,
+        targets: &[Ipv4Addr],
+        config: &ArpSweepConfig,
+    ) -> Result<Vec<(Ipv4Addr, String, f64)>> {
+        let fd = open_socket().context("Failed to open AF_PACKET socket")?;
+        bind_to_interface(&fd, interface, arp_protocol())?;
+        let src_mac = interface_mac(interface)?;
+
+        let interval = if config.rate_limit > 0 {
+            Duration::from_secs_f64(1.0 / config.rate_limit as f64)
+        } else {
+            Duration::ZERO
+        };
+        let timeout = Duration::from_millis(config.timeout_ms);
+
+        let mut state = SweepState {
+            index: targets.iter().enumerate().map(|(i, ip)| (*ip, i)).collect(),
+            sent_at: vec![None; targets.len()],
+            replies: vec![None; targets.len()],
+            answered: 0,
+        };
+        let mut buf = [0u8; 1514];
+
+        tracing::info!(
+            "ARP sweep of {} hosts on {} ({} pps, {} retries, {}ms timeout)",
+            targets.len(),
+            interface,
+            config.rate_limit,
+            config.retries,
+            config.timeout_ms
+        );
+
+        for attempt in 0..=config.retries {
+            let pending: Vec<usize> = (0..targets.len())
+                .filter(|&i| state.replies[i].is_none())
+                .collect();
+            if pending.is_empty() {
+                break;
+            }
+            if attempt > 0 {
+                tracing::debug!("ARP retry {}: {} hosts", attempt, pending.len());
+            }
+
+            let mut next_send = Instant::now();
+            for (n, &i) in pending.iter().enumerate() {
+                if n % 64 == 0 && is_scan_cancelled() {
+                    tracing::info!("ARP sweep cancelled");
+                    return Err(anyhow::anyhow!("Scan cancelled by user"));
+                }
+
+                // Rate limit: collect replies while waiting for the next send slot
+                loop {
+                    let now = Instant::now();
+                    if now >= next_send {
+                        break;
+                    }
+                    if wait_readable(&fd, next_send - now) {
+                        state.drain(&fd, &mut buf);
+                    }
+                }
+
+                let frame = build_arp_request(src_mac, local_ip, targets[i]);
+                let sent = unsafe {
+                    libc::send(
+                        fd.as_raw_fd(),
+                        frame.as_ptr() as *const libc::c_void,
+                        frame.len(),
+                        0,
+                    )
+                };
+                if sent < 0 {
+                    tracing::trace!(
+                        "ARP send to {} failed: {}",
+                        targets[i],
+                        io::Error::last_os_error()
+                    );
+                } else {
+                    state.sent_at[i] = Some(Instant::now());
+                }
+                next_send += interval;
+                state.drain(&fd, &mut buf);
+            }
+
+            // Wait for late replies
+            let deadline = Instant::now() + timeout;
+            while state.answered < targets.len() {
+                let now = Instant::now();
+                if now >= deadline || is_scan_cancelled() {
+                    break;
+                }
+                if wait_readable(&fd, deadline - now) {
+                    state.drain(&fd, &mut buf);
+                }
+            }
+        }
+
+        Ok(targets
+            .iter()
+            .zip(state.replies)
+            .filter_map(|(ip, reply)| reply.map(|(mac, rtt)| (*ip, format_mac(&mac), rtt)))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SRC_MAC: [u8; 6] = [0x02, 0x42, 0xac, 0x11, 0x00, 0x02];
+
+    #[test]
+    fn test_build_arp_request() {
+        let frame = build_arp_request(
+            SRC_MAC,
+            Ipv4Addr::new(192, 168, 1, 20),
+            Ipv4Addr::new(192, 168, 1, 1),
+        );
+        assert_eq!(frame.len(), FRAME_LEN);
+        assert_eq!(&frame[0..6], &[0xff; 6]);
+        assert_eq!(&frame[6..12], &SRC_MAC);
+        assert_eq!(&frame[12..14], &[0x08, 0x06]);
+        // opcode request, sender MAC/IP, target IP
+        assert_eq!(&frame[20..22], &[0x00, 0x01]);
+        assert_eq!(&frame[22..28], &SRC_MAC);
+        assert_eq!(&frame[28..32], &[192, 168, 1, 20]);
+        assert_eq!(&frame[38..42], &[192, 168, 1, 1]);
+        // Reply parser ignores requests
+        assert_eq!(parse_arp_reply(&frame), None);
+    }
+
+    #[test]
+    fn test_parse_arp_reply() {
+        // Reply from 192.168.1.1 (a0:63:91:12:34:56) to 192.168.1.20
+        let frame: [u8; 60] = [
+            0x02, 0x42, 0xac, 0x11, 0x00, 0x02, 0xa0, 0x63, 0x91, 0x12, 0x34, 0x56, 0x08, 0x06,
+            0x00, 0x01, 0x08, 0x00, 0x06, 0x04, 0x00, 0x02, 0xa0, 0x63, 0x91, 0x12, 0x34, 0x56,
+            0xc0, 0xa8, 0x01, 0x01, 0x02, 0x42, 0xac, 0x11, 0x00, 0x02, 0xc0, 0xa8, 0x01, 0x14, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        ];
+
+        let (ip, mac) = parse_arp_reply(&frame).expect("valid reply");
+        assert_eq!(ip, Ipv4Addr::new(192, 168, 1, 1));
+        assert_eq!(format_mac(&mac), "a0:63:91:12:34:56");
+
+        assert_eq!(parse_arp_reply(&frame[..30]), None);
+    }
+}
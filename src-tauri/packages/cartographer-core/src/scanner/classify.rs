@@ -0,0 +1,703 @@
+//! Rule-based device classification
+//!
+//! Device types and tags come from declarative rules instead of code: a
+//! built-in ruleset compiled into the agent (`classify_rules.toml`) plus an
+//! optional user rules file in the same format, `rules.toml` next to
+//! config.toml unless `[scan.classify] rules_file` points elsewhere.
+//!
+//! ```toml
+//! [[rule]]
+//! name = "lab-plcs"
+//! device_type = "iot"
+//! tags = ["ot", "lab"]
+//! priority = 50
+//! hostname = '^plc-[0-9]+$'   # regex, case-insensitive
+//! ports = [502]               # any of these open
+//! ```
+//!
+//! A rule matches when all of its conditions do: `vendor` (substrings),
+//! `mac_prefix`, `hostname` and `model` (regexes), `ports`, `mdns_services`,
+//! `upnp_type` (the URN's type name, e.g. "MediaRenderer"), `sys_object_id`
+//! (OID prefixes) and `os` (guessed family). The matching rule with the
+//! highest priority sets the device type; user rules come first, so they
+//! win ties, then file order decides. Every matching rule adds its tags.
+
+use super::Device;
+use super::config::ClassifyConfig;
+use super::dhcp::OsFamily;
+use anyhow::{Context, Result};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+const BUILTIN_RULES: &str = include_str!("classify_rules.toml");
+
+static BUILTIN: LazyLock<Vec<Rule>> = LazyLock::new(|| {
+    parse_rules(BUILTIN_RULES, RuleSource::Builtin).expect("built-in rules are valid")
+});
+
+/// Where a rule was defined
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleSource {
+    Builtin,
+    User,
+}
+
+impl std::fmt::Display for RuleSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RuleSource::Builtin => write!(f, "built-in"),
+            RuleSource::User => write!(f, "user"),
+        }
+    }
+}
+
+/// Which rule set a device's type, and why
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Classification {
+    pub rule: String,
+    pub source: RuleSource,
+    pub priority: i32,
+    /// Conditions of the rule that matched (e.g. `port 9100 open`)
+    pub matched: Vec<String>,
+}
+
+impl std::fmt::Display for Classification {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "rule {} ({}, priority {}): {}",
+            self.rule,
+            self.source,
+            self.priority,
+            self.matched.join(", ")
+        )
+    }
+}
+
+/// Outcome of running the rules against one device
+#[derive(Debug, Clone, PartialEq)]
+pub struct Verdict {
+    pub device_type: String,
+    pub classification: Classification,
+    /// Tags of every matching rule, sorted
+    pub tags: Vec<String>,
+}
+
+/// A rule as written in a rules file
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleSpec {
+    name: String,
+    device_type: String,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    priority: i32,
+    #[serde(default)]
+    vendor: Vec<String>,
+    #[serde(default)]
+    mac_prefix: Vec<String>,
+    hostname: Option<String>,
+    model: Option<String>,
+    #[serde(default)]
+    ports: Vec<u16>,
+    #[serde(default)]
+    mdns_services: Vec<String>,
+    #[serde(default)]
+    upnp_type: Vec<String>,
+    #[serde(default)]
+    sys_object_id: Vec<String>,
+    #[serde(default)]
+    os: Vec<OsFamily>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RulesFile {
+    #[serde(default)]
+    rule: Vec<RuleSpec>,
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+    name: String,
+    source: RuleSource,
+    device_type: String,
+    tags: Vec<String>,
+    priority: i32,
+    conditions: Vec<Condition>,
+}
+
+#[derive(Debug, Clone)]
+enum Condition {
+    /// Lowercase substrings of the vendor name
+    Vendor(Vec<String>),
+    /// Lowercase hex digits without separators
+    MacPrefix(Vec<String>),
+    Hostname(Regex),
+    Model(Regex),
+    Ports(Vec<u16>),
+    /// Lowercase service types without a trailing ".local"
+    MdnsServices(Vec<String>),
+    /// Lowercase UPnP type names
+    UpnpType(Vec<String>),
+    SysObjectId(Vec<String>),
+    Os(Vec<OsFamily>),
+}
+
+impl Condition {
+    /// Describe how the device satisfies this condition, or `None`
+    fn check(&self, device: &Device) -> Option<String> {
+        match self {
+            Condition::Vendor(needles) => {
+                let vendor = device.vendor.as_deref()?;
+                let lower = vendor.to_lowercase();
+                let needle = needles.iter().find(|n| lower.contains(n.as_str()))?;
+                Some(format!("vendor \"{}\" contains \"{}\"", vendor, needle))
+            }
+            Condition::MacPrefix(prefixes) => {
+                let mac = device.mac.as_deref()?;
+                let hex = mac_hex(mac);
+                let prefix = prefixes.iter().find(|p| hex.starts_with(p.as_str()))?;
+                Some(format!("mac {} starts with {}", mac, prefix))
+            }
+            Condition::Hostname(pattern) => {
+                let hostname = device.hostname.as_deref()?;
+                // Match "nas-1" in "nas-1.lan" as well as the full name
+                let short = hostname.split('.').next().unwrap_or(hostname);
+                (pattern.is_match(short) || pattern.is_match(hostname))
+                    .then(|| format!("hostname \"{}\" matches /{}/", hostname, pattern.as_str()))
+            }
+            Condition::Model(pattern) => {
+                let model = device
+                    .model
+                    .as_deref()
+                    .or_else(|| device.upnp.as_ref()?.model_name.as_deref())?;
+                pattern
+                    .is_match(model)
+                    .then(|| format!("model \"{}\" matches /{}/", model, pattern.as_str()))
+            }
+            Condition::Ports(ports) => {
+                let open = device.open_ports.iter().find(|p| ports.contains(&p.port))?;
+                Some(format!("port {} open", open.port))
+            }
+            Condition::MdnsServices(types) => {
+                let service = device
+                    .advertised_services
+                    .iter()
+                    .find(|s| types.contains(&service_type_key(&s.service_type)))?;
+                Some(format!("mdns {}", service.service_type))
+            }
+            Condition::UpnpType(types) => {
+                let urn = device.upnp.as_ref()?.device_type.as_deref()?;
+                let kind = upnp_kind(urn);
+                types
+                    .contains(&kind.to_lowercase())
+                    .then(|| format!("upnp {}", kind))
+            }
+            Condition::SysObjectId(prefixes) => {
+                let oid = device.snmp.as_ref()?.sys_object_id.as_deref()?;
+                let trimmed = oid.trim_start_matches('.');
+                let prefix = prefixes.iter().find(|p| oid_has_prefix(trimmed, p))?;
+                Some(format!("sysObjectID {} under {}", trimmed, prefix))
+            }
+            Condition::Os(families) => {
+                let guess = device.os_guess.as_ref()?;
+                families
+                    .contains(&guess.family)
+                    .then(|| format!("os {}", guess.family))
+            }
+        }
+    }
+}
+
+impl Rule {
+    fn from_spec(spec: RuleSpec, source: RuleSource) -> Result<Self> {
+        let lower = |items: Vec<String>| -> Vec<String> {
+            items.into_iter().map(|i| i.to_lowercase()).collect()
+        };
+        let pattern = |field: &str, pattern: &str| {
+            RegexBuilder::new(pattern)
+                .case_insensitive(true)
+                .build()
+                .with_context(|| format!("rule '{}': invalid {} pattern", spec.name, field))
+        };
+
+        let mut conditions = Vec::new();
+        if !spec.vendor.is_empty() {
+            conditions.push(Condition::Vendor(lower(spec.vendor)));
+        }
+        if !spec.mac_prefix.is_empty() {
+            let prefixes: Vec<String> = spec.mac_prefix.iter().map(|p| mac_hex(p)).collect();
+            if let Some(bad) = spec
+                .mac_prefix
+                .iter()
+                .zip(&prefixes)
+                .find(|(_, hex)| hex.is_empty() || !hex.chars().all(|c| c.is_ascii_hexdigit()))
+            {
+                anyhow::bail!("rule '{}': invalid mac_prefix '{}'", spec.name, bad.0);
+            }
+            conditions.push(Condition::MacPrefix(prefixes));
+        }
+        if let Some(hostname) = &spec.hostname {
+            conditions.push(Condition::Hostname(pattern("hostname", hostname)?));
+        }
+        if let Some(model) = &spec.model {
+            conditions.push(Condition::Model(pattern("model", model)?));
+        }
+        if !spec.ports.is_empty() {
+            conditions.push(Condition::Ports(spec.ports));
+        }
+        if !spec.mdns_services.is_empty() {
+            let types = spec
+                .mdns_services
+                .iter()
+                .map(|s| service_type_key(s))
+                .collect();
+            conditions.push(Condition::MdnsServices(types));
+        }
+        if !spec.upnp_type.is_empty() {
+            conditions.push(Condition::UpnpType(lower(spec.upnp_type)));
+        }
+        if !spec.sys_object_id.is_empty() {
+            let prefixes = spec
+                .sys_object_id
+                .iter()
+                .map(|p| p.trim_start_matches('.').to_string())
+                .collect();
+            conditions.push(Condition::SysObjectId(prefixes));
+        }
+        if !spec.os.is_empty() {
+            conditions.push(Condition::Os(spec.os));
+        }
+
+        if conditions.is_empty() {
+            anyhow::bail!("rule '{}' has no conditions", spec.name);
+        }
+        if spec.device_type.trim().is_empty() {
+            anyhow::bail!("rule '{}' has an empty device_type", spec.name);
+        }
+
+        Ok(Self {
+            name: spec.name,
+            source,
+            device_type: spec.device_type,
+            tags: spec.tags,
+            priority: spec.priority,
+            conditions,
+        })
+    }
+
+    /// Every condition's explanation, or `None` if one does not match
+    fn check(&self, device: &Device) -> Option<Vec<String>> {
+        self.conditions.iter().map(|c| c.check(device)).collect()
+    }
+}
+
+fn parse_rules(content: &str, source: RuleSource) -> Result<Vec<Rule>> {
+    let file: RulesFile = toml::from_str(content)?;
+    file.rule
+        .into_iter()
+        .map(|spec| Rule::from_spec(spec, source))
+        .collect()
+}
+
+/// Built-in rules followed by the user's own
+#[derive(Debug, Clone)]
+pub struct Ruleset {
+    /// User rules first, so that they win priority ties
+    rules: Vec<Rule>,
+    user_file: Option<PathBuf>,
+}
+
+impl Ruleset {
+    /// Only the rules compiled into the agent
+    pub fn builtin() -> Self {
+        Self {
+            rules: BUILTIN.clone(),
+            user_file: None,
+        }
+    }
+
+    /// The built-in rules with user rules parsed from `content` ahead of them
+    pub fn with_user_rules(content: &str) -> Result<Self> {
+        let mut rules = parse_rules(content, RuleSource::User)?;
+        rules.extend(BUILTIN.iter().cloned());
+        Ok(Self {
+            rules,
+            user_file: None,
+        })
+    }
+
+    /// Number of built-in rules
+    pub fn builtin_count(&self) -> usize {
+        self.rules
+            .iter()
+            .filter(|r| r.source == RuleSource::Builtin)
+            .count()
+    }
+
+    /// Number of rules from the user rules file
+    pub fn user_count(&self) -> usize {
+        self.rules.len() - self.builtin_count()
+    }
+
+    /// User rules file that was loaded, if any
+    pub fn user_file(&self) -> Option<&Path> {
+        self.user_file.as_deref()
+    }
+
+    /// Run every rule against the device
+    pub fn classify(&self, device: &Device) -> Option<Verdict> {
+        let mut best: Option<(&Rule, Vec<String>)> = None;
+        let mut tags = BTreeSet::new();
+
+        for rule in &self.rules {
+            let Some(matched) = rule.check(device) else {
+                continue;
+            };
+            tags.extend(rule.tags.iter().cloned());
+            if best
+                .as_ref()
+                .is_none_or(|(b, _)| rule.priority > b.priority)
+            {
+                best = Some((rule, matched));
+            }
+        }
+
+        let (rule, matched) = best?;
+        Some(Verdict {
+            device_type: rule.device_type.clone(),
+            classification: Classification {
+                rule: rule.name.clone(),
+                source: rule.source,
+                priority: rule.priority,
+                matched,
+            },
+            tags: tags.into_iter().collect(),
+        })
+    }
+
+    /// Set the type, tags and classification of every device a rule matches.
+    /// Devices no rule matches keep the type they had.
+    pub fn apply(&self, devices: &mut [Device]) {
+        for device in devices.iter_mut() {
+            match self.classify(device) {
+                Some(verdict) => {
+                    tracing::debug!(
+                        "Classified {} as {} by {}",
+                        device.primary_ip(),
+                        verdict.device_type,
+                        verdict.classification
+                    );
+                    device.device_type = Some(verdict.device_type);
+                    device.classification = Some(verdict.classification);
+                    device.tags = verdict.tags;
+                }
+                None => {
+                    device.classification = None;
+                    device.tags.clear();
+                }
+            }
+        }
+    }
+}
+
+/// Path of the user rules file for these settings (it may not exist)
+pub fn user_rules_path(config: &ClassifyConfig) -> Option<PathBuf> {
+    match &config.rules_file {
+        Some(path) => Some(PathBuf::from(path)),
+        None => crate::cloud::config::get_config_dir_path().map(|d| d.join("rules.toml")),
+    }
+}
+
+/// Load the built-in rules and the user rules file, if there is one.
+/// Fails if the user rules file cannot be read or has an invalid rule.
+pub fn load_rules(config: &ClassifyConfig) -> Result<Ruleset> {
+    let Some(path) = user_rules_path(config) else {
+        return Ok(Ruleset::builtin());
+    };
+    // An explicitly configured file must exist; the default one is optional
+    if config.rules_file.is_none() && !path.exists() {
+        return Ok(Ruleset::builtin());
+    }
+
+    let content = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read rules file {}", path.display()))?;
+    let mut ruleset = Ruleset::with_user_rules(&content)
+        .with_context(|| format!("Invalid rules file {}", path.display()))?;
+    ruleset.user_file = Some(path);
+    Ok(ruleset)
+}
+
+/// Rules for a scan: like `load_rules`, but falls back to the built-in
+/// rules with a warning instead of failing the scan.
+pub fn load_rules_or_builtin(config: &ClassifyConfig) -> Ruleset {
+    load_rules(config).unwrap_or_else(|e| {
+        tracing::warn!("{:#}; using built-in classification rules only", e);
+        Ruleset::builtin()
+    })
+}
+
+/// "00:1A-2b" -> "001a2b"
+fn mac_hex(mac: &str) -> String {
+    mac.replace([':', '-', '.'], "").to_lowercase()
+}
+
+/// "_IPP._tcp.local." -> "_ipp._tcp"
+fn service_type_key(service_type: &str) -> String {
+    let lower = service_type.trim_end_matches('.').to_lowercase();
+    lower
+        .strip_suffix(".local")
+        .map(str::to_string)
+        .unwrap_or(lower)
+}
+
+/// "urn:schemas-upnp-org:device:MediaRenderer:1" -> "MediaRenderer"
+fn upnp_kind(urn: &str) -> &str {
+    urn.rsplit(':')
+        .find(|part| part.parse::<u32>().is_err())
+        .unwrap_or(urn)
+}
+
+/// Whether `oid` is `prefix` or lies under it, arc by arc
+fn oid_has_prefix(oid: &str, prefix: &str) -> bool {
+    oid.strip_prefix(prefix)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::{AdvertisedService, OpenPort, SnmpInfo, UpnpDevice};
+
+    fn vendor_type(vendor: &str) -> Option<String> {
+        let device = Device {
+            vendor: Some(vendor.to_string()),
+            ..Default::default()
+        };
+        Ruleset::builtin().classify(&device).map(|v| v.device_type)
+    }
+
+    fn snmp_type(sys_object_id: &str) -> Option<String> {
+        let device = Device {
+            snmp: Some(SnmpInfo {
+                sys_object_id: Some(sys_object_id.to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        Ruleset::builtin().classify(&device).map(|v| v.device_type)
+    }
+
+    fn upnp_type(device_type: &str, manufacturer: Option<&str>) -> Option<String> {
+        let device = Device {
+            vendor: manufacturer.map(String::from),
+            upnp: Some(UpnpDevice {
+                device_type: Some(device_type.to_string()),
+                manufacturer: manufacturer.map(String::from),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        Ruleset::builtin().classify(&device).map(|v| v.device_type)
+    }
+
+    #[test]
+    fn test_builtin_vendor_rules() {
+        let cases = [
+            ("Cisco Systems, Inc.", Some("network_device")),
+            ("TP-Link Technologies", Some("network_device")),
+            ("Routerboard.com", Some("network_device")),
+            ("Ubiquiti Inc", Some("network_device")),
+            ("NETGEAR", Some("network_device")),
+            ("Apple, Inc.", Some("apple")),
+            ("Synology Incorporated", Some("nas")),
+            ("Sonos, Inc.", Some("iot")),
+            ("Canon Inc.", Some("printer")),
+            ("Nintendo Co., Ltd.", Some("gaming")),
+            ("Firewalla Inc.", Some("firewall")),
+            ("SonicWall", Some("firewall")),
+            ("VMware, Inc.", Some("service")),
+            ("Supermicro", Some("server")),
+            ("Unknown Vendor", None),
+        ];
+        for (vendor, expected) in cases {
+            assert_eq!(vendor_type(vendor).as_deref(), expected, "{}", vendor);
+        }
+    }
+
+    #[test]
+    fn test_builtin_mac_upnp_and_snmp_rules() {
+        for mac in [
+            "00:50:56:12:34:56",
+            "02:42:ac:12:34:56",
+            "08:00:27:12:34:56",
+        ] {
+            let device = Device {
+                mac: Some(mac.to_string()),
+                ..Default::default()
+            };
+            let verdict = Ruleset::builtin().classify(&device).expect(mac);
+            assert_eq!(verdict.device_type, "service");
+            assert_eq!(verdict.tags, vec!["virtual"]);
+        }
+
+        let cases = [
+            (
+                "urn:schemas-upnp-org:device:InternetGatewayDevice:1",
+                Some("Apple Inc."),
+                Some("network_device"),
+            ),
+            (
+                "urn:schemas-upnp-org:device:Printer:1",
+                None,
+                Some("printer"),
+            ),
+            ("urn:dial-multiscreen-org:device:dial:1", None, Some("iot")),
+            (
+                "urn:schemas-upnp-org:device:MediaServer:1",
+                Some("Dell Inc."),
+                Some("computer"),
+            ),
+            (
+                "urn:schemas-upnp-org:device:MediaServer:1",
+                Some("Synology Inc."),
+                Some("nas"),
+            ),
+            (
+                "urn:schemas-upnp-org:device:MediaServer:1",
+                None,
+                Some("nas"),
+            ),
+            (
+                "urn:schemas-upnp-org:device:Basic:1",
+                Some("Sonos, Inc."),
+                Some("iot"),
+            ),
+            ("urn:example:device:Widget:1", None, None),
+        ];
+        for (urn, manufacturer, expected) in cases {
+            assert_eq!(upnp_type(urn, manufacturer).as_deref(), expected, "{}", urn);
+        }
+
+        let cases = [
+            ("1.3.6.1.4.1.9.1.2494", Some("network_device")),
+            (".1.3.6.1.4.1.6574.1", Some("nas")),
+            ("1.3.6.1.4.1.11.2.3.9.1", Some("printer")),
+            ("1.3.6.1.4.1.11.2.3.7.11.181", Some("network_device")),
+            ("1.3.6.1.4.1.12356.101.1", Some("firewall")),
+            ("1.3.6.1.4.1.11.5.7", None),
+            ("1.3.6.1.4.1.8072.3.2.10", None),
+            // Enterprise 99 is not under enterprise 9
+            ("1.3.6.1.4.1.99.1", None),
+            ("1.3.6.1.2.1.1", None),
+        ];
+        for (oid, expected) in cases {
+            assert_eq!(snmp_type(oid).as_deref(), expected, "{}", oid);
+        }
+    }
+
+    #[test]
+    fn test_priority_and_explanation() {
+        // A Canon NIC, but SNMP says Cisco and mDNS says printer
+        let mut device = Device {
+            vendor: Some("Canon Inc.".to_string()),
+            hostname: Some("iPhone-Jane.lan".to_string()),
+            open_ports: vec![OpenPort {
+                port: 9100,
+                service: None,
+            }],
+            advertised_services: vec![AdvertisedService {
+                name: "Office".to_string(),
+                service_type: "_ipp._tcp.local".to_string(),
+                port: 631,
+                txt: Default::default(),
+            }],
+            ..Default::default()
+        };
+        let verdict = Ruleset::builtin().classify(&device).unwrap();
+        assert_eq!(verdict.device_type, "printer");
+        assert_eq!(verdict.classification.rule, "mdns-printer");
+        assert_eq!(verdict.classification.priority, 35);
+        assert_eq!(verdict.classification.matched, vec!["mdns _ipp._tcp.local"]);
+
+        device.advertised_services.clear();
+        device.open_ports.clear();
+        let verdict = Ruleset::builtin().classify(&device).unwrap();
+        assert_eq!(verdict.device_type, "mobile");
+        assert_eq!(
+            verdict.classification.to_string(),
+            "rule phone-hostname (built-in, priority 20): hostname \"iPhone-Jane.lan\" \
+             matches /^(iphone|ipad|android|galaxy|pixel)([-_ .]|$)|s-iphone$|s-ipad$/"
+        );
+
+        Ruleset::builtin().apply(std::slice::from_mut(&mut device));
+        assert_eq!(device.device_type.as_deref(), Some("mobile"));
+        assert_eq!(device.classification.unwrap().rule, "phone-hostname");
+    }
+
+    #[test]
+    fn test_user_rules() {
+        let rules = Ruleset::with_user_rules(
+            r#"
+[[rule]]
+name = "lab-plcs"
+device_type = "plc"
+tags = ["lab"]
+priority = 25
+hostname = '^plc-[0-9]+$'
+ports = [502]
+"#,
+        )
+        .unwrap();
+        assert_eq!(rules.user_count(), 1);
+        assert_eq!(rules.builtin_count(), Ruleset::builtin().builtin_count());
+
+        let mut device = Device {
+            hostname: Some("PLC-12".to_string()),
+            open_ports: vec![OpenPort {
+                port: 502,
+                service: None,
+            }],
+            ..Default::default()
+        };
+        // Ties with the built-in industrial-protocols rule; the user rule wins
+        // and both contribute tags
+        let verdict = rules.classify(&device).unwrap();
+        assert_eq!(verdict.device_type, "plc");
+        assert_eq!(verdict.classification.source, RuleSource::User);
+        assert_eq!(
+            verdict.classification.matched,
+            vec![
+                "hostname \"PLC-12\" matches /^plc-[0-9]+$/",
+                "port 502 open"
+            ]
+        );
+        assert_eq!(verdict.tags, vec!["lab", "ot"]);
+
+        // Both conditions must hold
+        device.hostname = Some("historian".to_string());
+        assert_eq!(rules.classify(&device).unwrap().device_type, "iot");
+
+        let err = Ruleset::with_user_rules(
+            "[[rule]]\nname = \"bad\"\ndevice_type = \"x\"\nhostname = \"(\"\n",
+        )
+        .unwrap_err();
+        assert!(format!("{:#}", err).contains("rule 'bad': invalid hostname pattern"));
+        let err = Ruleset::with_user_rules("[[rule]]\nname = \"empty\"\ndevice_type = \"x\"\n")
+            .unwrap_err();
+        assert_eq!(err.to_string(), "rule 'empty' has no conditions");
+        assert!(
+            Ruleset::with_user_rules(
+                "[[rule]]\nname = \"typo\"\ndevice_type = \"x\"\nport = [1]\n"
+            )
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,775 @@
+//! Scanner configuration from the `[scan]` section of config.toml.
+//!
+//! ```toml
+//! [scan]
+//! interfaces = ["eth0", "eth1"]  # default: every non-loopback interface
+//! include = ["10.20.0.0/24"]     # default: the interfaces' own subnets
+//! exclude = ["10.20.0.50", "10.20.0.128/28"]  # never probed or reported
+//! max_hosts = 4096               # addresses swept per scan
+//! ports = [22, 80, 443]          # port scan list (default: top 100)
+//!
+//! [scan.ping]
+//! engine = "auto"     # auto, datagram, raw or subprocess
+//! rate_limit = 500    # echo requests per second (0 = unlimited)
+//! retries = 1         # extra attempts for hosts that did not answer
+//! timeout_ms = 1000   # reply timeout per attempt
+//!
+//! [scan.arp_sweep]     # Linux with CAP_NET_RAW only
+//! enabled = true
+//! rate_limit = 1000
+//! retries = 1
+//! timeout_ms = 500
+//!
+//! [scan.mdns]         # multicast DNS / DNS-SD browsing
+//! enabled = true
+//! window_ms = 3000    # how long to collect responses
+//!
+//! [scan.ssdp]         # SSDP / UPnP discovery
+//! enabled = true
+//! window_ms = 3000    # how long to collect M-SEARCH replies
+//! fetch_timeout_ms = 2000  # per device description
+//!
+//! [scan.lldp]         # LLDP/CDP neighbor capture, Linux with CAP_NET_RAW only
+//! enabled = true
+//! window_ms = 30000   # listen time, overlapping the rest of the scan
+//!
+//! [scan.dhcp]         # passive DHCP fingerprinting while the agent runs,
+//! enabled = true      # Linux with CAP_NET_RAW only
+//!
+//! [scan.resolvers]    # name lookups besides the system resolver
+//! netbios = true      # NetBIOS node status (UDP 137)
+//! llmnr = true        # LLMNR reverse lookup (UDP 5355)
+//!
+//! [scan.snmp]
+//! enabled = false     # query every device over SNMP
+//! communities = ["public"]  # SNMPv2c communities, tried in order
+//! timeout_ms = 1000   # per request
+//! retries = 1
+//! concurrency = 32    # devices queried in parallel
+//!
+//! [scan.snmp.v3]      # tried before the communities when present
+//! username = "monitor"
+//! auth_protocol = "sha"        # md5, sha or sha256
+//! auth_password = "..."        # omit for noAuthNoPriv
+//! privacy_protocol = "aes"     # aes (AES-128) or none
+//! privacy_password = "..."
+//!
+//! [scan.port_scan]
+//! enabled = false     # TCP connect probes of `ports` on every device
+//! concurrency = 256   # connections in flight
+//! timeout_ms = 500    # connect timeout per probe
+//!
+//! [scan.services]      # banners and TLS certificates of open ports
+//! enabled = true
+//! concurrency = 32     # devices probed in parallel
+//! timeout_ms = 1500    # per port
+//! device_budget_ms = 5000  # total per device
+//!
+//! [scan.randomized_macs]   # devices with private (random) MAC addresses
+//! link_by_identity = true  # same hostname, DHCP client id or mDNS name
+//!                          # = same device, whatever the MAC
+//! ghost_ttl_hours = 24     # forget them after this long offline (0 = never)
+//!
+//! [scan.classify]      # device type rules added to the built-in set
+//! rules_file = "/etc/cartographer/rules.toml"  # default: rules.toml
+//!                                              # next to config.toml
+//!
+//! [scan.oui]           # MAC vendor lookup
+//! registry_dir = "/var/lib/cartographer/oui"  # newer IEEE CSV files
+//!                                             # (default: data dir/oui)
+//! [scan.oui.custom]    # in-house prefixes, ahead of the registry
+//! "F4:A6:E5" = "Meridian Test Rig"
+//! ```
+//!
+//! Unknown keys are rejected so that a misspelled `exclude` cannot silently
+//! let a scan probe hosts it must not touch.
+
+use anyhow::Result;
+use ipnetwork::IpNetwork;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+
+/// Scanner settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScanConfig {
+    /// Only scan these interfaces (empty = every non-loopback interface)
+    pub interfaces: Vec<String>,
+    /// IPv4 subnets to scan instead of the interfaces' own subnets
+    pub include: Vec<String>,
+    /// Subnets or single addresses that are never probed or reported
+    pub exclude: Vec<String>,
+    /// Maximum number of addresses swept per scan, across all subnets
+    pub max_hosts: usize,
+    /// TCP ports probed by the port scan (empty = top 100)
+    pub ports: Vec<u16>,
+    pub ping: PingConfig,
+    pub arp_sweep: ArpSweepConfig,
+    pub mdns: MdnsConfig,
+    pub ssdp: SsdpConfig,
+    pub lldp: LldpConfig,
+    pub dhcp: DhcpConfig,
+    pub resolvers: ResolverConfig,
+    pub snmp: SnmpConfig,
+    pub port_scan: PortScanConfig,
+    pub services: ServiceProbeConfig,
+    pub randomized_macs: RandomizedMacConfig,
+    pub classify: ClassifyConfig,
+    pub oui: OuiConfig,
+}
+
+impl Default for ScanConfig {
+    fn default() -> Self {
+        Self {
+            interfaces: Vec::new(),
+            include: Vec::new(),
+            exclude: Vec::new(),
+            max_hosts: 4096,
+            ports: Vec::new(),
+            ping: PingConfig::default(),
+            arp_sweep: ArpSweepConfig::default(),
+            mdns: MdnsConfig::default(),
+            ssdp: SsdpConfig::default(),
+            lldp: LldpConfig::default(),
+            dhcp: DhcpConfig::default(),
+            resolvers: ResolverConfig::default(),
+            snmp: SnmpConfig::default(),
+            port_scan: PortScanConfig::default(),
+            services: ServiceProbeConfig::default(),
+            randomized_macs: RandomizedMacConfig::default(),
+            classify: ClassifyConfig::default(),
+            oui: OuiConfig::default(),
+        }
+    }
+}
+
+impl ScanConfig {
+    /// Parsed `exclude` entries (invalid entries are reported by validation)
+    pub fn exclude_networks(&self) -> Vec<IpNetwork> {
+        self.exclude
+            .iter()
+            .filter_map(|e| e.trim().parse().ok())
+            .collect()
+    }
+
+    /// Ports for the port scan: `ports`, or the top 100 when unset
+    pub fn port_list(&self) -> Vec<u16> {
+        if self.ports.is_empty() {
+            super::ports::TOP_PORTS.to_vec()
+        } else {
+            let mut ports = self.ports.clone();
+            ports.sort_unstable();
+            ports.dedup();
+            ports
+        }
+    }
+
+    /// Whether an address must never be probed or reported
+    pub fn is_excluded(&self, ip: &str) -> bool {
+        let Ok(ip) = ip.parse::<IpAddr>() else {
+            return false;
+        };
+        self.exclude_networks().iter().any(|net| net.contains(ip))
+    }
+}
+
+/// Ping sweep settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PingConfig {
+    /// Which ICMP engine to use
+    pub engine: EnginePreference,
+    /// Maximum echo requests per second (0 = unlimited)
+    pub rate_limit: u32,
+    /// Extra attempts for hosts that did not answer
+    pub retries: u32,
+    /// How long to wait for a reply, in milliseconds
+    pub timeout_ms: u64,
+}
+
+impl Default for PingConfig {
+    fn default() -> Self {
+        Self {
+            engine: EnginePreference::Auto,
+            rate_limit: 500,
+            retries: 1,
+            timeout_ms: 1000,
+        }
+    }
+}
+
+/// Active ARP sweep settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ArpSweepConfig {
+    /// Run the sweep when the process has `CAP_NET_RAW`
+    pub enabled: bool,
+    /// Maximum ARP requests per second (0 = unlimited)
+    pub rate_limit: u32,
+    /// Extra attempts for hosts that did not answer
+    pub retries: u32,
+    /// How long to wait for a reply, in milliseconds
+    pub timeout_ms: u64,
+}
+
+impl Default for ArpSweepConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            rate_limit: 1000,
+            retries: 1,
+            timeout_ms: 500,
+        }
+    }
+}
+
+/// Multicast DNS / DNS-SD browse settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MdnsConfig {
+    /// Browse for mDNS advertisements during the scan
+    pub enabled: bool,
+    /// How long to collect responses, in milliseconds
+    pub window_ms: u64,
+}
+
+impl Default for MdnsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            window_ms: 3000,
+        }
+    }
+}
+
+/// SSDP / UPnP discovery settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SsdpConfig {
+    /// Search for UPnP devices during the scan
+    pub enabled: bool,
+    /// How long to collect M-SEARCH replies, in milliseconds
+    pub window_ms: u64,
+    /// Timeout for fetching one device description, in milliseconds
+    pub fetch_timeout_ms: u64,
+}
+
+impl Default for SsdpConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            window_ms: 3000,
+            fetch_timeout_ms: 2000,
+        }
+    }
+}
+
+/// Passive LLDP / CDP neighbor capture settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LldpConfig {
+    /// Listen for LLDP and CDP frames during the scan
+    pub enabled: bool,
+    /// How long to listen, in milliseconds. Switches announce every 30s
+    /// (LLDP) or 60s (CDP) by default; the capture starts with the scan, so
+    /// only the part of the window left after the other stages adds time.
+    pub window_ms: u64,
+}
+
+impl Default for LldpConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            window_ms: 30000,
+        }
+    }
+}
+
+/// Passive DHCP listener settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DhcpConfig {
+    /// Capture DHCP requests broadcast by other hosts while the daemon or
+    /// background scanning runs
+    pub enabled: bool,
+}
+
+impl Default for DhcpConfig {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+/// Hostname resolvers queried alongside the system resolver
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ResolverConfig {
+    /// Ask hosts for their NetBIOS name table
+    pub netbios: bool,
+    /// Send LLMNR reverse lookups to hosts
+    pub llmnr: bool,
+}
+
+impl Default for ResolverConfig {
+    fn default() -> Self {
+        Self {
+            netbios: true,
+            llmnr: true,
+        }
+    }
+}
+
+/// SNMP enrichment settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SnmpConfig {
+    /// Run the SNMP stage
+    pub enabled: bool,
+    /// SNMPv2c communities, tried in order
+    pub communities: Vec<String>,
+    /// Timeout per request, in milliseconds
+    pub timeout_ms: u64,
+    /// Extra attempts per request
+    pub retries: u32,
+    /// Devices queried in parallel
+    pub concurrency: usize,
+    /// SNMPv3 user, tried before the communities
+    pub v3: Option<SnmpV3Config>,
+}
+
+impl Default for SnmpConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            communities: vec!["public".to_string()],
+            timeout_ms: 1000,
+            retries: 1,
+            concurrency: 32,
+            v3: None,
+        }
+    }
+}
+
+/// SNMPv3 user-based security model credentials
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SnmpV3Config {
+    pub username: String,
+    #[serde(default)]
+    pub auth_protocol: SnmpAuthProtocol,
+    /// Authentication password; without it requests are noAuthNoPriv
+    pub auth_password: Option<String>,
+    #[serde(default)]
+    pub privacy_protocol: SnmpPrivacyProtocol,
+    /// Privacy password; requires `auth_password`
+    pub privacy_password: Option<String>,
+}
+
+/// SNMPv3 authentication protocol
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SnmpAuthProtocol {
+    /// HMAC-MD5-96
+    Md5,
+    /// HMAC-SHA-96
+    #[default]
+    Sha,
+    /// HMAC-SHA-256-192
+    Sha256,
+}
+
+/// SNMPv3 privacy protocol
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SnmpPrivacyProtocol {
+    #[default]
+    None,
+    /// AES-128 in CFB mode
+    Aes,
+}
+
+/// TCP port scan settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PortScanConfig {
+    /// Run the port scan stage
+    pub enabled: bool,
+    /// Maximum connection attempts in flight
+    pub concurrency: usize,
+    /// Connect timeout per probe, in milliseconds
+    pub timeout_ms: u64,
+}
+
+impl Default for PortScanConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            concurrency: 256,
+            timeout_ms: 500,
+        }
+    }
+}
+
+/// Banner and certificate collection for ports found by the port scan
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServiceProbeConfig {
+    /// Read banners after the port scan (only runs when the port scan does)
+    pub enabled: bool,
+    /// Devices probed in parallel
+    pub concurrency: usize,
+    /// Timeout for one port, connect included, in milliseconds
+    pub timeout_ms: u64,
+    /// Total time spent on one device, in milliseconds
+    pub device_budget_ms: u64,
+}
+
+impl Default for ServiceProbeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            concurrency: 32,
+            timeout_ms: 1500,
+            device_budget_ms: 5000,
+        }
+    }
+}
+
+/// Handling of devices whose MAC address is randomized for privacy
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RandomizedMacConfig {
+    /// Treat a device with a new random MAC as a known device when they
+    /// share a hostname, DHCP client identifier or mDNS name
+    pub link_by_identity: bool,
+    /// Forget offline devices with random MACs after this many hours
+    /// (0 = keep them)
+    pub ghost_ttl_hours: u64,
+}
+
+impl Default for RandomizedMacConfig {
+    fn default() -> Self {
+        Self {
+            link_by_identity: true,
+            ghost_ttl_hours: 24,
+        }
+    }
+}
+
+/// Device classification settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClassifyConfig {
+    /// User rules file (default: rules.toml next to config.toml, if present)
+    pub rules_file: Option<String>,
+}
+
+/// MAC vendor database settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OuiConfig {
+    /// Directory with IEEE registry CSV files (default: the "oui" folder in
+    /// the agent data directory, if present)
+    pub registry_dir: Option<String>,
+    /// In-house MAC prefixes (6 to 12 hex digits) and their vendor names
+    pub custom: BTreeMap<String, String>,
+}
+
+/// Requested ICMP engine
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EnginePreference {
+    /// Unprivileged datagram socket, then raw socket, then system ping
+    #[default]
+    Auto,
+    Datagram,
+    Raw,
+    Subprocess,
+}
+
+impl std::fmt::Display for EnginePreference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EnginePreference::Auto => write!(f, "auto"),
+            EnginePreference::Datagram => write!(f, "datagram"),
+            EnginePreference::Raw => write!(f, "raw"),
+            EnginePreference::Subprocess => write!(f, "subprocess"),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ConfigFile {
+    #[serde(default)]
+    scan: ScanConfig,
+}
+
+/// Load the `[scan]` section, falling back to defaults. Only for settings
+/// that never decide what gets probed; everything that sends packets uses
+/// `load_validated_scan_config`.
+pub(super) fn load_scan_config() -> ScanConfig {
+    crate::cloud::config::read_config_file::<ConfigFile>()
+        .map(|f| f.scan)
+        .unwrap_or_default()
+}
+
+/// Load the `[scan]` section for a scan, failing on any validation error
+/// instead of falling back to defaults (which would ignore `exclude`).
+pub fn load_validated_scan_config() -> Result<ScanConfig> {
+    let Some((path, content)) = crate::cloud::config::read_config_source()? else {
+        return Ok(ScanConfig::default());
+    };
+
+    let issues = validate_scan_config(&content);
+    if !issues.is_empty() {
+        let issues: Vec<String> = issues.iter().map(|i| i.to_string()).collect();
+        return Err(anyhow::anyhow!(
+            "Invalid [scan] settings in {}:\n  {}",
+            path.display(),
+            issues.join("\n  ")
+        ));
+    }
+
+    Ok(toml::from_str::<ConfigFile>(&content)?.scan)
+}
+
+/// Validation problems in the config file's `[scan]` section (empty when
+/// there is no config file).
+pub fn scan_config_issues() -> Result<Vec<ConfigIssue>> {
+    Ok(crate::cloud::config::read_config_source()?
+        .map(|(_, content)| validate_scan_config(&content))
+        .unwrap_or_default())
+}
+
+/// A problem found in config.toml
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConfigIssue {
+    /// 1-based line number
+    pub line: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// `[scan]` list entries with their positions, for validation messages
+#[derive(Debug, Default, Deserialize)]
+struct SpannedConfigFile {
+    #[serde(default)]
+    scan: SpannedScanSection,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct SpannedScanSection {
+    #[serde(default)]
+    interfaces: Vec<toml::Spanned<String>>,
+    #[serde(default)]
+    include: Vec<toml::Spanned<String>>,
+    #[serde(default)]
+    exclude: Vec<toml::Spanned<String>>,
+    max_hosts: Option<toml::Spanned<usize>>,
+    #[serde(default)]
+    ports: Vec<toml::Spanned<u16>>,
+    #[serde(default)]
+    snmp: SpannedSnmpSection,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct SpannedSnmpSection {
+    v3: Option<SpannedSnmpV3>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SpannedSnmpV3 {
+    username: toml::Spanned<String>,
+    auth_password: Option<toml::Spanned<String>>,
+    #[serde(default)]
+    privacy_protocol: SnmpPrivacyProtocol,
+    privacy_password: Option<toml::Spanned<String>>,
+}
+
+/// Check the `[scan]` section of a config file: TOML syntax, unknown keys,
+/// value types, and the CIDR/address lists.
+pub fn validate_scan_config(content: &str) -> Vec<ConfigIssue> {
+    let line_of = |offset: usize| content[..offset.min(content.len())].matches('\n').count() + 1;
+
+    if let Err(e) = toml::from_str::<ConfigFile>(content) {
+        return vec![ConfigIssue {
+            line: e.span().map(|s| line_of(s.start)).unwrap_or(1),
+            message: e.message().to_string(),
+        }];
+    }
+    let Ok(file) = toml::from_str::<SpannedConfigFile>(content) else {
+        return Vec::new();
+    };
+    let scan = file.scan;
+
+    let mut issues = Vec::new();
+    let mut issue = |entry: &toml::Spanned<String>, message: String| {
+        issues.push(ConfigIssue {
+            line: line_of(entry.span().start),
+            message,
+        });
+    };
+
+    for entry in &scan.interfaces {
+        if entry.get_ref().trim().is_empty() {
+            issue(entry, "interfaces: empty interface name".to_string());
+        }
+    }
+    for entry in &scan.include {
+        match entry.get_ref().trim().parse::<IpNetwork>() {
+            Ok(IpNetwork::V4(_)) => {}
+            Ok(IpNetwork::V6(_)) => issue(
+                entry,
+                format!(
+                    "include: '{}' is IPv6; only IPv4 subnets can be swept",
+                    entry.get_ref()
+                ),
+            ),
+            Err(_) => issue(
+                entry,
+                format!("include: '{}' is not an IPv4 CIDR", entry.get_ref()),
+            ),
+        }
+    }
+    for entry in &scan.exclude {
+        if entry.get_ref().trim().parse::<IpNetwork>().is_err() {
+            issue(
+                entry,
+                format!(
+                    "exclude: '{}' is not an IP address or CIDR",
+                    entry.get_ref()
+                ),
+            );
+        }
+    }
+
+    if let Some(max_hosts) = scan.max_hosts.as_ref().filter(|m| *m.get_ref() == 0) {
+        issues.push(ConfigIssue {
+            line: line_of(max_hosts.span().start),
+            message: "max_hosts: must be greater than 0".to_string(),
+        });
+    }
+    for port in scan.ports.iter().filter(|p| *p.get_ref() == 0) {
+        issues.push(ConfigIssue {
+            line: line_of(port.span().start),
+            message: "ports: 0 is not a valid TCP port".to_string(),
+        });
+    }
+
+    if let Some(v3) = &scan.snmp.v3 {
+        let mut issue = |line: usize, message: &str| {
+            issues.push(ConfigIssue {
+                line,
+                message: format!("snmp.v3: {}", message),
+            });
+        };
+        // RFC 3414 requires at least 8 characters for key derivation
+        for password in [&v3.auth_password, &v3.privacy_password]
+            .into_iter()
+            .flatten()
+        {
+            if password.get_ref().chars().count() < 8 {
+                issue(
+                    line_of(password.span().start),
+                    "passwords must be at least 8 characters",
+                );
+            }
+        }
+        let username_line = line_of(v3.username.span().start);
+        if v3.privacy_password.is_some() && v3.auth_password.is_none() {
+            issue(username_line, "privacy_password requires auth_password");
+        }
+        if v3.privacy_protocol != SnmpPrivacyProtocol::None && v3.privacy_password.is_none() {
+            issue(username_line, "privacy_protocol requires privacy_password");
+        }
+        if v3.privacy_password.is_some() && v3.privacy_protocol == SnmpPrivacyProtocol::None {
+            issue(
+                username_line,
+                "privacy_password is set but privacy_protocol is none",
+            );
+        }
+    }
+
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_scan_config() {
+        let content = r#"[cloud]
+api_url = "https://example.com/api"
+
+[scan]
+interfaces = ["eth0"]
+include = [
+    "10.20.0.0/24",
+    "10.30.0.0/33",
+    "2001:db8::/64",
+]
+exclude = ["10.20.0.50", "plc-1"]
+max_hosts = 0
+ports = [22, 0]
+"#;
+        let issues = validate_scan_config(content);
+        let lines: Vec<usize> = issues.iter().map(|i| i.line).collect();
+        assert_eq!(lines, vec![8, 9, 11, 12, 13]);
+        assert!(issues[3].to_string().starts_with("line 12: max_hosts"));
+    }
+
+    #[test]
+    fn test_validate_scan_config_rejects_unknown_keys() {
+        let issues = validate_scan_config("[scan]\nexlude = [\"10.0.0.5\"]\n");
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].line, 2);
+        assert!(issues[0].message.contains("exlude"));
+
+        assert!(validate_scan_config("").is_empty());
+    }
+
+    #[test]
+    fn test_validate_snmp_v3() {
+        let content = r#"[scan.snmp]
+enabled = true
+
+[scan.snmp.v3]
+username = "monitor"
+auth_password = "short"
+privacy_protocol = "aes"
+"#;
+        let issues = validate_scan_config(content);
+        let messages: Vec<String> = issues.iter().map(|i| i.to_string()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "line 6: snmp.v3: passwords must be at least 8 characters",
+                "line 5: snmp.v3: privacy_protocol requires privacy_password",
+            ]
+        );
+
+        let issues =
+            validate_scan_config("[scan.snmp.v3]\nusername = \"m\"\nauth_protocol = \"sha1\"\n");
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].line, 3);
+    }
+
+    #[test]
+    fn test_is_excluded() {
+        let config = ScanConfig {
+            exclude: vec!["10.20.0.50".to_string(), "10.20.0.128/28".to_string()],
+            ..Default::default()
+        };
+        assert!(config.is_excluded("10.20.0.50"));
+        assert!(config.is_excluded("10.20.0.140"));
+        assert!(!config.is_excluded("10.20.0.51"));
+        assert!(!config.is_excluded("not-an-ip"));
+    }
+}
@@ -0,0 +1,604 @@
+//! Passive DHCP fingerprinting (Linux, AF_PACKET)
+//!
+//! Clients broadcast DHCPDISCOVER and DHCPREQUEST when they join the
+//! network or renew a lease. Besides the hostname (option 12) these carry a
+//! vendor class (option 60) and a parameter request list (option 55) whose
+//! order is characteristic of the DHCP client, and therefore of the OS.
+//! While the daemon runs, a listener records the latest request of every
+//! MAC; scans look devices up in that store. Nothing is sent.
+
+use super::neighbor::format_mac;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::Ipv4Addr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{LazyLock, Mutex};
+
+const IPPROTO_UDP: u8 = 17;
+const DHCP_SERVER_PORT: u16 = 67;
+const BOOTREQUEST: u8 = 1;
+/// Fixed BOOTP header before the options (RFC 2131 section 2)
+const BOOTP_HEADER_LEN: usize = 236;
+const MAGIC_COOKIE: [u8; 4] = [0x63, 0x82, 0x53, 0x63];
+
+const OPTION_PAD: u8 = 0;
+const OPTION_HOSTNAME: u8 = 12;
+const OPTION_REQUESTED_IP: u8 = 50;
+const OPTION_MESSAGE_TYPE: u8 = 53;
+const OPTION_PARAMETER_LIST: u8 = 55;
+const OPTION_VENDOR_CLASS: u8 = 60;
+const OPTION_CLIENT_ID: u8 = 61;
+const OPTION_CLIENT_FQDN: u8 = 81;
+const OPTION_END: u8 = 255;
+
+const DHCPDISCOVER: u8 = 1;
+const DHCPREQUEST: u8 = 3;
+const DHCPINFORM: u8 = 8;
+
+/// Fingerprints kept; the least recently seen are dropped beyond this
+const MAX_FINGERPRINTS: usize = 4096;
+
+/// Operating system family inferred from a DHCP client's behavior
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OsFamily {
+    Windows,
+    Macos,
+    Ios,
+    Android,
+    Linux,
+}
+
+impl std::fmt::Display for OsFamily {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            OsFamily::Windows => "Windows",
+            OsFamily::Macos => "macOS",
+            OsFamily::Ios => "iOS",
+            OsFamily::Android => "Android",
+            OsFamily::Linux => "Linux",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// What a client revealed in its most recent DHCP request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DhcpFingerprint {
+    /// Client hardware address (chaddr)
+    pub mac: String,
+    /// Option 12, or the client FQDN (option 81) when absent
+    pub hostname: Option<String>,
+    /// Option 60 (e.g. "MSFT 5.0", "android-dhcp-13")
+    pub vendor_class: Option<String>,
+    /// Option 61 in hex (e.g. "01:3c:52:82:1a:2b:3c"); a DUID outlives the
+    /// MAC address of a client that randomizes it
+    #[serde(default)]
+    pub client_id: Option<String>,
+    /// Option 55, in the order the client sent it
+    pub parameter_list: Vec<u8>,
+    /// Option 50, or ciaddr for DHCPINFORM
+    pub requested_ip: Option<String>,
+    pub os_family: Option<OsFamily>,
+    pub last_seen: DateTime<Utc>,
+}
+
+impl DhcpFingerprint {
+    /// Parameter request list in the usual "1,3,6,15" notation
+    pub fn parameter_list_string(&self) -> String {
+        self.parameter_list
+            .iter()
+            .map(|o| o.to_string())
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+/// Parse an Ethernet frame carrying a DHCP client request. Replies and
+/// other message types are ignored.
+pub fn parse_frame(frame: &[u8]) -> Option<DhcpFingerprint> {
+    // Ethernet, then IPv4 with options, then UDP
+    if frame.len() < 14 || frame[12..14] != [0x08, 0x00] {
+        return None;
+    }
+    let ip = &frame[14..];
+    let ihl = (*ip.first()? & 0x0f) as usize * 4;
+    if ip.len() < ihl + 8 || ihl < 20 || ip[9] != IPPROTO_UDP {
+        return None;
+    }
+    let udp = &ip[ihl..];
+    if u16::from_be_bytes([udp[2], udp[3]]) != DHCP_SERVER_PORT {
+        return None;
+    }
+    parse_bootp(&udp[8..])
+}
+
+fn parse_bootp(bootp: &[u8]) -> Option<DhcpFingerprint> {
+    if bootp.len() < BOOTP_HEADER_LEN + MAGIC_COOKIE.len() || bootp[0] != BOOTREQUEST {
+        return None;
+    }
+    // Ethernet hardware type and address length
+    if bootp[1] != 1 || bootp[2] != 6 {
+        return None;
+    }
+    if bootp[BOOTP_HEADER_LEN..BOOTP_HEADER_LEN + 4] != MAGIC_COOKIE {
+        return None;
+    }
+    let ciaddr = Ipv4Addr::new(bootp[12], bootp[13], bootp[14], bootp[15]);
+    let mac = format_mac(&bootp[28..34])?;
+
+    let mut message_type = None;
+    let mut hostname = None;
+    let mut fqdn = None;
+    let mut fingerprint = DhcpFingerprint {
+        mac,
+        hostname: None,
+        vendor_class: None,
+        client_id: None,
+        parameter_list: Vec::new(),
+        requested_ip: None,
+        os_family: None,
+        last_seen: Utc::now(),
+    };
+
+    let options = &bootp[BOOTP_HEADER_LEN + 4..];
+    let mut pos = 0;
+    while pos < options.len() {
+        let code = options[pos];
+        match code {
+            OPTION_PAD => {
+                pos += 1;
+                continue;
+            }
+            OPTION_END => break,
+            _ => {}
+        }
+        let len = *options.get(pos + 1)? as usize;
+        let value = options.get(pos + 2..pos + 2 + len)?;
+        pos += 2 + len;
+
+        match code {
+            OPTION_MESSAGE_TYPE => message_type = value.first().copied(),
+            OPTION_HOSTNAME => hostname = text(value),
+            OPTION_CLIENT_FQDN => fqdn = client_fqdn(value),
+            OPTION_VENDOR_CLASS => fingerprint.vendor_class = text(value),
+            OPTION_CLIENT_ID if !value.is_empty() => {
+                fingerprint.client_id = Some(
+                    value
+                        .iter()
+                        .map(|b| format!("{:02x}", b))
+                        .collect::<Vec<_>>()
+                        .join(":"),
+                )
+            }
+            OPTION_PARAMETER_LIST => fingerprint.parameter_list = value.to_vec(),
+            OPTION_REQUESTED_IP => {
+                fingerprint.requested_ip = <[u8; 4]>::try_from(value)
+                    .ok()
+                    .map(|a| Ipv4Addr::from(a).to_string())
+            }
+            _ => {}
+        }
+    }
+
+    match message_type? {
+        DHCPDISCOVER | DHCPREQUEST => {}
+        DHCPINFORM if !ciaddr.is_unspecified() => {
+            fingerprint.requested_ip = Some(ciaddr.to_string());
+        }
+        _ => return None,
+    }
+    fingerprint.hostname = hostname.or(fqdn);
+    fingerprint.os_family = infer_os_family(
+        fingerprint.vendor_class.as_deref(),
+        &fingerprint.parameter_list,
+    );
+    Some(fingerprint)
+}
+
+/// Option 81: flags, two obsolete RCODE bytes, then the name in ASCII or,
+/// with the E flag set, in DNS wire format (RFC 4702)
+fn client_fqdn(value: &[u8]) -> Option<String> {
+    let (&flags, rest) = value.split_first()?;
+    let name = rest.get(2..)?;
+    if flags & 0x04 == 0 {
+        return text(name).map(|n| n.trim_end_matches('.').to_string());
+    }
+
+    let mut labels = Vec::new();
+    let mut pos = 0;
+    while let Some(&len) = name.get(pos) {
+        if len == 0 {
+            break;
+        }
+        let label = name.get(pos + 1..pos + 1 + len as usize)?;
+        labels.push(String::from_utf8_lossy(label).into_owned());
+        pos += 1 + len as usize;
+    }
+    (!labels.is_empty()).then(|| labels.join("."))
+}
+
+fn text(value: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(value);
+    let text = text.trim_matches(|c: char| c.is_whitespace() || c == '\0');
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// Infer the OS family from the vendor class, or failing that from the
+/// parameter request list of well-known DHCP clients.
+pub fn infer_os_family(vendor_class: Option<&str>, parameters: &[u8]) -> Option<OsFamily> {
+    if let Some(vendor_class) = vendor_class {
+        let vendor_class = vendor_class.to_ascii_lowercase();
+        if vendor_class.starts_with("msft") {
+            return Some(OsFamily::Windows);
+        }
+        if vendor_class.starts_with("android-dhcp") {
+            return Some(OsFamily::Android);
+        }
+        if vendor_class.starts_with("dhcpcd") || vendor_class.starts_with("udhcp") {
+            return Some(OsFamily::Linux);
+        }
+    }
+
+    let has = |option: u8| parameters.contains(&option);
+    // 249 is Microsoft's pre-standard classless static route option
+    if has(249) && has(252) {
+        return Some(OsFamily::Windows);
+    }
+    // Apple's client leads with 1,121,3,6,15; macOS also asks for LDAP
+    // (95) and NetBIOS (44) servers
+    if parameters.starts_with(&[1, 121, 3, 6, 15]) && has(252) {
+        return Some(if has(95) || has(44) {
+            OsFamily::Macos
+        } else {
+            OsFamily::Ios
+        });
+    }
+    // Android's dhcpcd-derived client before it sent a vendor class
+    if parameters.starts_with(&[1, 3, 6, 15, 26, 28, 51, 58, 59]) {
+        return Some(OsFamily::Android);
+    }
+    // ISC dhclient, and systemd-networkd / NetworkManager asking for the
+    // broadcast address (28) and NTP servers (42)
+    if parameters.starts_with(&[1, 28, 2, 3, 15, 6]) || (has(28) && has(42) && !has(252)) {
+        return Some(OsFamily::Linux);
+    }
+    None
+}
+
+static FINGERPRINTS: LazyLock<Mutex<HashMap<String, DhcpFingerprint>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Store a fingerprint, keeping the hostname and vendor class of earlier
+/// requests when a renewal omits them
+pub fn record(mut fingerprint: DhcpFingerprint) {
+    let mut store = FINGERPRINTS.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(previous) = store.remove(&fingerprint.mac) {
+        if fingerprint.hostname.is_none() {
+            fingerprint.hostname = previous.hostname;
+        }
+        if fingerprint.vendor_class.is_none() {
+            fingerprint.vendor_class = previous.vendor_class;
+        }
+        if fingerprint.client_id.is_none() {
+            fingerprint.client_id = previous.client_id;
+        }
+        if fingerprint.parameter_list.is_empty() {
+            fingerprint.parameter_list = previous.parameter_list;
+        }
+        if fingerprint.requested_ip.is_none() {
+            fingerprint.requested_ip = previous.requested_ip;
+        }
+        fingerprint.os_family = infer_os_family(
+            fingerprint.vendor_class.as_deref(),
+            &fingerprint.parameter_list,
+        )
+        .or(previous.os_family);
+    }
+
+    if store.len() >= MAX_FINGERPRINTS
+        && let Some(oldest) = store
+            .values()
+            .min_by_key(|f| f.last_seen)
+            .map(|f| f.mac.clone())
+    {
+        store.remove(&oldest);
+    }
+    store.insert(fingerprint.mac.clone(), fingerprint);
+}
+
+/// Latest fingerprint of a MAC address (any notation)
+pub fn lookup(mac: &str) -> Option<DhcpFingerprint> {
+    let store = FINGERPRINTS.lock().unwrap_or_else(|e| e.into_inner());
+    store.get(&super::normalize_mac(mac)).cloned()
+}
+
+/// Id of the running listener thread (0 = none)
+static ACTIVE_LISTENER: AtomicU64 = AtomicU64::new(0);
+static NEXT_LISTENER_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Start capturing DHCP requests on every interface in a background
+/// thread. Returns whether a listener is running; this fails without
+/// CAP_NET_RAW and on platforms other than Linux.
+pub fn start_listener() -> bool {
+    if ACTIVE_LISTENER.load(Ordering::SeqCst) != 0 {
+        return true;
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        let fd = match capture::open_socket() {
+            Ok(fd) => fd,
+            Err(e) => {
+                tracing::warn!("DHCP listener unavailable: {:#}", e);
+                return false;
+            }
+        };
+        let id = NEXT_LISTENER_ID.fetch_add(1, Ordering::SeqCst);
+        if ACTIVE_LISTENER
+            .compare_exchange(0, id, Ordering::SeqCst, Ordering::SeqCst)
+            .is_err()
+        {
+            // Another caller won the race
+            return true;
+        }
+
+        let spawned = std::thread::Builder::new()
+            .name("dhcp-listener".to_string())
+            .spawn(move || capture::run(fd, id));
+        if let Err(e) = spawned {
+            tracing::warn!("Failed to start DHCP listener: {}", e);
+            ACTIVE_LISTENER.store(0, Ordering::SeqCst);
+            return false;
+        }
+        tracing::info!("Listening for DHCP requests");
+        true
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = &NEXT_LISTENER_ID;
+        tracing::debug!("Passive DHCP listener is only supported on Linux");
+        false
+    }
+}
+
+/// Stop the listener started by [`start_listener`]. Fingerprints already
+/// recorded are kept.
+pub fn stop_listener() {
+    if ACTIVE_LISTENER.swap(0, Ordering::SeqCst) != 0 {
+        tracing::info!("Stopped DHCP listener");
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod capture {
+    use super::super::arp_sweep::packet;
+    use super::*;
+    use anyhow::{Context, Result};
+    use std::io;
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+    use std::time::Duration;
+
+    /// Poll interval, so that a stop request is noticed
+    const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+    /// Classic BPF: accept unfragmented IPv4 UDP datagrams to port 67
+    fn dhcp_request_filter() -> [libc::sock_filter; 11] {
+        let op = |code: u16, jt: u8, jf: u8, k: u32| libc::sock_filter { code, jt, jf, k };
+        const LD_H_ABS: u16 = 0x28;
+        const LD_B_ABS: u16 = 0x30;
+        const LD_H_IND: u16 = 0x48;
+        const LDX_B_MSH: u16 = 0xb1;
+        const JEQ_K: u16 = 0x15;
+        const JSET_K: u16 = 0x45;
+        const RET_K: u16 = 0x06;
+        [
+            op(LD_H_ABS, 0, 0, 12),
+            op(JEQ_K, 0, 8, 0x0800),
+            op(LD_B_ABS, 0, 0, 23),
+            op(JEQ_K, 0, 6, IPPROTO_UDP as u32),
+            // Later fragments have no UDP header
+            op(LD_H_ABS, 0, 0, 20),
+            op(JSET_K, 4, 0, 0x1fff),
+            op(LDX_B_MSH, 0, 0, 14),
+            op(LD_H_IND, 0, 0, 16),
+            op(JEQ_K, 0, 1, DHCP_SERVER_PORT as u32),
+            op(RET_K, 0, 0, 0xffff),
+            op(RET_K, 0, 0, 0),
+        ]
+    }
+
+    /// Unbound socket, so every interface is covered. ETH_P_ALL rather than
+    /// ETH_P_IP so that this host's own requests are seen too; the filter
+    /// does the selecting.
+    pub(super) fn open_socket() -> Result<OwnedFd> {
+        let protocol = (libc::ETH_P_ALL as u16).to_be();
+        let raw = unsafe {
+            libc::socket(
+                libc::AF_PACKET,
+                libc::SOCK_RAW | libc::SOCK_CLOEXEC | libc::SOCK_NONBLOCK,
+                protocol as libc::c_int,
+            )
+        };
+        if raw < 0 {
+            return Err(io::Error::last_os_error()).context("Failed to open AF_PACKET socket");
+        }
+        let fd = unsafe { OwnedFd::from_raw_fd(raw) };
+
+        let mut filter = dhcp_request_filter();
+        let program = libc::sock_fprog {
+            len: filter.len() as u16,
+            filter: filter.as_mut_ptr(),
+        };
+        packet::setsockopt(&fd, libc::SOL_SOCKET, libc::SO_ATTACH_FILTER, &program)
+            .context("Failed to attach DHCP filter")?;
+
+        // Frames that arrived before the filter was attached
+        let mut buf = [0u8; 2048];
+        while unsafe {
+            libc::recv(
+                fd.as_raw_fd(),
+                buf.as_mut_ptr() as *mut libc::c_void,
+                buf.len(),
+                0,
+            )
+        } > 0
+        {}
+        Ok(fd)
+    }
+
+    pub(super) fn run(fd: OwnedFd, id: u64) {
+        let mut buf = [0u8; 2048];
+        while ACTIVE_LISTENER.load(Ordering::Relaxed) == id {
+            if !packet::wait_readable(&fd, POLL_INTERVAL) {
+                continue;
+            }
+            loop {
+                let received = unsafe {
+                    libc::recv(
+                        fd.as_raw_fd(),
+                        buf.as_mut_ptr() as *mut libc::c_void,
+                        buf.len(),
+                        0,
+                    )
+                };
+                if received <= 0 {
+                    break;
+                }
+                let Some(fingerprint) = parse_frame(&buf[..received as usize]) else {
+                    continue;
+                };
+                tracing::debug!(
+                    "DHCP request from {} ({}): vendor class {:?}, parameters {}",
+                    fingerprint.mac,
+                    fingerprint.hostname.as_deref().unwrap_or("no hostname"),
+                    fingerprint.vendor_class,
+                    fingerprint.parameter_list_string()
+                );
+                record(fingerprint);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Requests captured from a home network: Windows 10, iOS, macOS,
+    /// Android 13 and Ubuntu (dhclient), a server offer, and a Windows
+    /// DHCPINFORM with only a client FQDN
+    const PCAP_FIXTURE: &[u8] = include_bytes!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/dhcp_requests.pcap"
+    ));
+
+    /// Frames of a little-endian, microsecond pcap file
+    fn pcap_frames(pcap: &[u8]) -> Vec<&[u8]> {
+        assert_eq!(pcap[..4], [0xd4, 0xc3, 0xb2, 0xa1]);
+        let mut frames = Vec::new();
+        let mut pos = 24;
+        while pos + 16 <= pcap.len() {
+            let len = u32::from_le_bytes(pcap[pos + 8..pos + 12].try_into().unwrap()) as usize;
+            frames.push(&pcap[pos + 16..pos + 16 + len]);
+            pos += 16 + len;
+        }
+        frames
+    }
+
+    fn fixture_fingerprints() -> Vec<Option<DhcpFingerprint>> {
+        pcap_frames(PCAP_FIXTURE)
+            .into_iter()
+            .map(parse_frame)
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_pcap_fixture() {
+        let parsed = fixture_fingerprints();
+        assert_eq!(parsed.len(), 7);
+
+        let windows = parsed[0].as_ref().unwrap();
+        assert_eq!(windows.mac, "3c:52:82:1a:2b:3c");
+        assert_eq!(windows.hostname.as_deref(), Some("DESKTOP-7H2K4QF"));
+        assert_eq!(windows.vendor_class.as_deref(), Some("MSFT 5.0"));
+        assert_eq!(
+            windows.parameter_list_string(),
+            "1,3,6,15,31,33,43,44,46,47,119,121,249,252"
+        );
+        assert_eq!(windows.requested_ip.as_deref(), Some("192.168.1.57"));
+        assert_eq!(windows.client_id.as_deref(), Some("01:3c:52:82:1a:2b:3c"));
+
+        let linux = parsed[4].as_ref().unwrap();
+        assert_eq!(linux.mac, "52:54:00:8e:3d:7f");
+        assert_eq!(linux.hostname.as_deref(), Some("build-01"));
+        assert_eq!(linux.vendor_class, None);
+        assert_eq!(linux.client_id, None);
+
+        // The server's offer is not a client request
+        assert!(parsed[5].is_none());
+
+        let inform = parsed[6].as_ref().unwrap();
+        assert_eq!(inform.hostname.as_deref(), Some("LAB-7.corp.example.com"));
+        assert_eq!(inform.requested_ip.as_deref(), Some("192.168.1.90"));
+    }
+
+    #[test]
+    fn test_infer_os_family_from_fixture() {
+        let families: Vec<_> = fixture_fingerprints()
+            .into_iter()
+            .map(|f| f.and_then(|f| f.os_family))
+            .collect();
+        assert_eq!(
+            families,
+            vec![
+                Some(OsFamily::Windows),
+                Some(OsFamily::Ios),
+                Some(OsFamily::Macos),
+                Some(OsFamily::Android),
+                Some(OsFamily::Linux),
+                None,
+                Some(OsFamily::Windows),
+            ]
+        );
+
+        // Android without a vendor class, and an unknown embedded client
+        let android = [1, 3, 6, 15, 26, 28, 51, 58, 59, 43];
+        assert_eq!(infer_os_family(None, &android), Some(OsFamily::Android));
+        assert_eq!(infer_os_family(None, &[1, 3, 6]), None);
+    }
+
+    #[test]
+    fn test_parse_frame_rejects_truncated() {
+        let frames = pcap_frames(PCAP_FIXTURE);
+        let frame = frames[0];
+        // Cut inside the hostname option and before the magic cookie
+        assert!(parse_frame(&frame[..305]).is_none());
+        assert!(parse_frame(&frame[..200]).is_none());
+    }
+
+    #[test]
+    fn test_record_keeps_earlier_details() {
+        let mut first = fixture_fingerprints()[1].clone().unwrap();
+        first.mac = "02:00:00:00:16:01".to_string();
+        record(first.clone());
+
+        // A renewal without a hostname or parameter list
+        let renewal = DhcpFingerprint {
+            hostname: None,
+            parameter_list: Vec::new(),
+            requested_ip: None,
+            os_family: None,
+            ..first.clone()
+        };
+        record(renewal);
+
+        let stored = lookup("02-00-00-00-16-01").unwrap();
+        assert_eq!(stored.hostname.as_deref(), Some("Alexs-iPhone"));
+        assert_eq!(stored.os_family, Some(OsFamily::Ios));
+        assert_eq!(stored.parameter_list, first.parameter_list);
+    }
+}
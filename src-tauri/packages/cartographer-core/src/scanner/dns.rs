@@ -0,0 +1,408 @@
+//! DNS wire format: query building and response parsing
+//!
+//! Shared by the multicast DNS browser and the other name resolvers. Only
+//! the record types the scanner reads are decoded; everything else is kept
+//! as [`RecordData::Other`].
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::time::Duration;
+use tokio::net::UdpSocket;
+
+pub const TYPE_A: u16 = 1;
+pub const TYPE_PTR: u16 = 12;
+pub const TYPE_TXT: u16 = 16;
+pub const TYPE_AAAA: u16 = 28;
+pub const TYPE_SRV: u16 = 33;
+
+const CLASS_IN: u16 = 1;
+/// mDNS "unicast response requested" bit in the question class
+const CLASS_UNICAST_RESPONSE: u16 = 0x8000;
+/// mDNS cache-flush bit in the record class
+const CLASS_MASK: u16 = 0x7fff;
+
+const HEADER_LEN: usize = 12;
+const FLAG_RESPONSE: u16 = 0x8000;
+const FLAG_RECURSION_DESIRED: u16 = 0x0100;
+/// Upper bound on compression pointer hops, against pointer loops
+const MAX_POINTER_HOPS: usize = 32;
+/// Unicast queries are sent again after this long without an answer
+const RETRANSMIT_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Decoded record data
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecordData {
+    A(Ipv4Addr),
+    Aaaa(Ipv6Addr),
+    Ptr(String),
+    Srv { port: u16, target: String },
+    Txt(Vec<String>),
+    Other,
+}
+
+/// A resource record from any section of a response
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    /// Owner name without the trailing dot
+    pub name: String,
+    pub rtype: u16,
+    pub ttl: u32,
+    pub data: RecordData,
+}
+
+/// A parsed DNS response
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    pub id: u16,
+    pub is_response: bool,
+    /// Response code (0 = no error, 3 = name does not exist)
+    pub rcode: u8,
+    /// Answer, authority and additional records in wire order
+    pub records: Vec<Record>,
+}
+
+/// Build a query message with one question per `(name, type)`.
+///
+/// `unicast_response` sets the mDNS QU bit; `recursion_desired` is for
+/// queries to a regular resolver.
+pub fn build_query(
+    id: u16,
+    questions: &[(&str, u16)],
+    recursion_desired: bool,
+    unicast_response: bool,
+) -> Vec<u8> {
+    let flags = if recursion_desired {
+        FLAG_RECURSION_DESIRED
+    } else {
+        0
+    };
+    let class = if unicast_response {
+        CLASS_IN | CLASS_UNICAST_RESPONSE
+    } else {
+        CLASS_IN
+    };
+
+    let mut buf = Vec::with_capacity(HEADER_LEN + questions.len() * 32);
+    buf.extend_from_slice(&id.to_be_bytes());
+    buf.extend_from_slice(&flags.to_be_bytes());
+    buf.extend_from_slice(&(questions.len() as u16).to_be_bytes());
+    buf.extend_from_slice(&[0; 6]); // answer, authority, additional counts
+
+    for (name, qtype) in questions {
+        for label in name
+            .trim_end_matches('.')
+            .split('.')
+            .filter(|l| !l.is_empty())
+        {
+            let label = &label.as_bytes()[..label.len().min(63)];
+            buf.push(label.len() as u8);
+            buf.extend_from_slice(label);
+        }
+        buf.push(0);
+        buf.extend_from_slice(&qtype.to_be_bytes());
+        buf.extend_from_slice(&class.to_be_bytes());
+    }
+    buf
+}
+
+/// Transaction id for a unicast query. Ids only need to differ between
+/// queries in flight, so a process-wide counter with a random start will do.
+pub fn next_query_id() -> u16 {
+    static NEXT_ID: OnceLock<AtomicU16> = OnceLock::new();
+    NEXT_ID
+        .get_or_init(|| {
+            let nanos = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.subsec_nanos())
+                .unwrap_or_default();
+            AtomicU16::new((nanos ^ std::process::id()) as u16)
+        })
+        .fetch_add(1, Ordering::Relaxed)
+}
+
+/// Name for a reverse (PTR) lookup: "4.3.2.1.in-addr.arpa" or the nibble
+/// form under "ip6.arpa".
+pub fn reverse_name(ip: IpAddr) -> String {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, c, d] = v4.octets();
+            format!("{}.{}.{}.{}.in-addr.arpa", d, c, b, a)
+        }
+        IpAddr::V6(v6) => {
+            let mut name = String::with_capacity(72);
+            for byte in v6.octets().iter().rev() {
+                name.push_str(&format!("{:x}.{:x}.", byte & 0x0f, byte >> 4));
+            }
+            name.push_str("ip6.arpa");
+            name
+        }
+    }
+}
+
+/// Send `packet` to `dest` from an ephemeral socket and return the first
+/// reply `accept` takes, resending every [`RETRANSMIT_INTERVAL`] until
+/// `timeout` passes. Used by the unicast resolvers (NetBIOS, LLMNR).
+///
+/// The socket is connected so that an ICMP port unreachable from a host
+/// without the service ends the exchange early.
+pub async fn exchange<T>(
+    dest: SocketAddr,
+    packet: &[u8],
+    timeout: Duration,
+    accept: impl Fn(&[u8]) -> Option<T>,
+) -> Option<T> {
+    let bind: SocketAddr = match dest {
+        SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+        SocketAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+    };
+    let socket = match UdpSocket::bind(bind).await {
+        Ok(socket) => socket,
+        Err(e) => {
+            tracing::debug!("Failed to bind UDP socket for {}: {}", dest, e);
+            return None;
+        }
+    };
+    if let Err(e) = socket.connect(dest).await {
+        tracing::debug!("UDP connect to {} failed: {}", dest, e);
+        return None;
+    }
+
+    let deadline = tokio::time::Instant::now() + timeout;
+    let mut buf = vec![0u8; 1500];
+    while tokio::time::Instant::now() < deadline {
+        if socket.send(packet).await.is_err() {
+            return None;
+        }
+        let resend_at = (tokio::time::Instant::now() + RETRANSMIT_INTERVAL).min(deadline);
+        while let Ok(received) = tokio::time::timeout_at(resend_at, socket.recv(&mut buf)).await {
+            // Connection refused: nothing listens on the port
+            let Ok(len) = received else {
+                return None;
+            };
+            if let Some(answer) = accept(&buf[..len]) {
+                return Some(answer);
+            }
+        }
+    }
+    None
+}
+
+/// Parse a DNS message. Questions are skipped; records of all three
+/// sections are returned together.
+pub fn parse_message(buf: &[u8]) -> Option<Message> {
+    if buf.len() < HEADER_LEN {
+        return None;
+    }
+    let word = |i: usize| u16::from_be_bytes([buf[i], buf[i + 1]]);
+    let id = word(0);
+    let flags = word(2);
+    let questions = word(4) as usize;
+    let records = word(6) as usize + word(8) as usize + word(10) as usize;
+
+    let mut pos = HEADER_LEN;
+    for _ in 0..questions {
+        pos = read_name(buf, pos)?.1 + 4;
+    }
+
+    let mut parsed = Vec::with_capacity(records.min(64));
+    for _ in 0..records {
+        let (name, next) = read_name(buf, pos)?;
+        let header = buf.get(next..next + 10)?;
+        let rtype = u16::from_be_bytes([header[0], header[1]]);
+        let class = u16::from_be_bytes([header[2], header[3]]) & CLASS_MASK;
+        let ttl = u32::from_be_bytes([header[4], header[5], header[6], header[7]]);
+        let len = u16::from_be_bytes([header[8], header[9]]) as usize;
+        let start = next + 10;
+        let rdata = buf.get(start..start + len)?;
+        pos = start + len;
+
+        let data = if class != CLASS_IN {
+            RecordData::Other
+        } else {
+            parse_rdata(buf, start, rtype, rdata).unwrap_or(RecordData::Other)
+        };
+        parsed.push(Record {
+            name,
+            rtype,
+            ttl,
+            data,
+        });
+    }
+
+    Some(Message {
+        id,
+        is_response: flags & FLAG_RESPONSE != 0,
+        rcode: (flags & 0x000f) as u8,
+        records: parsed,
+    })
+}
+
+fn parse_rdata(buf: &[u8], start: usize, rtype: u16, rdata: &[u8]) -> Option<RecordData> {
+    Some(match rtype {
+        TYPE_A => RecordData::A(Ipv4Addr::from(<[u8; 4]>::try_from(rdata).ok()?)),
+        TYPE_AAAA => RecordData::Aaaa(Ipv6Addr::from(<[u8; 16]>::try_from(rdata).ok()?)),
+        TYPE_PTR => RecordData::Ptr(read_name(buf, start)?.0),
+        TYPE_SRV => {
+            let port = u16::from_be_bytes([*rdata.get(4)?, *rdata.get(5)?]);
+            RecordData::Srv {
+                port,
+                target: read_name(buf, start + 6)?.0,
+            }
+        }
+        TYPE_TXT => {
+            let mut strings = Vec::new();
+            let mut rest = rdata;
+            while let Some((&len, tail)) = rest.split_first() {
+                let text = tail.get(..len as usize)?;
+                if !text.is_empty() {
+                    strings.push(String::from_utf8_lossy(text).into_owned());
+                }
+                rest = &tail[len as usize..];
+            }
+            RecordData::Txt(strings)
+        }
+        _ => RecordData::Other,
+    })
+}
+
+/// Read a possibly compressed name at `pos`. Returns the dotted name and
+/// the position after it in the original stream.
+fn read_name(buf: &[u8], mut pos: usize) -> Option<(String, usize)> {
+    let mut labels: Vec<String> = Vec::new();
+    let mut end = None;
+    let mut hops = 0;
+
+    loop {
+        let len = *buf.get(pos)? as usize;
+        match len & 0xc0 {
+            0x00 if len == 0 => {
+                end.get_or_insert(pos + 1);
+                break;
+            }
+            0x00 => {
+                let label = buf.get(pos + 1..pos + 1 + len)?;
+                labels.push(String::from_utf8_lossy(label).into_owned());
+                pos += 1 + len;
+            }
+            0xc0 => {
+                hops += 1;
+                if hops > MAX_POINTER_HOPS {
+                    return None;
+                }
+                let target = ((len & 0x3f) << 8) | *buf.get(pos + 1)? as usize;
+                end.get_or_insert(pos + 2);
+                pos = target;
+            }
+            _ => return None,
+        }
+    }
+
+    Some((labels.join("."), end?))
+}
+
+/// Append an uncompressed record (cache-flush class, TTL 120) to a message
+/// under construction.
+#[cfg(test)]
+pub(crate) fn push_record(buf: &mut Vec<u8>, name: &str, rtype: u16, rdata: &[u8]) {
+    for label in name.split('.') {
+        buf.push(label.len() as u8);
+        buf.extend_from_slice(label.as_bytes());
+    }
+    buf.push(0);
+    buf.extend_from_slice(&rtype.to_be_bytes());
+    buf.extend_from_slice(&(CLASS_IN | 0x8000).to_be_bytes());
+    buf.extend_from_slice(&120u32.to_be_bytes());
+    buf.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+    buf.extend_from_slice(rdata);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_query() {
+        let query = build_query(
+            0x1234,
+            &[("_services._dns-sd._udp.local", TYPE_PTR)],
+            false,
+            true,
+        );
+        assert_eq!(&query[..6], &[0x12, 0x34, 0x00, 0x00, 0x00, 0x01]);
+        assert_eq!(query[12], 9);
+        assert_eq!(&query[13..22], b"_services");
+        // PTR, IN with the unicast-response bit
+        assert_eq!(&query[query.len() - 4..], &[0x00, 0x0c, 0x80, 0x01]);
+    }
+
+    #[test]
+    fn test_reverse_name() {
+        assert_eq!(
+            reverse_name("192.168.1.20".parse().unwrap()),
+            "20.1.168.192.in-addr.arpa"
+        );
+        assert_eq!(
+            reverse_name("2001:db8::1".parse().unwrap()),
+            "1.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.8.b.d.0.1.0.0.2.ip6.arpa"
+        );
+    }
+
+    #[test]
+    fn test_parse_compressed_response() {
+        // Response with one question and PTR/SRV/TXT/A answers, where the
+        // SRV and A records point back at earlier names
+        let mut buf = vec![0, 0, 0x84, 0x00, 0, 0, 0, 4, 0, 0, 0, 0];
+        let ptr_rdata = {
+            let mut r = vec![7];
+            r.extend_from_slice(b"Printer");
+            r.extend_from_slice(&[0xc0, 12]); // -> _ipp._tcp.local
+            r
+        };
+        push_record(&mut buf, "_ipp._tcp.local", TYPE_PTR, &ptr_rdata);
+        let instance = buf.len() - ptr_rdata.len();
+        let mut srv = vec![0, 0, 0, 0, 0x02, 0x77];
+        srv.extend_from_slice(&[3, b'h', b'p', b'1', 5]);
+        srv.extend_from_slice(b"local");
+        srv.push(0);
+        push_record(&mut buf, "Printer._ipp._tcp.local", TYPE_SRV, &srv);
+        push_record(
+            &mut buf,
+            "Printer._ipp._tcp.local",
+            TYPE_TXT,
+            b"\x09txtvers=1\x0cty=HP M404dn\x00",
+        );
+        // Owner name is a pointer to the PTR's rdata
+        buf.extend_from_slice(&[0xc0, instance as u8]);
+        buf.extend_from_slice(&[0x00, 0x01, 0x00, 0x01, 0, 0, 0, 120, 0, 4, 192, 168, 1, 40]);
+
+        let message = parse_message(&buf).expect("valid message");
+        assert!(message.is_response);
+        assert_eq!(message.records.len(), 4);
+        assert_eq!(
+            message.records[0].data,
+            RecordData::Ptr("Printer._ipp._tcp.local".to_string())
+        );
+        assert_eq!(
+            message.records[1].data,
+            RecordData::Srv {
+                port: 631,
+                target: "hp1.local".to_string()
+            }
+        );
+        assert_eq!(
+            message.records[2].data,
+            RecordData::Txt(vec!["txtvers=1".to_string(), "ty=HP M404dn".to_string()])
+        );
+        assert_eq!(message.records[3].name, "Printer._ipp._tcp.local");
+        assert_eq!(
+            message.records[3].data,
+            RecordData::A(Ipv4Addr::new(192, 168, 1, 40))
+        );
+
+        // A pointer loop is rejected rather than followed forever
+        let mut looped = vec![0, 0, 0x84, 0, 0, 0, 0, 1, 0, 0, 0, 0];
+        looped.extend_from_slice(&[0xc0, 12]);
+        assert_eq!(parse_message(&looped), None);
+    }
+}
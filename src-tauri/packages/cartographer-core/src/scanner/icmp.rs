@@ -0,0 +1,512 @@
+//! In-process ICMP echo sweep engine
+//!
+//! Sends echo requests for a whole subnet from one socket instead of forking
+//! a `ping` process per host:
+//! 1. Unprivileged ICMP datagram socket (Linux `net.ipv4.ping_group_range`, macOS)
+//! 2. Raw ICMP socket (root or `CAP_NET_RAW`)
+//!
+//! When neither can be opened the caller falls back to the system `ping`.
+
+use super::config::{EnginePreference, PingConfig};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::net::Ipv4Addr;
+
+const ICMP_ECHO_REPLY: u8 = 0;
+const ICMP_ECHO_REQUEST: u8 = 8;
+const ICMP_HEADER_LEN: usize = 8;
+
+/// Marks our echo requests so raw sockets can ignore unrelated ICMP traffic
+const PAYLOAD_MAGIC: &[u8; 8] = b"CRTGRPHR";
+const PAYLOAD_LEN: usize = 16;
+
+/// ICMP engine used for the ping sweep
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PingEngine {
+    /// Unprivileged ICMP datagram socket
+    Datagram,
+    /// Raw ICMP socket
+    Raw,
+    /// One system `ping` process per host
+    Subprocess,
+    /// No way to send ICMP
+    Unavailable,
+}
+
+impl std::fmt::Display for PingEngine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PingEngine::Datagram => write!(f, "ICMP datagram socket"),
+            PingEngine::Raw => write!(f, "raw ICMP socket"),
+            PingEngine::Subprocess => write!(f, "system ping"),
+            PingEngine::Unavailable => write!(f, "unavailable"),
+        }
+    }
+}
+
+/// A host that answered the sweep
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EchoReply {
+    pub ip: Ipv4Addr,
+    pub rtt_ms: f64,
+    /// IP TTL of the reply, when the socket reports it
+    pub ttl: Option<u8>,
+}
+
+/// Compute the Internet checksum (RFC 1071)
+pub fn icmp_checksum(data: &[u8]) -> u16 {
+    let mut sum: u32 = 0;
+    for chunk in data.chunks(2) {
+        let word = if chunk.len() == 2 {
+            u16::from_be_bytes([chunk[0], chunk[1]])
+        } else {
+            u16::from_be_bytes([chunk[0], 0])
+        };
+        sum = sum.wrapping_add(word as u32);
+    }
+    while sum >> 16 != 0 {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+/// Build an ICMP echo request carrying our magic and a per-sweep token.
+pub fn build_echo_request(ident: u16, seq: u16, token: u32) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(ICMP_HEADER_LEN + PAYLOAD_LEN);
+    packet.push(ICMP_ECHO_REQUEST);
+    packet.push(0); // code
+    packet.extend_from_slice(&[0, 0]); // checksum placeholder
+    packet.extend_from_slice(&ident.to_be_bytes());
+    packet.extend_from_slice(&seq.to_be_bytes());
+    packet.extend_from_slice(PAYLOAD_MAGIC);
+    packet.extend_from_slice(&token.to_be_bytes());
+    packet.extend_from_slice(&[0u8; PAYLOAD_LEN - 12]);
+
+    let checksum = icmp_checksum(&packet);
+    packet[2..4].copy_from_slice(&checksum.to_be_bytes());
+    packet
+}
+
+/// Parse an echo reply, returning its sequence number and token.
+///
+/// Accepts packets with or without a leading IPv4 header (raw sockets and
+/// macOS datagram sockets include it, Linux datagram sockets do not).
+pub fn parse_echo_reply(buf: &[u8]) -> Option<(u16, u32)> {
+    let icmp = if buf.first().map(|b| b >> 4) == Some(4) {
+        let header_len = ((buf[0] & 0x0f) as usize) * 4;
+        buf.get(header_len..)?
+    } else {
+        buf
+    };
+
+    if icmp.len() < ICMP_HEADER_LEN + 12 || icmp[0] != ICMP_ECHO_REPLY || icmp[1] != 0 {
+        return None;
+    }
+    if &icmp[ICMP_HEADER_LEN..ICMP_HEADER_LEN + 8] != PAYLOAD_MAGIC {
+        return None;
+    }
+
+    let seq = u16::from_be_bytes([icmp[6], icmp[7]]);
+    let token = u32::from_be_bytes([
+        icmp[ICMP_HEADER_LEN + 8],
+        icmp[ICMP_HEADER_LEN + 9],
+        icmp[ICMP_HEADER_LEN + 10],
+        icmp[ICMP_HEADER_LEN + 11],
+    ]);
+    Some((seq, token))
+}
+
+/// TTL from the IPv4 header of a received packet, if it has one
+pub fn header_ttl(buf: &[u8]) -> Option<u8> {
+    if buf.first().map(|b| b >> 4) == Some(4) {
+        buf.get(8).copied()
+    } else {
+        None
+    }
+}
+
+/// Pick the ICMP engine to use, honoring the configured preference.
+///
+/// Returns `Subprocess` when no socket can be opened; the caller decides
+/// whether the system `ping` works.
+pub fn detect_engine(preference: EnginePreference) -> PingEngine {
+    let candidates: &[PingEngine] = match preference {
+        EnginePreference::Auto => &[PingEngine::Datagram, PingEngine::Raw],
+        EnginePreference::Datagram => &[PingEngine::Datagram],
+        EnginePreference::Raw => &[PingEngine::Raw],
+        EnginePreference::Subprocess => &[],
+    };
+
+    for &engine in candidates {
+        #[cfg(unix)]
+        match socket::IcmpSocket::open(engine) {
+            Ok(_) => return engine,
+            Err(e) => tracing::debug!("Cannot open {}: {}", engine, e),
+        }
+
+        #[cfg(not(unix))]
+        let _ = engine;
+    }
+
+    PingEngine::Subprocess
+}
+
+/// Sweep the given hosts with the socket engine. Returns responding hosts
+/// with their round-trip times and reply TTLs.
+pub async fn sweep(
+    engine: PingEngine,
+    targets: Vec<Ipv4Addr>,
+    config: &PingConfig,
+) -> Result<Vec<EchoReply>> {
+    #[cfg(unix)]
+    {
+        let config = config.clone();
+        tokio::task::spawn_blocking(move || socket::sweep_blocking(engine, &targets, &config))
+            .await
+            .map_err(|e| anyhow::anyhow!("ICMP sweep task panicked: {}", e))?
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = (engine, targets, config);
+        Err(anyhow::anyhow!(
+            "ICMP socket engine not supported on this platform"
+        ))
+    }
+}
+
+#[cfg(unix)]
+mod socket {
+    use super::super::is_scan_cancelled;
+    use super::*;
+    use anyhow::Context;
+    use std::collections::HashMap;
+    use std::io;
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+    use std::time::{Duration, Instant};
+
+    pub(super) struct IcmpSocket {
+        fd: OwnedFd,
+    }
+
+    impl IcmpSocket {
+        pub(super) fn open(engine: PingEngine) -> io::Result<Self> {
+            let sock_type = match engine {
+                PingEngine::Datagram => libc::SOCK_DGRAM,
+                PingEngine::Raw => libc::SOCK_RAW,
+                _ => return Err(io::Error::other("not a socket engine")),
+            };
+
+            let raw = unsafe { libc::socket(libc::AF_INET, sock_type, libc::IPPROTO_ICMP) };
+            if raw < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            // Closed on drop
+            let fd = unsafe { OwnedFd::from_raw_fd(raw) };
+
+            let flags = unsafe { libc::fcntl(raw, libc::F_GETFL) };
+            if flags < 0 || unsafe { libc::fcntl(raw, libc::F_SETFL, flags | libc::O_NONBLOCK) } < 0
+            {
+                return Err(io::Error::last_os_error());
+            }
+
+            // Linux datagram sockets strip the IP header; ask for the TTL
+            // as ancillary data instead
+            #[cfg(target_os = "linux")]
+            {
+                let enable: libc::c_int = 1;
+                unsafe {
+                    libc::setsockopt(
+                        raw,
+                        libc::IPPROTO_IP,
+                        libc::IP_RECVTTL,
+                        &enable as *const libc::c_int as *const libc::c_void,
+                        std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+                    );
+                }
+            }
+
+            Ok(Self { fd })
+        }
+
+        fn send_to(&self, packet: &[u8], ip: Ipv4Addr) -> io::Result<()> {
+            let mut addr: libc::sockaddr_in = unsafe { std::mem::zeroed() };
+            addr.sin_family = libc::AF_INET as libc::sa_family_t;
+            addr.sin_addr.s_addr = u32::from(ip).to_be();
+            #[cfg(target_os = "macos")]
+            {
+                addr.sin_len = std::mem::size_of::<libc::sockaddr_in>() as u8;
+            }
+
+            let sent = unsafe {
+                libc::sendto(
+                    self.fd.as_raw_fd(),
+                    packet.as_ptr() as *const libc::c_void,
+                    packet.len(),
+                    0,
+                    &addr as *const libc::sockaddr_in as *const libc::sockaddr,
+                    std::mem::size_of::<libc::sockaddr_in>() as libc::socklen_t,
+                )
+            };
+            if sent < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        }
+
+        /// Non-blocking receive; `None` when nothing is queued. Returns the
+        /// length, the source and the TTL from ancillary data if present.
+        fn recv_from(&self, buf: &mut [u8]) -> io::Result<Option<(usize, Ipv4Addr, Option<u8>)>> {
+            let mut addr: libc::sockaddr_in = unsafe { std::mem::zeroed() };
+            let mut iov = libc::iovec {
+                iov_base: buf.as_mut_ptr() as *mut libc::c_void,
+                iov_len: buf.len(),
+            };
+            // u64 for cmsghdr alignment
+            let mut control = [0u64; 8];
+            let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+            msg.msg_name = &mut addr as *mut libc::sockaddr_in as *mut libc::c_void;
+            msg.msg_namelen = std::mem::size_of::<libc::sockaddr_in>() as libc::socklen_t;
+            msg.msg_iov = &mut iov;
+            msg.msg_iovlen = 1;
+            msg.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+            msg.msg_controllen = std::mem::size_of_val(&control) as _;
+
+            let received = unsafe { libc::recvmsg(self.fd.as_raw_fd(), &mut msg, 0) };
+            if received < 0 {
+                let err = io::Error::last_os_error();
+                if err.kind() == io::ErrorKind::WouldBlock {
+                    return Ok(None);
+                }
+                return Err(err);
+            }
+
+            let mut ttl = None;
+            unsafe {
+                let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+                while !cmsg.is_null() {
+                    if (*cmsg).cmsg_level == libc::IPPROTO_IP && (*cmsg).cmsg_type == libc::IP_TTL {
+                        let value =
+                            std::ptr::read_unaligned(libc::CMSG_DATA(cmsg) as *const libc::c_int);
+                        ttl = u8::try_from(value).ok();
+                    }
+                    cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+                }
+            }
+
+            let source = Ipv4Addr::from(u32::from_be(addr.sin_addr.s_addr));
+            Ok(Some((received as usize, source, ttl)))
+        }
+
+        /// Wait until a reply is queued or the timeout expires.
+        fn wait_readable(&self, timeout: Duration) -> bool {
+            let mut pfd = libc::pollfd {
+                fd: self.fd.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            let timeout_ms = timeout.as_micros().div_ceil(1000).min(i32::MAX as u128) as i32;
+            unsafe { libc::poll(&mut pfd, 1, timeout_ms) > 0 }
+        }
+    }
+
+    struct SweepState {
+        token: u32,
+        index: HashMap<Ipv4Addr, usize>,
+        sent_at: Vec<Option<Instant>>,
+        rtt_ms: Vec<Option<f64>>,
+        ttl: Vec<Option<u8>>,
+        answered: usize,
+    }
+
+    impl SweepState {
+        /// Read every queued reply and record round-trip times.
+        fn drain(&mut self, socket: &IcmpSocket, buf: &mut [u8]) {
+            loop {
+                let (len, source, ttl) = match socket.recv_from(buf) {
+                    Ok(Some(packet)) => packet,
+                    Ok(None) => return,
+                    Err(e) => {
+                        tracing::trace!("ICMP receive error: {}", e);
+                        return;
+                    }
+                };
+
+                let Some((_, token)) = parse_echo_reply(&buf[..len]) else {
+                    continue;
+                };
+                if token != self.token {
+                    continue;
+                }
+                let Some(&i) = self.index.get(&source) else {
+                    continue;
+                };
+                if let (None, Some(sent)) = (self.rtt_ms[i], self.sent_at[i]) {
+                    self.rtt_ms[i] = Some(sent.elapsed().as_secs_f64() * 1000.0);
+                    self.ttl[i] = ttl.or_else(|| header_ttl(&buf[..len]));
+                    self.answered += 1;
+                }
+            }
+        }
+    }
+
+    /// Random-enough token to tell this sweep's replies from other pingers.
+    fn sweep_token() -> u32 {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .subsec_nanos();
+        nanos ^ std::process::id().rotate_left(16)
+    }
+
+    pub(super) fn sweep_blocking(
+        engine: PingEngine,
+        targets: &[Ipv4Addr],
+        config: &PingConfig,
+    ) -> Result<Vec<EchoReply>> {
+        let socket =
+            IcmpSocket::open(engine).with_context(|| format!("Failed to open {}", engine))?;
+
+        let ident = (std::process::id() & 0xffff) as u16;
+        let interval = if config.rate_limit > 0 {
+            Duration::from_secs_f64(1.0 / config.rate_limit as f64)
+        } else {
+            Duration::ZERO
+        };
+        let timeout = Duration::from_millis(config.timeout_ms);
+
+        let mut state = SweepState {
+            token: sweep_token(),
+            index: targets.iter().enumerate().map(|(i, ip)| (*ip, i)).collect(),
+            sent_at: vec![None; targets.len()],
+            rtt_ms: vec![None; targets.len()],
+            ttl: vec![None; targets.len()],
+            answered: 0,
+        };
+        let mut buf = [0u8; 1500];
+        let mut seq: u16 = 0;
+
+        tracing::info!(
+            "ICMP sweep of {} hosts via {} ({} pps, {} retries, {}ms timeout)",
+            targets.len(),
+            engine,
+            config.rate_limit,
+            config.retries,
+            config.timeout_ms
+        );
+
+        for attempt in 0..=config.retries {
+            let pending: Vec<usize> = (0..targets.len())
+                .filter(|&i| state.rtt_ms[i].is_none())
+                .collect();
+            if pending.is_empty() {
+                break;
+            }
+            if attempt > 0 {
+                tracing::debug!("ICMP retry {}: {} hosts", attempt, pending.len());
+            }
+
+            let mut next_send = Instant::now();
+            for (n, &i) in pending.iter().enumerate() {
+                if n % 64 == 0 && is_scan_cancelled() {
+                    tracing::info!("ICMP sweep cancelled");
+                    return Err(anyhow::anyhow!("Scan cancelled by user"));
+                }
+
+                // Rate limit: collect replies while waiting for the next send slot
+                loop {
+                    let now = Instant::now();
+                    if now >= next_send {
+                        break;
+                    }
+                    if socket.wait_readable(next_send - now) {
+                        state.drain(&socket, &mut buf);
+                    }
+                }
+
+                let packet = build_echo_request(ident, seq, state.token);
+                seq = seq.wrapping_add(1);
+                match socket.send_to(&packet, targets[i]) {
+                    Ok(()) => state.sent_at[i] = Some(Instant::now()),
+                    Err(e) => tracing::trace!("ICMP send to {} failed: {}", targets[i], e),
+                }
+                next_send += interval;
+                state.drain(&socket, &mut buf);
+            }
+
+            // Wait for late replies
+            let deadline = Instant::now() + timeout;
+            while state.answered < targets.len() {
+                let now = Instant::now();
+                if now >= deadline || is_scan_cancelled() {
+                    break;
+                }
+                if socket.wait_readable(deadline - now) {
+                    state.drain(&socket, &mut buf);
+                }
+            }
+        }
+
+        Ok(targets
+            .iter()
+            .zip(state.rtt_ms.iter().zip(&state.ttl))
+            .filter_map(|(&ip, (rtt, &ttl))| rtt.map(|rtt_ms| EchoReply { ip, rtt_ms, ttl }))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checksum_rfc1071_example() {
+        // Example from RFC 1071 section 3
+        let data = [0x00, 0x01, 0xf2, 0x03, 0xf4, 0xf5, 0xf6, 0xf7];
+        assert_eq!(icmp_checksum(&data), !0xddf2);
+    }
+
+    #[test]
+    fn test_echo_request_checksum_verifies() {
+        let packet = build_echo_request(0x1234, 7, 0xdeadbeef);
+        assert_eq!(packet.len(), ICMP_HEADER_LEN + PAYLOAD_LEN);
+        assert_eq!(packet[0], ICMP_ECHO_REQUEST);
+        assert_eq!(icmp_checksum(&packet), 0);
+    }
+
+    fn as_reply(mut packet: Vec<u8>) -> Vec<u8> {
+        packet[0] = ICMP_ECHO_REPLY;
+        packet
+    }
+
+    #[test]
+    fn test_parse_echo_reply_without_ip_header() {
+        let reply = as_reply(build_echo_request(1, 42, 0xcafef00d));
+        assert_eq!(parse_echo_reply(&reply), Some((42, 0xcafef00d)));
+    }
+
+    #[test]
+    fn test_parse_echo_reply_with_ip_header() {
+        let mut packet = vec![
+            0x45, 0, 0, 44, 0, 0, 0, 0, 64, 1, 0, 0, 192, 168, 1, 1, 192, 168, 1, 2,
+        ];
+        packet.extend(as_reply(build_echo_request(1, 9, 77)));
+        assert_eq!(parse_echo_reply(&packet), Some((9, 77)));
+        assert_eq!(header_ttl(&packet), Some(64));
+        assert_eq!(header_ttl(&as_reply(build_echo_request(1, 9, 77))), None);
+    }
+
+    #[test]
+    fn test_parse_echo_reply_rejects_foreign_packets() {
+        // Echo request (not a reply)
+        assert_eq!(parse_echo_reply(&build_echo_request(1, 1, 1)), None);
+
+        // Reply from someone else's ping
+        let mut foreign = as_reply(build_echo_request(1, 1, 1));
+        foreign[ICMP_HEADER_LEN..ICMP_HEADER_LEN + 8].copy_from_slice(b"abcdefgh");
+        assert_eq!(parse_echo_reply(&foreign), None);
+
+        assert_eq!(parse_echo_reply(&[0, 0, 0]), None);
+    }
+}
@@ -0,0 +1,208 @@
+//! Device identity across MAC address changes
+//!
+//! Phones and laptops pick a random MAC address per network, and some of
+//! them rotate it, so a known device can come back under a new MAC and a
+//! new DHCP lease. These helpers recognize it by what it calls itself
+//! instead: its DHCP client identifier, the hostname it asked DHCP for or
+//! answers to, and its mDNS service instance names. Devices with a random
+//! MAC that stay offline are aged out, so rotations do not pile up ghosts.
+
+use super::Device;
+use super::config::RandomizedMacConfig;
+use super::oui;
+use chrono::{DateTime, Utc};
+
+/// Names shared by many devices out of the box; they identify nothing
+const GENERIC_NAMES: &[&str] = &[
+    "localhost",
+    "iphone",
+    "ipad",
+    "android",
+    "espressif",
+    "unknown",
+    "galaxy",
+];
+
+/// Identity signals of a device, as "kind:value" keys (e.g.
+/// "hostname:janes-phone", "client-id:ff:00:12:...")
+pub fn identity_keys(device: &Device) -> Vec<String> {
+    let mut keys = Vec::new();
+    let mut push = |key: String| {
+        if !keys.contains(&key) {
+            keys.push(key);
+        }
+    };
+
+    if let Some(client_id) = device.dhcp.as_ref().and_then(|d| d.client_id.as_ref()) {
+        push(format!("client-id:{}", client_id.to_lowercase()));
+    }
+    let hostnames = [
+        device.hostname.as_deref(),
+        device.dhcp.as_ref().and_then(|d| d.hostname.as_deref()),
+    ];
+    for name in hostnames.into_iter().flatten().filter_map(short_name) {
+        push(format!("hostname:{}", name));
+    }
+    for service in &device.advertised_services {
+        let name = service.name.trim().to_lowercase();
+        if !is_generic(&name) {
+            push(format!("mdns:{}", name));
+        }
+    }
+    keys
+}
+
+/// The known device that `device` is by identity, with the key that
+/// linked them. Only pairs where one side has a random MAC are linked: two
+/// different vendor-assigned MACs are two network cards.
+pub fn find_same_device<'a>(
+    device: &Device,
+    known: impl IntoIterator<Item = &'a Device>,
+) -> Option<(&'a Device, String)> {
+    let keys = identity_keys(device);
+    if keys.is_empty() {
+        return None;
+    }
+    known.into_iter().find_map(|candidate| {
+        if !has_random_mac(device) && !has_random_mac(candidate) {
+            return None;
+        }
+        identity_keys(candidate)
+            .into_iter()
+            .find(|key| keys.contains(key))
+            .map(|key| (candidate, key))
+    })
+}
+
+/// Whether an offline device with a random MAC has been gone longer than
+/// the configured TTL. Devices never stamped with a last-seen time (saved
+/// by older agents) count as expired.
+pub fn is_expired_ghost(device: &Device, config: &RandomizedMacConfig, now: DateTime<Utc>) -> bool {
+    if config.ghost_ttl_hours == 0 || !has_random_mac(device) {
+        return false;
+    }
+    match device.last_seen {
+        Some(last_seen) => {
+            let offline_secs = (now - last_seen).num_seconds().max(0) as u64;
+            offline_secs > config.ghost_ttl_hours.saturating_mul(3600)
+        }
+        None => true,
+    }
+}
+
+/// The flag set by the scan, or the address itself for devices saved
+/// before the flag existed
+fn has_random_mac(device: &Device) -> bool {
+    device.randomized_mac || device.mac.as_deref().is_some_and(oui::is_randomized_mac)
+}
+
+/// "Janes-Phone.lan" -> "janes-phone", unless it is a generic name
+fn short_name(hostname: &str) -> Option<String> {
+    let name = hostname
+        .trim()
+        .trim_end_matches('.')
+        .split('.')
+        .next()?
+        .to_lowercase();
+    (!is_generic(&name)).then_some(name)
+}
+
+fn is_generic(name: &str) -> bool {
+    name.len() < 3 || GENERIC_NAMES.contains(&name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::{AdvertisedService, DhcpFingerprint};
+
+    fn phone(mac: &str, hostname: Option<&str>) -> Device {
+        Device {
+            addresses: vec!["192.168.1.23".to_string()],
+            mac: Some(mac.to_string()),
+            randomized_mac: oui::is_randomized_mac(mac),
+            hostname: hostname.map(String::from),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_identity_keys() {
+        let mut device = phone("a6:3f:0e:91:7d:22", Some("Janes-Phone.lan"));
+        device.dhcp = Some(DhcpFingerprint {
+            mac: "a6:3f:0e:91:7d:22".to_string(),
+            hostname: Some("Janes-Phone".to_string()),
+            vendor_class: None,
+            client_id: Some("FF:00:12:34:56".to_string()),
+            parameter_list: Vec::new(),
+            requested_ip: None,
+            os_family: None,
+            last_seen: Utc::now(),
+        });
+        device.advertised_services = vec![AdvertisedService {
+            name: "Jane's iPhone".to_string(),
+            service_type: "_companion-link._tcp".to_string(),
+            port: 49152,
+            txt: Default::default(),
+        }];
+        assert_eq!(
+            identity_keys(&device),
+            vec![
+                "client-id:ff:00:12:34:56",
+                "hostname:janes-phone",
+                "mdns:jane's iphone",
+            ]
+        );
+
+        assert!(identity_keys(&phone("a6:3f:0e:91:7d:22", Some("iPhone"))).is_empty());
+    }
+
+    #[test]
+    fn test_find_same_device() {
+        let known = vec![
+            phone("3c:52:82:1a:2b:3c", Some("desktop-7h2k4qf")),
+            phone("a6:3f:0e:91:7d:22", Some("janes-phone")),
+        ];
+
+        // Same phone, next random MAC
+        let rotated = phone("5e:11:22:33:44:55", Some("Janes-Phone.local"));
+        let (found, key) = find_same_device(&rotated, &known).unwrap();
+        assert_eq!(found.mac, known[1].mac);
+        assert_eq!(key, "hostname:janes-phone");
+
+        // A second vendor-assigned NIC with the same name is another card
+        let dock = phone("00:e0:4c:68:00:01", Some("DESKTOP-7H2K4QF"));
+        assert!(find_same_device(&dock, &known).is_none());
+
+        // Generic names link nothing
+        let generic = phone("5e:11:22:33:44:66", Some("iPhone"));
+        assert!(find_same_device(&generic, &known).is_none());
+    }
+
+    #[test]
+    fn test_is_expired_ghost() {
+        let config = RandomizedMacConfig::default();
+        let now = Utc::now();
+        let mut ghost = phone("a6:3f:0e:91:7d:22", None);
+
+        ghost.last_seen = Some(now - chrono::Duration::hours(2));
+        assert!(!is_expired_ghost(&ghost, &config, now));
+        ghost.last_seen = Some(now - chrono::Duration::hours(25));
+        assert!(is_expired_ghost(&ghost, &config, now));
+
+        // Saved by an older agent: no flag and no timestamp
+        ghost.randomized_mac = false;
+        ghost.last_seen = None;
+        assert!(is_expired_ghost(&ghost, &config, now));
+
+        let keep = RandomizedMacConfig {
+            ghost_ttl_hours: 0,
+            ..config.clone()
+        };
+        assert!(!is_expired_ghost(&ghost, &keep, now));
+
+        let mut desktop = phone("3c:52:82:1a:2b:3c", None);
+        desktop.last_seen = Some(now - chrono::Duration::days(30));
+        assert!(!is_expired_ghost(&desktop, &config, now));
+    }
+}
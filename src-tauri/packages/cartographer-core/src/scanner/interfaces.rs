@@ -0,0 +1,496 @@
+//! Interface discovery: parse interface addresses and default routes into
+//! scan targets, and apply the `[scan]` interface and include lists.
+
+use super::config::ScanConfig;
+use super::{ScanTarget, is_ipv6_link_local};
+use anyhow::Result;
+use ipnetwork::IpNetwork;
+use std::net::IpAddr;
+
+/// Apply the `[scan]` interface allow-list and include list to the detected
+/// targets. An included CIDR inside a directly attached subnet is scanned
+/// through that interface; any other CIDR is treated as routed (ping only).
+pub(super) fn select_targets(
+    config: &ScanConfig,
+    detected: Vec<ScanTarget>,
+) -> Result<Vec<ScanTarget>> {
+    let allowed: Vec<ScanTarget> = if config.interfaces.is_empty() {
+        detected
+    } else {
+        for name in &config.interfaces {
+            if !detected.iter().any(|t| t.interface == name.trim()) {
+                return Err(anyhow::anyhow!(
+                    "Interface '{}' from [scan] interfaces not found, down, or without an IPv4 address",
+                    name
+                ));
+            }
+        }
+        detected
+            .into_iter()
+            .filter(|t| {
+                config
+                    .interfaces
+                    .iter()
+                    .any(|name| name.trim() == t.interface)
+            })
+            .collect()
+    };
+
+    if config.include.is_empty() {
+        return Ok(allowed);
+    }
+
+    let mut targets: Vec<ScanTarget> = Vec::new();
+    for entry in &config.include {
+        let net = match entry.trim().parse::<IpNetwork>() {
+            Ok(IpNetwork::V4(net)) => net,
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "[scan] include entry '{}' is not an IPv4 CIDR",
+                    entry
+                ));
+            }
+        };
+
+        let subnet = format!("{}/{}", net.network(), net.prefix());
+        if targets.iter().any(|t| t.subnet == subnet) {
+            continue;
+        }
+
+        let attached = allowed.iter().find(|t| {
+            t.subnet
+                .parse::<IpNetwork>()
+                .is_ok_and(|s| s.prefix() <= net.prefix() && s.contains(IpAddr::V4(net.network())))
+        });
+
+        targets.push(match attached {
+            Some(target) => ScanTarget {
+                subnet,
+                ..target.clone()
+            },
+            None => ScanTarget {
+                interface: allowed
+                    .first()
+                    .map(|t| t.interface.clone())
+                    .unwrap_or_default(),
+                subnet,
+                gateway_ip: None,
+                local_ip: None,
+                ipv6_prefixes: Vec::new(),
+            },
+        });
+    }
+
+    Ok(targets)
+}
+
+/// Addresses configured on one interface
+#[derive(Debug, Default)]
+pub(super) struct InterfaceAddresses {
+    name: String,
+    ipv4: Vec<ipnetwork::Ipv4Network>,
+    ipv6_prefixes: Vec<String>,
+}
+
+/// Parse `ip route show default` into (interface, gateway) pairs, in route order.
+///
+/// `default via 192.168.1.1 dev eth0 proto dhcp metric 100`
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub(super) fn parse_default_routes(output: &str) -> Vec<(String, Option<String>)> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            let after = |key: &str| {
+                parts
+                    .iter()
+                    .position(|p| *p == key)
+                    .and_then(|i| parts.get(i + 1))
+                    .map(|s| s.to_string())
+            };
+            Some((after("dev")?, after("via")))
+        })
+        .collect()
+}
+
+/// Parse `ip -o addr show` output, one line per address:
+///
+/// `2: eth0    inet 192.168.1.20/24 brd 192.168.1.255 scope global eth0\       valid_lft ...`
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub(super) fn parse_ip_addr_oneline(output: &str) -> Vec<InterfaceAddresses> {
+    let mut blocks: Vec<(String, String)> = Vec::new();
+
+    for line in output.lines() {
+        let mut parts = line.split_whitespace();
+        let (Some(_index), Some(name)) = (parts.next(), parts.next()) else {
+            continue;
+        };
+        let name = name.trim_end_matches(':');
+        let name = name.split('@').next().unwrap_or(name).to_string();
+        let address_line = parts.collect::<Vec<_>>().join(" ");
+
+        match blocks.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, block)) => {
+                block.push('\n');
+                block.push_str(&address_line);
+            }
+            None => blocks.push((name, address_line)),
+        }
+    }
+
+    blocks
+        .into_iter()
+        .filter(|(name, _)| name != "lo")
+        .map(|(name, block)| interface_addresses(name, &block))
+        .collect()
+}
+
+/// Parse macOS `ifconfig` output, skipping loopback and interfaces that are down.
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+pub(super) fn parse_ifconfig(output: &str) -> Vec<InterfaceAddresses> {
+    let mut interfaces = Vec::new();
+    let mut current: Option<(String, String)> = None;
+
+    for line in output.lines() {
+        if !line.starts_with(char::is_whitespace) {
+            if let Some((name, block)) = current.take() {
+                interfaces.push(interface_addresses(name, &block));
+            }
+
+            // en0: flags=8863<UP,BROADCAST,SMART,RUNNING,SIMPLEX,MULTICAST> mtu 1500
+            let Some((name, rest)) = line.split_once(':') else {
+                continue;
+            };
+            let flags = rest
+                .split_once('<')
+                .and_then(|(_, f)| f.split_once('>'))
+                .map(|(f, _)| f)
+                .unwrap_or_default();
+            if name.starts_with("lo") || !flags.split(',').any(|f| f == "UP") {
+                continue;
+            }
+            current = Some((name.to_string(), String::new()));
+        } else if let Some((_, block)) = current.as_mut() {
+            block.push_str(line);
+            block.push('\n');
+        }
+    }
+
+    if let Some((name, block)) = current {
+        interfaces.push(interface_addresses(name, &block));
+    }
+
+    interfaces
+}
+
+fn interface_addresses(name: String, block: &str) -> InterfaceAddresses {
+    InterfaceAddresses {
+        name,
+        ipv4: parse_ipv4_networks(block),
+        ipv6_prefixes: parse_ipv6_prefixes(block),
+    }
+}
+
+/// Extract IPv4 networks from `ip addr` (Linux) or `ifconfig` (macOS) output.
+/// Loopback and link-local (169.254/16) addresses are skipped.
+fn parse_ipv4_networks(output: &str) -> Vec<ipnetwork::Ipv4Network> {
+    let mut networks = Vec::new();
+
+    for line in output.lines() {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.first() != Some(&"inet") || parts.len() < 2 {
+            continue;
+        }
+
+        // Linux: "inet 192.168.1.20/24 brd 192.168.1.255 scope global eth0"
+        // macOS: "inet 192.168.1.21 netmask 0xffffff00 broadcast 192.168.1.255"
+        let network = if parts[1].contains('/') {
+            parts[1].parse::<ipnetwork::Ipv4Network>().ok()
+        } else {
+            let ip = parts[1].parse::<std::net::Ipv4Addr>().ok();
+            let mask = parts
+                .iter()
+                .position(|p| *p == "netmask")
+                .and_then(|i| parts.get(i + 1))
+                .and_then(|m| u32::from_str_radix(m.trim_start_matches("0x"), 16).ok())
+                .map(std::net::Ipv4Addr::from);
+            ip.zip(mask)
+                .and_then(|(ip, mask)| ipnetwork::Ipv4Network::with_netmask(ip, mask).ok())
+        };
+
+        let Some(network) = network else {
+            continue;
+        };
+        if network.ip().is_loopback() || network.ip().is_link_local() {
+            continue;
+        }
+        networks.push(network);
+    }
+
+    networks
+}
+
+/// Build scan targets from interface addresses. Interfaces carrying a
+/// default route come first, in route order.
+pub(super) fn targets_from_interfaces(
+    interfaces: Vec<InterfaceAddresses>,
+    default_routes: &[(String, Option<String>)],
+) -> Vec<ScanTarget> {
+    let route_rank = |name: &str| {
+        default_routes
+            .iter()
+            .position(|(interface, _)| interface == name)
+            .unwrap_or(usize::MAX)
+    };
+
+    let mut targets: Vec<ScanTarget> = Vec::new();
+    for interface in interfaces {
+        let gateway = default_routes
+            .iter()
+            .find(|(name, _)| *name == interface.name)
+            .and_then(|(_, gateway)| gateway.clone());
+
+        for network in &interface.ipv4 {
+            let subnet = format!("{}/{}", network.network(), network.prefix());
+            if targets.iter().any(|t| t.subnet == subnet) {
+                continue;
+            }
+
+            let gateway_ip = gateway.clone().filter(|gw| {
+                gw.parse::<std::net::Ipv4Addr>()
+                    .is_ok_and(|gw| network.contains(gw))
+            });
+
+            targets.push(ScanTarget {
+                interface: interface.name.clone(),
+                subnet,
+                gateway_ip,
+                local_ip: Some(network.ip().to_string()),
+                ipv6_prefixes: interface.ipv6_prefixes.clone(),
+            });
+        }
+    }
+
+    targets.sort_by_key(|t| route_rank(&t.interface));
+    targets
+}
+
+/// Extract global/unique-local IPv6 prefixes from `ip addr show` (Linux) or
+/// `ifconfig` (macOS) output. Link-local and loopback addresses are skipped.
+fn parse_ipv6_prefixes(output: &str) -> Vec<String> {
+    let mut prefixes = Vec::new();
+
+    for line in output.lines() {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.first() != Some(&"inet6") || parts.len() < 2 {
+            continue;
+        }
+
+        // Linux: "inet6 2001:db8::5/64 scope global"
+        // macOS: "inet6 2001:db8::5 prefixlen 64 autoconf secured"
+        let cidr = if parts[1].contains('/') {
+            parts[1].to_string()
+        } else {
+            let prefix_len = parts
+                .iter()
+                .position(|p| *p == "prefixlen")
+                .and_then(|i| parts.get(i + 1))
+                .unwrap_or(&"64");
+            format!(
+                "{}/{}",
+                parts[1].split('%').next().unwrap_or_default(),
+                prefix_len
+            )
+        };
+
+        let Ok(IpNetwork::V6(network)) = cidr.parse::<IpNetwork>() else {
+            continue;
+        };
+        if network.ip().is_loopback() || is_ipv6_link_local(&network.ip()) {
+            continue;
+        }
+
+        let prefix = format!("{}/{}", network.network(), network.prefix());
+        if !prefixes.contains(&prefix) {
+            prefixes.push(prefix);
+        }
+    }
+
+    prefixes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ipv6_prefixes() {
+        let linux = "\
+2: eth0: <BROADCAST,MULTICAST,UP,LOWER_UP> mtu 1500 qdisc fq_codel state UP group default qlen 1000
+    inet 192.168.1.20/24 brd 192.168.1.255 scope global dynamic eth0
+    inet6 2001:db8:1::20/64 scope global dynamic mngtmpaddr
+    inet6 fd00:1::20/64 scope global
+    inet6 fe80::20/64 scope link
+";
+        assert_eq!(
+            parse_ipv6_prefixes(linux),
+            vec!["2001:db8:1::/64", "fd00:1::/64"]
+        );
+
+        let macos = "\
+en0: flags=8863<UP,BROADCAST,SMART,RUNNING,SIMPLEX,MULTICAST> mtu 1500
+\tinet6 fe80::1c2d:3e4f:5a6b:7c8d%en0 prefixlen 64 secured scopeid 0x6
+\tinet 192.168.1.21 netmask 0xffffff00 broadcast 192.168.1.255
+\tinet6 2001:db8:1::21 prefixlen 64 autoconf secured
+";
+        assert_eq!(parse_ipv6_prefixes(macos), vec!["2001:db8:1::/64"]);
+    }
+
+    #[test]
+    fn test_parse_ip_addr_oneline() {
+        let output = "\
+1: lo    inet 127.0.0.1/8 scope host lo\\       valid_lft forever preferred_lft forever
+2: eth0    inet 192.168.1.20/24 brd 192.168.1.255 scope global dynamic eth0\\       valid_lft 86000sec preferred_lft 86000sec
+2: eth0    inet6 2001:db8:1::20/64 scope global dynamic mngtmpaddr \\       valid_lft 86000sec preferred_lft 14000sec
+2: eth0    inet6 fe80::20/64 scope link \\       valid_lft forever preferred_lft forever
+3: eth0.20@eth0    inet 10.20.0.5/24 brd 10.20.0.255 scope global eth0.20\\       valid_lft forever preferred_lft forever
+3: eth0.20@eth0    inet 169.254.7.7/16 brd 169.254.255.255 scope link eth0.20\\       valid_lft forever preferred_lft forever
+";
+        let interfaces = parse_ip_addr_oneline(output);
+        assert_eq!(interfaces.len(), 2);
+        assert_eq!(interfaces[0].name, "eth0");
+        assert_eq!(interfaces[0].ipv4, vec!["192.168.1.20/24".parse().unwrap()]);
+        assert_eq!(interfaces[0].ipv6_prefixes, vec!["2001:db8:1::/64"]);
+        assert_eq!(interfaces[1].name, "eth0.20");
+        assert_eq!(interfaces[1].ipv4, vec!["10.20.0.5/24".parse().unwrap()]);
+    }
+
+    #[test]
+    fn test_parse_ifconfig() {
+        let output = "\
+lo0: flags=8049<UP,LOOPBACK,RUNNING,MULTICAST> mtu 16384
+\tinet 127.0.0.1 netmask 0xff000000
+en0: flags=8863<UP,BROADCAST,SMART,RUNNING,SIMPLEX,MULTICAST> mtu 1500
+\tether a0:63:91:12:34:56
+\tinet6 fe80::1c2d:3e4f:5a6b:7c8d%en0 prefixlen 64 secured scopeid 0x6
+\tinet 192.168.1.21 netmask 0xffffff00 broadcast 192.168.1.255
+\tinet6 2001:db8:1::21 prefixlen 64 autoconf secured
+en1: flags=8822<BROADCAST,SMART,SIMPLEX,MULTICAST> mtu 1500
+\tinet 10.9.9.9 netmask 0xffffff00 broadcast 10.9.9.255
+";
+        let interfaces = parse_ifconfig(output);
+        assert_eq!(interfaces.len(), 1);
+        assert_eq!(interfaces[0].name, "en0");
+        assert_eq!(interfaces[0].ipv4, vec!["192.168.1.21/24".parse().unwrap()]);
+        assert_eq!(interfaces[0].ipv6_prefixes, vec!["2001:db8:1::/64"]);
+    }
+
+    #[test]
+    fn test_targets_from_interfaces_default_route_first() {
+        let routes = parse_default_routes(
+            "default via 10.20.0.1 dev eth1 proto dhcp metric 100\n\
+             default via 192.168.1.1 dev eth0 proto dhcp metric 600\n",
+        );
+        assert_eq!(
+            routes[0],
+            ("eth1".to_string(), Some("10.20.0.1".to_string()))
+        );
+
+        let interfaces = vec![
+            InterfaceAddresses {
+                name: "eth0".to_string(),
+                ipv4: vec!["192.168.1.20/24".parse().unwrap()],
+                ipv6_prefixes: vec!["2001:db8:1::/64".to_string()],
+            },
+            InterfaceAddresses {
+                name: "docker0".to_string(),
+                ipv4: vec!["172.17.0.1/16".parse().unwrap()],
+                ipv6_prefixes: Vec::new(),
+            },
+            InterfaceAddresses {
+                name: "eth1".to_string(),
+                ipv4: vec!["10.20.0.5/24".parse().unwrap()],
+                ipv6_prefixes: Vec::new(),
+            },
+        ];
+
+        let targets = targets_from_interfaces(interfaces, &routes);
+        let summary: Vec<(&str, &str, Option<&str>)> = targets
+            .iter()
+            .map(|t| {
+                (
+                    t.interface.as_str(),
+                    t.subnet.as_str(),
+                    t.gateway_ip.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("eth1", "10.20.0.0/24", Some("10.20.0.1")),
+                ("eth0", "192.168.1.0/24", Some("192.168.1.1")),
+                ("docker0", "172.17.0.0/16", None),
+            ]
+        );
+        assert_eq!(targets[1].local_ip.as_deref(), Some("192.168.1.20"));
+    }
+
+    #[test]
+    fn test_select_targets() {
+        let detected = vec![
+            ScanTarget {
+                interface: "eth0".to_string(),
+                subnet: "192.168.1.0/24".to_string(),
+                gateway_ip: Some("192.168.1.1".to_string()),
+                local_ip: Some("192.168.1.20".to_string()),
+                ipv6_prefixes: Vec::new(),
+            },
+            ScanTarget {
+                interface: "docker0".to_string(),
+                subnet: "172.17.0.0/16".to_string(),
+                gateway_ip: None,
+                local_ip: Some("172.17.0.1".to_string()),
+                ipv6_prefixes: Vec::new(),
+            },
+        ];
+        let strings = |e: &[&str]| e.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        // No settings: every detected target
+        let targets = select_targets(&ScanConfig::default(), detected.clone()).unwrap();
+        assert_eq!(targets, detected);
+
+        // Interface allow-list
+        let config = ScanConfig {
+            interfaces: strings(&["eth0"]),
+            ..Default::default()
+        };
+        assert_eq!(
+            select_targets(&config, detected.clone()).unwrap(),
+            vec![detected[0].clone()]
+        );
+
+        let config = ScanConfig {
+            interfaces: strings(&["eth0"]),
+            include: strings(&["192.168.1.128/25", "10.30.0.7/24", "192.168.1.128/25"]),
+            ..Default::default()
+        };
+        let targets = select_targets(&config, detected.clone()).unwrap();
+        assert_eq!(targets.len(), 2);
+
+        // Part of an attached subnet: still on-link through eth0
+        assert_eq!(targets[0].subnet, "192.168.1.128/25");
+        assert!(targets[0].is_on_link());
+
+        // Routed subnet: no local address, so no ARP or neighbor discovery
+        assert_eq!(targets[1].subnet, "10.30.0.0/24");
+        assert_eq!(targets[1].interface, "eth0");
+        assert!(!targets[1].is_on_link());
+
+        let config = ScanConfig {
+            interfaces: strings(&["wlan9"]),
+            ..Default::default()
+        };
+        assert!(select_targets(&config, detected).is_err());
+    }
+}
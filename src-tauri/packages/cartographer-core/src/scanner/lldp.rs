@@ -0,0 +1,517 @@
+//! Passive LLDP and CDP neighbor capture (Linux, AF_PACKET)
+//!
+//! Switches announce themselves on every port: which chassis they are,
+//! which port the frame left from, and usually a name and management
+//! address. Listening for these tells us which switch port this host is
+//! plugged into, the start of a physical topology. Nothing is sent.
+
+use super::neighbor::format_mac;
+use serde::{Deserialize, Serialize};
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::time::Duration;
+
+const ETHERTYPE_LLDP: u16 = 0x88cc;
+/// Nearest-bridge LLDP group address
+const LLDP_MULTICAST: [u8; 6] = [0x01, 0x80, 0xc2, 0x00, 0x00, 0x0e];
+const CDP_MULTICAST: [u8; 6] = [0x01, 0x00, 0x0c, 0xcc, 0xcc, 0xcc];
+/// 802.2 LLC + SNAP header of CDP frames: DSAP, SSAP, control, Cisco OUI, PID
+const CDP_SNAP_HEADER: [u8; 8] = [0xaa, 0xaa, 0x03, 0x00, 0x00, 0x0c, 0x20, 0x00];
+
+const LLDP_END: u8 = 0;
+const LLDP_CHASSIS_ID: u8 = 1;
+const LLDP_PORT_ID: u8 = 2;
+const LLDP_PORT_DESCRIPTION: u8 = 4;
+const LLDP_SYSTEM_NAME: u8 = 5;
+const LLDP_SYSTEM_DESCRIPTION: u8 = 6;
+const LLDP_MANAGEMENT_ADDRESS: u8 = 8;
+/// Chassis ID subtype 4 and port ID subtype 3: a MAC address
+const LLDP_CHASSIS_MAC: u8 = 4;
+const LLDP_PORT_MAC: u8 = 3;
+/// Chassis ID subtype 5 and port ID subtype 4: a network address
+const LLDP_CHASSIS_NETWORK_ADDRESS: u8 = 5;
+const LLDP_PORT_NETWORK_ADDRESS: u8 = 4;
+
+const CDP_DEVICE_ID: u16 = 0x0001;
+const CDP_ADDRESSES: u16 = 0x0002;
+const CDP_PORT_ID: u16 = 0x0003;
+const CDP_PLATFORM: u16 = 0x0006;
+const CDP_MANAGEMENT_ADDRESSES: u16 = 0x0016;
+
+/// Discovery protocol an adjacency was learned from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkProtocol {
+    Lldp,
+    Cdp,
+}
+
+impl std::fmt::Display for LinkProtocol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LinkProtocol::Lldp => write!(f, "lldp"),
+            LinkProtocol::Cdp => write!(f, "cdp"),
+        }
+    }
+}
+
+/// A link between one of this host's interfaces and a neighbor's port
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TopologyEdge {
+    pub protocol: LinkProtocol,
+    /// Local interface the announcement arrived on
+    pub local_interface: String,
+    /// MAC address of `local_interface`
+    pub local_mac: Option<String>,
+    /// Source MAC of the announcement (the neighbor's port)
+    pub neighbor_mac: String,
+    /// LLDP chassis ID or CDP device ID
+    pub chassis_id: String,
+    /// Neighbor port the link is on (e.g. "Gi1/0/12")
+    pub port_id: String,
+    pub port_description: Option<String>,
+    pub system_name: Option<String>,
+    /// LLDP system description or CDP platform
+    pub system_description: Option<String>,
+    pub management_address: Option<String>,
+}
+
+/// Parse an Ethernet frame carrying an LLDPDU or CDP announcement. The
+/// local interface fields are left for the caller to fill in.
+pub fn parse_frame(frame: &[u8]) -> Option<TopologyEdge> {
+    if frame.len() < 14 {
+        return None;
+    }
+    let neighbor_mac = format_mac(&frame[6..12])?;
+    let ethertype = u16::from_be_bytes([frame[12], frame[13]]);
+
+    if ethertype == ETHERTYPE_LLDP {
+        parse_lldp(&frame[14..], neighbor_mac)
+    } else if frame[..6] == CDP_MULTICAST && frame[14..].starts_with(&CDP_SNAP_HEADER) {
+        // 802.3 length field instead of an ethertype; trailing padding is
+        // not part of the frame
+        let end = (14 + ethertype as usize).min(frame.len());
+        let cdp = frame.get(14 + CDP_SNAP_HEADER.len()..end)?;
+        parse_cdp(cdp, neighbor_mac)
+    } else {
+        None
+    }
+}
+
+fn new_edge(protocol: LinkProtocol, neighbor_mac: String) -> TopologyEdge {
+    TopologyEdge {
+        protocol,
+        local_interface: String::new(),
+        local_mac: None,
+        neighbor_mac,
+        chassis_id: String::new(),
+        port_id: String::new(),
+        port_description: None,
+        system_name: None,
+        system_description: None,
+        management_address: None,
+    }
+}
+
+/// LLDPDU: TLVs with a 7-bit type and 9-bit length (IEEE 802.1AB 8.4)
+fn parse_lldp(pdu: &[u8], neighbor_mac: String) -> Option<TopologyEdge> {
+    let mut edge = new_edge(LinkProtocol::Lldp, neighbor_mac);
+    let mut pos = 0;
+    while pos + 2 <= pdu.len() {
+        let header = u16::from_be_bytes([pdu[pos], pdu[pos + 1]]);
+        let tlv_type = (header >> 9) as u8;
+        let len = (header & 0x01ff) as usize;
+        let value = pdu.get(pos + 2..pos + 2 + len)?;
+        pos += 2 + len;
+
+        match tlv_type {
+            LLDP_END => break,
+            LLDP_CHASSIS_ID => {
+                edge.chassis_id = lldp_id(value, LLDP_CHASSIS_MAC, LLDP_CHASSIS_NETWORK_ADDRESS)?
+            }
+            LLDP_PORT_ID => {
+                edge.port_id = lldp_id(value, LLDP_PORT_MAC, LLDP_PORT_NETWORK_ADDRESS)?
+            }
+            LLDP_PORT_DESCRIPTION => edge.port_description = text(value),
+            LLDP_SYSTEM_NAME => edge.system_name = text(value),
+            LLDP_SYSTEM_DESCRIPTION => edge.system_description = text(value),
+            LLDP_MANAGEMENT_ADDRESS if edge.management_address.is_none() => {
+                // Address string length (subtype included), IANA family, address
+                let addr_len = *value.first()? as usize;
+                let family = *value.get(1)?;
+                let address = value.get(2..1 + addr_len)?;
+                edge.management_address = network_address(family, address);
+            }
+            _ => {}
+        }
+    }
+
+    // Chassis and port ID are mandatory
+    (!edge.chassis_id.is_empty() && !edge.port_id.is_empty()).then_some(edge)
+}
+
+/// Chassis or port ID: a subtype byte, then a MAC, an address or text
+fn lldp_id(value: &[u8], mac_subtype: u8, address_subtype: u8) -> Option<String> {
+    let (&subtype, id) = value.split_first()?;
+    if subtype == mac_subtype {
+        format_mac(id)
+    } else if subtype == address_subtype {
+        let (&family, address) = id.split_first()?;
+        network_address(family, address)
+    } else {
+        text(id)
+    }
+}
+
+/// IANA address family 1 (IPv4) or 2 (IPv6)
+fn network_address(family: u8, address: &[u8]) -> Option<String> {
+    match family {
+        1 => <[u8; 4]>::try_from(address)
+            .ok()
+            .map(|a| Ipv4Addr::from(a).to_string()),
+        2 => <[u8; 16]>::try_from(address)
+            .ok()
+            .map(|a| Ipv6Addr::from(a).to_string()),
+        _ => None,
+    }
+}
+
+/// CDP: version, TTL and checksum, then TLVs with 16-bit type and length
+/// (length includes the 4-byte header)
+fn parse_cdp(pdu: &[u8], neighbor_mac: String) -> Option<TopologyEdge> {
+    let mut edge = new_edge(LinkProtocol::Cdp, neighbor_mac);
+    let mut addresses = None;
+    let mut pos = 4;
+    while pos + 4 <= pdu.len() {
+        let tlv_type = u16::from_be_bytes([pdu[pos], pdu[pos + 1]]);
+        let len = u16::from_be_bytes([pdu[pos + 2], pdu[pos + 3]]) as usize;
+        if len < 4 {
+            break;
+        }
+        let value = pdu.get(pos + 4..pos + len)?;
+        pos += len;
+
+        match tlv_type {
+            CDP_DEVICE_ID => {
+                edge.chassis_id = text(value)?;
+                edge.system_name = Some(edge.chassis_id.clone());
+            }
+            CDP_PORT_ID => edge.port_id = text(value)?,
+            CDP_PLATFORM => edge.system_description = text(value),
+            CDP_MANAGEMENT_ADDRESSES => {
+                edge.management_address = cdp_address(value).or(edge.management_address)
+            }
+            CDP_ADDRESSES => addresses = cdp_address(value),
+            _ => {}
+        }
+    }
+    // Prefer the management address over the interface addresses
+    if edge.management_address.is_none() {
+        edge.management_address = addresses;
+    }
+
+    (!edge.chassis_id.is_empty() && !edge.port_id.is_empty()).then_some(edge)
+}
+
+/// First IPv4 address of a CDP address list
+fn cdp_address(value: &[u8]) -> Option<String> {
+    let count = u32::from_be_bytes(value.get(..4)?.try_into().ok()?);
+    let mut pos = 4;
+    for _ in 0..count {
+        let protocol_len = *value.get(pos + 1)? as usize;
+        let protocol = value.get(pos + 2..pos + 2 + protocol_len)?;
+        pos += 2 + protocol_len;
+        let address_len = u16::from_be_bytes(value.get(pos..pos + 2)?.try_into().ok()?) as usize;
+        let address = value.get(pos + 2..pos + 2 + address_len)?;
+        pos += 2 + address_len;
+
+        // NLPID 0xcc is IP
+        if protocol == [0xcc]
+            && let Ok(octets) = <[u8; 4]>::try_from(address)
+        {
+            return Some(Ipv4Addr::from(octets).to_string());
+        }
+    }
+    None
+}
+
+fn text(value: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(value);
+    let text = text.trim_matches(|c: char| c.is_whitespace() || c == '\0');
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// Listen on each interface for `window`, returning one edge per neighbor
+/// port heard. Frames heard again replace older ones.
+pub async fn capture(interfaces: Vec<String>, window: Duration) -> Vec<TopologyEdge> {
+    #[cfg(target_os = "linux")]
+    {
+        let tasks: Vec<_> = interfaces
+            .into_iter()
+            .map(|interface| {
+                tokio::task::spawn_blocking(move || {
+                    match capture::listen_blocking(&interface, window) {
+                        Ok(edges) => edges,
+                        Err(e) => {
+                            tracing::debug!("LLDP/CDP capture on {} failed: {:#}", interface, e);
+                            Vec::new()
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        let mut edges = Vec::new();
+        for task in tasks {
+            edges.extend(task.await.unwrap_or_default());
+        }
+        edges
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = (interfaces, window);
+        Vec::new()
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod capture {
+    use super::super::arp_sweep::packet;
+    use super::super::is_scan_cancelled;
+    use super::*;
+    use anyhow::{Context, Result};
+    use std::io;
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+    use std::time::Instant;
+
+    /// Poll interval, so that cancellation is noticed
+    const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+    /// Classic BPF: accept frames sent to the LLDP or CDP group address
+    fn group_address_filter() -> [libc::sock_filter; 9] {
+        let op = |code: u16, jt: u8, jf: u8, k: u32| libc::sock_filter { code, jt, jf, k };
+        const LD_W_ABS: u16 = 0x20;
+        const LD_H_ABS: u16 = 0x28;
+        const JEQ_K: u16 = 0x15;
+        const RET_K: u16 = 0x06;
+        [
+            op(LD_W_ABS, 0, 0, 0),
+            op(JEQ_K, 0, 2, 0x0180_c200),
+            op(LD_H_ABS, 0, 0, 4),
+            op(JEQ_K, 3, 4, 0x000e),
+            op(JEQ_K, 0, 3, 0x0100_0ccc),
+            op(LD_H_ABS, 0, 0, 4),
+            op(JEQ_K, 0, 1, 0xcccc),
+            op(RET_K, 0, 0, 0xffff),
+            op(RET_K, 0, 0, 0),
+        ]
+    }
+
+    fn open_socket(interface: &str) -> Result<OwnedFd> {
+        let protocol = (libc::ETH_P_ALL as u16).to_be();
+        let raw = unsafe {
+            libc::socket(
+                libc::AF_PACKET,
+                libc::SOCK_RAW | libc::SOCK_CLOEXEC | libc::SOCK_NONBLOCK,
+                protocol as libc::c_int,
+            )
+        };
+        if raw < 0 {
+            return Err(io::Error::last_os_error()).context("Failed to open AF_PACKET socket");
+        }
+        let fd = unsafe { OwnedFd::from_raw_fd(raw) };
+
+        // Filter before binding so that no unrelated frame is queued
+        let mut filter = group_address_filter();
+        let program = libc::sock_fprog {
+            len: filter.len() as u16,
+            filter: filter.as_mut_ptr(),
+        };
+        packet::setsockopt(&fd, libc::SOL_SOCKET, libc::SO_ATTACH_FILTER, &program)
+            .context("Failed to attach LLDP/CDP filter")?;
+
+        let ifindex = packet::bind_to_interface(&fd, interface, protocol)?;
+
+        // The NIC drops multicast it was not asked for
+        for group in [LLDP_MULTICAST, CDP_MULTICAST] {
+            let mut request: libc::packet_mreq = unsafe { std::mem::zeroed() };
+            request.mr_ifindex = ifindex as i32;
+            request.mr_type = libc::PACKET_MR_MULTICAST as u16;
+            request.mr_alen = 6;
+            request.mr_address[..6].copy_from_slice(&group);
+            if let Err(e) =
+                packet::setsockopt(&fd, libc::SOL_PACKET, libc::PACKET_ADD_MEMBERSHIP, &request)
+            {
+                tracing::debug!("Failed to join {:02x?} on {}: {}", group, interface, e);
+            }
+        }
+        Ok(fd)
+    }
+
+    pub(super) fn listen_blocking(interface: &str, window: Duration) -> Result<Vec<TopologyEdge>> {
+        let fd = open_socket(interface)?;
+        let local_mac = packet::interface_mac(interface)
+            .ok()
+            .and_then(|m| format_mac(&m));
+        tracing::info!(
+            "Listening for LLDP/CDP on {} for {}s",
+            interface,
+            window.as_secs()
+        );
+
+        let mut edges: Vec<TopologyEdge> = Vec::new();
+        let mut buf = [0u8; 9216];
+        let deadline = Instant::now() + window;
+        loop {
+            let now = Instant::now();
+            if now >= deadline || is_scan_cancelled() {
+                break;
+            }
+            if !packet::wait_readable(&fd, (deadline - now).min(POLL_INTERVAL)) {
+                continue;
+            }
+            loop {
+                let received = unsafe {
+                    libc::recv(
+                        fd.as_raw_fd(),
+                        buf.as_mut_ptr() as *mut libc::c_void,
+                        buf.len(),
+                        0,
+                    )
+                };
+                if received <= 0 {
+                    break;
+                }
+                let Some(mut edge) = parse_frame(&buf[..received as usize]) else {
+                    continue;
+                };
+                edge.local_interface = interface.to_string();
+                edge.local_mac = local_mac.clone();
+                tracing::debug!(
+                    "{} neighbor on {}: {} port {}",
+                    edge.protocol,
+                    interface,
+                    edge.chassis_id,
+                    edge.port_id
+                );
+                edges.retain(|e| {
+                    (e.protocol, &e.chassis_id, &e.port_id)
+                        != (edge.protocol, &edge.chassis_id, &edge.port_id)
+                });
+                edges.push(edge);
+            }
+        }
+
+        tracing::info!("{} LLDP/CDP neighbors heard on {}", edges.len(), interface);
+        Ok(edges)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SWITCH_MAC: [u8; 6] = [0x00, 0x1b, 0x54, 0xaa, 0xbb, 0x0c];
+
+    fn lldp_tlv(tlv_type: u8, value: &[u8]) -> Vec<u8> {
+        let header = ((tlv_type as u16) << 9) | value.len() as u16;
+        [&header.to_be_bytes()[..], value].concat()
+    }
+
+    /// An LLDPDU as sent by a Cisco Catalyst access port
+    fn lldp_frame() -> Vec<u8> {
+        let mut frame = LLDP_MULTICAST.to_vec();
+        frame.extend_from_slice(&SWITCH_MAC);
+        frame.extend_from_slice(&ETHERTYPE_LLDP.to_be_bytes());
+        frame.extend(lldp_tlv(1, &[4, 0x00, 0x1b, 0x54, 0xaa, 0xbb, 0x00]));
+        frame.extend(lldp_tlv(2, b"\x05Gi1/0/12"));
+        frame.extend(lldp_tlv(3, &[0x00, 0x78]));
+        frame.extend(lldp_tlv(4, b"GigabitEthernet1/0/12"));
+        frame.extend(lldp_tlv(5, b"core-sw1.lan"));
+        frame.extend(lldp_tlv(6, b"Cisco IOS Software, C2960X Software"));
+        frame.extend(lldp_tlv(8, &[5, 1, 10, 0, 0, 2, 2, 0, 0, 0, 1, 0]));
+        frame.extend(lldp_tlv(0, &[]));
+        frame
+    }
+
+    fn cdp_tlv(tlv_type: u16, value: &[u8]) -> Vec<u8> {
+        let len = (value.len() + 4) as u16;
+        [&tlv_type.to_be_bytes()[..], &len.to_be_bytes(), value].concat()
+    }
+
+    fn cdp_addresses(ip: [u8; 4]) -> Vec<u8> {
+        [&[0, 0, 0, 1, 1, 1, 0xcc, 0, 4][..], &ip].concat()
+    }
+
+    /// A CDPv2 announcement, padded to the Ethernet minimum like on the wire
+    fn cdp_frame() -> Vec<u8> {
+        let mut cdp = vec![2, 180, 0x12, 0x34];
+        cdp.extend(cdp_tlv(0x0001, b"dist-sw2"));
+        cdp.extend(cdp_tlv(0x0002, &cdp_addresses([10, 0, 0, 3])));
+        cdp.extend(cdp_tlv(0x0003, b"TenGigabitEthernet1/1/4"));
+        cdp.extend(cdp_tlv(0x0006, b"cisco WS-C3850-24T"));
+        cdp.extend(cdp_tlv(0x0016, &cdp_addresses([192, 168, 100, 3])));
+
+        let payload_len = (CDP_SNAP_HEADER.len() + cdp.len()) as u16;
+        let mut frame = CDP_MULTICAST.to_vec();
+        frame.extend_from_slice(&SWITCH_MAC);
+        frame.extend_from_slice(&payload_len.to_be_bytes());
+        frame.extend_from_slice(&CDP_SNAP_HEADER);
+        frame.extend(cdp);
+        frame.resize(frame.len().max(60), 0);
+        frame
+    }
+
+    #[test]
+    fn test_parse_lldp_frame() {
+        let edge = parse_frame(&lldp_frame()).unwrap();
+        assert_eq!(edge.protocol, LinkProtocol::Lldp);
+        assert_eq!(edge.neighbor_mac, "00:1b:54:aa:bb:0c");
+        assert_eq!(edge.chassis_id, "00:1b:54:aa:bb:00");
+        assert_eq!(edge.port_id, "Gi1/0/12");
+        assert_eq!(
+            edge.port_description.as_deref(),
+            Some("GigabitEthernet1/0/12")
+        );
+        assert_eq!(edge.system_name.as_deref(), Some("core-sw1.lan"));
+        assert_eq!(edge.management_address.as_deref(), Some("10.0.0.2"));
+    }
+
+    #[test]
+    fn test_parse_cdp_frame() {
+        let edge = parse_frame(&cdp_frame()).unwrap();
+        assert_eq!(edge.protocol, LinkProtocol::Cdp);
+        assert_eq!(edge.chassis_id, "dist-sw2");
+        assert_eq!(edge.system_name.as_deref(), Some("dist-sw2"));
+        assert_eq!(edge.port_id, "TenGigabitEthernet1/1/4");
+        assert_eq!(
+            edge.system_description.as_deref(),
+            Some("cisco WS-C3850-24T")
+        );
+        // The management address wins over the interface address
+        assert_eq!(edge.management_address.as_deref(), Some("192.168.100.3"));
+    }
+
+    #[test]
+    fn test_parse_frame_rejects_others() {
+        // Truncated LLDPDU
+        let frame = lldp_frame();
+        assert!(parse_frame(&frame[..30]).is_none());
+
+        // LLDPDU without a port ID
+        let mut frame = LLDP_MULTICAST.to_vec();
+        frame.extend_from_slice(&SWITCH_MAC);
+        frame.extend_from_slice(&ETHERTYPE_LLDP.to_be_bytes());
+        frame.extend(lldp_tlv(1, b"\x07chassis"));
+        frame.extend(lldp_tlv(0, &[]));
+        assert!(parse_frame(&frame).is_none());
+
+        // Ordinary 802.3 frame to the CDP address without the SNAP header
+        let mut frame = CDP_MULTICAST.to_vec();
+        frame.extend_from_slice(&SWITCH_MAC);
+        frame.extend_from_slice(&[0, 46]);
+        frame.resize(60, 0x42);
+        assert!(parse_frame(&frame).is_none());
+    }
+}
@@ -0,0 +1,92 @@
+//! LLMNR reverse lookups (RFC 4795)
+//!
+//! Sends a PTR query for the host's own reverse name straight to its LLMNR
+//! responder on UDP 5355. Windows hosts answer with their machine name,
+//! which fills the gap when the network's DNS has no PTR records.
+
+use super::dns::{self, RecordData};
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+const LLMNR_PORT: u16 = 5355;
+
+/// The name the host gives for its own address, if it runs a responder.
+pub async fn reverse_lookup(ip: IpAddr, timeout: Duration) -> Option<String> {
+    query_ptr(ip, SocketAddr::new(ip, LLMNR_PORT), timeout).await
+}
+
+async fn query_ptr(ip: IpAddr, dest: SocketAddr, timeout: Duration) -> Option<String> {
+    let id = dns::next_query_id();
+    let name = dns::reverse_name(ip);
+    let query = dns::build_query(id, &[(&name, dns::TYPE_PTR)], false, false);
+    dns::exchange(dest, &query, timeout, |reply| {
+        parse_response(id, &name, reply)
+    })
+    .await
+}
+
+fn parse_response(id: u16, name: &str, reply: &[u8]) -> Option<String> {
+    let message = dns::parse_message(reply)?;
+    if message.id != id || !message.is_response || message.rcode != 0 {
+        return None;
+    }
+    message
+        .records
+        .into_iter()
+        .find_map(|record| match record.data {
+            RecordData::Ptr(target) if record.name.eq_ignore_ascii_case(name) => {
+                (!target.is_empty()).then_some(target)
+            }
+            _ => None,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::UdpSocket;
+
+    fn response(id: u16, name: &str, target: &str) -> Vec<u8> {
+        let mut reply = id.to_be_bytes().to_vec();
+        reply.extend_from_slice(&[0x80, 0x00, 0, 0, 0, 1, 0, 0, 0, 0]);
+        let mut rdata = Vec::new();
+        for label in target.split('.') {
+            rdata.push(label.len() as u8);
+            rdata.extend_from_slice(label.as_bytes());
+        }
+        rdata.push(0);
+        dns::push_record(&mut reply, name, dns::TYPE_PTR, &rdata);
+        reply
+    }
+
+    #[test]
+    fn test_parse_response() {
+        let name = "20.1.168.192.in-addr.arpa";
+        let reply = response(9, name, "DESKTOP-7Q2LM4A");
+        assert_eq!(
+            parse_response(9, name, &reply).as_deref(),
+            Some("DESKTOP-7Q2LM4A")
+        );
+        assert_eq!(parse_response(10, name, &reply), None);
+        assert_eq!(parse_response(9, "21.1.168.192.in-addr.arpa", &reply), None);
+    }
+
+    #[tokio::test]
+    async fn test_reverse_lookup_exchange() {
+        let responder = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let dest = responder.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut buf = [0u8; 512];
+            while let Ok((len, from)) = responder.recv_from(&mut buf).await {
+                let id = u16::from_be_bytes([buf[0], buf[1]]);
+                assert!(len > 12 && buf[2] & 0x80 == 0);
+                let reply = response(id, "1.0.0.127.in-addr.arpa", "build-box.lan");
+                let _ = responder.send_to(&reply, from).await;
+            }
+        });
+
+        let ip = "127.0.0.1".parse().unwrap();
+        let name = query_ptr(ip, dest, Duration::from_secs(1)).await;
+        assert_eq!(name.as_deref(), Some("build-box.lan"));
+    }
+}
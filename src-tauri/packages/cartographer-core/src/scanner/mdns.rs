@@ -0,0 +1,448 @@
+//! Multicast DNS / DNS-SD browser (RFC 6762, RFC 6763)
+//!
+//! Asks `_services._dns-sd._udp.local` which service types exist, then
+//! follows up on each type, instance and host name until the browse window
+//! closes. Queries come from an ephemeral port, so responders answer us by
+//! unicast ("legacy unicast", RFC 6762 section 6.7) and nothing has to bind
+//! port 5353 next to avahi or mDNSResponder.
+
+use super::config::MdnsConfig;
+use super::dns::{self, Record, RecordData};
+use super::is_scan_cancelled;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::time::{Duration, Instant};
+use tokio::net::UdpSocket;
+
+const MDNS_GROUP: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(224, 0, 0, 251), 5353);
+const SERVICES_META_QUERY: &str = "_services._dns-sd._udp.local";
+/// Questions per follow-up query packet
+const MAX_QUESTIONS: usize = 16;
+/// The first query is repeated once in case it was lost
+const REPEAT_AFTER: Duration = Duration::from_millis(1000);
+
+/// TXT keys that carry a model name, most specific first: HomeKit and
+/// Chromecast (`md`), AirPlay and `_device-info` (`model`), IPP printers
+/// (`ty`, `product`, `usb_MDL`)
+const MODEL_TXT_KEYS: &[&str] = &["md", "model", "ty", "usb_mdl", "product"];
+
+/// A service instance advertised over DNS-SD
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AdvertisedService {
+    /// Instance name (e.g. "Office Printer")
+    pub name: String,
+    /// Service type (e.g. "_ipp._tcp")
+    pub service_type: String,
+    pub port: u16,
+    /// TXT record key/value pairs (keys lowercased)
+    pub txt: BTreeMap<String, String>,
+}
+
+/// What one responder told us about itself
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MdnsHost {
+    /// Advertised host name (e.g. "office-printer.local")
+    pub hostname: Option<String>,
+    pub services: Vec<AdvertisedService>,
+    /// Model from TXT records
+    pub model: Option<String>,
+}
+
+/// Browse on every local IPv4 address for `config.window_ms` and return
+/// what each responder advertised, keyed by its address.
+pub async fn browse(local_ips: &[Ipv4Addr], config: &MdnsConfig) -> HashMap<IpAddr, MdnsHost> {
+    let window = Duration::from_millis(config.window_ms);
+    let browsers: Vec<_> = local_ips
+        .iter()
+        // RFC 6762 section 11: responders ignore packets with a TTL below 255
+        .filter_map(|ip| match multicast_socket(*ip, 255) {
+            Ok(socket) => Some(browse_on(socket, SocketAddr::V4(MDNS_GROUP), window)),
+            Err(e) => {
+                tracing::warn!("mDNS browse on {} unavailable: {:#}", ip, e);
+                None
+            }
+        })
+        .collect();
+
+    let mut hosts = HashMap::new();
+    for records in futures::future::join_all(browsers).await {
+        for (address, records) in records {
+            let host = summarize(&address, &records);
+            if host.hostname.is_some() || !host.services.is_empty() {
+                hosts.insert(address, host);
+            }
+        }
+    }
+
+    tracing::info!(
+        "mDNS: {} hosts advertised {} services",
+        hosts.len(),
+        hosts.values().map(|h| h.services.len()).sum::<usize>()
+    );
+    hosts
+}
+
+/// UDP socket on an ephemeral port that sends multicast out of the
+/// interface owning `local_ip`. Also used for SSDP.
+pub(super) fn multicast_socket(local_ip: Ipv4Addr, ttl: u32) -> Result<UdpSocket> {
+    use socket2::{Domain, Protocol, Socket, Type};
+
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))
+        .context("Failed to create UDP socket")?;
+    socket.set_nonblocking(true)?;
+    socket
+        .bind(&SocketAddr::new(local_ip.into(), 0).into())
+        .with_context(|| format!("Failed to bind to {}", local_ip))?;
+    socket.set_multicast_if_v4(&local_ip)?;
+    socket.set_multicast_ttl_v4(ttl)?;
+    Ok(UdpSocket::from_std(socket.into())?)
+}
+
+/// Query `dest` and collect response records per responder address until
+/// `window` has passed.
+async fn browse_on(
+    socket: UdpSocket,
+    dest: SocketAddr,
+    window: Duration,
+) -> HashMap<IpAddr, Vec<Record>> {
+    let start = Instant::now();
+    let deadline = start + window;
+    let mut by_source: HashMap<IpAddr, Vec<Record>> = HashMap::new();
+    let mut asked: HashSet<(String, u16)> = HashSet::new();
+    let mut repeated = false;
+    let mut buf = vec![0u8; 9000];
+
+    let meta = [(SERVICES_META_QUERY.to_string(), dns::TYPE_PTR)];
+    send_questions(&socket, dest, &meta, &mut asked).await;
+
+    loop {
+        let now = Instant::now();
+        if now >= deadline || is_scan_cancelled() {
+            break;
+        }
+        if !repeated && now >= start + REPEAT_AFTER {
+            repeated = true;
+            let again: Vec<_> = asked.iter().cloned().collect();
+            send_questions(&socket, dest, &again, &mut HashSet::new()).await;
+        }
+
+        let wait = (deadline - now).min(Duration::from_millis(250));
+        let (len, source) = match tokio::time::timeout(wait, socket.recv_from(&mut buf)).await {
+            Ok(Ok(received)) => received,
+            Ok(Err(e)) => {
+                tracing::debug!("mDNS receive failed: {}", e);
+                continue;
+            }
+            Err(_) => continue,
+        };
+        let Some(message) = dns::parse_message(&buf[..len]) else {
+            continue;
+        };
+        if !message.is_response {
+            continue;
+        }
+
+        let records = by_source.entry(source.ip()).or_default();
+        records.extend(message.records);
+        let questions = follow_ups(records);
+        send_questions(&socket, dest, &questions, &mut asked).await;
+    }
+
+    by_source
+}
+
+/// Send the questions not asked before, a few per packet.
+async fn send_questions(
+    socket: &UdpSocket,
+    dest: SocketAddr,
+    questions: &[(String, u16)],
+    asked: &mut HashSet<(String, u16)>,
+) {
+    let new: Vec<(&str, u16)> = questions
+        .iter()
+        .filter(|q| asked.insert((q.0.to_ascii_lowercase(), q.1)))
+        .map(|(name, qtype)| (name.as_str(), *qtype))
+        .collect();
+
+    for batch in new.chunks(MAX_QUESTIONS) {
+        let packet = dns::build_query(0, batch, false, false);
+        if let Err(e) = socket.send_to(&packet, dest).await {
+            tracing::debug!("mDNS send to {} failed: {}", dest, e);
+        }
+    }
+}
+
+/// Questions that would complete what a responder has told us so far:
+/// instances of each service type, SRV/TXT of each instance and the
+/// address of each SRV target.
+fn follow_ups(records: &[Record]) -> Vec<(String, u16)> {
+    let has = |name: &str, rtype: u16| {
+        records
+            .iter()
+            .any(|r| r.rtype == rtype && r.name.eq_ignore_ascii_case(name))
+    };
+
+    let mut questions = Vec::new();
+    for record in records {
+        match &record.data {
+            RecordData::Ptr(target) if record.name.eq_ignore_ascii_case(SERVICES_META_QUERY) => {
+                questions.push((target.clone(), dns::TYPE_PTR));
+            }
+            RecordData::Ptr(instance) if split_instance(instance).is_some() => {
+                if !has(instance, dns::TYPE_SRV) {
+                    questions.push((instance.clone(), dns::TYPE_SRV));
+                }
+                if !has(instance, dns::TYPE_TXT) {
+                    questions.push((instance.clone(), dns::TYPE_TXT));
+                }
+            }
+            RecordData::Srv { target, .. } if !has(target, dns::TYPE_A) => {
+                questions.push((target.clone(), dns::TYPE_A));
+            }
+            _ => {}
+        }
+    }
+    questions
+}
+
+/// Split "Office Printer._ipp._tcp.local" into ("Office Printer", "_ipp._tcp").
+/// Instance names may themselves contain dots.
+fn split_instance(name: &str) -> Option<(&str, &str)> {
+    let without_domain = name.strip_suffix(".local")?;
+    let proto = without_domain
+        .rfind("._tcp")
+        .or_else(|| without_domain.rfind("._udp"))?;
+    let type_start = without_domain[..proto].rfind("._")?;
+    let instance = &without_domain[..type_start];
+    if instance.is_empty() {
+        return None;
+    }
+    Some((instance, &without_domain[type_start + 1..]))
+}
+
+/// Turn one responder's records into a host name, services and model.
+fn summarize(address: &IpAddr, records: &[Record]) -> MdnsHost {
+    let hostname = records
+        .iter()
+        .find(|r| match r.data {
+            RecordData::A(ip) => IpAddr::V4(ip) == *address,
+            RecordData::Aaaa(ip) => IpAddr::V6(ip) == *address,
+            _ => false,
+        })
+        .map(|r| r.name.clone())
+        .or_else(|| {
+            records.iter().find_map(|r| match &r.data {
+                RecordData::Srv { target, .. } => Some(target.clone()),
+                _ => None,
+            })
+        });
+
+    let mut services: Vec<AdvertisedService> = Vec::new();
+    for record in records {
+        let RecordData::Srv { port, .. } = record.data else {
+            continue;
+        };
+        let Some((name, service_type)) = split_instance(&record.name) else {
+            continue;
+        };
+        if services
+            .iter()
+            .any(|s| s.name == name && s.service_type == service_type)
+        {
+            continue;
+        }
+        services.push(AdvertisedService {
+            name: name.to_string(),
+            service_type: service_type.to_string(),
+            port,
+            txt: txt_entries(
+                records
+                    .iter()
+                    .filter(|r| r.name.eq_ignore_ascii_case(&record.name)),
+            )
+            .collect(),
+        });
+    }
+    services.sort_by(|a, b| (&a.service_type, &a.name).cmp(&(&b.service_type, &b.name)));
+
+    // `_device-info._tcp` carries a model but has no SRV record
+    let all_txt: Vec<(String, String)> = txt_entries(records.iter()).collect();
+    let model = MODEL_TXT_KEYS.iter().find_map(|key| {
+        all_txt
+            .iter()
+            .find(|(k, v)| k == key && !v.is_empty())
+            .map(|(_, v)| v.trim_matches(|c| c == '(' || c == ')').trim().to_string())
+    });
+
+    MdnsHost {
+        hostname,
+        services,
+        model,
+    }
+}
+
+/// Key/value pairs of the TXT records among `records`, keys lowercased.
+/// A bare key (no `=`) has an empty value.
+fn txt_entries<'a>(
+    records: impl Iterator<Item = &'a Record>,
+) -> impl Iterator<Item = (String, String)> {
+    records
+        .filter_map(|r| match &r.data {
+            RecordData::Txt(strings) => Some(strings),
+            _ => None,
+        })
+        .flatten()
+        .map(|entry| match entry.split_once('=') {
+            Some((key, value)) => (key.to_ascii_lowercase(), value.to_string()),
+            None => (entry.to_ascii_lowercase(), String::new()),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::dns::push_record;
+
+    fn name_rdata(name: &str) -> Vec<u8> {
+        let mut rdata = Vec::new();
+        for label in name.split('.') {
+            rdata.push(label.len() as u8);
+            rdata.extend_from_slice(label.as_bytes());
+        }
+        rdata.push(0);
+        rdata
+    }
+
+    fn txt_rdata(entries: &[&str]) -> Vec<u8> {
+        let mut rdata = Vec::new();
+        for entry in entries {
+            rdata.push(entry.len() as u8);
+            rdata.extend_from_slice(entry.as_bytes());
+        }
+        rdata
+    }
+
+    fn response(records: &[(&str, u16, Vec<u8>)]) -> Vec<u8> {
+        let mut buf = vec![0, 0, 0x84, 0x00, 0, 0, 0, records.len() as u8, 0, 0, 0, 0];
+        for (name, rtype, rdata) in records {
+            push_record(&mut buf, name, *rtype, rdata);
+        }
+        buf
+    }
+
+    #[test]
+    fn test_split_instance() {
+        assert_eq!(
+            split_instance("Office Printer._ipp._tcp.local"),
+            Some(("Office Printer", "_ipp._tcp"))
+        );
+        assert_eq!(
+            split_instance("Living Room v2.1._googlecast._tcp.local"),
+            Some(("Living Room v2.1", "_googlecast._tcp"))
+        );
+        assert_eq!(split_instance("_ipp._tcp.local"), None);
+        assert_eq!(split_instance("printer.local"), None);
+    }
+
+    #[tokio::test]
+    async fn test_browse_follows_up_to_host() {
+        // A responder that answers each question the way a printer would,
+        // one record at a time, so the browser has to ask follow-ups
+        let responder = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let dest = responder.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut buf = [0u8; 1500];
+            while let Ok((len, from)) = responder.recv_from(&mut buf).await {
+                let Some(query) = parse_question_names(&buf[..len]) else {
+                    continue;
+                };
+                for (name, qtype) in query {
+                    let reply = match (name.as_str(), qtype) {
+                        (SERVICES_META_QUERY, dns::TYPE_PTR) => response(&[(
+                            SERVICES_META_QUERY,
+                            dns::TYPE_PTR,
+                            name_rdata("_ipp._tcp.local"),
+                        )]),
+                        ("_ipp._tcp.local", dns::TYPE_PTR) => response(&[(
+                            "_ipp._tcp.local",
+                            dns::TYPE_PTR,
+                            name_rdata("Office Printer._ipp._tcp.local"),
+                        )]),
+                        ("Office Printer._ipp._tcp.local", dns::TYPE_SRV) => {
+                            let mut srv = vec![0, 0, 0, 0, 0x02, 0x77];
+                            srv.extend(name_rdata("hp-m404.local"));
+                            response(&[("Office Printer._ipp._tcp.local", dns::TYPE_SRV, srv)])
+                        }
+                        ("Office Printer._ipp._tcp.local", dns::TYPE_TXT) => response(&[(
+                            "Office Printer._ipp._tcp.local",
+                            dns::TYPE_TXT,
+                            txt_rdata(&["txtvers=1", "ty=HP LaserJet M404dn", "Color=F"]),
+                        )]),
+                        ("hp-m404.local", dns::TYPE_A) => {
+                            response(&[("hp-m404.local", dns::TYPE_A, vec![127, 0, 0, 1])])
+                        }
+                        _ => continue,
+                    };
+                    let _ = responder.send_to(&reply, from).await;
+                }
+            }
+        });
+
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let records = browse_on(socket, dest, Duration::from_millis(500)).await;
+        let address: IpAddr = "127.0.0.1".parse().unwrap();
+        let host = summarize(&address, &records[&address]);
+
+        assert_eq!(host.hostname.as_deref(), Some("hp-m404.local"));
+        assert_eq!(host.model.as_deref(), Some("HP LaserJet M404dn"));
+        assert_eq!(host.services.len(), 1);
+        let service = &host.services[0];
+        assert_eq!(service.name, "Office Printer");
+        assert_eq!(service.service_type, "_ipp._tcp");
+        assert_eq!(service.port, 631);
+        assert_eq!(service.txt.get("color").map(String::as_str), Some("F"));
+    }
+
+    /// Question names and types of a query (test responder helper)
+    fn parse_question_names(buf: &[u8]) -> Option<Vec<(String, u16)>> {
+        let count = u16::from_be_bytes([buf[4], buf[5]]) as usize;
+        let mut pos = 12;
+        let mut questions = Vec::new();
+        for _ in 0..count {
+            let mut labels = Vec::new();
+            loop {
+                let len = *buf.get(pos)? as usize;
+                pos += 1;
+                if len == 0 {
+                    break;
+                }
+                labels.push(String::from_utf8_lossy(buf.get(pos..pos + len)?).into_owned());
+                pos += len;
+            }
+            let qtype = u16::from_be_bytes([*buf.get(pos)?, *buf.get(pos + 1)?]);
+            pos += 4;
+            questions.push((labels.join("."), qtype));
+        }
+        Some(questions)
+    }
+
+    #[test]
+    fn test_summarize_device_info_model() {
+        let buf = response(&[
+            (
+                "MacBook._device-info._tcp.local",
+                dns::TYPE_TXT,
+                txt_rdata(&["model=MacBookPro18,3", "osxvers=23"]),
+            ),
+            ("MacBook.local", dns::TYPE_A, vec![192, 168, 1, 30]),
+        ]);
+        let records = dns::parse_message(&buf).unwrap().records;
+        let host = summarize(&"192.168.1.30".parse().unwrap(), &records);
+        assert_eq!(host.hostname.as_deref(), Some("MacBook.local"));
+        assert_eq!(host.model.as_deref(), Some("MacBookPro18,3"));
+        assert!(host.services.is_empty());
+    }
+}
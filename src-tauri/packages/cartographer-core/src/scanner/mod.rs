@@ -1,22 +1,65 @@
 //! Network scanning module.
 //!
 //! Provides cross-platform network discovery using:
-//! - ARP table reading
-//! - ICMP ping sweep
-//! - DNS/mDNS hostname resolution
-//! - MAC OUI vendor lookup
+//! - ARP/neighbor table reading and active ARP sweep (Linux)
+//! - ICMP ping sweep (in-process socket engine or system ping)
+//! - DNS/mDNS, NetBIOS and LLMNR hostname resolution
+//! - SSDP/UPnP device descriptions
+//! - SNMP system group and interface tables
+//! - Passive LLDP/CDP capture of the switch ports this host is attached to (Linux)
+//! - Passive DHCP fingerprinting of hostnames and OS families (Linux)
+//! - OS guesses from reply TTLs, TCP handshakes and the above
+//! - MAC OUI vendor lookup and randomized MAC detection
 
 mod arp;
+mod arp_sweep;
+pub mod classify;
+pub mod config;
+mod dhcp;
+mod dns;
+mod icmp;
+pub mod identity;
+// Parsers for `ip`/`ifconfig` output go unused on Windows
+#[cfg_attr(target_os = "windows", allow(dead_code))]
+mod interfaces;
+mod llmnr;
+mod lldp;
+mod mdns;
+mod netbios;
+pub mod neighbor;
 mod ping;
 pub mod oui;
+mod os_guess;
+mod ports;
 pub mod privileges;
+mod rdns;
+mod services;
+mod snmp;
+mod ssdp;
+mod x509;
 
 // Re-export privilege types at module level for cleaner public API
+pub use classify::{Classification, Ruleset};
+pub use config::{load_validated_scan_config, ScanConfig};
+pub use dhcp::{DhcpFingerprint, OsFamily};
+pub use icmp::PingEngine;
+pub use lldp::{LinkProtocol, TopologyEdge};
+pub use mdns::AdvertisedService;
+pub use neighbor::{NeighborEntry, NeighborState};
+pub use os_guess::OsGuess;
+pub use ports::{OpenPort, TcpFingerprint};
+pub use services::ServiceInfo;
+pub use snmp::{SnmpInfo, SnmpInterface};
+pub use ssdp::UpnpDevice;
+pub use x509::TlsCertificate;
 pub use privileges::ScanCapabilities;
 
 use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use ipnetwork::IpNetwork;
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
+use std::net::IpAddr;
 use std::process::Command;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Instant;
@@ -40,6 +83,27 @@ pub fn clear_scan_cancel() {
     SCAN_CANCEL_REQUESTED.store(false, Ordering::SeqCst);
 }
 
+/// Start the passive DHCP listener if `[scan.dhcp]` enables it. Meant for
+/// long-running agents; later scans attach what it heard to devices.
+/// Returns whether the listener is running; an invalid `[scan]` section
+/// keeps it off.
+pub fn start_dhcp_listener() -> bool {
+    match config::load_validated_scan_config() {
+        Ok(config) if config.dhcp.enabled => {}
+        Ok(_) => return false,
+        Err(e) => {
+            tracing::warn!("Not starting the DHCP listener: {:#}", e);
+            return false;
+        }
+    }
+    dhcp::start_listener()
+}
+
+/// Stop the passive DHCP listener
+pub fn stop_dhcp_listener() {
+    dhcp::stop_listener();
+}
+
 #[cfg(target_os = "windows")]
 const CREATE_NO_WINDOW: u32 = 0x08000000;
 
@@ -68,26 +132,336 @@ pub(crate) fn hidden_command_sync(program: &str) -> Command {
     Command::new(program)
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Device {
-    pub ip: String,
+    /// All addresses seen for this device (IPv4 first, then IPv6).
+    /// Accepts the legacy single `ip` field when deserializing.
+    #[serde(alias = "ip", deserialize_with = "deserialize_addresses")]
+    pub addresses: Vec<String>,
     pub mac: Option<String>,
+    /// `mac` is a locally administered address a phone or laptop picked
+    /// for privacy; it may change, so identity falls back to other signals
+    #[serde(default)]
+    pub randomized_mac: bool,
     pub response_time_ms: Option<f64>,
+    /// When a scan last found the device
+    #[serde(default)]
+    pub last_seen: Option<DateTime<Utc>>,
+    /// IP TTL of the ICMP echo reply
+    #[serde(default)]
+    pub ttl: Option<u8>,
     pub hostname: Option<String>,
+    /// Which resolver produced `hostname`
+    #[serde(default)]
+    pub hostname_source: Option<HostnameSource>,
     /// Device vendor/manufacturer from MAC OUI lookup
     pub vendor: Option<String>,
-    /// Inferred device type based on vendor
+    /// Device type set by the classification rules
     pub device_type: Option<String>,
+    /// Rule that set `device_type`, and the conditions it matched
+    #[serde(default)]
+    pub classification: Option<Classification>,
+    /// Tags of every classification rule the device matched
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Interface the device was seen on
+    pub interface: Option<String>,
+    /// State of the device's kernel neighbor table entry (Linux)
+    #[serde(default)]
+    pub neighbor_state: Option<NeighborState>,
+    /// Scanned subnet the device belongs to
+    pub subnet: Option<String>,
+    /// Open TCP ports found by the port scan
+    #[serde(default)]
+    pub open_ports: Vec<OpenPort>,
+    /// Banners and certificates read from open ports
+    #[serde(default)]
+    pub services: Vec<ServiceInfo>,
+    /// Model name advertised over mDNS or UPnP (e.g. "HP LaserJet M404dn")
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Services advertised over mDNS / DNS-SD
+    #[serde(default)]
+    pub advertised_services: Vec<AdvertisedService>,
+    /// UPnP device description found over SSDP
+    #[serde(default)]
+    pub upnp: Option<UpnpDevice>,
+    /// System group and interfaces read over SNMP
+    #[serde(default)]
+    pub snmp: Option<SnmpInfo>,
+    /// Latest DHCP request heard from the device's MAC address
+    #[serde(default)]
+    pub dhcp: Option<DhcpFingerprint>,
+    /// SYN-ACK window and options from the port scan
+    #[serde(default)]
+    pub tcp_fingerprint: Option<TcpFingerprint>,
+    /// Operating system guessed from the TTL, TCP handshake and hints above
+    #[serde(default)]
+    pub os_guess: Option<OsGuess>,
 }
 
-/// Network information including interface, subnet, and gateway
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct NetworkInfo {
+/// Where a device's hostname came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HostnameSource {
+    /// This machine's own hostname
+    Local,
+    /// The hosts file
+    Hosts,
+    /// DNS PTR record
+    Dns,
+    /// Multicast DNS browse
+    Mdns,
+    /// UPnP friendly name
+    Upnp,
+    /// NetBIOS node status
+    Netbios,
+    /// LLMNR reverse lookup
+    Llmnr,
+    /// SNMP sysName
+    Snmp,
+    /// DHCP client hostname option
+    Dhcp,
+}
+
+impl std::fmt::Display for HostnameSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            HostnameSource::Local => "local",
+            HostnameSource::Hosts => "hosts",
+            HostnameSource::Dns => "dns",
+            HostnameSource::Mdns => "mdns",
+            HostnameSource::Upnp => "upnp",
+            HostnameSource::Netbios => "netbios",
+            HostnameSource::Llmnr => "llmnr",
+            HostnameSource::Snmp => "snmp",
+            HostnameSource::Dhcp => "dhcp",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Accept either a single address string or a list of addresses.
+fn deserialize_addresses<'de, D>(deserializer: D) -> std::result::Result<Vec<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(ip) => vec![ip],
+        OneOrMany::Many(addresses) => addresses,
+    })
+}
+
+/// Sort key for device addresses: IPv4, then global IPv6, then link-local IPv6.
+fn address_rank(address: &str) -> u8 {
+    match address.parse::<IpAddr>() {
+        Ok(IpAddr::V4(_)) => 0,
+        Ok(IpAddr::V6(v6)) if is_ipv6_link_local(&v6) => 2,
+        Ok(IpAddr::V6(_)) => 1,
+        Err(_) => 3,
+    }
+}
+
+/// Whether an IPv6 address is link-local (fe80::/10)
+pub fn is_ipv6_link_local(ip: &std::net::Ipv6Addr) -> bool {
+    (ip.segments()[0] & 0xffc0) == 0xfe80
+}
+
+/// Normalize a MAC address for comparison ("0:1A-2b..." -> "00:1a:2b...").
+pub fn normalize_mac(mac: &str) -> String {
+    mac.split([':', '-'])
+        .map(|octet| format!("{:0>2}", octet.to_lowercase()))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+impl Device {
+    /// Create a device with a single address and no other details.
+    pub fn new(ip: impl Into<String>) -> Self {
+        Self {
+            addresses: vec![ip.into()],
+            ..Default::default()
+        }
+    }
+
+    /// Primary address used for display, health checks and the cloud payload.
+    /// Prefers IPv4, then global IPv6, then link-local IPv6.
+    pub fn primary_ip(&self) -> &str {
+        self.addresses.first().map(String::as_str).unwrap_or("")
+    }
+
+    /// First IPv4 address, if any
+    pub fn ipv4(&self) -> Option<&str> {
+        self.addresses
+            .iter()
+            .map(String::as_str)
+            .find(|a| a.parse::<std::net::Ipv4Addr>().is_ok())
+    }
+
+    /// All IPv6 addresses
+    pub fn ipv6_addresses(&self) -> impl Iterator<Item = &str> {
+        self.addresses
+            .iter()
+            .map(String::as_str)
+            .filter(|a| a.parse::<std::net::Ipv6Addr>().is_ok())
+    }
+
+    /// Whether the device has the given address
+    pub fn has_address(&self, ip: &str) -> bool {
+        self.addresses.iter().any(|a| a == ip)
+    }
+
+    /// Add an address, keeping the list deduplicated and in preference order.
+    pub fn add_address(&mut self, ip: impl Into<String>) {
+        let ip = ip.into();
+        if !self.has_address(&ip) {
+            self.addresses.push(ip);
+            self.addresses.sort_by_key(|a| address_rank(a));
+        }
+    }
+
+    /// Merge another record for the same device, filling in missing details.
+    pub fn merge(&mut self, other: Device) {
+        for address in other.addresses {
+            self.add_address(address);
+        }
+        if self.mac.is_none() && other.mac.is_some() {
+            self.mac = other.mac;
+            self.randomized_mac = other.randomized_mac;
+        }
+        self.last_seen = self.last_seen.max(other.last_seen);
+        if self.hostname.is_none() && other.hostname.is_some() {
+            self.hostname = other.hostname;
+            self.hostname_source = other.hostname_source;
+        }
+        if self.vendor.is_none() && other.vendor.is_some() {
+            self.vendor = other.vendor;
+        }
+        if self.device_type.is_none() && other.device_type.is_some() {
+            self.device_type = other.device_type;
+            self.classification = other.classification;
+        }
+        for tag in other.tags {
+            if !self.tags.contains(&tag) {
+                self.tags.push(tag);
+            }
+        }
+        self.tags.sort();
+        if self.interface.is_none() && other.interface.is_some() {
+            self.interface = other.interface;
+            self.subnet = other.subnet;
+        }
+        if self.neighbor_state.is_none() {
+            self.neighbor_state = other.neighbor_state;
+        }
+        for port in other.open_ports {
+            if !self.open_ports.iter().any(|p| p.port == port.port) {
+                self.open_ports.push(port);
+            }
+        }
+        self.open_ports.sort_by_key(|p| p.port);
+        for service in other.services {
+            if !self.services.iter().any(|s| s.port == service.port) {
+                self.services.push(service);
+            }
+        }
+        self.services.sort_by_key(|s| s.port);
+        if self.model.is_none() && other.model.is_some() {
+            self.model = other.model;
+        }
+        for service in other.advertised_services {
+            if !self.advertised_services.contains(&service) {
+                self.advertised_services.push(service);
+            }
+        }
+        if self.upnp.is_none() && other.upnp.is_some() {
+            self.upnp = other.upnp;
+        }
+        if self.snmp.is_none() && other.snmp.is_some() {
+            self.snmp = other.snmp;
+        }
+        if self.dhcp.is_none() && other.dhcp.is_some() {
+            self.dhcp = other.dhcp;
+        }
+        if self.ttl.is_none() && other.ttl.is_some() {
+            self.ttl = other.ttl;
+        }
+        if self.tcp_fingerprint.is_none() && other.tcp_fingerprint.is_some() {
+            self.tcp_fingerprint = other.tcp_fingerprint;
+        }
+        if self.os_guess.is_none() && other.os_guess.is_some() {
+            self.os_guess = other.os_guess;
+        }
+        if self.response_time_ms.is_none()
+            || (other.response_time_ms.unwrap_or(0.0) > 0.0
+                && self.response_time_ms.unwrap_or(0.0) == 0.0)
+        {
+            self.response_time_ms = other.response_time_ms.or(self.response_time_ms);
+        }
+    }
+}
+
+/// A subnet to scan and the interface it is reached through
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ScanTarget {
     pub interface: String,
     pub subnet: String,
     pub gateway_ip: Option<String>,
+    /// Our address on the subnet; `None` for routed subnets from config
     pub local_ip: Option<String>,
+    /// Global/unique-local IPv6 prefixes on the interface (e.g. "2001:db8::/64")
+    #[serde(default)]
+    pub ipv6_prefixes: Vec<String>,
+}
+
+impl ScanTarget {
+    /// Whether the subnet is directly attached (ARP and neighbor discovery work)
+    pub fn is_on_link(&self) -> bool {
+        self.local_ip.is_some()
+    }
+}
+
+/// Network information: every subnet covered by a scan.
+/// The target on the default route comes first.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NetworkInfo {
+    pub targets: Vec<ScanTarget>,
+}
+
+impl NetworkInfo {
+    /// Target on the default route (or the first one found)
+    pub fn primary(&self) -> Option<&ScanTarget> {
+        self.targets.first()
+    }
+
+    /// Gateways of all targets
+    pub fn gateway_ips(&self) -> Vec<&str> {
+        self.targets
+            .iter()
+            .filter_map(|t| t.gateway_ip.as_deref())
+            .collect()
+    }
+
+    /// Subnets of all targets
+    pub fn subnets(&self) -> Vec<&str> {
+        self.targets.iter().map(|t| t.subnet.as_str()).collect()
+    }
+
+    /// Whether an address falls inside any target subnet
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        self.targets
+            .iter()
+            .filter_map(|t| t.subnet.parse::<IpNetwork>().ok())
+            .any(|subnet| subnet.contains(ip))
+    }
 }
 
 /// Scan result containing devices and network information
@@ -96,6 +470,8 @@ pub struct ScanResult {
     pub devices: Vec<Device>,
     pub network_info: NetworkInfo,
     pub capabilities: privileges::ScanCapabilities,
+    /// Switch ports this host is attached to, from LLDP/CDP announcements
+    pub topology: Vec<TopologyEdge>,
 }
 
 /// Progress updates during network scanning
@@ -117,7 +493,14 @@ pub enum ScanStage {
     DetectingNetwork,
     ReadingArp,
     PingSweep,
+    ArpSweep,
+    BrowsingMdns,
+    SsdpDiscovery,
     ResolvingHostnames,
+    SnmpQuery,
+    LldpCapture,
+    PortScan,
+    ServiceDetection,
     Complete,
     Failed,
 }
@@ -147,43 +530,262 @@ fn get_local_hostname() -> Option<String> {
     }
 }
 
-/// Deduplicate devices by IP address, keeping the most complete record.
-fn deduplicate_devices_by_ip(devices: Vec<Device>) -> Vec<Device> {
+/// Tag a device with the target it was discovered on.
+fn seen_on(device: Device, target: &ScanTarget) -> Device {
+    Device {
+        interface: Some(target.interface.clone()),
+        subnet: Some(target.subnet.clone()),
+        ..device
+    }
+}
+
+fn interface_names<'a>(targets: &[&'a ScanTarget]) -> Vec<&'a str> {
+    targets.iter().map(|t| t.interface.as_str()).collect()
+}
+
+/// Neighbors with the target of the interface they were seen on
+fn neighbors_on_targets(neighbors: Vec<Device>, targets: &[&ScanTarget]) -> Vec<Device> {
+    neighbors
+        .into_iter()
+        .filter_map(|device| {
+            let target = targets
+                .iter()
+                .find(|t| device.interface.as_deref() == Some(t.interface.as_str()))?;
+            Some(seen_on(device, target))
+        })
+        .collect()
+}
+
+/// Record the scan target of each device: the subnet holding its IPv4
+/// address, else the target whose IPv6 prefix holds one of its addresses.
+/// Devices matching neither keep the target they were discovered on.
+fn assign_scan_targets(devices: &mut [Device], network_info: &NetworkInfo) {
+    let targets: Vec<(&ScanTarget, Option<IpNetwork>, Vec<IpNetwork>)> = network_info
+        .targets
+        .iter()
+        .map(|t| {
+            let prefixes = t.ipv6_prefixes.iter().filter_map(|p| p.parse().ok()).collect();
+            (t, t.subnet.parse().ok(), prefixes)
+        })
+        .collect();
+
+    for device in devices.iter_mut() {
+        let ips: Vec<IpAddr> = device.addresses.iter().filter_map(|a| a.parse().ok()).collect();
+
+        let by_ipv4 = targets.iter().find(|(_, subnet, _)| {
+            subnet.is_some_and(|s| ips.iter().any(|ip| ip.is_ipv4() && s.contains(*ip)))
+        });
+        let by_ipv6 = || {
+            targets.iter().find(|(_, _, prefixes)| {
+                ips.iter()
+                    .any(|ip| ip.is_ipv6() && prefixes.iter().any(|p| p.contains(*ip)))
+            })
+        };
+
+        if let Some((target, _, _)) = by_ipv4.or_else(by_ipv6) {
+            device.interface = Some(target.interface.clone());
+            device.subnet = Some(target.subnet.clone());
+        }
+    }
+}
+
+/// Merge device records from all sources into one device per host.
+///
+/// Records sharing a MAC address on the same subnet (e.g. an IPv4 ARP entry
+/// and IPv6 neighbor entries) are combined; records without a MAC are merged
+/// into whichever device already owns one of their addresses. A router with
+/// the same MAC on several VLANs stays one device per subnet.
+fn merge_devices_by_mac(devices: Vec<Device>) -> Vec<Device> {
     use std::collections::HashMap;
 
-    let mut by_ip: HashMap<String, Device> = HashMap::new();
+    let mut merged: Vec<Device> = Vec::new();
+    let mut by_mac: HashMap<(Option<String>, String), usize> = HashMap::new();
 
-    for device in devices {
-        if let Some(existing) = by_ip.get_mut(&device.ip) {
-            if existing.mac.is_none() && device.mac.is_some() {
-                existing.mac = device.mac;
-            }
-            if existing.hostname.is_none() && device.hostname.is_some() {
-                existing.hostname = device.hostname;
-            }
-            if existing.vendor.is_none() && device.vendor.is_some() {
-                existing.vendor = device.vendor;
-            }
-            if existing.device_type.is_none() && device.device_type.is_some() {
-                existing.device_type = device.device_type;
-            }
-            if existing.response_time_ms.is_none()
-                || (device.response_time_ms.is_some()
-                    && device.response_time_ms.unwrap_or(0.0) > 0.0
-                    && existing.response_time_ms.unwrap_or(0.0) == 0.0)
-            {
-                existing.response_time_ms = device.response_time_ms;
-            }
+    let (with_mac, without_mac): (Vec<Device>, Vec<Device>) =
+        devices.into_iter().partition(|d| d.mac.is_some());
+
+    for device in with_mac {
+        let key = (
+            device.subnet.clone(),
+            normalize_mac(device.mac.as_deref().unwrap_or_default()),
+        );
+        if let Some(&index) = by_mac.get(&key) {
+            merged[index].merge(device);
         } else {
-            by_ip.insert(device.ip.clone(), device);
+            by_mac.insert(key, merged.len());
+            merged.push(device);
+        }
+    }
+
+    for device in without_mac {
+        let existing = merged
+            .iter()
+            .position(|d| device.addresses.iter().any(|a| d.has_address(a)));
+        match existing {
+            Some(index) => merged[index].merge(device),
+            None => merged.push(device),
+        }
+    }
+
+    merged
+}
+
+/// Add ping sweep replies to the devices found so far. Hosts already known
+/// (mostly ARP cache hits) take the reply's round-trip time and TTL.
+fn merge_pinged_devices(devices: &mut Vec<Device>, pinged_devices: Vec<Device>) {
+    for pinged in pinged_devices {
+        if let Some(existing) = devices
+            .iter_mut()
+            .find(|d| d.has_address(pinged.primary_ip()))
+        {
+            existing.response_time_ms = pinged.response_time_ms;
+            existing.ttl = pinged.ttl.or(existing.ttl);
+        } else {
+            devices.push(pinged);
+        }
+    }
+}
+
+/// The device with `address`, added if the sweeps missed it.
+fn device_for_address<'a>(
+    devices: &'a mut Vec<Device>,
+    address: IpAddr,
+    network_info: &NetworkInfo,
+) -> &'a mut Device {
+    let address = address.to_string();
+    let index = match devices.iter().position(|d| d.has_address(&address)) {
+        Some(index) => index,
+        None => {
+            tracing::debug!("Discovery found {} missed by the sweeps", address);
+            let mut device = Device {
+                response_time_ms: Some(0.0),
+                ..Device::new(address)
+            };
+            assign_scan_targets(std::slice::from_mut(&mut device), network_info);
+            devices.push(device);
+            devices.len() - 1
+        }
+    };
+    &mut devices[index]
+}
+
+/// Fill in names, models and services from mDNS responses. Responders
+/// the sweeps missed are added as devices.
+fn apply_mdns_hosts(
+    devices: &mut Vec<Device>,
+    hosts: std::collections::HashMap<IpAddr, mdns::MdnsHost>,
+    network_info: &NetworkInfo,
+) {
+    for (address, host) in hosts {
+        let device = device_for_address(devices, address, network_info);
+        if device.hostname.is_none() && host.hostname.is_some() {
+            device.hostname = host.hostname;
+            device.hostname_source = Some(HostnameSource::Mdns);
         }
+        if device.model.is_none() {
+            device.model = host.model;
+        }
+        device.advertised_services = host.services;
     }
+}
+
+/// Attach UPnP descriptions and use their friendly name and model where
+/// nothing better is known. Responders the sweeps missed are added.
+fn apply_upnp_devices(
+    devices: &mut Vec<Device>,
+    described: std::collections::HashMap<IpAddr, UpnpDevice>,
+    network_info: &NetworkInfo,
+) {
+    for (address, upnp) in described {
+        let device = device_for_address(devices, address, network_info);
+        if device.hostname.is_none() && upnp.friendly_name.is_some() {
+            device.hostname = upnp.friendly_name.clone();
+            device.hostname_source = Some(HostnameSource::Upnp);
+        }
+        if device.model.is_none() {
+            device.model = upnp.model();
+        }
+        device.upnp = Some(upnp);
+    }
+}
 
-    by_ip.into_values().collect()
+/// Attach fingerprints recorded by the DHCP listener, and use the hostname
+/// the client sent where nothing better is known.
+fn apply_dhcp_fingerprints(devices: &mut [Device]) {
+    for device in devices.iter_mut() {
+        let Some(fingerprint) = device.mac.as_deref().and_then(dhcp::lookup) else {
+            continue;
+        };
+        if device.hostname.is_none() && fingerprint.hostname.is_some() {
+            device.hostname = fingerprint.hostname.clone();
+            device.hostname_source = Some(HostnameSource::Dhcp);
+        }
+        device.dhcp = Some(fingerprint);
+    }
+}
+
+/// Filter devices to the scan targets to avoid syncing neighbors from
+/// unrelated interfaces (VPN, containers, virtual adapters, etc.), and drop
+/// `[scan] exclude` addresses. Expects targets to have been assigned with
+/// `assign_scan_targets`.
+fn filter_devices_to_targets(
+    devices: Vec<Device>,
+    network_info: &NetworkInfo,
+    scan_config: &ScanConfig,
+) -> Vec<Device> {
+    let exclude = scan_config.exclude_networks();
+    let total_before = devices.len();
+    let filtered: Vec<Device> = devices
+        .into_iter()
+        .filter_map(|mut device| {
+            let target = network_info
+                .targets
+                .iter()
+                .find(|t| device.subnet.as_deref() == Some(t.subnet.as_str()))?;
+            let subnet: Option<IpNetwork> = target.subnet.parse().ok();
+            let ipv6_prefixes: Vec<IpNetwork> = target
+                .ipv6_prefixes
+                .iter()
+                .filter_map(|p| p.parse().ok())
+                .collect();
+
+            // IPv4 must be in the subnet. IPv6 neighbors are only collected
+            // from attached interfaces, so link-local is always in scope and
+            // global addresses must match an interface prefix when we know any.
+            device.addresses.retain(|address| match address.parse::<IpAddr>() {
+                Ok(ip) if exclude.iter().any(|net| net.contains(ip)) => false,
+                Ok(ip @ IpAddr::V4(_)) => subnet.is_some_and(|s| s.contains(ip)),
+                Ok(ip @ IpAddr::V6(v6)) => {
+                    is_ipv6_link_local(&v6)
+                        || ipv6_prefixes.is_empty()
+                        || ipv6_prefixes.iter().any(|p| p.contains(ip))
+                }
+                Err(_) => false,
+            });
+
+            if device.addresses.is_empty() {
+                None
+            } else {
+                Some(device)
+            }
+        })
+        .collect();
+
+    if filtered.len() < total_before {
+        tracing::info!(
+            "Filtered {} out-of-subnet devices before sync (kept {}/{} in {})",
+            total_before - filtered.len(),
+            filtered.len(),
+            total_before,
+            network_info.subnets().join(", ")
+        );
+    }
+
+    filtered
 }
 
 /// Enrich devices with vendor information from MAC OUI lookup.
-fn enrich_devices_with_vendor(devices: &mut [Device]) {
+fn enrich_devices_with_vendor(devices: &mut [Device], vendors: &oui::Database) {
     let total_devices = devices.len();
     let devices_with_mac = devices.iter().filter(|d| d.mac.is_some()).count();
 
@@ -197,6 +799,12 @@ fn enrich_devices_with_vendor(devices: &mut [Device]) {
     let mut found_count = 0;
 
     for device in devices.iter_mut() {
+        device.randomized_mac = device.mac.as_deref().is_some_and(oui::is_randomized_mac);
+        // A random address says nothing about who made the device
+        if device.randomized_mac {
+            tracing::debug!("OUI: {} -> randomized MAC", device.primary_ip());
+            continue;
+        }
         if device.vendor.is_some() {
             continue;
         }
@@ -207,42 +815,31 @@ fn enrich_devices_with_vendor(devices: &mut [Device]) {
         if let Some(ref mac) = device.mac {
             lookup_count += 1;
 
-            if let Some(vendor) = oui::lookup_vendor(mac) {
+            if let Some(vendor) = vendors.lookup(mac) {
                 found_count += 1;
-
-                let mut device_type = oui::infer_device_type(&vendor).map(String::from);
-
-                if device_type.is_none() {
-                    device_type = oui::infer_device_type_from_mac(mac).map(String::from);
-                }
-
-                tracing::info!(
-                    "OUI: {} ({}) -> {} (type: {:?})",
-                    device.ip,
-                    mac,
-                    vendor,
-                    device_type
-                );
+                tracing::info!("OUI: {} ({}) -> {}", device.primary_ip(), mac, vendor);
                 device.vendor = Some(vendor);
-                device.device_type = device_type;
             } else {
-                if let Some(device_type) = oui::infer_device_type_from_mac(mac) {
+                if oui::is_virtual_mac(mac) {
                     found_count += 1;
-                    tracing::info!(
-                        "OUI: {} ({}) -> VM/Container (type: {})",
-                        device.ip,
-                        mac,
-                        device_type
-                    );
+                    tracing::info!("OUI: {} ({}) -> VM/Container", device.primary_ip(), mac);
                     device.vendor = Some("Virtual Machine".to_string());
-                    device.device_type = Some(device_type.to_string());
                 } else {
-                    tracing::warn!("OUI: {} ({}) -> NOT FOUND", device.ip, mac);
+                    tracing::warn!("OUI: {} ({}) -> NOT FOUND", device.primary_ip(), mac);
                 }
             }
         }
     }
 
+    // Devices whose NIC vendor is unknown go by their UPnP manufacturer
+    for device in devices.iter_mut() {
+        if device.vendor.is_none()
+            && let Some(upnp) = &device.upnp
+        {
+            device.vendor = upnp.manufacturer.clone();
+        }
+    }
+
     tracing::info!(
         "OUI enrichment complete: looked up {} MACs, found {} vendors ({:.0}%)",
         lookup_count,
@@ -291,6 +888,7 @@ pub async fn scan_network_with_progress(
         Some(2),
         None,
     );
+    let scan_config = config::load_validated_scan_config()?;
     let capabilities = privileges::detect_capabilities().await;
 
     if capabilities.mode == privileges::ScanMode::Limited {
@@ -311,7 +909,10 @@ pub async fn scan_network_with_progress(
             None,
         );
     } else {
-        tracing::info!("Running with full scan capabilities");
+        tracing::info!(
+            "Running with full scan capabilities (ping engine: {})",
+            capabilities.ping_engine
+        );
     }
 
     // Stage 1: Detect network configuration
@@ -321,14 +922,35 @@ pub async fn scan_network_with_progress(
         Some(5),
         None,
     );
-    let network_info = get_full_network_info().await?;
+    let network_info = get_scan_targets(&scan_config).await?;
+
+    for target in &network_info.targets {
+        tracing::info!(
+            "Network: {} on {} (gateway: {:?})",
+            target.subnet,
+            target.interface,
+            target.gateway_ip
+        );
+    }
 
-    tracing::info!(
-        "Network: {} on {} (gateway: {:?})",
-        network_info.subnet,
-        network_info.interface,
-        network_info.gateway_ip
-    );
+    // Directly attached targets, one per interface, for neighbor discovery
+    let mut link_targets: Vec<&ScanTarget> = Vec::new();
+    for target in network_info.targets.iter().filter(|t| t.is_on_link()) {
+        if !link_targets.iter().any(|t| t.interface == target.interface) {
+            link_targets.push(target);
+        }
+    }
+
+    // Switches announce themselves only every 30-60s, so listen for LLDP/CDP
+    // in the background while the other stages run
+    let lldp_window = std::time::Duration::from_millis(scan_config.lldp.window_ms);
+    let lldp_capture = (scan_config.lldp.enabled
+        && capabilities.can_arp_sweep
+        && !link_targets.is_empty())
+    .then(|| {
+        let interfaces = link_targets.iter().map(|t| t.interface.clone()).collect();
+        tokio::spawn(lldp::capture(interfaces, lldp_window))
+    });
 
     // Stage 2: Read ARP table
     emit_progress(
@@ -340,57 +962,115 @@ pub async fn scan_network_with_progress(
     let mut devices = arp::get_arp_table().await.unwrap_or_default();
     let arp_count = devices.len();
 
+    match arp::get_ipv6_neighbors(&interface_names(&link_targets)).await {
+        Ok(neighbors) => {
+            tracing::info!("Found {} IPv6 neighbor entries", neighbors.len());
+            devices.extend(neighbors_on_targets(neighbors, &link_targets));
+        }
+        Err(e) => tracing::debug!("Failed to read IPv6 neighbors: {}", e),
+    }
+
     emit_progress(
         ScanStage::ReadingArp,
-        &format!("Found {} devices in ARP cache", arp_count),
+        &format!(
+            "Found {} devices in ARP cache ({} IPv6 neighbors)",
+            arp_count,
+            devices.len() - arp_count
+        ),
         Some(15),
         Some(arp_count),
     );
 
+    // Addresses to sweep per target, minus `[scan] exclude`, up to `max_hosts`
+    let sweep_plan = ping::plan_sweep(&network_info.targets, &scan_config);
+
     // Stage 3: Ping sweep
     if capabilities.can_ping {
+        let target_count = network_info.targets.len();
+        for (index, (target, hosts)) in network_info.targets.iter().zip(&sweep_plan).enumerate() {
+            if is_scan_cancelled() {
+                break;
+            }
+
+            emit_progress(
+                ScanStage::PingSweep,
+                &format!(
+                    "Discovering devices on {} (ping sweep)...",
+                    target.subnet
+                ),
+                Some(20 + (30 * index / target_count) as u8),
+                Some(devices.len()),
+            );
+
+            let ping_start = Instant::now();
+            match ping::ping_sweep(hosts, capabilities.ping_engine, &scan_config.ping).await
+            {
+                Ok(pinged_devices) => {
+                    tracing::info!(
+                        "Ping sweep of {} complete: {} responding hosts in {:.1}s",
+                        target.subnet,
+                        pinged_devices.len(),
+                        ping_start.elapsed().as_secs_f64()
+                    );
+
+                    merge_pinged_devices(&mut devices, pinged_devices);
+                }
+                Err(e) => {
+                    tracing::warn!("Ping sweep of {} failed: {}", target.subnet, e);
+                    emit_progress(
+                        ScanStage::PingSweep,
+                        &format!("Ping sweep had issues: {}", e),
+                        None,
+                        Some(devices.len()),
+                    );
+                }
+            }
+        }
+
         emit_progress(
             ScanStage::PingSweep,
-            "Discovering devices on network (ping sweep)...",
-            Some(20),
+            &format!("Discovered {} total devices", devices.len()),
+            Some(50),
             Some(devices.len()),
         );
 
-        let ping_start = Instant::now();
-        match ping::ping_sweep(&network_info.subnet).await {
-            Ok(pinged_devices) => {
-                let ping_duration = ping_start.elapsed();
-                tracing::info!(
-                    "Ping sweep complete: {} responding hosts in {:.1}s",
-                    pinged_devices.len(),
-                    ping_duration.as_secs_f64()
-                );
-
-                for pinged in pinged_devices {
-                    if let Some(existing) = devices.iter_mut().find(|d| d.ip == pinged.ip) {
-                        existing.response_time_ms = pinged.response_time_ms;
-                    } else {
-                        devices.push(pinged);
+        // IPv6 hosts don't answer a sweep, but all of them answer the
+        // all-nodes multicast group. Replies fill the neighbor cache, which is
+        // re-read to pick up their MACs. The multicast probe reaches every
+        // host, so it is skipped when any address is excluded.
+        let probe_targets: &[&ScanTarget] = if scan_config.exclude.is_empty() {
+            &link_targets
+        } else {
+            tracing::debug!("IPv6 all-nodes probe skipped: [scan] exclude is set");
+            &[]
+        };
+        let mut probed: Vec<&ScanTarget> = Vec::new();
+        for target in probe_targets {
+            if is_scan_cancelled() {
+                break;
+            }
+            match ping::probe_all_nodes(&target.interface).await {
+                Ok(responders) => {
+                    for responder in responders {
+                        if let Some(existing) = devices
+                            .iter_mut()
+                            .find(|d| d.has_address(responder.primary_ip()))
+                        {
+                            existing.response_time_ms = responder.response_time_ms;
+                        } else {
+                            devices.push(seen_on(responder, target));
+                        }
                     }
+                    probed.push(target);
                 }
-
-                emit_progress(
-                    ScanStage::PingSweep,
-                    &format!("Discovered {} total devices", devices.len()),
-                    Some(50),
-                    Some(devices.len()),
-                );
-            }
-            Err(e) => {
-                tracing::warn!("Ping sweep failed: {}", e);
-                emit_progress(
-                    ScanStage::PingSweep,
-                    &format!("Ping sweep had issues: {}", e),
-                    Some(50),
-                    Some(devices.len()),
-                );
+                Err(e) => tracing::debug!("IPv6 all-nodes probe failed: {}", e),
             }
         }
+        if !probed.is_empty()
+            && let Ok(neighbors) = arp::get_ipv6_neighbors(&interface_names(&probed)).await
+        {
+            devices.extend(neighbors_on_targets(neighbors, &probed));
+        }
     } else {
         tracing::warn!("Ping sweep skipped: insufficient privileges");
         emit_progress(
@@ -401,42 +1081,176 @@ pub async fn scan_network_with_progress(
         );
     }
 
-    // Ensure local machine is included
-    if let Some(ref local_ip) = network_info.local_ip {
-        let local_hostname = get_local_hostname();
-        if let Some(existing) = devices.iter_mut().find(|d| &d.ip == local_ip) {
-            if existing.hostname.is_none() {
-                existing.hostname = local_hostname;
+    // Stage 3b: Active ARP sweep (finds hosts that drop ICMP)
+    if capabilities.can_arp_sweep && scan_config.arp_sweep.enabled {
+        for (target, hosts) in network_info.targets.iter().zip(&sweep_plan) {
+            if is_scan_cancelled() {
+                break;
+            }
+
+            let Some(local_ip) = target
+                .local_ip
+                .as_deref()
+                .and_then(|ip| ip.parse::<std::net::Ipv4Addr>().ok())
+            else {
+                continue;
+            };
+            if hosts.is_empty() {
+                continue;
+            }
+
+            emit_progress(
+                ScanStage::ArpSweep,
+                &format!(
+                    "Probing {} addresses on {} with ARP...",
+                    hosts.len(),
+                    target.interface
+                ),
+                Some(51),
+                Some(devices.len()),
+            );
+
+            match arp_sweep::arp_sweep(&target.interface, local_ip, hosts.clone(), &scan_config.arp_sweep)
+                .await
+            {
+                Ok(replies) => {
+                    let mut new_count = 0;
+                    for (ip, mac, rtt) in replies {
+                        let ip = ip.to_string();
+                        if let Some(existing) = devices.iter_mut().find(|d| d.has_address(&ip)) {
+                            if existing.mac.is_none() {
+                                existing.mac = Some(mac);
+                            }
+                            if existing.response_time_ms.unwrap_or(0.0) == 0.0 {
+                                existing.response_time_ms = Some(rtt);
+                            }
+                        } else {
+                            new_count += 1;
+                            devices.push(Device {
+                                mac: Some(mac),
+                                response_time_ms: Some(rtt),
+                                ..Device::new(ip)
+                            });
+                        }
+                    }
+
+                    emit_progress(
+                        ScanStage::ArpSweep,
+                        &format!(
+                            "ARP sweep found {} additional devices on {}",
+                            new_count, target.interface
+                        ),
+                        Some(54),
+                        Some(devices.len()),
+                    );
+                }
+                Err(e) => {
+                    tracing::warn!("ARP sweep on {} failed: {}", target.interface, e);
+                }
+            }
+        }
+    }
+
+    // Ensure local machine is included on every attached network
+    let local_hostname = get_local_hostname();
+    for target in &network_info.targets {
+        let Some(ref local_ip) = target.local_ip else {
+            continue;
+        };
+        if let Some(existing) = devices.iter_mut().find(|d| d.has_address(local_ip)) {
+            if existing.hostname.is_none() && local_hostname.is_some() {
+                existing.hostname = local_hostname.clone();
+                existing.hostname_source = Some(HostnameSource::Local);
             }
             if existing.response_time_ms.is_none() {
                 existing.response_time_ms = Some(0.0);
             }
         } else {
             devices.push(Device {
-                ip: local_ip.clone(),
-                mac: None,
                 response_time_ms: Some(0.0),
-                hostname: local_hostname,
-                vendor: None,
-                device_type: None,
+                hostname: local_hostname.clone(),
+                hostname_source: local_hostname.as_ref().map(|_| HostnameSource::Local),
+                ..Device::new(local_ip.clone())
             });
         }
     }
 
-    // Stage 4: Hostname resolution
+    // Record where each device was seen, then combine IPv4 and IPv6 records
+    // of the same host on the same network
+    assign_scan_targets(&mut devices, &network_info);
+    let mut devices = merge_devices_by_mac(devices);
+
+    // Stage 4: mDNS / DNS-SD browse, then SSDP / UPnP discovery
+    let local_ipv4s: Vec<std::net::Ipv4Addr> = link_targets
+        .iter()
+        .filter_map(|t| t.local_ip.as_deref()?.parse().ok())
+        .collect();
+    if scan_config.mdns.enabled && !local_ipv4s.is_empty() && !is_scan_cancelled() {
+        emit_progress(
+            ScanStage::BrowsingMdns,
+            "Browsing mDNS/DNS-SD advertisements...",
+            Some(55),
+            Some(devices.len()),
+        );
+
+        let hosts = mdns::browse(&local_ipv4s, &scan_config.mdns).await;
+        let advertised = hosts.len();
+        apply_mdns_hosts(&mut devices, hosts, &network_info);
+
+        emit_progress(
+            ScanStage::BrowsingMdns,
+            &format!("{} devices advertised over mDNS", advertised),
+            Some(57),
+            Some(devices.len()),
+        );
+    }
+
+    if scan_config.ssdp.enabled && !local_ipv4s.is_empty() && !is_scan_cancelled() {
+        emit_progress(
+            ScanStage::SsdpDiscovery,
+            "Discovering UPnP devices over SSDP...",
+            Some(57),
+            Some(devices.len()),
+        );
+
+        // Descriptions are fetched over HTTP, so only from hosts we may probe
+        let exclude = scan_config.exclude_networks();
+        let in_scope =
+            |ip: IpAddr| network_info.contains(ip) && !exclude.iter().any(|net| net.contains(ip));
+        let described = ssdp::discover(&local_ipv4s, &scan_config.ssdp, in_scope).await;
+        let upnp_count = described.len();
+        apply_upnp_devices(&mut devices, described, &network_info);
+
+        emit_progress(
+            ScanStage::SsdpDiscovery,
+            &format!("{} UPnP devices described", upnp_count),
+            Some(59),
+            Some(devices.len()),
+        );
+    }
+
+    if scan_config.dhcp.enabled {
+        apply_dhcp_fingerprints(&mut devices);
+    }
+
+    // Drop out-of-target and `[scan] exclude` devices before NetBIOS, LLMNR,
+    // SNMP or the port scan send them anything
+    let mut devices = filter_devices_to_targets(devices, &network_info, &scan_config);
+
+    // Stage 5: Hostname resolution for devices mDNS did not name
     if !devices.is_empty() {
         emit_progress(
             ScanStage::ResolvingHostnames,
             &format!(
                 "Resolving hostnames for {} devices (may take a moment)...",
-                devices.len()
+                devices.iter().filter(|d| d.hostname.is_none()).count()
             ),
-            Some(55),
+            Some(60),
             Some(devices.len()),
         );
 
         let dns_start = Instant::now();
-        resolve_hostnames_fast(&mut devices).await;
+        resolve_hostnames_fast(&mut devices, &network_info, &scan_config.resolvers).await;
         let resolved_count = devices.iter().filter(|d| d.hostname.is_some()).count();
 
         emit_progress(
@@ -452,11 +1266,123 @@ pub async fn scan_network_with_progress(
         );
     }
 
-    // Deduplicate and enrich
-    let mut devices = deduplicate_devices_by_ip(devices);
-    enrich_devices_with_vendor(&mut devices);
+    // Enrich
+    let vendors = oui::load_database_or_builtin(&scan_config.oui);
+    enrich_devices_with_vendor(&mut devices, &vendors);
+    // Classified twice: now so SNMP knows which devices are switches and
+    // printers, and again once ports, SNMP and the OS guess are known
+    let rules = classify::load_rules_or_builtin(&scan_config.classify);
+    rules.apply(&mut devices);
 
-    // Stage 5: Complete
+    if scan_config.snmp.enabled && !devices.is_empty() && !is_scan_cancelled() {
+        emit_progress(
+            ScanStage::SnmpQuery,
+            &format!("Querying SNMP agents on {} devices...", devices.len()),
+            Some(95),
+            Some(devices.len()),
+        );
+        snmp::collect(&mut devices, &scan_config.snmp, &rules).await;
+        let snmp_count = devices.iter().filter(|d| d.snmp.is_some()).count();
+        emit_progress(
+            ScanStage::SnmpQuery,
+            &format!("{} devices answered SNMP", snmp_count),
+            Some(96),
+            Some(devices.len()),
+        );
+    }
+
+    // Stage 6: Optional TCP port scan
+    if scan_config.port_scan.enabled && !devices.is_empty() && !is_scan_cancelled() {
+        let ports = scan_config.port_list();
+        emit_progress(
+            ScanStage::PortScan,
+            &format!(
+                "Probing {} TCP ports on {} devices...",
+                ports.len(),
+                devices.len()
+            ),
+            Some(96),
+            Some(devices.len()),
+        );
+
+        // Link-local IPv6 addresses need a scope id to connect; skip them
+        let hosts: Vec<String> = devices
+            .iter()
+            .map(|d| d.primary_ip().to_string())
+            .filter(|ip| match ip.parse::<IpAddr>() {
+                Ok(IpAddr::V6(v6)) => !is_ipv6_link_local(&v6),
+                Ok(IpAddr::V4(_)) => true,
+                Err(_) => false,
+            })
+            .collect();
+        let mut open = ports::scan_ports(&hosts, &ports, &scan_config.port_scan).await;
+        for device in devices.iter_mut() {
+            if let Some(found) = open.remove(device.primary_ip()) {
+                device.open_ports = found.open;
+                device.tcp_fingerprint = found.tcp;
+            }
+        }
+
+        let open_count: usize = devices.iter().map(|d| d.open_ports.len()).sum();
+        emit_progress(
+            ScanStage::PortScan,
+            &format!("Found {} open ports", open_count),
+            Some(97),
+            Some(devices.len()),
+        );
+
+        if scan_config.services.enabled && open_count > 0 && !is_scan_cancelled() {
+            emit_progress(
+                ScanStage::ServiceDetection,
+                "Reading service banners and certificates...",
+                Some(98),
+                Some(devices.len()),
+            );
+            services::collect_services(&mut devices, &scan_config.services).await;
+            let service_count: usize = devices.iter().map(|d| d.services.len()).sum();
+            emit_progress(
+                ScanStage::ServiceDetection,
+                &format!("Identified {} services", service_count),
+                Some(99),
+                Some(devices.len()),
+            );
+        }
+    }
+
+    os_guess::apply(&mut devices);
+    rules.apply(&mut devices);
+
+    let mut topology = Vec::new();
+    if let Some(capture) = lldp_capture {
+        let remaining = lldp_window.saturating_sub(scan_start.elapsed());
+        if !remaining.is_zero() {
+            emit_progress(
+                ScanStage::LldpCapture,
+                &format!(
+                    "Listening for LLDP/CDP announcements ({}s left)...",
+                    remaining.as_secs()
+                ),
+                Some(99),
+                Some(devices.len()),
+            );
+        }
+        topology = capture.await.unwrap_or_default();
+        if !topology.is_empty() {
+            emit_progress(
+                ScanStage::LldpCapture,
+                &format!("Heard {} LLDP/CDP neighbors", topology.len()),
+                Some(99),
+                Some(devices.len()),
+            );
+        }
+    }
+
+    let now = Utc::now();
+    for device in devices.iter_mut() {
+        device.last_seen = Some(now);
+    }
+
+    // Stage 7: Complete
     let total_duration = scan_start.elapsed();
     emit_progress(
         ScanStage::Complete,
@@ -473,6 +1399,7 @@ pub async fn scan_network_with_progress(
         devices,
         network_info,
         capabilities,
+        topology,
     })
 }
 
@@ -484,11 +1411,36 @@ pub async fn scan_network_devices_only() -> Result<Vec<Device>> {
 
 pub async fn get_network_info() -> Result<String> {
     let info = get_full_network_info().await?;
-    Ok(format!("{} ({})", info.subnet, info.interface))
+    Ok(info
+        .targets
+        .iter()
+        .map(|t| format!("{} ({})", t.subnet, t.interface))
+        .collect::<Vec<_>>()
+        .join(", "))
 }
 
-/// Get full network information including gateway IP
+/// Get the networks to scan: every non-loopback interface with an IPv4
+/// address, narrowed by `[scan] interfaces` and `[scan] include`.
 pub async fn get_full_network_info() -> Result<NetworkInfo> {
+    get_scan_targets(&config::load_validated_scan_config()?).await
+}
+
+async fn get_scan_targets(scan_config: &ScanConfig) -> Result<NetworkInfo> {
+    let detected = detect_interface_targets().await?;
+    let targets = interfaces::select_targets(scan_config, detected)?;
+
+    if targets.is_empty() {
+        return Err(anyhow::anyhow!(
+            "No IPv4 network found on any non-loopback interface; \
+             set [scan] include in config.toml to scan a subnet explicitly"
+        ));
+    }
+
+    Ok(NetworkInfo { targets })
+}
+
+/// One target per IPv4 subnet on every non-loopback interface that is up.
+async fn detect_interface_targets() -> Result<Vec<ScanTarget>> {
     #[cfg(target_os = "windows")]
     {
         get_windows_network_info_full().await
@@ -510,32 +1462,69 @@ pub async fn get_full_network_info() -> Result<NetworkInfo> {
     }
 }
 
-/// Fast hostname resolution using DNS with high parallelism.
-async fn resolve_hostnames_fast(devices: &mut [Device]) {
-    use tokio::time::{timeout, Duration};
+/// Name unnamed devices from the hosts file; returns the addresses of the
+/// ones still unnamed
+fn apply_hosts_file(
+    devices: &mut [Device],
+    hosts_file: &std::collections::HashMap<IpAddr, String>,
+) -> Vec<IpAddr> {
+    let mut unnamed = Vec::new();
+    for device in devices.iter_mut().filter(|d| d.hostname.is_none()) {
+        let Ok(ip) = device.primary_ip().parse::<IpAddr>() else {
+            continue;
+        };
+        match hosts_file.get(&ip) {
+            Some(name) => {
+                device.hostname = Some(name.clone());
+                device.hostname_source = Some(HostnameSource::Hosts);
+            }
+            None => unnamed.push(ip),
+        }
+    }
+    unnamed
+}
 
-    const BATCH_SIZE: usize = 32;
+/// Fast hostname resolution: the hosts file and reverse DNS for every
+/// device at once, then NetBIOS and LLMNR in batches for the rest.
+async fn resolve_hostnames_fast(
+    devices: &mut [Device],
+    network_info: &NetworkInfo,
+    resolvers: &config::ResolverConfig,
+) {
+    use tokio::time::{Duration, Instant};
 
-    #[cfg(target_os = "windows")]
-    const TIMEOUT_MS: u64 = 5000;
-    #[cfg(not(target_os = "windows"))]
+    const BATCH_SIZE: usize = 32;
     const TIMEOUT_MS: u64 = 2000;
+    /// All reverse DNS lookups together
+    const DNS_DEADLINE_MS: u64 = 5000;
+
+    // Hosts file entries, then reverse DNS for everything else in parallel
+    let lookups = apply_hosts_file(devices, &rdns::hosts_file_names());
+
+    let resolver = rdns::ReverseResolver::system(&network_info.gateway_ips());
+    let deadline = Instant::now() + Duration::from_millis(DNS_DEADLINE_MS);
+    let names = resolver.resolve_all(&lookups, deadline).await;
+    for device in devices.iter_mut().filter(|d| d.hostname.is_none()) {
+        if let Some(name) = device.primary_ip().parse().ok().and_then(|ip| names.get(&ip)) {
+            device.hostname = Some(name.clone());
+            device.hostname_source = Some(HostnameSource::Dns);
+        }
+    }
+
+    if !resolvers.netbios && !resolvers.llmnr {
+        return;
+    }
 
     for chunk in devices.chunks_mut(BATCH_SIZE) {
         let futures: Vec<_> = chunk
             .iter()
+            .filter(|d| d.hostname.is_none())
             .map(|d| {
-                let ip = d.ip.clone();
+                let ip = d.primary_ip().to_string();
                 async move {
-                    match timeout(
-                        Duration::from_millis(TIMEOUT_MS),
-                        resolve_hostname_fast(&ip),
-                    )
-                    .await
-                    {
-                        Ok(hostname) => (ip, hostname),
-                        Err(_) => (ip, None),
-                    }
+                    let hostname =
+                        resolve_hostname(&ip, resolvers, Duration::from_millis(TIMEOUT_MS)).await;
+                    (ip, hostname)
                 }
             })
             .collect();
@@ -543,129 +1532,58 @@ async fn resolve_hostnames_fast(devices: &mut [Device]) {
         let results = futures::future::join_all(futures).await;
 
         for (ip, hostname) in results {
-            if let Some(device) = chunk.iter_mut().find(|d| d.ip == ip) {
-                if hostname.is_some() {
-                    device.hostname = hostname;
-                }
+            if let Some(device) = chunk.iter_mut().find(|d| d.primary_ip() == ip)
+                && let Some((hostname, source)) = hostname
+            {
+                device.hostname = Some(hostname);
+                device.hostname_source = Some(source);
             }
         }
     }
 }
 
-/// Fast hostname resolution using system DNS resolver.
-async fn resolve_hostname_fast(ip: &str) -> Option<String> {
-    let ip_owned = ip.to_string();
-    tokio::task::spawn_blocking(move || {
-        #[cfg(any(target_os = "linux", target_os = "macos"))]
-        {
-            // Method 1: getent hosts
-            if let Ok(output) = hidden_command_sync("getent")
-                .args(["hosts", &ip_owned])
-                .output()
-            {
-                if output.status.success() {
-                    let out = String::from_utf8_lossy(&output.stdout);
-                    if let Some(hostname) = out.split_whitespace().nth(1) {
-                        if !hostname.is_empty() {
-                            return Some(hostname.to_string());
-                        }
-                    }
-                }
-            }
-
-            // Method 2: host command
-            if let Ok(output) = hidden_command_sync("host").arg(&ip_owned).output() {
-                if output.status.success() {
-                    let out = String::from_utf8_lossy(&output.stdout);
-                    if let Some(hostname) = out.split("pointer").nth(1) {
-                        let hostname = hostname.trim().trim_end_matches('.');
-                        if !hostname.is_empty() {
-                            return Some(hostname.to_string());
-                        }
-                    }
-                }
-            }
-
-            // Method 3: avahi-resolve on Linux
-            #[cfg(target_os = "linux")]
-            if let Ok(output) = hidden_command_sync("avahi-resolve")
-                .args(["-a", &ip_owned])
-                .output()
-            {
-                if output.status.success() {
-                    let out = String::from_utf8_lossy(&output.stdout);
-                    if let Some(hostname) = out.split_whitespace().nth(1) {
-                        if !hostname.is_empty() {
-                            return Some(hostname.to_string());
-                        }
-                    }
-                }
-            }
+/// Query NetBIOS and LLMNR at once, each within `limit`, preferring the
+/// NetBIOS name.
+async fn resolve_hostname(
+    ip: &str,
+    resolvers: &config::ResolverConfig,
+    limit: std::time::Duration,
+) -> Option<(String, HostnameSource)> {
+    let address: IpAddr = ip.parse().ok()?;
+    let netbios = async {
+        if resolvers.netbios {
+            netbios::node_status(address, limit).await
+        } else {
+            None
         }
-
-        #[cfg(target_os = "windows")]
-        {
-            // Method 1: PowerShell Resolve-DnsName
-            if let Ok(output) = hidden_command_sync("powershell")
-                .args([
-                    "-NoProfile",
-                    "-ExecutionPolicy",
-                    "Bypass",
-                    "-Command",
-                    &format!(
-                        "try {{ (Resolve-DnsName -Name '{}' -Type PTR -ErrorAction Stop).NameHost }} catch {{ }}",
-                        ip_owned
-                    ),
-                ])
-                .output()
-            {
-                if output.status.success() {
-                    let out = String::from_utf8_lossy(&output.stdout).trim().to_string();
-                    if !out.is_empty() && !out.contains("error") && !out.contains(&ip_owned) {
-                        return Some(out);
-                    }
-                }
-            }
-
-            // Method 2: nbtstat for NetBIOS names
-            if let Ok(output) = hidden_command_sync("nbtstat")
-                .args(["-A", &ip_owned])
-                .output()
-            {
-                let out = String::from_utf8_lossy(&output.stdout);
-                for line in out.lines() {
-                    let trimmed = line.trim();
-                    if trimmed.contains("<00>") && trimmed.contains("UNIQUE") {
-                        if let Some(name) = trimmed.split_whitespace().next() {
-                            if !name.is_empty() {
-                                return Some(name.to_string());
-                            }
-                        }
-                    }
-                }
-            }
+    };
+    let llmnr = async {
+        if resolvers.llmnr {
+            llmnr::reverse_lookup(address, limit).await
+        } else {
+            None
         }
+    };
 
-        None
-    })
-    .await
-    .ok()
-    .flatten()
+    let (netbios, llmnr) = tokio::join!(netbios, llmnr);
+    netbios
+        .map(|name| (name, HostnameSource::Netbios))
+        .or_else(|| llmnr.map(|name| (name, HostnameSource::Llmnr)))
 }
 
 // Platform-specific network info implementations
 #[cfg(target_os = "windows")]
-async fn get_windows_network_info_full() -> Result<NetworkInfo> {
-    if let Ok(info) = get_windows_network_info_ipconfig().await {
-        if !info.subnet.starts_with("0.0.0.0") && !info.subnet.is_empty() {
-            return Ok(info);
+async fn get_windows_network_info_full() -> Result<Vec<ScanTarget>> {
+    if let Ok(targets) = get_windows_network_info_ipconfig().await {
+        if !targets.is_empty() {
+            return Ok(targets);
         }
     }
     get_windows_network_info_powershell().await
 }
 
 #[cfg(target_os = "windows")]
-async fn get_windows_network_info_powershell() -> Result<NetworkInfo> {
+async fn get_windows_network_info_powershell() -> Result<Vec<ScanTarget>> {
     let output = hidden_command("powershell")
         .args(["-NoProfile", "-ExecutionPolicy", "Bypass", "-Command", r#"
             $virtualPatterns = @('vEthernet', 'WSL', 'Hyper-V', 'VirtualBox', 'VMware', 'Docker', 'Loopback', 'Tailscale')
@@ -739,12 +1657,13 @@ async fn get_windows_network_info_powershell() -> Result<NetworkInfo> {
     if output_str.contains('|') {
         let parts: Vec<&str> = output_str.split('|').collect();
         if parts.len() >= 2 {
-            return Ok(NetworkInfo {
+            return Ok(vec![ScanTarget {
                 interface: parts[0].to_string(),
                 subnet: parts[1].to_string(),
                 gateway_ip: parts.get(2).map(|s| s.to_string()).filter(|s| !s.is_empty()),
                 local_ip: parts.get(3).map(|s| s.to_string()).filter(|s| !s.is_empty()),
-            });
+                ipv6_prefixes: Vec::new(),
+            }]);
         }
     }
 
@@ -752,7 +1671,7 @@ async fn get_windows_network_info_powershell() -> Result<NetworkInfo> {
 }
 
 #[cfg(target_os = "windows")]
-async fn get_windows_network_info_ipconfig() -> Result<NetworkInfo> {
+async fn get_windows_network_info_ipconfig() -> Result<Vec<ScanTarget>> {
     let output = hidden_command("ipconfig")
         .output()
         .context("Failed to run ipconfig")?;
@@ -833,98 +1752,49 @@ async fn get_windows_network_info_ipconfig() -> Result<NetworkInfo> {
         adapters.push(current);
     }
 
-    let best_adapter = adapters
+    // Physical adapters with a default gateway first
+    adapters.sort_by_key(|a| a.gateway.is_none());
+
+    let targets = adapters
         .iter()
-        .find(|a| !a.is_virtual && a.ip.is_some() && a.gateway.is_some())
-        .or_else(|| adapters.iter().find(|a| !a.is_virtual && a.ip.is_some()));
-
-    if let Some(adapter) = best_adapter {
-        if let (Some(ip_str), Some(mask_str)) = (&adapter.ip, &adapter.mask) {
-            if let (Ok(ip), Ok(mask)) = (
-                ip_str.parse::<std::net::Ipv4Addr>(),
-                mask_str.parse::<std::net::Ipv4Addr>(),
-            ) {
-                let ip_u32 = u32::from(ip);
-                let mask_u32 = u32::from(mask);
-                let network_u32 = ip_u32 & mask_u32;
-                let network = std::net::Ipv4Addr::from(network_u32);
-                let prefix = mask_u32.count_ones();
-
-                return Ok(NetworkInfo {
-                    interface: adapter.name.clone(),
-                    subnet: format!("{}/{}", network, prefix),
-                    gateway_ip: adapter.gateway.clone(),
-                    local_ip: adapter.ip.clone(),
-                });
-            }
-        }
-    }
+        .filter(|a| !a.is_virtual)
+        .filter_map(|adapter| {
+            let ip = adapter.ip.as_ref()?.parse::<std::net::Ipv4Addr>().ok()?;
+            let mask = adapter.mask.as_ref()?.parse::<std::net::Ipv4Addr>().ok()?;
+            let network = ipnetwork::Ipv4Network::with_netmask(ip, mask).ok()?;
+
+            Some(ScanTarget {
+                interface: adapter.name.clone(),
+                subnet: format!("{}/{}", network.network(), network.prefix()),
+                gateway_ip: adapter.gateway.clone(),
+                local_ip: adapter.ip.clone(),
+                ipv6_prefixes: Vec::new(),
+            })
+        })
+        .collect();
 
-    Ok(NetworkInfo {
-        interface: "Ethernet".to_string(),
-        subnet: "192.168.1.0/24".to_string(),
-        gateway_ip: Some("192.168.1.1".to_string()),
-        local_ip: None,
-    })
+    Ok(targets)
 }
 
 #[cfg(target_os = "linux")]
-async fn get_linux_network_info_full() -> Result<NetworkInfo> {
+async fn get_linux_network_info_full() -> Result<Vec<ScanTarget>> {
     let route_output = hidden_command("ip")
         .args(["route", "show", "default"])
         .output()
         .context("Failed to run ip route command")?;
-
-    let route_str = String::from_utf8_lossy(&route_output.stdout);
-
-    let gateway_ip = route_str
-        .split_whitespace()
-        .skip_while(|&s| s != "via")
-        .nth(1)
-        .map(|s| s.to_string());
-
-    let interface = route_str
-        .split_whitespace()
-        .skip_while(|&s| s != "dev")
-        .nth(1)
-        .unwrap_or("eth0")
-        .to_string();
+    let default_routes = interfaces::parse_default_routes(&String::from_utf8_lossy(&route_output.stdout));
 
     let addr_output = hidden_command("ip")
-        .args(["addr", "show", &interface])
+        .args(["-o", "addr", "show", "up"])
         .output()
         .context("Failed to run ip addr command")?;
+    let addresses = interfaces::parse_ip_addr_oneline(&String::from_utf8_lossy(&addr_output.stdout));
 
-    let addr_str = String::from_utf8_lossy(&addr_output.stdout);
-
-    for line in addr_str.lines() {
-        let trimmed = line.trim();
-        if trimmed.starts_with("inet ") && !trimmed.contains("127.0.0.1") {
-            if let Some(cidr) = trimmed.split_whitespace().nth(1) {
-                if let Ok(network) = cidr.parse::<ipnetwork::IpNetwork>() {
-                    let local_ip = network.ip().to_string();
-                    let subnet = format!("{}/{}", network.network(), network.prefix());
-                    return Ok(NetworkInfo {
-                        interface,
-                        subnet,
-                        gateway_ip,
-                        local_ip: Some(local_ip),
-                    });
-                }
-            }
-        }
-    }
-
-    Ok(NetworkInfo {
-        interface,
-        subnet: "192.168.1.0/24".to_string(),
-        gateway_ip,
-        local_ip: None,
-    })
+    Ok(interfaces::targets_from_interfaces(addresses, &default_routes))
 }
 
 #[cfg(target_os = "macos")]
-async fn get_macos_network_info_full() -> Result<NetworkInfo> {
+async fn get_macos_network_info_full() -> Result<Vec<ScanTarget>> {
     let route_output = hidden_command("route")
         .args(["-n", "get", "default"])
         .output()
@@ -938,52 +1808,29 @@ async fn get_macos_network_info_full() -> Result<NetworkInfo> {
         .and_then(|line| line.split(':').nth(1))
         .map(|s| s.trim().to_string());
 
-    let interface = route_str
+    let default_routes: Vec<(String, Option<String>)> = route_str
         .lines()
         .find(|line| line.contains("interface:"))
         .and_then(|line| line.split(':').nth(1))
-        .map(|s| s.trim().to_string())
-        .unwrap_or_else(|| "en0".to_string());
+        .map(|interface| vec![(interface.trim().to_string(), gateway_ip)])
+        .unwrap_or_default();
 
     let ifconfig_output = hidden_command("ifconfig")
-        .arg(&interface)
         .output()
         .context("Failed to run ifconfig command")?;
+    let addresses = interfaces::parse_ifconfig(&String::from_utf8_lossy(&ifconfig_output.stdout));
 
-    let ifconfig_str = String::from_utf8_lossy(&ifconfig_output.stdout);
-
-    for line in ifconfig_str.lines() {
-        let trimmed = line.trim();
-        if trimmed.starts_with("inet ") && !trimmed.contains("127.0.0.1") {
-            let parts: Vec<&str> = trimmed.split_whitespace().collect();
-            if let (Some(ip), Some(mask)) = (parts.get(1), parts.get(3)) {
-                if let Ok(mask_int) = u32::from_str_radix(mask.trim_start_matches("0x"), 16) {
-                    let prefix = mask_int.count_ones();
-                    let ip_addr: std::net::Ipv4Addr = ip.parse().unwrap_or([192, 168, 1, 1].into());
-                    let mask_addr = std::net::Ipv4Addr::from(mask_int);
-                    let network_int = u32::from(ip_addr) & u32::from(mask_addr);
-                    let network = std::net::Ipv4Addr::from(network_int);
-                    return Ok(NetworkInfo {
-                        interface,
-                        subnet: format!("{}/{}", network, prefix),
-                        gateway_ip,
-                        local_ip: Some(ip.to_string()),
-                    });
-                }
-            }
-        }
-    }
-
-    Ok(NetworkInfo {
-        interface,
-        subnet: "192.168.1.0/24".to_string(),
-        gateway_ip,
-        local_ip: None,
-    })
+    Ok(interfaces::targets_from_interfaces(addresses, &default_routes))
 }
 
 /// Ping a single device and return response time in ms if successful.
+/// Nothing is sent while the `[scan]` section is invalid, since its
+/// `exclude` list cannot be trusted.
 pub async fn ping_device(ip: &str) -> Result<f64> {
+    if config::load_validated_scan_config()?.is_excluded(ip) {
+        return Err(anyhow::anyhow!("{} is excluded from probing by [scan] exclude", ip));
+    }
+
     let ip_owned = ip.to_string();
 
     let result = tokio::task::spawn_blocking(move || {
@@ -1068,7 +1915,7 @@ fn parse_ping_time(output: &str) -> Option<f64> {
 /// Get a set of all IP addresses currently in the ARP table.
 pub async fn get_arp_table_ips() -> HashSet<String> {
     match arp::get_arp_table().await {
-        Ok(devices) => devices.into_iter().map(|d| d.ip).collect(),
+        Ok(devices) => devices.into_iter().flat_map(|d| d.addresses).collect(),
         Err(_) => HashSet::new(),
     }
 }
@@ -1090,3 +1937,252 @@ pub async fn check_device_reachable(ip: &str, arp_ips: &HashSet<String>) -> Resu
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(interface: &str, subnet: &str, ipv6_prefixes: &[&str]) -> ScanTarget {
+        ScanTarget {
+            interface: interface.to_string(),
+            subnet: subnet.to_string(),
+            gateway_ip: None,
+            local_ip: None,
+            ipv6_prefixes: ipv6_prefixes.iter().map(|p| p.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_hosts_file_names_are_tagged() {
+        let hosts_file = [("192.168.1.5".parse().unwrap(), "nas.lan".to_string())].into();
+        let mut devices = vec![
+            Device::new("192.168.1.5"),
+            Device::new("192.168.1.6"),
+            Device {
+                hostname: Some("printer".to_string()),
+                hostname_source: Some(HostnameSource::Mdns),
+                ..Device::new("192.168.1.7")
+            },
+        ];
+
+        let unnamed = apply_hosts_file(&mut devices, &hosts_file);
+        assert_eq!(unnamed, ["192.168.1.6".parse::<IpAddr>().unwrap()]);
+        assert_eq!(devices[0].hostname.as_deref(), Some("nas.lan"));
+        assert_eq!(devices[0].hostname_source, Some(HostnameSource::Hosts));
+        assert_eq!(devices[2].hostname_source, Some(HostnameSource::Mdns));
+        assert_eq!(HostnameSource::Hosts.to_string(), "hosts");
+    }
+
+    #[test]
+    fn test_neighbors_keep_interface_and_state() {
+        let eth0 = target("eth0", "192.168.1.0/24", &["2001:db8:1::/64"]);
+        let wlan0 = target("wlan0", "10.0.0.0/24", &[]);
+        let neighbor = |ip: &str, interface: &str| Device {
+            mac: Some("3c:52:82:1a:2b:3c".to_string()),
+            interface: Some(interface.to_string()),
+            neighbor_state: Some(NeighborState::Stale),
+            ..Device::new(ip)
+        };
+
+        let found = neighbors_on_targets(
+            vec![
+                neighbor("fe80::1", "wlan0"),
+                neighbor("2001:db8:1::20", "eth0"),
+                neighbor("fe80::2", "docker0"),
+            ],
+            &[&eth0, &wlan0],
+        );
+        let seen: Vec<(&str, Option<&str>)> = found
+            .iter()
+            .map(|d| (d.primary_ip(), d.subnet.as_deref()))
+            .collect();
+        assert_eq!(
+            seen,
+            [
+                ("fe80::1", Some("10.0.0.0/24")),
+                ("2001:db8:1::20", Some("192.168.1.0/24"))
+            ]
+        );
+
+        // The IPv4 ARP entry keeps the state when merged with a ping reply
+        let mut pinged = Device {
+            response_time_ms: Some(1.0),
+            ..Device::new("192.168.1.20")
+        };
+        pinged.merge(neighbor("192.168.1.20", "eth0"));
+        assert_eq!(pinged.neighbor_state, Some(NeighborState::Stale));
+        assert_eq!(pinged.interface.as_deref(), Some("eth0"));
+    }
+
+    #[test]
+    fn test_merge_devices_by_mac() {
+        let devices = vec![
+            Device {
+                mac: Some("a0:63:91:12:34:56".to_string()),
+                ..Device::new("fe80::a263:91ff:fe12:3456")
+            },
+            Device {
+                mac: Some("A0-63-91-12-34-56".to_string()),
+                ..Device::new("192.168.1.1")
+            },
+            Device {
+                response_time_ms: Some(1.5),
+                ..Device::new("192.168.1.1")
+            },
+            Device {
+                mac: Some("a0:63:91:12:34:56".to_string()),
+                ..Device::new("2001:db8:1::1")
+            },
+            Device::new("192.168.1.50"),
+        ];
+
+        let merged = merge_devices_by_mac(devices);
+        assert_eq!(merged.len(), 2);
+
+        let router = &merged[0];
+        assert_eq!(
+            router.addresses,
+            vec!["192.168.1.1", "2001:db8:1::1", "fe80::a263:91ff:fe12:3456"]
+        );
+        assert_eq!(router.primary_ip(), "192.168.1.1");
+        assert_eq!(router.response_time_ms, Some(1.5));
+        assert_eq!(router.ipv6_addresses().count(), 2);
+
+        assert_eq!(merged[1].primary_ip(), "192.168.1.50");
+    }
+
+    #[test]
+    fn test_ping_reply_ttl_reaches_os_guess() {
+        // Known from the ARP cache, then answering the sweep
+        let mut devices = vec![Device {
+            mac: Some("02:00:00:00:00:01".to_string()),
+            ..Device::new("192.168.1.20")
+        }];
+        let pinged = vec![
+            Device {
+                response_time_ms: Some(2.5),
+                ttl: Some(128),
+                ..Device::new("192.168.1.20")
+            },
+            Device {
+                response_time_ms: Some(1.0),
+                ttl: Some(64),
+                ..Device::new("192.168.1.30")
+            },
+        ];
+
+        merge_pinged_devices(&mut devices, pinged);
+        assert_eq!(devices.len(), 2);
+        assert_eq!(devices[0].response_time_ms, Some(2.5));
+        assert_eq!(devices[0].ttl, Some(128));
+
+        os_guess::apply(&mut devices);
+        let guess = devices[0].os_guess.as_ref().unwrap();
+        assert_eq!(guess.family, dhcp::OsFamily::Windows);
+        assert_eq!(guess.evidence, vec!["ttl 128"]);
+
+        // A reply without a TTL keeps the one already known
+        merge_pinged_devices(&mut devices, vec![Device::new("192.168.1.20")]);
+        assert_eq!(devices[0].ttl, Some(128));
+    }
+
+    #[test]
+    fn test_device_deserializes_legacy_ip() {
+        let device: Device =
+            serde_json::from_str(r#"{"ip":"192.168.1.5","mac":null,"responseTimeMs":null,"hostname":null,"vendor":null,"deviceType":null}"#)
+                .unwrap();
+        assert_eq!(device.addresses, vec!["192.168.1.5"]);
+
+        let json = serde_json::to_value(&device).unwrap();
+        assert_eq!(json["addresses"], serde_json::json!(["192.168.1.5"]));
+    }
+
+    #[test]
+    fn test_filter_devices_to_targets_dual_stack() {
+        let devices = vec![
+            Device {
+                addresses: vec![
+                    "192.168.1.10".to_string(),
+                    "2001:db8:1::10".to_string(),
+                    "2001:db8:ffff::10".to_string(),
+                    "fe80::10".to_string(),
+                ],
+                ..Default::default()
+            },
+            Device::new("fe80::20"),
+            Device::new("172.17.0.2"),
+        ];
+
+        let eth0 = target("eth0", "192.168.1.0/24", &["2001:db8:1::/64"]);
+        let mut devices = devices;
+        devices[1] = seen_on(devices[1].clone(), &eth0);
+        let network_info = NetworkInfo {
+            targets: vec![eth0],
+        };
+
+        assign_scan_targets(&mut devices, &network_info);
+        let filtered = filter_devices_to_targets(devices, &network_info, &ScanConfig::default());
+        assert_eq!(filtered.len(), 2);
+        assert_eq!(
+            filtered[0].addresses,
+            vec!["192.168.1.10", "2001:db8:1::10", "fe80::10"]
+        );
+        assert_eq!(filtered[1].primary_ip(), "fe80::20");
+    }
+
+    #[test]
+    fn test_devices_carry_scan_target() {
+        let network_info = NetworkInfo {
+            targets: vec![
+                target("eth0", "192.168.1.0/24", &["2001:db8:1::/64"]),
+                target("eth1", "10.20.0.0/24", &[]),
+            ],
+        };
+
+        // Same router MAC on both VLANs
+        let mut devices = vec![
+            Device {
+                mac: Some("a0:63:91:12:34:56".to_string()),
+                ..Device::new("192.168.1.1")
+            },
+            Device {
+                mac: Some("a0:63:91:12:34:56".to_string()),
+                ..Device::new("10.20.0.1")
+            },
+            Device::new("2001:db8:1::40"),
+            seen_on(Device::new("fe80::50"), &network_info.targets[1]),
+            Device::new("172.17.0.2"),
+        ];
+
+        assign_scan_targets(&mut devices, &network_info);
+        let scan_config = ScanConfig {
+            exclude: vec!["10.20.0.0/24".to_string()],
+            ..Default::default()
+        };
+        let devices = filter_devices_to_targets(
+            merge_devices_by_mac(devices),
+            &network_info,
+            &ScanConfig::default(),
+        );
+        assert_eq!(devices.len(), 4);
+
+        let seen: Vec<(&str, Option<&str>, Option<&str>)> = devices
+            .iter()
+            .map(|d| (d.primary_ip(), d.interface.as_deref(), d.subnet.as_deref()))
+            .collect();
+        assert_eq!(
+            seen,
+            vec![
+                ("192.168.1.1", Some("eth0"), Some("192.168.1.0/24")),
+                ("10.20.0.1", Some("eth1"), Some("10.20.0.0/24")),
+                ("2001:db8:1::40", Some("eth0"), Some("192.168.1.0/24")),
+                ("fe80::50", Some("eth1"), Some("10.20.0.0/24")),
+            ]
+        );
+
+        // Excluded addresses are never reported
+        let devices = filter_devices_to_targets(devices, &network_info, &scan_config);
+        assert_eq!(devices.len(), 3);
+        assert!(devices.iter().all(|d| !d.has_address("10.20.0.1")));
+    }
+}
@@ -0,0 +1,536 @@
+//! Native neighbor (ARP) table reader for Linux
+//!
+//! Reads the kernel neighbor table directly instead of relying on `arp -n`
+//! from net-tools, which minimal server images no longer ship:
+//! 1. rtnetlink `RTM_GETNEIGH` dump (includes NUD state)
+//! 2. `/proc/net/arp` fallback (only complete/incomplete/permanent flags)
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// Path of the procfs ARP table
+pub const PROC_NET_ARP: &str = "/proc/net/arp";
+
+// rtnetlink constants (from linux/netlink.h, linux/rtnetlink.h, linux/neighbour.h).
+// Defined here so the parsers build and test on every platform.
+const NLMSG_HDR_LEN: usize = 16;
+const NDMSG_LEN: usize = 12;
+const NLMSG_ERROR: u16 = 2;
+const NLMSG_DONE: u16 = 3;
+const RTM_NEWNEIGH: u16 = 28;
+const RTM_GETNEIGH: u16 = 30;
+const NLM_F_REQUEST: u16 = 0x01;
+const NLM_F_DUMP: u16 = 0x300;
+const NDA_DST: u16 = 1;
+const NDA_LLADDR: u16 = 2;
+const AF_UNSPEC: u8 = 0;
+const AF_INET: u8 = 2;
+const AF_INET6: u8 = 10;
+
+// ARP flags reported in /proc/net/arp (from linux/if_arp.h)
+const ATF_COM: u32 = 0x02;
+const ATF_PERM: u32 = 0x04;
+
+/// Kernel neighbor unreachability detection (NUD) state
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NeighborState {
+    Incomplete,
+    Reachable,
+    Stale,
+    Delay,
+    Probe,
+    Failed,
+    Noarp,
+    Permanent,
+    Unknown,
+}
+
+impl NeighborState {
+    /// Map a `NUD_*` bit value to a state
+    pub fn from_nud(nud: u16) -> Self {
+        match nud {
+            0x01 => NeighborState::Incomplete,
+            0x02 => NeighborState::Reachable,
+            0x04 => NeighborState::Stale,
+            0x08 => NeighborState::Delay,
+            0x10 => NeighborState::Probe,
+            0x20 => NeighborState::Failed,
+            0x40 => NeighborState::Noarp,
+            0x80 => NeighborState::Permanent,
+            _ => NeighborState::Unknown,
+        }
+    }
+
+    /// Whether the entry has a resolved link-layer address for a live host
+    pub fn is_resolved(&self) -> bool {
+        matches!(
+            self,
+            NeighborState::Reachable
+                | NeighborState::Stale
+                | NeighborState::Delay
+                | NeighborState::Probe
+                | NeighborState::Permanent
+        )
+    }
+}
+
+impl std::fmt::Display for NeighborState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            NeighborState::Incomplete => "INCOMPLETE",
+            NeighborState::Reachable => "REACHABLE",
+            NeighborState::Stale => "STALE",
+            NeighborState::Delay => "DELAY",
+            NeighborState::Probe => "PROBE",
+            NeighborState::Failed => "FAILED",
+            NeighborState::Noarp => "NOARP",
+            NeighborState::Permanent => "PERMANENT",
+            NeighborState::Unknown => "UNKNOWN",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A single entry from the kernel neighbor table
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NeighborEntry {
+    pub ip: String,
+    /// Lowercase colon-separated MAC, `None` if unresolved
+    pub mac: Option<String>,
+    pub interface: String,
+    pub state: NeighborState,
+}
+
+/// Format a 6-byte link-layer address as "aa:bb:cc:dd:ee:ff"
+pub(super) fn format_mac(bytes: &[u8]) -> Option<String> {
+    if bytes.len() != 6 || bytes.iter().all(|b| *b == 0) {
+        return None;
+    }
+    Some(
+        bytes
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<Vec<_>>()
+            .join(":"),
+    )
+}
+
+/// Parse the contents of `/proc/net/arp`.
+///
+/// procfs only exposes ARP flags, so complete entries are reported as
+/// `Reachable`, permanent ones as `Permanent` and unresolved ones as `Failed`.
+pub fn parse_proc_net_arp(content: &str) -> Vec<NeighborEntry> {
+    let mut entries = Vec::new();
+
+    // Columns: IP address, HW type, Flags, HW address, Mask, Device
+    for line in content.lines().skip(1) {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.len() < 6 {
+            continue;
+        }
+
+        let ip = parts[0];
+        if ip.parse::<std::net::IpAddr>().is_err() {
+            continue;
+        }
+
+        let flags = u32::from_str_radix(parts[2].trim_start_matches("0x"), 16).unwrap_or(0);
+        let mac = parts[3].to_lowercase();
+        let mac = if mac.len() == 17 && mac != "00:00:00:00:00:00" {
+            Some(mac)
+        } else {
+            None
+        };
+
+        let state = if flags & ATF_PERM != 0 {
+            NeighborState::Permanent
+        } else if flags & ATF_COM != 0 && mac.is_some() {
+            NeighborState::Reachable
+        } else {
+            NeighborState::Failed
+        };
+
+        entries.push(NeighborEntry {
+            ip: ip.to_string(),
+            mac,
+            interface: parts[5].to_string(),
+            state,
+        });
+    }
+
+    entries
+}
+
+fn read_u16(buf: &[u8], offset: usize) -> u16 {
+    u16::from_ne_bytes([buf[offset], buf[offset + 1]])
+}
+
+fn read_u32(buf: &[u8], offset: usize) -> u32 {
+    u32::from_ne_bytes([
+        buf[offset],
+        buf[offset + 1],
+        buf[offset + 2],
+        buf[offset + 3],
+    ])
+}
+
+fn align4(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+/// Parse one buffer of rtnetlink `RTM_NEWNEIGH` messages.
+///
+/// `resolve_interface` maps an interface index to its name. Parsed entries
+/// are appended to `out`. Returns `true` once `NLMSG_DONE` has been seen.
+pub fn parse_netlink_neighbors<F>(
+    buf: &[u8],
+    resolve_interface: F,
+    out: &mut Vec<NeighborEntry>,
+) -> Result<bool>
+where
+    F: Fn(u32) -> Option<String>,
+{
+    let mut offset = 0;
+
+    while offset + NLMSG_HDR_LEN <= buf.len() {
+        let msg_len = read_u32(buf, offset) as usize;
+        let msg_type = read_u16(buf, offset + 4);
+
+        if msg_len < NLMSG_HDR_LEN || offset + msg_len > buf.len() {
+            return Err(anyhow::anyhow!("Truncated netlink message"));
+        }
+
+        match msg_type {
+            NLMSG_DONE => return Ok(true),
+            NLMSG_ERROR => {
+                let errno = if msg_len >= NLMSG_HDR_LEN + 4 {
+                    read_u32(buf, offset + NLMSG_HDR_LEN) as i32
+                } else {
+                    0
+                };
+                return Err(anyhow::anyhow!("Netlink error response (errno {})", -errno));
+            }
+            RTM_NEWNEIGH if msg_len >= NLMSG_HDR_LEN + NDMSG_LEN => {
+                let nd = offset + NLMSG_HDR_LEN;
+                let family = buf[nd];
+                let ifindex = read_u32(buf, nd + 4);
+                let state = NeighborState::from_nud(read_u16(buf, nd + 8));
+
+                let mut ip = None;
+                let mut mac = None;
+
+                let mut attr = nd + NDMSG_LEN;
+                let msg_end = offset + msg_len;
+                while attr + 4 <= msg_end {
+                    let attr_len = read_u16(buf, attr) as usize;
+                    let attr_type = read_u16(buf, attr + 2);
+                    if attr_len < 4 || attr + attr_len > msg_end {
+                        break;
+                    }
+                    let data = &buf[attr + 4..attr + attr_len];
+
+                    match attr_type {
+                        NDA_DST => {
+                            ip = match (family, data.len()) {
+                                (AF_INET, 4) => {
+                                    let octets: [u8; 4] = data.try_into().unwrap_or_default();
+                                    Some(std::net::Ipv4Addr::from(octets).to_string())
+                                }
+                                (AF_INET6, 16) => {
+                                    let octets: [u8; 16] = data.try_into().unwrap_or_default();
+                                    Some(std::net::Ipv6Addr::from(octets).to_string())
+                                }
+                                _ => None,
+                            };
+                        }
+                        NDA_LLADDR => mac = format_mac(data),
+                        _ => {}
+                    }
+
+                    attr += align4(attr_len);
+                }
+
+                if let Some(ip) = ip {
+                    out.push(NeighborEntry {
+                        ip,
+                        mac,
+                        interface: resolve_interface(ifindex)
+                            .unwrap_or_else(|| format!("if{}", ifindex)),
+                        state,
+                    });
+                }
+            }
+            _ => {}
+        }
+
+        offset += align4(msg_len);
+    }
+
+    Ok(false)
+}
+
+/// Build an `RTM_GETNEIGH` dump request for the given address family.
+fn build_getneigh_request(family: u8, seq: u32) -> Vec<u8> {
+    let len = (NLMSG_HDR_LEN + NDMSG_LEN) as u32;
+    let mut req = Vec::with_capacity(len as usize);
+    req.extend_from_slice(&len.to_ne_bytes());
+    req.extend_from_slice(&RTM_GETNEIGH.to_ne_bytes());
+    req.extend_from_slice(&(NLM_F_REQUEST | NLM_F_DUMP).to_ne_bytes());
+    req.extend_from_slice(&seq.to_ne_bytes());
+    req.extend_from_slice(&0u32.to_ne_bytes());
+    // struct ndmsg: family, 3 bytes padding, ifindex, state, flags, type
+    req.push(family);
+    req.extend_from_slice(&[0u8; NDMSG_LEN - 1]);
+    req
+}
+
+/// Look up an interface name by index.
+#[cfg(target_os = "linux")]
+fn interface_name(index: u32) -> Option<String> {
+    let mut buf = [0 as libc::c_char; libc::IF_NAMESIZE];
+    let ptr = unsafe { libc::if_indextoname(index, buf.as_mut_ptr()) };
+    if ptr.is_null() {
+        return None;
+    }
+    let name = unsafe { std::ffi::CStr::from_ptr(buf.as_ptr()) };
+    Some(name.to_string_lossy().into_owned())
+}
+
+/// Dump the IPv4 and IPv6 neighbor tables over rtnetlink.
+#[cfg(target_os = "linux")]
+pub fn read_netlink_neighbors() -> Result<Vec<NeighborEntry>> {
+    use std::collections::HashMap;
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+
+    let raw = unsafe {
+        libc::socket(
+            libc::AF_NETLINK,
+            libc::SOCK_RAW | libc::SOCK_CLOEXEC,
+            libc::NETLINK_ROUTE,
+        )
+    };
+    if raw < 0 {
+        return Err(std::io::Error::last_os_error()).context("Failed to open netlink socket");
+    }
+    // Closed on drop
+    let socket = unsafe { OwnedFd::from_raw_fd(raw) };
+    let fd = socket.as_raw_fd();
+
+    // Never block a scan on a misbehaving kernel interface
+    let timeout = libc::timeval {
+        tv_sec: 2,
+        tv_usec: 0,
+    };
+    unsafe {
+        libc::setsockopt(
+            fd,
+            libc::SOL_SOCKET,
+            libc::SO_RCVTIMEO,
+            &timeout as *const libc::timeval as *const libc::c_void,
+            std::mem::size_of::<libc::timeval>() as libc::socklen_t,
+        );
+    }
+
+    let request = build_getneigh_request(AF_UNSPEC, 1);
+    let sent = unsafe {
+        libc::send(
+            fd,
+            request.as_ptr() as *const libc::c_void,
+            request.len(),
+            0,
+        )
+    };
+    if sent < 0 {
+        return Err(std::io::Error::last_os_error()).context("Failed to send RTM_GETNEIGH");
+    }
+
+    let names: std::cell::RefCell<HashMap<u32, Option<String>>> = Default::default();
+    let resolve = |index: u32| {
+        names
+            .borrow_mut()
+            .entry(index)
+            .or_insert_with(|| interface_name(index))
+            .clone()
+    };
+
+    let mut entries = Vec::new();
+    let mut buf = vec![0u8; 32 * 1024];
+
+    loop {
+        let received =
+            unsafe { libc::recv(fd, buf.as_mut_ptr() as *mut libc::c_void, buf.len(), 0) };
+        if received < 0 {
+            return Err(std::io::Error::last_os_error())
+                .context("Failed to read netlink neighbor dump");
+        }
+        if received == 0 {
+            break;
+        }
+
+        if parse_netlink_neighbors(&buf[..received as usize], resolve, &mut entries)? {
+            break;
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Read the neighbor table, preferring rtnetlink and falling back to
+/// `/proc/net/arp` (IPv4 only).
+#[cfg(target_os = "linux")]
+pub fn read_neighbor_table() -> Result<Vec<NeighborEntry>> {
+    match read_netlink_neighbors() {
+        Ok(entries) => {
+            tracing::debug!("Read {} neighbor entries via netlink", entries.len());
+            Ok(entries)
+        }
+        Err(e) => {
+            tracing::debug!(
+                "Netlink neighbor dump failed ({}), reading {}",
+                e,
+                PROC_NET_ARP
+            );
+            let content = std::fs::read_to_string(PROC_NET_ARP)
+                .with_context(|| format!("Failed to read {}", PROC_NET_ARP))?;
+            Ok(parse_proc_net_arp(&content))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROC_NET_ARP_FIXTURE: &str = include_str!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/proc_net_arp.txt"
+    ));
+
+    const NETLINK_FIXTURE: &[u8] = include_bytes!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/rtm_getneigh_v4.bin"
+    ));
+
+    const DUAL_STACK_FIXTURE: &[u8] = include_bytes!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/fixtures/rtm_getneigh_dual_stack.bin"
+    ));
+
+    fn fixture_interfaces(index: u32) -> Option<String> {
+        match index {
+            2 => Some("eth0".to_string()),
+            3 => Some("wlan0".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_parse_proc_net_arp() {
+        let entries = parse_proc_net_arp(PROC_NET_ARP_FIXTURE);
+        assert_eq!(entries.len(), 4);
+
+        assert_eq!(entries[0].ip, "192.168.1.1");
+        assert_eq!(entries[0].mac.as_deref(), Some("a0:63:91:12:34:56"));
+        assert_eq!(entries[0].interface, "eth0");
+        assert_eq!(entries[0].state, NeighborState::Reachable);
+
+        // Incomplete entry has an all-zero MAC
+        assert_eq!(entries[2].ip, "192.168.1.77");
+        assert_eq!(entries[2].mac, None);
+        assert_eq!(entries[2].state, NeighborState::Failed);
+
+        assert_eq!(entries[3].interface, "wlan0");
+        assert_eq!(entries[3].state, NeighborState::Permanent);
+    }
+
+    #[test]
+    fn test_parse_netlink_neighbors() {
+        let mut entries = Vec::new();
+        let done = parse_netlink_neighbors(NETLINK_FIXTURE, fixture_interfaces, &mut entries)
+            .expect("fixture should parse");
+        assert!(done, "fixture ends with NLMSG_DONE");
+        assert_eq!(entries.len(), 5);
+
+        let states: Vec<NeighborState> = entries.iter().map(|e| e.state).collect();
+        assert_eq!(
+            states,
+            vec![
+                NeighborState::Reachable,
+                NeighborState::Stale,
+                NeighborState::Failed,
+                NeighborState::Delay,
+                NeighborState::Permanent,
+            ]
+        );
+
+        assert_eq!(entries[0].ip, "192.168.1.1");
+        assert_eq!(entries[0].mac.as_deref(), Some("a0:63:91:12:34:56"));
+        assert_eq!(entries[0].interface, "eth0");
+
+        // FAILED entries carry no link-layer address
+        assert_eq!(entries[2].ip, "192.168.1.77");
+        assert_eq!(entries[2].mac, None);
+
+        assert_eq!(entries[4].interface, "wlan0");
+    }
+
+    #[test]
+    fn test_parse_netlink_dual_stack() {
+        let mut entries = Vec::new();
+        parse_netlink_neighbors(DUAL_STACK_FIXTURE, fixture_interfaces, &mut entries).unwrap();
+        assert_eq!(entries.len(), 5);
+
+        let ips: Vec<&str> = entries.iter().map(|e| e.ip.as_str()).collect();
+        assert_eq!(
+            ips,
+            vec![
+                "192.168.1.1",
+                "fe80::a263:91ff:fe12:3456",
+                "2001:db8:1::1",
+                "fe80::217:f2ff:feab:cdef",
+                "ff02::16",
+            ]
+        );
+
+        // Router answers on both families with the same MAC
+        assert_eq!(entries[0].mac, entries[1].mac);
+        assert_eq!(entries[2].state, NeighborState::Stale);
+        assert_eq!(entries[4].state, NeighborState::Noarp);
+    }
+
+    #[test]
+    fn test_parse_netlink_unknown_interface() {
+        let mut entries = Vec::new();
+        parse_netlink_neighbors(NETLINK_FIXTURE, |_| None, &mut entries).unwrap();
+        assert_eq!(entries[0].interface, "if2");
+    }
+
+    #[test]
+    fn test_parse_netlink_truncated() {
+        let mut entries = Vec::new();
+        let result =
+            parse_netlink_neighbors(&NETLINK_FIXTURE[..40], fixture_interfaces, &mut entries);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_build_getneigh_request() {
+        let req = build_getneigh_request(AF_INET, 7);
+        assert_eq!(req.len(), NLMSG_HDR_LEN + NDMSG_LEN);
+        assert_eq!(read_u32(&req, 0) as usize, req.len());
+        assert_eq!(read_u16(&req, 4), RTM_GETNEIGH);
+        assert_eq!(read_u32(&req, 8), 7);
+        assert_eq!(req[NLMSG_HDR_LEN], AF_INET);
+    }
+
+    #[test]
+    fn test_resolved_states() {
+        assert!(NeighborState::Reachable.is_resolved());
+        assert!(NeighborState::Stale.is_resolved());
+        assert!(!NeighborState::Failed.is_resolved());
+        assert!(!NeighborState::Incomplete.is_resolved());
+        assert_eq!(NeighborState::from_nud(0x04).to_string(), "STALE");
+    }
+}
@@ -0,0 +1,165 @@
+//! NetBIOS node status (NBSTAT) queries
+//!
+//! Asks a host on UDP 137 for its NetBIOS name table, the in-process
+//! equivalent of `nbtstat -A`. Windows machines answer these even when no
+//! PTR record exists for them.
+
+use super::dns;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+const NETBIOS_NAME_PORT: u16 = 137;
+const TYPE_NBSTAT: u16 = 0x0021;
+const CLASS_IN: u16 = 0x0001;
+/// Header plus the encoded wildcard name, type and class
+const QUERY_LEN: usize = 12 + 34 + 4;
+/// Each name table entry: 15 name bytes, suffix byte, 2 flag bytes
+const NAME_ENTRY_LEN: usize = 18;
+/// Workstation service suffix, the name a machine calls itself
+const SUFFIX_WORKSTATION: u8 = 0x00;
+const FLAG_GROUP: u16 = 0x8000;
+
+/// The host's NetBIOS machine name, if it answers a node status query.
+pub async fn node_status(ip: IpAddr, timeout: Duration) -> Option<String> {
+    if !ip.is_ipv4() {
+        return None;
+    }
+    query_node_status(SocketAddr::new(ip, NETBIOS_NAME_PORT), timeout).await
+}
+
+async fn query_node_status(dest: SocketAddr, timeout: Duration) -> Option<String> {
+    let id = dns::next_query_id();
+    let query = build_query(id);
+    dns::exchange(dest, &query, timeout, |reply| parse_response(id, reply)).await
+}
+
+/// Node status request for the wildcard name "*"
+fn build_query(id: u16) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(QUERY_LEN);
+    buf.extend_from_slice(&id.to_be_bytes());
+    buf.extend_from_slice(&[0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+
+    // First-level encoding (RFC 1001 14.1): each nibble of the 16-byte name
+    // becomes 'A' + nibble
+    let mut name = [0u8; 16];
+    name[0] = b'*';
+    buf.push(32);
+    for byte in name {
+        buf.push(b'A' + (byte >> 4));
+        buf.push(b'A' + (byte & 0x0f));
+    }
+    buf.push(0);
+    buf.extend_from_slice(&TYPE_NBSTAT.to_be_bytes());
+    buf.extend_from_slice(&CLASS_IN.to_be_bytes());
+    buf
+}
+
+/// Pick the unique workstation name out of a node status response
+fn parse_response(id: u16, reply: &[u8]) -> Option<String> {
+    if reply.len() < 12 || u16::from_be_bytes([reply[0], reply[1]]) != id {
+        return None;
+    }
+    // Response bit set, at least one answer
+    if reply[2] & 0x80 == 0 || u16::from_be_bytes([reply[6], reply[7]]) == 0 {
+        return None;
+    }
+
+    // Skip the answer's owner name (length-prefixed labels, no compression)
+    let mut pos = 12;
+    loop {
+        let len = *reply.get(pos)? as usize;
+        pos += 1 + len;
+        if len == 0 {
+            break;
+        }
+    }
+    let rtype = u16::from_be_bytes([*reply.get(pos)?, *reply.get(pos + 1)?]);
+    if rtype != TYPE_NBSTAT {
+        return None;
+    }
+    // type, class, TTL and RDLENGTH
+    pos += 10;
+
+    let count = *reply.get(pos)? as usize;
+    let table = reply.get(pos + 1..pos + 1 + count * NAME_ENTRY_LEN)?;
+    table.chunks_exact(NAME_ENTRY_LEN).find_map(|entry| {
+        let flags = u16::from_be_bytes([entry[16], entry[17]]);
+        if entry[15] != SUFFIX_WORKSTATION || flags & FLAG_GROUP != 0 {
+            return None;
+        }
+        let name = String::from_utf8_lossy(&entry[..15]).trim_end().to_string();
+        (!name.is_empty()).then_some(name)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::UdpSocket;
+
+    /// Node status response with a group name listed before the machine name
+    fn response(id: u16) -> Vec<u8> {
+        let mut reply = id.to_be_bytes().to_vec();
+        reply.extend_from_slice(&[0x84, 0x00, 0, 0, 0, 1, 0, 0, 0, 0]);
+        reply.extend_from_slice(&build_query(id)[12..12 + 34]);
+        reply.extend_from_slice(&TYPE_NBSTAT.to_be_bytes());
+        reply.extend_from_slice(&CLASS_IN.to_be_bytes());
+        reply.extend_from_slice(&[0, 0, 0, 0]);
+        let names: [(&[u8; 15], u8, u16); 3] = [
+            (b"WORKGROUP      ", 0x00, 0x8400),
+            (b"DESKTOP-7Q2LM4A", 0x00, 0x0400),
+            (b"DESKTOP-7Q2LM4A", 0x20, 0x0400),
+        ];
+        let rdlength = 1 + names.len() * NAME_ENTRY_LEN + 6;
+        reply.extend_from_slice(&(rdlength as u16).to_be_bytes());
+        reply.push(names.len() as u8);
+        for (name, suffix, flags) in names {
+            reply.extend_from_slice(name);
+            reply.push(suffix);
+            reply.extend_from_slice(&flags.to_be_bytes());
+        }
+        reply.extend_from_slice(&[0x00, 0x15, 0x5d, 0x01, 0x02, 0x03]);
+        reply
+    }
+
+    #[test]
+    fn test_build_query() {
+        let query = build_query(0x1234);
+        assert_eq!(query.len(), QUERY_LEN);
+        assert_eq!(&query[12..15], &[32, b'C', b'K']);
+        assert_eq!(&query[15..45], &[b'A'; 30]);
+        assert_eq!(&query[46..], &[0x00, 0x21, 0x00, 0x01]);
+    }
+
+    #[test]
+    fn test_parse_response() {
+        assert_eq!(
+            parse_response(7, &response(7)).as_deref(),
+            Some("DESKTOP-7Q2LM4A")
+        );
+        // Mismatched transaction id and truncated table
+        assert_eq!(parse_response(8, &response(7)), None);
+        assert_eq!(parse_response(7, &response(7)[..70]), None);
+    }
+
+    #[tokio::test]
+    async fn test_node_status_exchange() {
+        let responder = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let dest = responder.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut buf = [0u8; 512];
+            let mut first = true;
+            while let Ok((len, from)) = responder.recv_from(&mut buf).await {
+                // Drop the first query to exercise the retransmit
+                if std::mem::take(&mut first) || len != QUERY_LEN {
+                    continue;
+                }
+                let id = u16::from_be_bytes([buf[0], buf[1]]);
+                let _ = responder.send_to(&response(id), from).await;
+            }
+        });
+
+        let name = query_node_status(dest, Duration::from_secs(2)).await;
+        assert_eq!(name.as_deref(), Some("DESKTOP-7Q2LM4A"));
+    }
+}
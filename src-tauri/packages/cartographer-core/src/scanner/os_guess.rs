@@ -0,0 +1,391 @@
+//! Lightweight OS fingerprinting from what the scan already collected
+//!
+//! No probes of its own: the ICMP reply TTL, the SYN-ACK window and options
+//! of the port scan handshakes, and hints the device gave about itself
+//! (DHCP fingerprint, mDNS model, UPnP and service banners) each vote for
+//! one or more OS families. The family with the most weight wins; the
+//! confidence is its share of the total, discounted when there is little
+//! evidence.
+
+use super::Device;
+use super::dhcp::OsFamily;
+use super::ports::TcpFingerprint;
+use serde::{Deserialize, Serialize};
+
+/// Evidence weights; a DHCP fingerprint or an Apple model name alone is a
+/// fair guess, a TTL alone is not
+const WEIGHT_DHCP: f32 = 0.6;
+const WEIGHT_MODEL: f32 = 0.5;
+const WEIGHT_BANNER: f32 = 0.4;
+const WEIGHT_MDNS_SERVICE: f32 = 0.3;
+const WEIGHT_TTL: f32 = 0.3;
+const WEIGHT_TCP: f32 = 0.3;
+
+/// Total weight below which the confidence is scaled down
+const FULL_EVIDENCE: f32 = 1.0;
+/// Guesses below this confidence are not reported
+const MIN_CONFIDENCE: f32 = 0.15;
+const MAX_CONFIDENCE: f32 = 0.95;
+
+/// Unix-like systems that share an initial TTL of 64
+const TTL_64_FAMILIES: &[OsFamily] = &[
+    OsFamily::Linux,
+    OsFamily::Android,
+    OsFamily::Macos,
+    OsFamily::Ios,
+];
+const APPLE_FAMILIES: &[OsFamily] = &[OsFamily::Macos, OsFamily::Ios];
+
+/// Best guess at a device's operating system
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OsGuess {
+    pub family: OsFamily,
+    /// 0.0 to 1.0
+    pub confidence: f32,
+    /// Signals that supported the guess (e.g. "ttl 128", "dhcp")
+    pub evidence: Vec<String>,
+}
+
+impl std::fmt::Display for OsGuess {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({:.0}%)", self.family, self.confidence * 100.0)
+    }
+}
+
+/// One signal voting for a set of families
+struct Vote {
+    families: &'static [OsFamily],
+    weight: f32,
+    evidence: String,
+}
+
+impl Vote {
+    fn new(families: &'static [OsFamily], weight: f32, evidence: impl Into<String>) -> Self {
+        Self {
+            families,
+            weight,
+            evidence: evidence.into(),
+        }
+    }
+}
+
+/// Combine everything known about a device into an OS guess
+pub fn guess(device: &Device) -> Option<OsGuess> {
+    let mut votes = Vec::new();
+
+    if let Some(family) = device.dhcp.as_ref().and_then(|d| d.os_family) {
+        votes.push(Vote::new(single(family), WEIGHT_DHCP, "dhcp"));
+    }
+    if let Some(vote) = device.model.as_deref().and_then(model_vote) {
+        votes.push(vote);
+    }
+    votes.extend(mdns_service_votes(device));
+    votes.extend(banner_votes(device));
+    if let Some(vote) = device.ttl.and_then(ttl_vote) {
+        votes.push(vote);
+    }
+    if let Some(vote) = device.tcp_fingerprint.as_ref().and_then(tcp_vote) {
+        votes.push(vote);
+    }
+
+    tally(votes)
+}
+
+/// Attach a guess to every device that has any evidence
+pub fn apply(devices: &mut [Device]) {
+    for device in devices.iter_mut() {
+        device.os_guess = guess(device);
+    }
+}
+
+fn tally(votes: Vec<Vote>) -> Option<OsGuess> {
+    let total: f32 = votes.iter().map(|v| v.weight).sum();
+    if total <= 0.0 {
+        return None;
+    }
+
+    // Ambiguous votes split their weight; ties go to the earlier family
+    let families = [
+        OsFamily::Windows,
+        OsFamily::Linux,
+        OsFamily::Android,
+        OsFamily::Macos,
+        OsFamily::Ios,
+    ];
+    let score = |family: OsFamily| -> f32 {
+        votes
+            .iter()
+            .filter(|v| v.families.contains(&family))
+            .map(|v| v.weight / v.families.len() as f32)
+            .sum()
+    };
+    let (family, best) = families.iter().map(|&f| (f, score(f))).fold(
+        (OsFamily::Windows, 0.0),
+        |best, candidate| {
+            if candidate.1 > best.1 {
+                candidate
+            } else {
+                best
+            }
+        },
+    );
+
+    let confidence = (best / total.max(FULL_EVIDENCE)).min(MAX_CONFIDENCE);
+    if confidence < MIN_CONFIDENCE {
+        return None;
+    }
+    Some(OsGuess {
+        family,
+        confidence: (confidence * 100.0).round() / 100.0,
+        evidence: votes
+            .into_iter()
+            .filter(|v| v.families.contains(&family))
+            .map(|v| v.evidence)
+            .collect(),
+    })
+}
+
+fn single(family: OsFamily) -> &'static [OsFamily] {
+    match family {
+        OsFamily::Windows => &[OsFamily::Windows],
+        OsFamily::Macos => &[OsFamily::Macos],
+        OsFamily::Ios => &[OsFamily::Ios],
+        OsFamily::Android => &[OsFamily::Android],
+        OsFamily::Linux => &[OsFamily::Linux],
+    }
+}
+
+/// Initial TTL is 64 on Unix-likes and 128 on Windows; the observed value is
+/// lower by the number of routers on the way
+fn ttl_vote(ttl: u8) -> Option<Vote> {
+    let families = match ttl {
+        33..=64 => TTL_64_FAMILIES,
+        65..=128 => single(OsFamily::Windows),
+        // 255 is network gear and embedded stacks
+        _ => return None,
+    };
+    Some(Vote::new(families, WEIGHT_TTL, format!("ttl {}", ttl)))
+}
+
+/// SYN-ACK signatures: Windows scales by 8 without timestamps, Linux
+/// scales by 7 with them, Apple's stack offers 65535 scaled by 5 or 6
+fn tcp_vote(tcp: &TcpFingerprint) -> Option<Vote> {
+    let families = match (tcp.window_scale, tcp.timestamps, tcp.window) {
+        (Some(8), false, _) => single(OsFamily::Windows),
+        (Some(7), true, _) => &[OsFamily::Linux, OsFamily::Android],
+        (Some(5 | 6), true, Some(65535) | None) => APPLE_FAMILIES,
+        _ => return None,
+    };
+    let mut evidence = format!("tcp/{}", tcp.port);
+    if let Some(window) = tcp.window {
+        evidence.push_str(&format!(" win {}", window));
+    }
+    if let Some(scale) = tcp.window_scale {
+        evidence.push_str(&format!(" ws {}", scale));
+    }
+    Some(Vote::new(families, WEIGHT_TCP, evidence))
+}
+
+/// Apple model identifiers ("MacBookPro18,3", "iPhone14,2") from mDNS
+/// `_device-info` and AirPlay TXT records
+fn model_vote(model: &str) -> Option<Vote> {
+    const IOS_MODELS: &[&str] = &["iPhone", "iPad", "iPod"];
+    const MAC_MODELS: &[&str] = &["Mac", "iMac"];
+
+    if !model.contains(',') {
+        return None;
+    }
+    let family = if IOS_MODELS.iter().any(|m| model.starts_with(m)) {
+        OsFamily::Ios
+    } else if MAC_MODELS.iter().any(|m| model.starts_with(m)) {
+        OsFamily::Macos
+    } else {
+        return None;
+    };
+    Some(Vote::new(
+        single(family),
+        WEIGHT_MODEL,
+        format!("model {}", model),
+    ))
+}
+
+fn mdns_service_votes(device: &Device) -> Vec<Vote> {
+    let mut votes = Vec::new();
+    let advertises = |service_type: &str| {
+        device
+            .advertised_services
+            .iter()
+            .any(|s| s.service_type == service_type)
+    };
+    // Apple Continuity and device pairing
+    if advertises("_companion-link._tcp") || advertises("_apple-mobdev2._tcp") {
+        votes.push(Vote::new(APPLE_FAMILIES, WEIGHT_MDNS_SERVICE, "mdns apple"));
+    }
+    // Avahi announces the machine itself by default
+    if advertises("_workstation._tcp") {
+        votes.push(Vote::new(
+            single(OsFamily::Linux),
+            WEIGHT_MDNS_SERVICE,
+            "mdns workstation",
+        ));
+    }
+    votes
+}
+
+/// OS names in SSH banners, HTTP `Server` headers and the UPnP `SERVER`
+/// header (e.g. "OpenSSH_9.6p1 Ubuntu", "Microsoft-IIS/10.0",
+/// "Linux/5.10 UPnP/1.0")
+fn banner_votes(device: &Device) -> Vec<Vote> {
+    let banners = device
+        .services
+        .iter()
+        .flat_map(|s| [s.banner.as_deref(), s.http_server.as_deref()])
+        .chain([device.upnp.as_ref().and_then(|u| u.server.as_deref())])
+        .flatten();
+
+    let mut votes: Vec<Vote> = Vec::new();
+    for banner in banners {
+        let Some(family) = banner_family(banner) else {
+            continue;
+        };
+        // One vote per family, however many ports say so
+        if votes.iter().any(|v| v.families == single(family)) {
+            continue;
+        }
+        votes.push(Vote::new(single(family), WEIGHT_BANNER, banner.to_string()));
+    }
+    votes
+}
+
+fn banner_family(banner: &str) -> Option<OsFamily> {
+    let lower = banner.to_ascii_lowercase();
+    const LINUX: &[&str] = &["ubuntu", "debian", "raspbian", "fedora", "linux"];
+    if lower.contains("microsoft") || lower.contains("windows") {
+        Some(OsFamily::Windows)
+    } else if lower.contains("darwin") || lower.contains("macos") || lower.contains("mac os") {
+        Some(OsFamily::Macos)
+    } else if lower.contains("android") {
+        Some(OsFamily::Android)
+    } else if LINUX.iter().any(|l| lower.contains(l)) {
+        Some(OsFamily::Linux)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::{DhcpFingerprint, ServiceInfo};
+
+    fn tcp(window: u32, window_scale: u8, timestamps: bool) -> TcpFingerprint {
+        TcpFingerprint {
+            port: 445,
+            window: Some(window),
+            window_scale: Some(window_scale),
+            sack: true,
+            timestamps,
+        }
+    }
+
+    fn dhcp(os_family: OsFamily) -> DhcpFingerprint {
+        DhcpFingerprint {
+            mac: "3c:52:82:1a:2b:3c".to_string(),
+            hostname: None,
+            vendor_class: None,
+            client_id: None,
+            parameter_list: Vec::new(),
+            requested_ip: None,
+            os_family: Some(os_family),
+            last_seen: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_windows_from_ttl_and_tcp() {
+        let device = Device {
+            ttl: Some(128),
+            tcp_fingerprint: Some(tcp(65535, 8, false)),
+            ..Device::new("192.168.1.20")
+        };
+        let guess = guess(&device).unwrap();
+        assert_eq!(guess.family, OsFamily::Windows);
+        assert_eq!(guess.confidence, 0.6);
+        assert_eq!(guess.evidence, vec!["ttl 128", "tcp/445 win 65535 ws 8"]);
+
+        // A DHCP fingerprint on top raises the confidence
+        let device = Device {
+            dhcp: Some(dhcp(OsFamily::Windows)),
+            ..device
+        };
+        assert_eq!(super::guess(&device).unwrap().confidence, 0.95);
+    }
+
+    #[test]
+    fn test_ttl_64_needs_more_evidence() {
+        let device = Device {
+            ttl: Some(63),
+            ..Device::new("192.168.1.30")
+        };
+        assert_eq!(guess(&device), None);
+
+        // TTL and a Linux-style SYN-ACK narrow it to Linux or Android
+        let device = Device {
+            tcp_fingerprint: Some(tcp(65160, 7, true)),
+            ..device
+        };
+        let guess = guess(&device).unwrap();
+        assert_eq!(guess.family, OsFamily::Linux);
+        assert_eq!(guess.confidence, 0.23);
+
+        // An Ubuntu SSH banner settles it
+        let device = Device {
+            services: vec![ServiceInfo {
+                port: 22,
+                banner: Some("SSH-2.0-OpenSSH_9.6p1 Ubuntu-3ubuntu13".to_string()),
+                http_server: None,
+                http_title: None,
+                tls_certificate: None,
+            }],
+            ..device
+        };
+        let guess = super::guess(&device).unwrap();
+        assert_eq!(guess.family, OsFamily::Linux);
+        assert_eq!(guess.confidence, 0.63);
+    }
+
+    #[test]
+    fn test_apple_model_and_dhcp() {
+        let device = Device {
+            ttl: Some(64),
+            model: Some("MacBookPro18,3".to_string()),
+            dhcp: Some(dhcp(OsFamily::Macos)),
+            ..Device::new("192.168.1.71")
+        };
+        let guess = guess(&device).unwrap();
+        assert_eq!(guess.family, OsFamily::Macos);
+        assert_eq!(
+            guess.evidence,
+            vec!["dhcp", "model MacBookPro18,3", "ttl 64"]
+        );
+        assert!(guess.confidence > 0.8);
+
+        let iphone = Device {
+            model: Some("iPhone14,2".to_string()),
+            ..Device::new("192.168.1.63")
+        };
+        assert_eq!(super::guess(&iphone).unwrap().family, OsFamily::Ios);
+    }
+
+    #[test]
+    fn test_no_evidence() {
+        assert_eq!(guess(&Device::new("192.168.1.1")), None);
+        // Network gear answers with TTL 255
+        let router = Device {
+            ttl: Some(255),
+            ..Device::new("192.168.1.1")
+        };
+        assert_eq!(guess(&router), None);
+    }
+}
@@ -1,6 +1,268 @@
 //! MAC OUI (Organizationally Unique Identifier) vendor lookup
 //!
 //! Uses the IEEE OUI database to identify device manufacturers from MAC addresses.
+//!
+//! A copy of the registry is compiled into the agent. A newer one can be
+//! dropped into the data directory as the CSV files the IEEE publishes
+//! (MA-L `oui.csv`, MA-M `mam.csv`, MA-S `oui36.csv`), and in-house
+//! hardware can be named in `[scan.oui.custom]`. A lookup tries the custom
+//! prefixes, then the downloaded registry, then the built-in copy.
+
+use super::config::OuiConfig;
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Assignment lengths in hex digits, longest first: MA-S (and the older
+/// IAB), MA-M, MA-L
+const ASSIGNMENT_LENGTHS: [usize; 3] = [9, 7, 6];
+
+/// Vendor names by MAC prefix, from the user's custom mapping, a downloaded
+/// IEEE registry and the built-in copy, in that order
+#[derive(Debug, Clone, Default)]
+pub struct Database {
+    /// (lowercase hex prefix, vendor), longest prefix first
+    custom: Vec<(String, String)>,
+    /// Lowercase hex assignment -> organization
+    registry: HashMap<String, String>,
+    registry_source: Option<RegistrySource>,
+}
+
+/// A registry loaded from the data directory
+#[derive(Debug, Clone, Serialize)]
+pub struct RegistrySource {
+    pub dir: PathBuf,
+    pub files: Vec<String>,
+    pub entries: usize,
+    /// Modification time of the newest file; the IEEE files carry no
+    /// version of their own
+    pub updated: Option<DateTime<Utc>>,
+}
+
+impl std::fmt::Display for RegistrySource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "IEEE registry in {} ({}, {} entries",
+            self.dir.display(),
+            self.files.join(", "),
+            self.entries
+        )?;
+        if let Some(updated) = self.updated {
+            write!(f, ", updated {}", updated.format("%Y-%m-%d"))?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl Database {
+    /// The built-in registry only
+    pub fn builtin() -> Self {
+        Self::default()
+    }
+
+    /// Add in-house prefixes ("F4:A6:E5", "70-b3-d5-f2-c", ...) and names
+    pub fn with_custom<'a>(
+        mut self,
+        custom: impl IntoIterator<Item = (&'a String, &'a String)>,
+    ) -> Result<Self> {
+        for (prefix, vendor) in custom {
+            let hex = prefix.replace([':', '-', '.', ' '], "").to_lowercase();
+            if !(6..=12).contains(&hex.len()) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                bail!(
+                    "custom OUI '{}': expected a MAC prefix of 6 to 12 hex digits",
+                    prefix
+                );
+            }
+            if vendor.trim().is_empty() {
+                bail!("custom OUI '{}': empty vendor name", prefix);
+            }
+            self.custom.push((hex, vendor.trim().to_string()));
+        }
+        self.custom
+            .sort_by_key(|(hex, _)| std::cmp::Reverse(hex.len()));
+        Ok(self)
+    }
+
+    /// Add the assignments of an IEEE registry CSV file. Returns how many
+    /// were read; rows that are not MA-L/MA-M/MA-S assignments are skipped.
+    pub fn add_registry_csv(&mut self, content: &str) -> usize {
+        let mut added = 0;
+        for line in content.lines().skip(1) {
+            let fields = split_csv_line(line);
+            let [registry, assignment, organization, ..] = fields.as_slice() else {
+                continue;
+            };
+            let hex = assignment.trim().to_lowercase();
+            let expected_len = match registry.trim() {
+                "MA-L" => 6,
+                "MA-M" => 7,
+                "MA-S" | "IAB" => 9,
+                _ => continue,
+            };
+            if hex.len() != expected_len
+                || !hex.chars().all(|c| c.is_ascii_hexdigit())
+                || organization.trim().is_empty()
+            {
+                continue;
+            }
+            self.registry.insert(hex, organization.trim().to_string());
+            added += 1;
+        }
+        added
+    }
+
+    /// The vendor name for a MAC address, from the most specific source
+    pub fn lookup(&self, mac: &str) -> Option<String> {
+        let normalized = normalize_mac(mac)?;
+        let hex = normalized.replace(':', "").to_lowercase();
+
+        if let Some((_, vendor)) = self
+            .custom
+            .iter()
+            .find(|(prefix, _)| hex.starts_with(prefix))
+        {
+            tracing::debug!("OUI lookup for {}: found {} (custom)", mac, vendor);
+            return Some(vendor.clone());
+        }
+        for len in ASSIGNMENT_LENGTHS {
+            if let Some(vendor) = self.registry.get(&hex[..len]) {
+                tracing::debug!(
+                    "OUI lookup for {}: found {} (IEEE registry file)",
+                    mac,
+                    vendor
+                );
+                return Some(vendor.clone());
+            }
+        }
+        lookup_vendor(mac)
+    }
+
+    /// Number of custom prefixes
+    pub fn custom_count(&self) -> usize {
+        self.custom.len()
+    }
+
+    /// The registry loaded from the data directory, if any
+    pub fn registry_source(&self) -> Option<&RegistrySource> {
+        self.registry_source.as_ref()
+    }
+}
+
+/// Description of the built-in registry copy
+pub fn builtin_source() -> String {
+    format!(
+        "built-in IEEE registry (agent {})",
+        env!("CARGO_PKG_VERSION")
+    )
+}
+
+/// Directory searched for registry CSV files (it may not exist)
+pub fn registry_dir(config: &OuiConfig) -> Option<PathBuf> {
+    match &config.registry_dir {
+        Some(dir) => Some(PathBuf::from(dir)),
+        None => crate::cloud::config::get_data_dir_path().map(|d| d.join("oui")),
+    }
+}
+
+/// Load the custom prefixes and the registry files, if there are any.
+/// Fails on an invalid custom prefix, an unreadable registry file or one
+/// without a single assignment (an error page saved as oui.csv).
+pub fn load_database(config: &OuiConfig) -> Result<Database> {
+    let mut database = Database::builtin().with_custom(&config.custom)?;
+
+    let Some(dir) = registry_dir(config) else {
+        return Ok(database);
+    };
+    // An explicitly configured directory must exist; the default one is optional
+    if config.registry_dir.is_none() && !dir.exists() {
+        return Ok(database);
+    }
+    if let Some(source) = load_registry_dir(&mut database, &dir)? {
+        database.registry_source = Some(source);
+    }
+    Ok(database)
+}
+
+/// Vendor database for a scan: like `load_database`, but falls back to the
+/// built-in registry with a warning instead of failing the scan.
+pub fn load_database_or_builtin(config: &OuiConfig) -> Database {
+    load_database(config).unwrap_or_else(|e| {
+        tracing::warn!("{:#}; using the built-in OUI database only", e);
+        Database::builtin()
+    })
+}
+
+fn load_registry_dir(database: &mut Database, dir: &Path) -> Result<Option<RegistrySource>> {
+    let entries = std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to read OUI directory {}", dir.display()))?;
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"))
+        })
+        .collect();
+    if paths.is_empty() {
+        return Ok(None);
+    }
+    paths.sort();
+
+    let mut files = Vec::new();
+    let mut updated: Option<DateTime<Utc>> = None;
+    for path in &paths {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read OUI registry {}", path.display()))?;
+        if database.add_registry_csv(&content) == 0 {
+            bail!(
+                "OUI registry {} has no MA-L/MA-M/MA-S assignments",
+                path.display()
+            );
+        }
+        if let Ok(modified) = std::fs::metadata(path).and_then(|m| m.modified()) {
+            let modified = DateTime::<Utc>::from(modified);
+            updated = Some(updated.map_or(modified, |u| u.max(modified)));
+        }
+        files.push(
+            path.file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+        );
+    }
+
+    let source = RegistrySource {
+        dir: dir.to_path_buf(),
+        files,
+        entries: database.registry.len(),
+        updated,
+    };
+    tracing::debug!("OUI: using {}", source);
+    Ok(Some(source))
+}
+
+/// Split a CSV line into fields, honoring double quotes ("" inside quotes
+/// is a literal quote)
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
 
 /// Lookup the vendor/manufacturer name for a MAC address.
 ///
@@ -60,244 +322,188 @@ fn normalize_mac(mac: &str) -> Option<String> {
     ))
 }
 
-/// Infer device type from vendor name.
-///
-/// Categories:
-/// - firewall: Security appliances
-/// - network_device: Network equipment (routers, switches, APs)
-/// - service: Virtualization and container hosts
-/// - server: Server hardware
-/// - nas: Network attached storage
-/// - apple: Apple devices
-/// - iot: Smart home and IoT devices
-/// - printer: Printers and scanners
-/// - gaming: Gaming consoles
-/// - mobile: Mobile phones and tablets
-/// - computer: Desktop/laptop computers
-pub fn infer_device_type(vendor: &str) -> Option<&'static str> {
-    let vendor_lower = vendor.to_lowercase();
-
-    // Firewall / Security appliances
-    if vendor_lower.contains("firewalla")
-        || vendor_lower.contains("pfsense")
-        || vendor_lower.contains("opnsense")
-        || vendor_lower.contains("sophos")
-        || vendor_lower.contains("watchguard")
-        || vendor_lower.contains("sonicwall")
-        || vendor_lower.contains("barracuda")
-        || vendor_lower.contains("checkpoint")
-        || vendor_lower.contains("forcepoint")
-        || vendor_lower.contains("untangle")
-    {
-        return Some("firewall");
-    }
+/// Whether a MAC address belongs to a hypervisor's or container runtime's
+/// range. Docker's locally administered prefix is not in the OUI database.
+pub fn is_virtual_mac(mac: &str) -> bool {
+    let mac_normalized = mac.replace([':', '-', '.'], "").to_lowercase();
 
-    // Virtualization / Container hosts
-    if vendor_lower.contains("proxmox")
-        || vendor_lower.contains("vmware")
-        || vendor_lower.contains("xensource")
-        || vendor_lower.contains("parallels")
-        || vendor_lower.contains("virtualbox")
-        || vendor_lower.contains("qemu")
-        || vendor_lower.contains("docker")
-        || vendor_lower.contains("kubernetes")
-    {
-        return Some("service");
+    if mac_normalized.len() < 6 {
+        return false;
     }
 
-    // Network equipment
-    if vendor_lower.contains("cisco")
-        || vendor_lower.contains("juniper")
-        || vendor_lower.contains("arista")
-        || vendor_lower.contains("ubiquiti")
-        || vendor_lower.contains("netgear")
-        || vendor_lower.contains("tp-link")
-        || vendor_lower.contains("linksys")
-        || vendor_lower.contains("d-link")
-        || vendor_lower.contains("mikrotik")
-        || vendor_lower.contains("aruba")
-        || vendor_lower.contains("ruckus")
-        || vendor_lower.contains("fortinet")
-        || vendor_lower.contains("palo alto")
-        || vendor_lower.contains("zyxel")
-        || vendor_lower.contains("draytek")
-        || vendor_lower.contains("meraki")
-        || vendor_lower.contains("cambium")
-        || vendor_lower.contains("routerboard")
-    {
-        return Some("network_device");
-    }
+    let prefix = &mac_normalized[..6];
 
-    // Server hardware
-    if vendor_lower.contains("supermicro")
-        || vendor_lower.contains("dell emc")
-        || vendor_lower.contains("hpe")
-        || vendor_lower.contains("hewlett packard enterprise")
-        || vendor_lower.contains("ibm")
-        || vendor_lower.contains("oracle")
-        || vendor_lower.contains("fujitsu")
-        || vendor_lower.contains("inspur")
-    {
-        return Some("server");
-    }
+    matches!(
+        prefix,
+        // Docker containers
+        "0242ac"
+            // VMware
+            | "005056" | "000c29" | "000569"
+            // Xen
+            | "00163e"
+            // Microsoft Hyper-V
+            | "00155d"
+            // Parallels
+            | "001c42"
+            // QEMU/KVM
+            | "525400"
+            // VirtualBox
+            | "080027"
+            // Proxmox VE
+            | "bc2411"
+    )
+}
 
-    // Apple devices
-    if vendor_lower.contains("apple") {
-        return Some("apple");
-    }
+/// Whether the locally administered bit (0x02 of the first octet) is set:
+/// the address was assigned by software, not burned in by the vendor.
+pub fn is_locally_administered(mac: &str) -> bool {
+    let hex = mac.replace([':', '-', '.'], "");
+    hex.get(..2)
+        .and_then(|octet| u8::from_str_radix(octet, 16).ok())
+        .is_some_and(|octet| octet & 0x02 != 0)
+}
 
-    // NAS/Storage
-    if vendor_lower.contains("synology")
-        || vendor_lower.contains("qnap")
-        || vendor_lower.contains("western digital")
-        || vendor_lower.contains("buffalo")
-        || vendor_lower.contains("drobo")
-        || vendor_lower.contains("netgear readynas")
-        || vendor_lower.contains("ugreen")
-        || vendor_lower.contains("asustor")
-        || vendor_lower.contains("terramaster")
-    {
-        return Some("nas");
-    }
+/// Whether a MAC address looks randomized for privacy (phones, laptops):
+/// locally administered, but not one of the hypervisor or container ranges
+/// that are locally administered and stable.
+pub fn is_randomized_mac(mac: &str) -> bool {
+    is_locally_administered(mac) && !is_virtual_mac(mac)
+}
 
-    // Smart home / IoT
-    if vendor_lower.contains("sonos")
-        || vendor_lower.contains("philips")
-        || vendor_lower.contains("signify")
-        || vendor_lower.contains("ring")
-        || vendor_lower.contains("nest")
-        || vendor_lower.contains("ecobee")
-        || vendor_lower.contains("wyze")
-        || vendor_lower.contains("tuya")
-        || vendor_lower.contains("shelly")
-        || vendor_lower.contains("espressif")
-        || vendor_lower.contains("amazon")
-        || vendor_lower.contains("google")
-        || vendor_lower.contains("roku")
-        || vendor_lower.contains("wemo")
-        || vendor_lower.contains("lifx")
-        || vendor_lower.contains("nanoleaf")
-    {
-        return Some("iot");
-    }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
 
-    // Printers
-    if vendor_lower.contains("hewlett packard")
-        || vendor_lower.contains("hp inc")
-        || vendor_lower.contains("canon")
-        || vendor_lower.contains("epson")
-        || vendor_lower.contains("brother")
-        || vendor_lower.contains("xerox")
-        || vendor_lower.contains("lexmark")
-        || vendor_lower.contains("ricoh")
-        || vendor_lower.contains("konica")
-        || vendor_lower.contains("kyocera")
-    {
-        return Some("printer");
+    #[test]
+    fn test_lookup_vendor_invalid_mac() {
+        assert!(lookup_vendor("invalid").is_none());
+        assert!(lookup_vendor("00:ZZ:XX").is_none());
+        assert!(lookup_vendor("00").is_none());
     }
 
-    // Gaming consoles
-    if vendor_lower.contains("sony")
-        || vendor_lower.contains("nintendo")
-        || vendor_lower.contains("microsoft")
-        || vendor_lower.contains("valve")
-    {
-        return Some("gaming");
-    }
+    #[test]
+    fn test_lookup_known_vendors() {
+        let apple = lookup_vendor("00:17:F2:00:00:00");
+        assert!(apple.is_some(), "Apple MAC should be found");
+        assert!(
+            apple.unwrap().to_lowercase().contains("apple"),
+            "Should contain 'apple'"
+        );
 
-    // Mobile devices
-    if vendor_lower.contains("samsung")
-        || vendor_lower.contains("huawei")
-        || vendor_lower.contains("xiaomi")
-        || vendor_lower.contains("oneplus")
-        || vendor_lower.contains("oppo")
-        || vendor_lower.contains("vivo")
-        || vendor_lower.contains("motorola")
-        || vendor_lower.contains("lg electronics")
-        || vendor_lower.contains("realme")
-        || vendor_lower.contains("honor")
-    {
-        return Some("mobile");
+        assert!(
+            lookup_vendor("00:00:0C:00:00:00").is_some(),
+            "Cisco MAC should be found"
+        );
+        assert!(
+            lookup_vendor("00:02:B3:00:00:00").is_some(),
+            "Intel MAC should be found"
+        );
+        assert!(
+            lookup_vendor("04:F4:1C:00:00:00").is_some(),
+            "MikroTik RouterBOARD MAC should be found"
+        );
     }
 
-    // PC manufacturers
-    if vendor_lower.contains("dell")
-        || vendor_lower.contains("lenovo")
-        || vendor_lower.contains("acer")
-        || vendor_lower.contains("asus")
-        || vendor_lower.contains("asustek")
-        || vendor_lower.contains("intel")
-        || vendor_lower.contains("realtek")
-        || vendor_lower.contains("gigabyte")
-        || vendor_lower.contains("msi")
-        || vendor_lower.contains("hp ")
-        || vendor_lower.contains("toshiba")
-    {
-        return Some("computer");
+    #[test]
+    fn test_lookup_lowercase_and_dashed_mac() {
+        assert!(
+            lookup_vendor("00:17:f2:aa:bb:cc").is_some(),
+            "Lowercase Apple MAC should be found"
+        );
+        assert!(
+            lookup_vendor("00-02-b3-Aa-Bb-Cc").is_some(),
+            "Dashed mixed case Intel MAC should be found"
+        );
     }
 
-    None
-}
-
-/// Infer device type from MAC address OUI prefix.
-/// Some MAC prefixes indicate VMs or containers.
-pub fn infer_device_type_from_mac(mac: &str) -> Option<&'static str> {
-    let mac_normalized = mac.replace([':', '-', '.'], "").to_lowercase();
-
-    if mac_normalized.len() < 6 {
-        return None;
+    #[test]
+    fn test_is_virtual_mac() {
+        assert!(is_virtual_mac("00:50:56:12:34:56"));
+        assert!(is_virtual_mac("02:42:ac:12:34:56"));
+        assert!(is_virtual_mac("08-00-27-12-34-56"));
+        assert!(!is_virtual_mac("00:17:F2:12:34:56"));
+        assert!(!is_virtual_mac("00:50"));
     }
 
-    let prefix = &mac_normalized[..6];
-
-    match prefix {
-        // Docker containers
-        "0242ac" => Some("service"),
-        // VMware
-        "005056" | "000c29" | "000569" => Some("service"),
-        // Xen
-        "00163e" => Some("service"),
-        // Microsoft Hyper-V
-        "00155d" => Some("service"),
-        // Parallels
-        "001c42" => Some("service"),
-        // QEMU/KVM
-        "525400" => Some("service"),
-        // VirtualBox
-        "080027" => Some("service"),
-        // Proxmox VE
-        "bc2411" => Some("service"),
-        _ => None,
+    #[test]
+    fn test_is_randomized_mac() {
+        // iPhone and Android private addresses
+        assert!(is_randomized_mac("a6:3f:0e:91:7d:22"));
+        assert!(is_randomized_mac("2E-7D-10-C4-9A-3B"));
+        // Vendor-assigned
+        assert!(!is_randomized_mac("3c:52:82:1a:2b:3c"));
+        // Locally administered, but Docker and QEMU keep theirs
+        assert!(is_locally_administered("02:42:ac:11:00:02"));
+        assert!(!is_randomized_mac("02:42:ac:11:00:02"));
+        assert!(!is_randomized_mac("52:54:00:8e:3d:7f"));
+        assert!(!is_randomized_mac("zz:00:00:00:00:00"));
+        assert!(!is_randomized_mac(""));
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
 
     #[test]
-    fn test_infer_device_type() {
+    fn test_registry_csv() {
+        let content = include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/oui_registry.csv"
+        ));
+        let mut database = Database::builtin();
+        assert_eq!(database.add_registry_csv(content), 5);
+
+        assert_eq!(
+            database.lookup("f4:a6:e5:00:11:22").as_deref(),
+            Some("Meridian Test Systems")
+        );
+        // The MA-M and MA-S blocks win over the MA-L they are carved from
+        assert_eq!(
+            database.lookup("F4-A6-E5-1A-BB-CC").as_deref(),
+            Some("Tinkerworks, Ltd.")
+        );
+        assert_eq!(
+            database.lookup("70:b3:d5:f2:c0:01").as_deref(),
+            Some("Rivet \"Labs\" GmbH")
+        );
         assert_eq!(
-            infer_device_type("Cisco Systems, Inc."),
-            Some("network_device")
+            database.lookup("70:b3:d5:00:00:01").as_deref(),
+            Some("IEEE Registration Authority")
         );
-        assert_eq!(infer_device_type("Apple, Inc."), Some("apple"));
-        assert_eq!(infer_device_type("Synology Incorporated"), Some("nas"));
-        assert_eq!(infer_device_type("VMware, Inc."), Some("service"));
-        assert_eq!(infer_device_type("Firewalla Inc."), Some("firewall"));
-        assert_eq!(infer_device_type("Unknown Vendor"), None);
+        // Not in the file: the built-in copy answers
+        assert!(database.lookup("00:00:0C:00:00:00").is_some());
+
+        assert_eq!(database.add_registry_csv("<html>Not Found</html>"), 0);
     }
 
     #[test]
-    fn test_infer_device_type_from_mac() {
+    fn test_custom_prefixes() {
+        let custom = BTreeMap::from([
+            ("F4:A6:E5".to_string(), "Meridian Test Rig".to_string()),
+            ("f4a6e512".to_string(), "Meridian Sensor".to_string()),
+            ("00-17-F2".to_string(), "Lab Apple Pool".to_string()),
+        ]);
+        let database = Database::builtin().with_custom(&custom).unwrap();
+        assert_eq!(database.custom_count(), 3);
+
+        // Longest prefix first, and ahead of the registry
+        assert_eq!(
+            database.lookup("f4:a6:e5:12:34:56").as_deref(),
+            Some("Meridian Sensor")
+        );
         assert_eq!(
-            infer_device_type_from_mac("00:50:56:12:34:56"),
-            Some("service")
+            database.lookup("f4:a6:e5:99:34:56").as_deref(),
+            Some("Meridian Test Rig")
         );
         assert_eq!(
-            infer_device_type_from_mac("02:42:ac:12:34:56"),
-            Some("service")
+            database.lookup("00:17:f2:00:00:00").as_deref(),
+            Some("Lab Apple Pool")
         );
-        assert_eq!(infer_device_type_from_mac("00:17:F2:12:34:56"), None);
+
+        for bad in ["F4:A6", "F4:A6:E5:12:34:56:78", "ZZ:A6:E5"] {
+            let custom = BTreeMap::from([(bad.to_string(), "x".to_string())]);
+            assert!(
+                Database::builtin().with_custom(&custom).is_err(),
+                "{} should be rejected",
+                bad
+            );
+        }
     }
 }
@@ -1,32 +1,110 @@
-//! Ping sweep using system ping command
+//! Ping sweep using the in-process ICMP engine, with the system ping command
+//! as fallback
 
-use super::{hidden_command_sync, is_scan_cancelled, Device};
+use super::config::{PingConfig, ScanConfig};
+use super::icmp::{self, PingEngine};
+use super::{hidden_command_sync, is_scan_cancelled, Device, ScanTarget};
 use anyhow::{Context, Result};
 use ipnetwork::IpNetwork;
+use std::net::Ipv4Addr;
 use std::time::Instant;
 
-/// Perform a ping sweep of the subnet using the system ping command.
+/// Perform a ping sweep of the planned hosts (see `plan_sweep`).
 /// Supports cancellation via `request_scan_cancel()`.
-pub async fn ping_sweep(subnet: &str) -> Result<Vec<Device>> {
-    let ip_net: IpNetwork = subnet.parse().context("Failed to parse subnet")?;
+pub async fn ping_sweep(hosts: &[Ipv4Addr], engine: PingEngine, config: &PingConfig) -> Result<Vec<Device>> {
+    tracing::info!("Pinging {} hosts", hosts.len());
 
-    let mut devices = Vec::new();
+    if matches!(engine, PingEngine::Datagram | PingEngine::Raw) {
+        match icmp::sweep(engine, hosts.to_vec(), config).await {
+            Ok(replies) => {
+                return Ok(replies
+                    .into_iter()
+                    .map(|reply| Device {
+                        response_time_ms: Some(reply.rtt_ms),
+                        ttl: reply.ttl,
+                        ..Device::new(reply.ip.to_string())
+                    })
+                    .collect());
+            }
+            Err(e) if is_scan_cancelled() => return Err(e),
+            Err(e) => tracing::warn!("ICMP socket sweep failed, falling back to system ping: {}", e),
+        }
+    }
 
-    // Generate IP list (skip network and broadcast addresses)
-    let ips: Vec<std::net::IpAddr> = ip_net
+    subprocess_sweep(hosts, config).await
+}
+
+/// Addresses to sweep for each target, in target order. Excluded addresses
+/// are skipped and the total is capped at `max_hosts`; later targets are
+/// truncated (with a warning) once the cap is reached.
+pub(super) fn plan_sweep(targets: &[ScanTarget], config: &ScanConfig) -> Vec<Vec<Ipv4Addr>> {
+    let exclude = config.exclude_networks();
+    let mut budget = config.max_hosts;
+
+    targets
         .iter()
-        .skip(1) // Skip network address
-        .take(253) // Limit to prevent scanning too many hosts
-        .collect();
+        .map(|target| {
+            let mut hosts = match target.subnet.parse::<IpNetwork>() {
+                Ok(net) => sweep_targets(&net).unwrap_or_default(),
+                Err(e) => {
+                    tracing::warn!("Skipping sweep of '{}': {}", target.subnet, e);
+                    Vec::new()
+                }
+            };
+
+            let before = hosts.len();
+            hosts.retain(|ip| !exclude.iter().any(|net| net.contains((*ip).into())));
+            if hosts.len() < before {
+                tracing::info!(
+                    "Skipping {} excluded addresses in {}",
+                    before - hosts.len(),
+                    target.subnet
+                );
+            }
+
+            if hosts.len() > budget {
+                tracing::warn!(
+                    "Sweeping only {} of {} addresses in {} ([scan] max_hosts = {})",
+                    budget,
+                    hosts.len(),
+                    target.subnet,
+                    config.max_hosts
+                );
+                hosts.truncate(budget);
+            }
+            budget -= hosts.len();
+
+            hosts
+        })
+        .collect()
+}
 
-    let total_hosts = ips.len();
-    tracing::info!("Pinging {} hosts in subnet {}", total_hosts, subnet);
+/// Hosts to sweep in an IPv4 subnet (network and broadcast addresses skipped).
+fn sweep_targets(ip_net: &IpNetwork) -> Result<Vec<Ipv4Addr>> {
+    let IpNetwork::V4(net) = ip_net else {
+        return Err(anyhow::anyhow!(
+            "Ping sweep only supports IPv4 subnets ({} given)",
+            ip_net
+        ));
+    };
+
+    let (network, broadcast) = (net.network(), net.broadcast());
+    Ok(net
+        .iter()
+        .filter(|ip| net.prefix() >= 31 || (*ip != network && *ip != broadcast))
+        .collect())
+}
+
+/// Sweep with one system `ping` process per host.
+async fn subprocess_sweep(targets: &[Ipv4Addr], config: &PingConfig) -> Result<Vec<Device>> {
+    let mut devices = Vec::new();
+    let total_hosts = targets.len();
 
     // High parallelism for fast scanning
     let batch_size = 50;
     let mut completed = 0;
 
-    for (batch_idx, batch) in ips.chunks(batch_size).enumerate() {
+    for (batch_idx, batch) in targets.chunks(batch_size).enumerate() {
         // Check for cancellation before starting each batch
         if is_scan_cancelled() {
             tracing::info!("Ping sweep cancelled after {} hosts", completed);
@@ -37,14 +115,23 @@ pub async fn ping_sweep(subnet: &str) -> Result<Vec<Device>> {
 
         for ip in batch {
             let ip_str = ip.to_string();
-            let handle = tokio::spawn(async move { ping_host(&ip_str).await });
+            let timeout_ms = config.timeout_ms;
+            let attempts = config.retries + 1;
+            let handle = tokio::spawn(async move {
+                for _ in 0..attempts {
+                    if let Ok(Some(device)) = ping_host(&ip_str, timeout_ms).await {
+                        return Some(device);
+                    }
+                }
+                None
+            });
             batch_handles.push(handle);
         }
 
         // Wait for this batch to complete
         let mut batch_found = 0;
         for handle in batch_handles {
-            if let Ok(Ok(Some(device))) = handle.await {
+            if let Ok(Some(device)) = handle.await {
                 devices.push(device);
                 batch_found += 1;
             }
@@ -65,7 +152,7 @@ pub async fn ping_sweep(subnet: &str) -> Result<Vec<Device>> {
 }
 
 /// Ping a single host using the system ping command.
-async fn ping_host(ip: &str) -> Result<Option<Device>> {
+async fn ping_host(ip: &str, timeout_ms: u64) -> Result<Option<Device>> {
     let ip_owned = ip.to_string();
 
     let result = tokio::task::spawn_blocking(move || {
@@ -73,12 +160,19 @@ async fn ping_host(ip: &str) -> Result<Option<Device>> {
 
         #[cfg(target_os = "windows")]
         let output = hidden_command_sync("ping")
-            .args(["-n", "1", "-w", "1000", &ip_owned])
+            .args(["-n", "1", "-w", &timeout_ms.to_string(), &ip_owned])
             .output();
 
+        // -W takes whole seconds
         #[cfg(any(target_os = "linux", target_os = "macos"))]
         let output = hidden_command_sync("ping")
-            .args(["-c", "1", "-W", "1", &ip_owned])
+            .args([
+                "-c",
+                "1",
+                "-W",
+                &timeout_ms.div_ceil(1000).max(1).to_string(),
+                &ip_owned,
+            ])
             .output();
 
         #[cfg(not(any(target_os = "windows", target_os = "linux", target_os = "macos")))]
@@ -120,12 +214,9 @@ async fn ping_host(ip: &str) -> Result<Option<Device>> {
                 let ping_time = parse_ping_time(&output_str).unwrap_or(response_time_ms);
 
                 Ok(Some(Device {
-                    ip: ip_owned,
-                    mac: None,
                     response_time_ms: Some(ping_time),
-                    hostname: None,
-                    vendor: None,
-                    device_type: None,
+                    ttl: parse_ping_ttl(&output_str),
+                    ..Device::new(ip_owned)
                 }))
             }
             Err(_) => Ok(None),
@@ -139,6 +230,92 @@ async fn ping_host(ip: &str) -> Result<Option<Device>> {
     }
 }
 
+/// IPv6 all-nodes link-local multicast group
+const ALL_NODES_MULTICAST: &str = "ff02::1";
+
+/// Ping the IPv6 all-nodes multicast group on an interface.
+///
+/// Every IPv6 host on the link answers, which also populates the kernel
+/// neighbor cache so MACs can be read back afterwards.
+pub async fn probe_all_nodes(interface: &str) -> Result<Vec<Device>> {
+    let target = format!("{}%{}", ALL_NODES_MULTICAST, interface);
+
+    let output = tokio::task::spawn_blocking(move || {
+        #[cfg(target_os = "linux")]
+        let output = hidden_command_sync("ping")
+            .args(["-6", "-c", "2", "-i", "0.5", "-w", "3", &target])
+            .output();
+
+        #[cfg(target_os = "macos")]
+        let output = hidden_command_sync("ping6")
+            .args(["-c", "2", "-i", "1", &target])
+            .output();
+
+        // Windows ping does not report multicast replies
+        #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+        let output: std::io::Result<std::process::Output> = {
+            let _ = target;
+            Err(std::io::Error::other("Multicast ping not supported"))
+        };
+
+        output
+    })
+    .await
+    .context("Multicast ping task panicked")?
+    .context("Failed to run multicast ping")?;
+
+    let output_str = String::from_utf8_lossy(&output.stdout);
+    let devices = parse_multicast_replies(&output_str)
+        .into_iter()
+        .map(|(ip, time)| Device {
+            response_time_ms: Some(time),
+            ..Device::new(ip)
+        })
+        .collect::<Vec<_>>();
+
+    tracing::debug!(
+        "All-nodes probe on {} answered by {} hosts",
+        interface,
+        devices.len()
+    );
+
+    Ok(devices)
+}
+
+/// Parse reply lines from a multicast ping, keeping the fastest reply
+/// per source address.
+///
+/// Linux: `64 bytes from fe80::1%eth0: icmp_seq=1 ttl=64 time=0.45 ms`
+/// macOS: `16 bytes from fe80::1%en0, icmp_seq=0 hlim=64 time=0.45 ms`
+fn parse_multicast_replies(output: &str) -> Vec<(String, f64)> {
+    let mut replies: Vec<(String, f64)> = Vec::new();
+
+    for line in output.lines() {
+        let Some(rest) = line.split(" from ").nth(1) else {
+            continue;
+        };
+        let source = rest
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .trim_end_matches([':', ',']);
+        let ip = source.split('%').next().unwrap_or_default();
+
+        if ip.parse::<std::net::Ipv6Addr>().is_err() {
+            continue;
+        }
+
+        let time = parse_ping_time(line).unwrap_or(0.0);
+        match replies.iter_mut().find(|(addr, _)| addr == ip) {
+            Some(existing) if time > 0.0 && time < existing.1 => existing.1 = time,
+            Some(_) => {}
+            None => replies.push((ip.to_string(), time)),
+        }
+    }
+
+    replies
+}
+
 /// Parse ping response time from command output
 fn parse_ping_time(output: &str) -> Option<f64> {
     for word in output.split_whitespace() {
@@ -155,3 +332,105 @@ fn parse_ping_time(output: &str) -> Option<f64> {
     }
     None
 }
+
+/// Parse the reply TTL from command output (`ttl=64`, Windows `TTL=128`)
+fn parse_ping_ttl(output: &str) -> Option<u8> {
+    output.split_whitespace().find_map(|word| {
+        let (key, value) = word.split_once('=')?;
+        if key.eq_ignore_ascii_case("ttl") {
+            value.parse().ok()
+        } else {
+            None
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sweep_targets_skip_network_and_broadcast() {
+        let targets = sweep_targets(&"192.168.1.0/30".parse().unwrap()).unwrap();
+        assert_eq!(
+            targets,
+            vec![Ipv4Addr::new(192, 168, 1, 1), Ipv4Addr::new(192, 168, 1, 2)]
+        );
+
+        // Larger than a /24 is no longer truncated
+        let targets = sweep_targets(&"10.0.0.0/22".parse().unwrap()).unwrap();
+        assert_eq!(targets.len(), 1022);
+        assert_eq!(targets.last(), Some(&Ipv4Addr::new(10, 0, 3, 254)));
+
+        assert!(sweep_targets(&"2001:db8::/64".parse().unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_plan_sweep_exclude_and_max_hosts() {
+        let target = |subnet: &str| ScanTarget {
+            interface: "eth0".to_string(),
+            subnet: subnet.to_string(),
+            gateway_ip: None,
+            local_ip: None,
+            ipv6_prefixes: Vec::new(),
+        };
+        let config = ScanConfig {
+            exclude: vec!["10.0.0.2".to_string(), "10.0.1.0/29".to_string()],
+            max_hosts: 260,
+            ..Default::default()
+        };
+
+        let plan = plan_sweep(&[target("10.0.0.0/24"), target("10.0.1.0/24")], &config);
+        assert_eq!(plan[0].len(), 253);
+        assert!(!plan[0].contains(&Ipv4Addr::new(10, 0, 0, 2)));
+
+        // 7 left in the budget; 10.0.1.1-7 are excluded
+        assert_eq!(plan[1].len(), 7);
+        assert_eq!(plan[1][0], Ipv4Addr::new(10, 0, 1, 8));
+    }
+
+    #[test]
+    fn test_parse_multicast_replies_linux() {
+        let output = "\
+PING ff02::1%eth0(ff02::1%eth0) 56 data bytes
+64 bytes from fe80::a263:91ff:fe12:3456%eth0: icmp_seq=1 ttl=64 time=0.045 ms
+64 bytes from fe80::217:f2ff:feab:cdef%eth0: icmp_seq=1 ttl=64 time=3.21 ms (DUP!)
+64 bytes from fe80::a263:91ff:fe12:3456%eth0: icmp_seq=2 ttl=64 time=0.031 ms
+64 bytes from fe80::217:f2ff:feab:cdef%eth0: icmp_seq=2 ttl=64 time=2.80 ms (DUP!)
+
+--- ff02::1%eth0 ping statistics ---
+2 packets transmitted, 2 received, +2 duplicates, 0% packet loss, time 501ms
+";
+        let replies = parse_multicast_replies(output);
+        assert_eq!(
+            replies,
+            vec![
+                ("fe80::a263:91ff:fe12:3456".to_string(), 0.031),
+                ("fe80::217:f2ff:feab:cdef".to_string(), 2.80),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_multicast_replies_macos() {
+        let output = "\
+PING6(56=40+8+8 bytes) fe80::1c2d:3e4f:5a6b:7c8d%en0 --> ff02::1%en0
+16 bytes from fe80::1c2d:3e4f:5a6b:7c8d%en0, icmp_seq=0 hlim=64 time=0.120 ms
+16 bytes from 2001:db8:1::1, icmp_seq=0 hlim=64 time=4.512 ms
+";
+        let replies = parse_multicast_replies(output);
+        assert_eq!(replies.len(), 2);
+        assert_eq!(replies[1], ("2001:db8:1::1".to_string(), 4.512));
+    }
+
+    #[test]
+    fn test_parse_ping_ttl() {
+        let linux = "64 bytes from 192.168.1.10: icmp_seq=1 ttl=64 time=0.412 ms";
+        assert_eq!(parse_ping_ttl(linux), Some(64));
+
+        let windows = "Reply from 192.168.1.20: bytes=32 time<1ms TTL=128";
+        assert_eq!(parse_ping_ttl(windows), Some(128));
+
+        assert_eq!(parse_ping_ttl("Request timed out."), None);
+    }
+}
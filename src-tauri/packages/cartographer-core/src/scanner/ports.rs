@@ -0,0 +1,331 @@
+//! TCP connect port probing
+//!
+//! A full TCP handshake per (host, port) needs no privileges. Probes run
+//! with bounded concurrency and a short connect timeout; a refused or timed
+//! out connection counts as closed. On Linux the kernel's view of the
+//! handshake (SYN-ACK window and options) is kept for OS fingerprinting.
+
+use super::config::PortScanConfig;
+use super::is_scan_cancelled;
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+/// Most commonly open TCP ports (nmap top 100)
+pub const TOP_PORTS: [u16; 100] = [
+    7, 9, 13, 21, 22, 23, 25, 26, 37, 53, 79, 80, 81, 88, 106, 110, 111, 113, 119, 135, 139, 143,
+    144, 179, 199, 389, 427, 443, 444, 445, 465, 513, 514, 515, 543, 544, 548, 554, 587, 631, 646,
+    873, 990, 993, 995, 1025, 1026, 1027, 1028, 1029, 1110, 1433, 1720, 1723, 1755, 1900, 2000,
+    2001, 2049, 2121, 2717, 3000, 3128, 3306, 3389, 3986, 4899, 5000, 5009, 5051, 5060, 5101, 5190,
+    5357, 5432, 5631, 5666, 5800, 5900, 6000, 6001, 6646, 7070, 8000, 8008, 8009, 8080, 8081, 8443,
+    8888, 9100, 9999, 10000, 32768, 49152, 49153, 49154, 49155, 49156, 49157,
+];
+
+/// An open TCP port and the service usually found on it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OpenPort {
+    pub port: u16,
+    /// Guessed from the port number (e.g. "ssh", "http")
+    pub service: Option<String>,
+}
+
+impl OpenPort {
+    pub fn new(port: u16) -> Self {
+        Self {
+            port,
+            service: service_name(port).map(str::to_string),
+        }
+    }
+}
+
+impl std::fmt::Display for OpenPort {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.service {
+            Some(service) => write!(f, "{}/{}", self.port, service),
+            None => write!(f, "{}", self.port),
+        }
+    }
+}
+
+/// Window and options of a host's SYN-ACK, read back after a handshake
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TcpFingerprint {
+    /// Port the handshake was made with
+    pub port: u16,
+    /// Receive window advertised in the SYN-ACK (unscaled). Needs a kernel
+    /// that reports `tcpi_snd_wnd`.
+    pub window: Option<u32>,
+    /// Window scale option, if offered
+    pub window_scale: Option<u8>,
+    pub sack: bool,
+    pub timestamps: bool,
+}
+
+/// Open ports found on one host
+#[derive(Debug, Default)]
+pub struct HostPorts {
+    pub open: Vec<OpenPort>,
+    /// Handshake of the lowest open port
+    pub tcp: Option<TcpFingerprint>,
+}
+
+/// Well-known service name for a TCP port
+pub fn service_name(port: u16) -> Option<&'static str> {
+    Some(match port {
+        7 => "echo",
+        9 => "discard",
+        13 => "daytime",
+        21 => "ftp",
+        22 => "ssh",
+        23 => "telnet",
+        25 | 26 => "smtp",
+        37 => "time",
+        53 => "dns",
+        79 => "finger",
+        80 | 81 | 8000 | 8008 | 8080 | 8081 | 8888 => "http",
+        88 => "kerberos",
+        102 => "s7comm",
+        110 => "pop3",
+        111 => "rpcbind",
+        113 => "ident",
+        119 => "nntp",
+        135 => "msrpc",
+        139 => "netbios-ssn",
+        143 => "imap",
+        161 => "snmp",
+        179 => "bgp",
+        389 => "ldap",
+        427 => "svrloc",
+        443 | 8443 => "https",
+        445 => "microsoft-ds",
+        465 => "smtps",
+        502 => "modbus",
+        513 => "rlogin",
+        514 => "rsh",
+        515 => "printer",
+        548 => "afp",
+        554 => "rtsp",
+        587 => "submission",
+        631 => "ipp",
+        636 => "ldaps",
+        873 => "rsync",
+        990 => "ftps",
+        993 => "imaps",
+        995 => "pop3s",
+        1433 => "ms-sql",
+        1720 => "h323",
+        1723 => "pptp",
+        1883 => "mqtt",
+        1900 => "upnp",
+        2049 => "nfs",
+        3128 => "squid-http",
+        3306 => "mysql",
+        3389 => "rdp",
+        4899 => "radmin",
+        5000 => "upnp",
+        5060 => "sip",
+        5357 => "wsdapi",
+        5432 => "postgresql",
+        5631 => "pcanywhere",
+        5666 => "nrpe",
+        5800 => "vnc-http",
+        5900 => "vnc",
+        6000 | 6001 => "x11",
+        6379 => "redis",
+        8009 => "ajp13",
+        9100 => "jetdirect",
+        27017 => "mongodb",
+        49152..=49157 => "msrpc",
+        _ => return None,
+    })
+}
+
+/// Probe `ports` on every host and return the open ports per host address.
+///
+/// Stops starting new probes once the scan is cancelled and returns what
+/// was found so far.
+pub async fn scan_ports(
+    hosts: &[String],
+    ports: &[u16],
+    config: &PortScanConfig,
+) -> HashMap<String, HostPorts> {
+    let timeout = Duration::from_millis(config.timeout_ms);
+    let probes: Vec<(String, SocketAddr)> = hosts
+        .iter()
+        .filter_map(|host| host.parse::<IpAddr>().ok().map(|ip| (host, ip)))
+        .flat_map(|(host, ip)| {
+            ports
+                .iter()
+                .map(move |&port| (host.clone(), SocketAddr::new(ip, port)))
+        })
+        .collect();
+
+    tracing::info!(
+        "Probing {} ports on {} hosts ({} connections, concurrency {})",
+        ports.len(),
+        hosts.len(),
+        probes.len(),
+        config.concurrency
+    );
+
+    let open: Vec<(String, u16, Option<TcpFingerprint>)> = stream::iter(probes)
+        .map(|(host, addr)| async move {
+            if is_scan_cancelled() {
+                return None;
+            }
+            let tcp = probe(addr, timeout).await?;
+            Some((host, addr.port(), tcp))
+        })
+        .buffer_unordered(config.concurrency.max(1))
+        .filter_map(|result| async move { result })
+        .collect()
+        .await;
+
+    let mut by_host: HashMap<String, HostPorts> = HashMap::new();
+    for (host, port, tcp) in open {
+        let found = by_host.entry(host).or_default();
+        found.open.push(OpenPort::new(port));
+        if let Some(tcp) = tcp
+            && found.tcp.as_ref().is_none_or(|t| t.port > port)
+        {
+            found.tcp = Some(tcp);
+        }
+    }
+    for found in by_host.values_mut() {
+        found.open.sort_by_key(|p| p.port);
+    }
+
+    by_host
+}
+
+/// Connect to `addr`; `Some` if the handshake completes within `timeout`,
+/// with its fingerprint where the platform reports one.
+async fn probe(addr: SocketAddr, timeout: Duration) -> Option<Option<TcpFingerprint>> {
+    match tokio::time::timeout(timeout, tokio::net::TcpStream::connect(addr)).await {
+        Ok(Ok(stream)) => Some(handshake_fingerprint(&stream, addr.port())),
+        _ => None,
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn handshake_fingerprint(stream: &tokio::net::TcpStream, port: u16) -> Option<TcpFingerprint> {
+    use std::os::fd::AsRawFd;
+
+    let mut info = [0u8; 256];
+    let mut len = info.len() as libc::socklen_t;
+    let result = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::IPPROTO_TCP,
+            libc::TCP_INFO,
+            info.as_mut_ptr() as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    if result < 0 {
+        return None;
+    }
+    parse_tcp_info(&info[..(len as usize).min(info.len())], port)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn handshake_fingerprint(_stream: &tokio::net::TcpStream, _port: u16) -> Option<TcpFingerprint> {
+    None
+}
+
+/// Read the peer's options and window from Linux `struct tcp_info`
+/// (include/uapi/linux/tcp.h). Right after connect, `tcpi_snd_wnd` still
+/// holds the SYN-ACK window.
+#[cfg(any(target_os = "linux", test))]
+fn parse_tcp_info(info: &[u8], port: u16) -> Option<TcpFingerprint> {
+    const TCPI_OPT_TIMESTAMPS: u8 = 1;
+    const TCPI_OPT_SACK: u8 = 2;
+    const TCPI_OPT_WSCALE: u8 = 4;
+    const OPTIONS_OFFSET: usize = 5;
+    /// Low nibble: `tcpi_snd_wscale`
+    const WSCALE_OFFSET: usize = 6;
+    const SND_WND_OFFSET: usize = 228;
+
+    let options = *info.get(OPTIONS_OFFSET)?;
+    let wscale = *info.get(WSCALE_OFFSET)? & 0x0f;
+    let window = info
+        .get(SND_WND_OFFSET..SND_WND_OFFSET + 4)
+        .map(|b| u32::from_ne_bytes([b[0], b[1], b[2], b[3]]));
+
+    Some(TcpFingerprint {
+        port,
+        window,
+        window_scale: (options & TCPI_OPT_WSCALE != 0).then_some(wscale),
+        sack: options & TCPI_OPT_SACK != 0,
+        timestamps: options & TCPI_OPT_TIMESTAMPS != 0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_top_ports() {
+        let mut sorted = TOP_PORTS.to_vec();
+        sorted.sort_unstable();
+        sorted.dedup();
+        assert_eq!(sorted.len(), 100);
+        assert_eq!(OpenPort::new(22).to_string(), "22/ssh");
+        assert_eq!(OpenPort::new(8443).to_string(), "8443/https");
+        assert_eq!(OpenPort::new(2717).to_string(), "2717");
+    }
+
+    #[tokio::test]
+    async fn test_scan_ports_finds_listener() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let open_port = listener.local_addr().unwrap().port();
+
+        // A port that was just released is closed
+        let closed_port = {
+            let l = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            l.local_addr().unwrap().port()
+        };
+
+        let config = PortScanConfig {
+            enabled: true,
+            concurrency: 4,
+            timeout_ms: 500,
+        };
+        let hosts = vec!["127.0.0.1".to_string()];
+        let found = scan_ports(&hosts, &[closed_port, open_port], &config).await;
+
+        assert_eq!(found["127.0.0.1"].open, vec![OpenPort::new(open_port)]);
+
+        // Loopback handshakes carry the Linux defaults
+        #[cfg(target_os = "linux")]
+        {
+            let tcp = found["127.0.0.1"].tcp.as_ref().unwrap();
+            assert_eq!(tcp.port, open_port);
+            assert!(tcp.sack && tcp.timestamps);
+        }
+    }
+
+    #[test]
+    fn test_parse_tcp_info() {
+        let mut info = [0u8; 232];
+        info[5] = 1 | 2 | 4;
+        // snd_wscale 8, rcv_wscale 7
+        info[6] = 0x78;
+        info[228..232].copy_from_slice(&65535u32.to_ne_bytes());
+        let tcp = parse_tcp_info(&info, 445).unwrap();
+        assert_eq!(tcp.window, Some(65535));
+        assert_eq!(tcp.window_scale, Some(8));
+        assert!(tcp.sack && tcp.timestamps);
+
+        // Older kernels return a shorter struct without the window
+        info[5] = 2;
+        let tcp = parse_tcp_info(&info[..104], 445).unwrap();
+        assert_eq!(tcp.window, None);
+        assert_eq!(tcp.window_scale, None);
+        assert!(!tcp.timestamps);
+    }
+}
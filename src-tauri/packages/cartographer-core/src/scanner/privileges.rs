@@ -5,6 +5,7 @@
 //! - Linux: Raw socket access may require root/CAP_NET_RAW, but system ping usually has setuid
 //! - macOS: ICMP ping works without root for most operations
 
+use super::icmp::{self, PingEngine};
 use serde::{Deserialize, Serialize};
 
 /// Scan mode indicating the level of access available
@@ -32,7 +33,11 @@ impl std::fmt::Display for ScanMode {
 pub struct ScanCapabilities {
     pub mode: ScanMode,
     pub can_ping: bool,
+    /// ICMP engine chosen for the ping sweep
+    pub ping_engine: PingEngine,
     pub can_read_arp: bool,
+    /// Active ARP sweep over AF_PACKET (Linux with `CAP_NET_RAW`)
+    pub can_arp_sweep: bool,
     pub can_resolve_hostnames: bool,
     pub is_elevated: bool,
     pub warning: Option<String>,
@@ -44,7 +49,9 @@ impl Default for ScanCapabilities {
         Self {
             mode: ScanMode::Full,
             can_ping: true,
+            ping_engine: PingEngine::Subprocess,
             can_read_arp: true,
+            can_arp_sweep: false,
             can_resolve_hostnames: true,
             is_elevated: false,
             warning: None,
@@ -139,9 +146,18 @@ pub async fn test_ping_capability() -> bool {
 /// Detect scan capabilities based on current privileges
 pub async fn detect_capabilities() -> ScanCapabilities {
     let elevated = is_elevated();
-    let can_ping = test_ping_capability().await;
+
+    // Prefer an in-process ICMP socket; fall back to the system ping
+    let preference = super::config::load_scan_config().ping.engine;
+    let ping_engine = match icmp::detect_engine(preference) {
+        PingEngine::Subprocess if !test_ping_capability().await => PingEngine::Unavailable,
+        engine => engine,
+    };
+    let can_ping = ping_engine != PingEngine::Unavailable;
+    tracing::debug!("Ping engine: {}", ping_engine);
 
     let can_read_arp = true;
+    let can_arp_sweep = super::arp_sweep::can_arp_sweep();
     let can_resolve_hostnames = true;
 
     let mode = if can_ping {
@@ -164,7 +180,9 @@ pub async fn detect_capabilities() -> ScanCapabilities {
     ScanCapabilities {
         mode,
         can_ping,
+        ping_engine,
         can_read_arp,
+        can_arp_sweep,
         can_resolve_hostnames,
         is_elevated: elevated,
         warning,
@@ -194,11 +212,14 @@ pub fn get_elevation_instructions() -> String {
          Option 2 - Grant CAP_NET_RAW capability:\n\
          $ sudo setcap cap_net_raw+ep /usr/local/bin/cartographer\n\
          \n\
-         Option 3 - Ensure the system ping has setuid (usually default):\n\
+         Option 3 - Allow unprivileged ICMP sockets for your group:\n\
+         $ sudo sysctl -w net.ipv4.ping_group_range=\"0 2147483647\"\n\
+         \n\
+         Option 4 - Ensure the system ping has setuid (usually default):\n\
          $ ls -la /bin/ping  # Should show '-rwsr-xr-x'\n\
          \n\
-         The agent uses the system ping command, which typically works\n\
-         without elevation on most Linux distributions."
+         Without an ICMP socket the agent falls back to the system ping\n\
+         command, which typically works without elevation."
             .to_string()
     }
 
@@ -222,6 +243,28 @@ pub fn get_elevation_instructions() -> String {
     }
 }
 
+/// Explain how to enable the active ARP sweep, if it is unavailable
+pub fn arp_sweep_instructions(caps: &ScanCapabilities) -> Option<String> {
+    if caps.can_arp_sweep {
+        return None;
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        Some(
+            "Active ARP sweep unavailable (finds hosts that block ping).\n\
+             To enable it, grant CAP_NET_RAW or run as root:\n\
+             $ sudo setcap cap_net_raw+ep $(command -v cartographer)"
+                .to_string(),
+        )
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        Some("Active ARP sweep is only available on Linux.".to_string())
+    }
+}
+
 /// Format a user-friendly message about current scan capabilities
 pub fn format_capabilities_message(caps: &ScanCapabilities) -> String {
     if caps.mode == ScanMode::Full {
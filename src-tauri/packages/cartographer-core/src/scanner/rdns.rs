@@ -0,0 +1,353 @@
+//! Reverse DNS (PTR) lookups without subprocesses
+//!
+//! Queries the nameservers from resolv.conf (or the gateways when there are
+//! none) over UDP, all addresses in parallel under one deadline. Addresses
+//! with no PTR record are remembered for a while so that the daemon does
+//! not ask again every scan.
+
+use super::dns::{self, RecordData};
+use futures::stream::{self, StreamExt};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+const DNS_PORT: u16 = 53;
+/// resolv.conf(5) uses at most three nameservers
+const MAX_NAMESERVERS: usize = 3;
+/// How long one server gets before the next is asked
+const ATTEMPT_TIMEOUT: Duration = Duration::from_millis(1000);
+/// How long "no PTR record" answers are remembered
+const NEGATIVE_TTL: Duration = Duration::from_secs(15 * 60);
+/// Lookups in flight
+const CONCURRENCY: usize = 64;
+const RCODE_NXDOMAIN: u8 = 3;
+
+/// Addresses with no PTR record, and when that was learned
+static NEGATIVE_CACHE: LazyLock<Mutex<HashMap<IpAddr, Instant>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Result of asking one server
+#[derive(Debug, PartialEq)]
+enum Answer {
+    Name(String),
+    /// NXDOMAIN, or an answer without a PTR record
+    NotFound,
+    /// SERVFAIL, REFUSED and the like: try the next server
+    Failed,
+}
+
+/// Reverse resolver over a fixed set of nameservers
+#[derive(Debug, Clone)]
+pub struct ReverseResolver {
+    servers: Vec<SocketAddr>,
+}
+
+impl ReverseResolver {
+    /// Use the system's nameservers, or `gateways` if none are configured.
+    pub fn system(gateways: &[&str]) -> Self {
+        let mut servers = system_nameservers();
+        if servers.is_empty() {
+            servers = gateways.iter().filter_map(|g| g.parse().ok()).collect();
+            tracing::debug!("No nameservers configured, using gateways {:?}", servers);
+        }
+        servers.truncate(MAX_NAMESERVERS);
+        Self::new(
+            servers
+                .into_iter()
+                .map(|ip| SocketAddr::new(ip, DNS_PORT))
+                .collect(),
+        )
+    }
+
+    pub fn new(servers: Vec<SocketAddr>) -> Self {
+        Self { servers }
+    }
+
+    /// Look up every address in parallel. Lookups still running at
+    /// `deadline` are abandoned; only the names found are returned.
+    pub async fn resolve_all(
+        &self,
+        ips: &[IpAddr],
+        deadline: tokio::time::Instant,
+    ) -> HashMap<IpAddr, String> {
+        let mut names = HashMap::new();
+        if self.servers.is_empty() {
+            return names;
+        }
+
+        let mut lookups = stream::iter(ips.iter().copied().filter(|ip| !is_cached_negative(*ip)))
+            .map(|ip| async move { (ip, self.lookup(ip).await) })
+            .buffer_unordered(CONCURRENCY);
+
+        loop {
+            match tokio::time::timeout_at(deadline, lookups.next()).await {
+                Ok(Some((ip, Some(name)))) => {
+                    names.insert(ip, name);
+                }
+                Ok(Some((_, None))) => {}
+                Ok(None) => break,
+                Err(_) => {
+                    tracing::debug!("Reverse DNS deadline reached");
+                    break;
+                }
+            }
+        }
+        names
+    }
+
+    /// Ask each server in turn until one gives a definite answer
+    async fn lookup(&self, ip: IpAddr) -> Option<String> {
+        let name = dns::reverse_name(ip);
+        for server in &self.servers {
+            let id = dns::next_query_id();
+            let query = dns::build_query(id, &[(&name, dns::TYPE_PTR)], true, false);
+            let answer = dns::exchange(*server, &query, ATTEMPT_TIMEOUT, |reply| {
+                parse_answer(id, &name, reply)
+            })
+            .await;
+
+            match answer {
+                Some(Answer::Name(hostname)) => return Some(hostname),
+                Some(Answer::NotFound) => {
+                    cache_negative(ip);
+                    return None;
+                }
+                Some(Answer::Failed) | None => continue,
+            }
+        }
+        None
+    }
+}
+
+fn parse_answer(id: u16, name: &str, reply: &[u8]) -> Option<Answer> {
+    let message = dns::parse_message(reply)?;
+    if message.id != id || !message.is_response {
+        return None;
+    }
+    Some(match message.rcode {
+        0 => message
+            .records
+            .into_iter()
+            .find_map(|record| match record.data {
+                RecordData::Ptr(target)
+                    if record.name.eq_ignore_ascii_case(name) && !target.is_empty() =>
+                {
+                    Some(Answer::Name(target))
+                }
+                _ => None,
+            })
+            .unwrap_or(Answer::NotFound),
+        RCODE_NXDOMAIN => Answer::NotFound,
+        _ => Answer::Failed,
+    })
+}
+
+fn is_cached_negative(ip: IpAddr) -> bool {
+    let cache = NEGATIVE_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    cache
+        .get(&ip)
+        .is_some_and(|learned| learned.elapsed() < NEGATIVE_TTL)
+}
+
+fn cache_negative(ip: IpAddr) {
+    let mut cache = NEGATIVE_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    cache.retain(|_, learned| learned.elapsed() < NEGATIVE_TTL);
+    cache.insert(ip, Instant::now());
+}
+
+/// Nameservers from resolv.conf, in file order
+fn parse_resolv_conf(content: &str) -> Vec<IpAddr> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            if fields.next()? != "nameserver" {
+                return None;
+            }
+            // Drop an IPv6 zone ("fe80::1%eth0"); the OS picks the interface
+            let address = fields.next()?.split('%').next()?;
+            address.parse().ok()
+        })
+        .collect()
+}
+
+#[cfg(not(target_os = "windows"))]
+fn system_nameservers() -> Vec<IpAddr> {
+    match std::fs::read_to_string("/etc/resolv.conf") {
+        Ok(content) => parse_resolv_conf(&content),
+        Err(e) => {
+            tracing::debug!("Failed to read /etc/resolv.conf: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn system_nameservers() -> Vec<IpAddr> {
+    // One PowerShell call per scan, not per device
+    let output = super::hidden_command_sync("powershell")
+        .args([
+            "-NoProfile",
+            "-ExecutionPolicy",
+            "Bypass",
+            "-Command",
+            "Get-DnsClientServerAddress -AddressFamily IPv4 | ForEach-Object { $_.ServerAddresses } | Select-Object -Unique",
+        ])
+        .output();
+    match output {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| line.trim().parse().ok())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Names from the hosts file, so that entries there still win over DNS as
+/// they did with `getent`
+pub fn hosts_file_names() -> HashMap<IpAddr, String> {
+    #[cfg(target_os = "windows")]
+    let path = r"C:\Windows\System32\drivers\etc\hosts";
+    #[cfg(not(target_os = "windows"))]
+    let path = "/etc/hosts";
+
+    std::fs::read_to_string(path)
+        .map(|content| parse_hosts(&content))
+        .unwrap_or_default()
+}
+
+/// First name for each address in a hosts file; loopback entries are skipped
+fn parse_hosts(content: &str) -> HashMap<IpAddr, String> {
+    let mut names = HashMap::new();
+    for line in content.lines() {
+        let line = line.split('#').next().unwrap_or_default();
+        let mut fields = line.split_whitespace();
+        let (Some(address), Some(name)) = (fields.next(), fields.next()) else {
+            continue;
+        };
+        let Ok(address) = address.parse::<IpAddr>() else {
+            continue;
+        };
+        if !address.is_loopback() && !address.is_unspecified() {
+            names.entry(address).or_insert_with(|| name.to_string());
+        }
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::UdpSocket;
+
+    /// Answer PTR queries from `names`; NXDOMAIN for anything else.
+    /// Returns the stub's address and a counter of queries received.
+    async fn dns_stub(
+        names: HashMap<String, String>,
+        rcode: u8,
+    ) -> (SocketAddr, std::sync::Arc<std::sync::atomic::AtomicUsize>) {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = socket.local_addr().unwrap();
+        let queries = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = queries.clone();
+        tokio::spawn(async move {
+            let mut buf = [0u8; 512];
+            while let Ok((len, from)) = socket.recv_from(&mut buf).await {
+                counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                // Question name starts right after the header
+                let mut labels = Vec::new();
+                let mut pos = 12;
+                while pos < len && buf[pos] != 0 {
+                    let l = buf[pos] as usize;
+                    labels.push(String::from_utf8_lossy(&buf[pos + 1..pos + 1 + l]).into_owned());
+                    pos += 1 + l;
+                }
+                let qname = labels.join(".");
+
+                let mut reply = buf[..2].to_vec();
+                let (code, answer) = match names.get(&qname) {
+                    Some(target) => (0, Some(target)),
+                    None if rcode != 0 => (rcode, None),
+                    None => (RCODE_NXDOMAIN, None),
+                };
+                reply.extend_from_slice(&[0x81, 0x80 | code, 0, 0]);
+                reply.extend_from_slice(&[0, answer.is_some() as u8, 0, 0, 0, 0]);
+                if let Some(target) = answer {
+                    let mut rdata = Vec::new();
+                    for label in target.split('.') {
+                        rdata.push(label.len() as u8);
+                        rdata.extend_from_slice(label.as_bytes());
+                    }
+                    rdata.push(0);
+                    dns::push_record(&mut reply, &qname, dns::TYPE_PTR, &rdata);
+                }
+                let _ = socket.send_to(&reply, from).await;
+            }
+        });
+        (addr, queries)
+    }
+
+    #[test]
+    fn test_parse_resolv_conf() {
+        let content = "# Generated by NetworkManager\nsearch lan\nnameserver 192.168.1.1\n\
+                       nameserver fe80::1%eth0\n; nameserver 10.0.0.1\noptions edns0\n";
+        assert_eq!(
+            parse_resolv_conf(content),
+            vec![
+                "192.168.1.1".parse::<IpAddr>().unwrap(),
+                "fe80::1".parse().unwrap()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_hosts() {
+        let names = parse_hosts(
+            "127.0.0.1 localhost\n192.168.1.10  nas.lan nas  # storage\n\
+             192.168.1.10 other\n#192.168.1.11 old\n",
+        );
+        assert_eq!(names.len(), 1);
+        assert_eq!(names[&"192.168.1.10".parse().unwrap()], "nas.lan");
+    }
+
+    #[tokio::test]
+    async fn test_resolve_all_with_fallback_and_negative_cache() {
+        // First server refuses everything, second knows one address
+        let (refusing, _) = dns_stub(HashMap::new(), 5).await;
+        let (server, queries) = dns_stub(
+            HashMap::from([(
+                "20.2.0.192.in-addr.arpa".to_string(),
+                "printer.lan".to_string(),
+            )]),
+            0,
+        )
+        .await;
+        let resolver = ReverseResolver::new(vec![refusing, server]);
+
+        let ips: Vec<IpAddr> = vec!["192.0.2.20".parse().unwrap(), "192.0.2.21".parse().unwrap()];
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(3);
+        let names = resolver.resolve_all(&ips, deadline).await;
+        assert_eq!(names.len(), 1);
+        assert_eq!(names[&ips[0]], "printer.lan");
+        assert_eq!(queries.load(std::sync::atomic::Ordering::SeqCst), 2);
+
+        // The NXDOMAIN for .21 is cached: only .20 is asked again
+        let names = resolver.resolve_all(&ips, deadline).await;
+        assert_eq!(names.len(), 1);
+        assert_eq!(queries.load(std::sync::atomic::Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_resolve_all_deadline() {
+        // A server that never answers must not hold the scan past the deadline
+        let silent = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let resolver = ReverseResolver::new(vec![silent.local_addr().unwrap()]);
+        let ips: Vec<IpAddr> = vec!["192.0.2.30".parse().unwrap()];
+
+        let started = Instant::now();
+        let deadline = tokio::time::Instant::now() + Duration::from_millis(300);
+        assert!(resolver.resolve_all(&ips, deadline).await.is_empty());
+        assert!(started.elapsed() < Duration::from_millis(800));
+    }
+}
@@ -0,0 +1,424 @@
+//! Service banner and TLS certificate collection
+//!
+//! Runs after the port scan on the ports it found open: reads the SSH
+//! identification line, the HTTP `Server` header and page title, and the
+//! certificate of TLS ports. Every port gets a short timeout and each
+//! device a total budget, so one slow host cannot stall the scan.
+
+use super::Device;
+use super::config::ServiceProbeConfig;
+use super::is_scan_cancelled;
+use super::ports::OpenPort;
+use super::x509::{self, TlsCertificate};
+use anyhow::{Context, Result};
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+/// Upper bound on bytes read from a single service
+const MAX_RESPONSE_BYTES: usize = 32 * 1024;
+
+/// Stop reading an HTTP response once it stalls this long after data arrived
+const READ_IDLE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// What a service told us about itself
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceInfo {
+    pub port: u16,
+    /// SSH identification string (e.g. "SSH-2.0-OpenSSH_9.6")
+    pub banner: Option<String>,
+    /// HTTP `Server` response header
+    pub http_server: Option<String>,
+    /// HTML `<title>` of the root page
+    pub http_title: Option<String>,
+    /// Certificate presented on a TLS port
+    pub tls_certificate: Option<TlsCertificate>,
+}
+
+/// How to talk to an open port
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Probe {
+    Ssh,
+    Http,
+    Https,
+    /// TLS-wrapped protocol we only read the certificate of
+    Tls,
+}
+
+fn probe_for(port: &OpenPort) -> Option<Probe> {
+    match port.service.as_deref()? {
+        "ssh" => Some(Probe::Ssh),
+        "http" => Some(Probe::Http),
+        "https" => Some(Probe::Https),
+        "smtps" | "ldaps" | "ftps" | "imaps" | "pop3s" => Some(Probe::Tls),
+        _ => None,
+    }
+}
+
+type PortProbes = Vec<(u16, Probe)>;
+
+/// Collect service details for every device with open ports.
+pub async fn collect_services(devices: &mut [Device], config: &ServiceProbeConfig) {
+    // (device index, address, ports to probe)
+    let work: Vec<(usize, String, PortProbes)> = devices
+        .iter()
+        .enumerate()
+        .filter_map(|(i, d)| {
+            let probes: PortProbes = d
+                .open_ports
+                .iter()
+                .filter_map(|p| probe_for(p).map(|probe| (p.port, probe)))
+                .collect();
+            (!probes.is_empty()).then(|| (i, d.primary_ip().to_string(), probes))
+        })
+        .collect();
+
+    tracing::info!(
+        "Reading service details on {} devices (concurrency {}, {}ms per device)",
+        work.len(),
+        config.concurrency,
+        config.device_budget_ms
+    );
+
+    let results: Vec<(usize, Vec<ServiceInfo>)> = stream::iter(work)
+        .map(|(i, ip, probes)| async move {
+            if is_scan_cancelled() {
+                return (i, Vec::new());
+            }
+            (i, probe_device(&ip, &probes, config).await)
+        })
+        .buffer_unordered(config.concurrency.max(1))
+        .collect()
+        .await;
+
+    for (i, services) in results {
+        devices[i].services = services;
+    }
+}
+
+/// Probe one device's ports in turn until its budget is spent.
+async fn probe_device(
+    ip: &str,
+    probes: &[(u16, Probe)],
+    config: &ServiceProbeConfig,
+) -> Vec<ServiceInfo> {
+    let deadline = Instant::now() + Duration::from_millis(config.device_budget_ms);
+    let timeout = Duration::from_millis(config.timeout_ms);
+    let mut services = Vec::new();
+
+    for &(port, probe) in probes {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            tracing::debug!("{}: service budget spent, skipping remaining ports", ip);
+            break;
+        }
+
+        let timeout = timeout.min(remaining);
+        match tokio::time::timeout(timeout, probe_port(ip, port, probe, timeout)).await {
+            Ok(Ok(info)) => services.push(info),
+            Ok(Err(e)) => tracing::debug!("{}:{} {:?} probe failed: {:#}", ip, port, probe, e),
+            Err(_) => tracing::debug!("{}:{} {:?} probe timed out", ip, port, probe),
+        }
+    }
+
+    services
+}
+
+/// Run one probe. `timeout` is the caller's limit; the HTTPS request gets
+/// half of it so a silent web server does not cost us the certificate.
+async fn probe_port(ip: &str, port: u16, probe: Probe, timeout: Duration) -> Result<ServiceInfo> {
+    let stream = TcpStream::connect((ip, port))
+        .await
+        .context("connect failed")?;
+    let mut info = ServiceInfo {
+        port,
+        ..Default::default()
+    };
+
+    match probe {
+        Probe::Ssh => info.banner = Some(read_ssh_banner(stream).await?),
+        Probe::Http => (info.http_server, info.http_title) = http_get(stream, ip).await?,
+        Probe::Https | Probe::Tls => {
+            let mut tls = tls_connect(stream, ip).await?;
+            info.tls_certificate = tls
+                .get_ref()
+                .peer_certificate()
+                .context("failed to read peer certificate")?
+                .and_then(|cert| cert.to_der().ok())
+                .and_then(|der| x509::parse_certificate(&der));
+            if probe == Probe::Https {
+                // Keep the certificate even if the HTTP exchange fails
+                if let Ok(Ok((server, title))) =
+                    tokio::time::timeout(timeout / 2, http_get(&mut tls, ip)).await
+                {
+                    (info.http_server, info.http_title) = (server, title);
+                }
+            }
+        }
+    }
+
+    Ok(info)
+}
+
+/// Read the SSH identification line the server sends on connect.
+async fn read_ssh_banner<S: AsyncRead + Unpin>(mut stream: S) -> Result<String> {
+    let mut buf = Vec::with_capacity(256);
+    let mut chunk = [0u8; 256];
+    // RFC 4253 allows other lines before the version line
+    loop {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            break;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+        if buf.len() >= 4096 || find_ssh_line(&buf).is_some() {
+            break;
+        }
+    }
+    find_ssh_line(&buf).ok_or_else(|| anyhow::anyhow!("no SSH identification line"))
+}
+
+fn find_ssh_line(buf: &[u8]) -> Option<String> {
+    // Only complete lines count; the last one may still be arriving
+    let complete = &buf[..buf.iter().rposition(|&b| b == b'\n')? + 1];
+    String::from_utf8_lossy(complete)
+        .lines()
+        .find(|l| l.starts_with("SSH-"))
+        .map(clean_text)
+}
+
+/// Send `GET /` and return the `Server` header and page title.
+async fn http_get<S: AsyncRead + AsyncWrite + Unpin>(
+    mut stream: S,
+    host: &str,
+) -> Result<(Option<String>, Option<String>)> {
+    let request = format!(
+        "GET / HTTP/1.0\r\nHost: {}\r\nUser-Agent: cartographer-agent\r\nAccept: text/html\r\nConnection: close\r\n\r\n",
+        host
+    );
+    stream.write_all(request.as_bytes()).await?;
+
+    let mut response = Vec::new();
+    let mut chunk = [0u8; 4096];
+    loop {
+        // Servers that ignore `Connection: close` would otherwise hold the
+        // probe until the port timeout
+        let read = if response.is_empty() {
+            stream.read(&mut chunk).await
+        } else {
+            match tokio::time::timeout(READ_IDLE_TIMEOUT, stream.read(&mut chunk)).await {
+                Ok(read) => read,
+                Err(_) => break,
+            }
+        };
+        match read {
+            Ok(0) => break,
+            Ok(n) => response.extend_from_slice(&chunk[..n]),
+            // TLS peers often close without close_notify
+            Err(_) if !response.is_empty() => break,
+            Err(e) => return Err(e.into()),
+        }
+        if response.len() >= MAX_RESPONSE_BYTES
+            || response
+                .to_ascii_lowercase()
+                .windows(8)
+                .any(|w| w == b"</title>")
+        {
+            break;
+        }
+    }
+
+    parse_http_response(&response).ok_or_else(|| anyhow::anyhow!("not an HTTP response"))
+}
+
+/// Extract the `Server` header and `<title>` from a raw HTTP response.
+fn parse_http_response(response: &[u8]) -> Option<(Option<String>, Option<String>)> {
+    let text = String::from_utf8_lossy(response);
+    if !text.starts_with("HTTP/") {
+        return None;
+    }
+
+    let (head, body) = text
+        .split_once("\r\n\r\n")
+        .or_else(|| text.split_once("\n\n"))
+        .unwrap_or((&text, ""));
+
+    let server = head.lines().skip(1).find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        name.trim()
+            .eq_ignore_ascii_case("server")
+            .then(|| clean_text(value))
+            .filter(|v| !v.is_empty())
+    });
+
+    Some((server, parse_title(body)))
+}
+
+fn parse_title(body: &str) -> Option<String> {
+    // ASCII lowercasing keeps byte offsets valid for slicing `body`
+    let lower = body.to_ascii_lowercase();
+    let open = lower.find("<title")?;
+    let start = open + lower[open..].find('>')? + 1;
+    let end = start + lower[start..].find("</title")?;
+    Some(clean_text(&body[start..end])).filter(|t| !t.is_empty())
+}
+
+/// Collapse whitespace, drop control characters and cap the length.
+fn clean_text(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(200)
+        .collect()
+}
+
+/// TLS handshake that accepts any certificate: we are recording it, not
+/// trusting it.
+async fn tls_connect(
+    stream: TcpStream,
+    host: &str,
+) -> Result<tokio_native_tls::TlsStream<TcpStream>> {
+    let connector = tokio_native_tls::native_tls::TlsConnector::builder()
+        .danger_accept_invalid_certs(true)
+        .danger_accept_invalid_hostnames(true)
+        .use_sni(false)
+        .build()
+        .context("failed to create TLS connector")?;
+    tokio_native_tls::TlsConnector::from(connector)
+        .connect(host, stream)
+        .await
+        .context("TLS handshake failed")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    fn config() -> ServiceProbeConfig {
+        ServiceProbeConfig {
+            timeout_ms: 2000,
+            device_budget_ms: 5000,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_http_response() {
+        let response = b"HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nserver:  lighttpd/1.4.59 \r\n\r\n<html><head><TITLE>\n  Printer  Status\n</TITLE></head></html>";
+        let (server, title) = parse_http_response(response).unwrap();
+        assert_eq!(server.as_deref(), Some("lighttpd/1.4.59"));
+        assert_eq!(title.as_deref(), Some("Printer Status"));
+
+        let (server, title) = parse_http_response(b"HTTP/1.0 404 Not Found\r\n\r\n").unwrap();
+        assert_eq!((server, title), (None, None));
+
+        assert_eq!(parse_http_response(b"SSH-2.0-dropbear\r\n"), None);
+    }
+
+    #[test]
+    fn test_find_ssh_line() {
+        assert_eq!(
+            find_ssh_line(b"SSH-2.0-OpenSSH_9.6p1 Ubuntu-3ubuntu13\r\n").as_deref(),
+            Some("SSH-2.0-OpenSSH_9.6p1 Ubuntu-3ubuntu13")
+        );
+        assert_eq!(
+            find_ssh_line(b"Welcome\r\nSSH-2.0-dropbear_2022.83\r\n").as_deref(),
+            Some("SSH-2.0-dropbear_2022.83")
+        );
+        // Incomplete line: keep reading
+        assert_eq!(find_ssh_line(b"SSH-2.0-Open"), None);
+    }
+
+    #[tokio::test]
+    async fn test_probe_device_ssh_and_http() {
+        let ssh = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let http = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let ssh_port = ssh.local_addr().unwrap().port();
+        let http_port = http.local_addr().unwrap().port();
+
+        tokio::spawn(async move {
+            let (mut s, _) = ssh.accept().await.unwrap();
+            s.write_all(b"SSH-2.0-OpenSSH_9.6\r\n").await.unwrap();
+            let _ = s.read(&mut [0u8; 64]).await;
+        });
+        tokio::spawn(async move {
+            let (mut s, _) = http.accept().await.unwrap();
+            let _ = s.read(&mut [0u8; 1024]).await;
+            s.write_all(b"HTTP/1.1 200 OK\r\nServer: nginx\r\n\r\n<title>NAS</title>")
+                .await
+                .unwrap();
+        });
+
+        let services = probe_device(
+            "127.0.0.1",
+            &[(ssh_port, Probe::Ssh), (http_port, Probe::Http)],
+            &config(),
+        )
+        .await;
+
+        assert_eq!(services.len(), 2);
+        assert_eq!(services[0].banner.as_deref(), Some("SSH-2.0-OpenSSH_9.6"));
+        assert_eq!(services[1].http_server.as_deref(), Some("nginx"));
+        assert_eq!(services[1].http_title.as_deref(), Some("NAS"));
+    }
+
+    #[tokio::test]
+    async fn test_probe_device_reads_tls_certificate() {
+        let cert = include_bytes!("../../testdata/printer-cert.pem");
+        let key = include_bytes!("../../testdata/printer-key.pem");
+        let identity = tokio_native_tls::native_tls::Identity::from_pkcs8(cert, key).unwrap();
+        let acceptor = tokio_native_tls::TlsAcceptor::from(
+            tokio_native_tls::native_tls::TlsAcceptor::new(identity).unwrap(),
+        );
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (s, _) = listener.accept().await.unwrap();
+            let mut tls = acceptor.accept(s).await.unwrap();
+            let _ = tls.read(&mut [0u8; 1024]).await;
+            let _ = tls
+                .write_all(b"HTTP/1.1 200 OK\r\nServer: printer-httpd\r\n\r\n")
+                .await;
+            let _ = tls.shutdown().await;
+        });
+
+        let services = probe_device("127.0.0.1", &[(port, Probe::Https)], &config()).await;
+
+        assert_eq!(services.len(), 1);
+        let cert = services[0].tls_certificate.as_ref().expect("certificate");
+        assert_eq!(cert.subject, "C=US, O=Example Labs, CN=printer.example.lan");
+        assert!(cert.subject_alt_names.contains(&"192.168.1.20".to_string()));
+        assert_eq!(services[0].http_server.as_deref(), Some("printer-httpd"));
+    }
+
+    #[tokio::test]
+    async fn test_probe_device_respects_budget() {
+        // Accepts but never speaks: every SSH probe waits for its timeout
+        let silent = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = silent.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let mut held = Vec::new();
+            while let Ok((s, _)) = silent.accept().await {
+                held.push(s);
+            }
+        });
+
+        let config = ServiceProbeConfig {
+            timeout_ms: 200,
+            device_budget_ms: 300,
+            ..Default::default()
+        };
+        let started = Instant::now();
+        let services = probe_device("127.0.0.1", &[(port, Probe::Ssh); 5], &config).await;
+
+        assert!(services.is_empty());
+        assert!(started.elapsed() < Duration::from_millis(1000));
+    }
+}
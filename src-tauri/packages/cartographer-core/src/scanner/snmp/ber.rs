@@ -0,0 +1,383 @@
+//! BER encoding of SNMP messages: the handful of ASN.1 types and PDUs a
+//! manager needs to send GET/GETBULK requests and read the responses.
+
+pub const INTEGER: u8 = 0x02;
+pub const OCTET_STRING: u8 = 0x04;
+pub const NULL: u8 = 0x05;
+pub const OID: u8 = 0x06;
+pub const SEQUENCE: u8 = 0x30;
+const IP_ADDRESS: u8 = 0x40;
+const COUNTER32: u8 = 0x41;
+const GAUGE32: u8 = 0x42;
+const TIMETICKS: u8 = 0x43;
+const COUNTER64: u8 = 0x46;
+const NO_SUCH_OBJECT: u8 = 0x80;
+const NO_SUCH_INSTANCE: u8 = 0x81;
+const END_OF_MIB_VIEW: u8 = 0x82;
+
+pub const GET_REQUEST: u8 = 0xa0;
+pub const RESPONSE: u8 = 0xa2;
+pub const GET_BULK: u8 = 0xa5;
+pub const REPORT: u8 = 0xa8;
+
+/// A variable binding value
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Integer(i64),
+    OctetString(Vec<u8>),
+    Null,
+    Oid(Vec<u32>),
+    IpAddress([u8; 4]),
+    /// Counter32, Gauge32 and Counter64
+    Unsigned(u64),
+    /// Hundredths of a second
+    TimeTicks(u32),
+    NoSuchObject,
+    NoSuchInstance,
+    EndOfMibView,
+    Other,
+}
+
+impl Value {
+    pub fn as_text(&self) -> Option<String> {
+        match self {
+            Value::OctetString(bytes) => {
+                let text = String::from_utf8_lossy(bytes);
+                let text = text.trim_matches(|c: char| c.is_whitespace() || c == '\0');
+                (!text.is_empty()).then(|| text.to_string())
+            }
+            _ => None,
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            Value::Integer(v) => u64::try_from(*v).ok(),
+            Value::Unsigned(v) => Some(*v),
+            Value::TimeTicks(v) => Some(*v as u64),
+            _ => None,
+        }
+    }
+
+    /// NoSuchObject, NoSuchInstance or EndOfMibView
+    pub fn is_exception(&self) -> bool {
+        matches!(
+            self,
+            Value::NoSuchObject | Value::NoSuchInstance | Value::EndOfMibView
+        )
+    }
+}
+
+/// A decoded PDU
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pdu {
+    pub tag: u8,
+    pub request_id: i32,
+    pub error_status: i64,
+    pub varbinds: Vec<(Vec<u32>, Value)>,
+}
+
+/// Tag, length and contents
+pub fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    let len = content.len();
+    if len < 0x80 {
+        out.push(len as u8);
+    } else {
+        let bytes: Vec<u8> = len
+            .to_be_bytes()
+            .into_iter()
+            .skip_while(|b| *b == 0)
+            .collect();
+        out.push(0x80 | bytes.len() as u8);
+        out.extend_from_slice(&bytes);
+    }
+    out.extend_from_slice(content);
+    out
+}
+
+pub fn integer(value: i64) -> Vec<u8> {
+    let bytes = value.to_be_bytes();
+    // Shortest two's complement form
+    let mut start = 0;
+    while start < 7
+        && ((bytes[start] == 0x00 && bytes[start + 1] & 0x80 == 0)
+            || (bytes[start] == 0xff && bytes[start + 1] & 0x80 != 0))
+    {
+        start += 1;
+    }
+    tlv(INTEGER, &bytes[start..])
+}
+
+pub fn octet_string(value: &[u8]) -> Vec<u8> {
+    tlv(OCTET_STRING, value)
+}
+
+pub fn sequence(parts: &[Vec<u8>]) -> Vec<u8> {
+    tlv(SEQUENCE, &parts.concat())
+}
+
+pub fn oid(arcs: &[u32]) -> Vec<u8> {
+    let mut content = Vec::new();
+    if arcs.len() >= 2 {
+        push_base128(&mut content, arcs[0] * 40 + arcs[1]);
+    }
+    for arc in arcs.iter().skip(2) {
+        push_base128(&mut content, *arc);
+    }
+    tlv(OID, &content)
+}
+
+fn push_base128(out: &mut Vec<u8>, mut value: u32) {
+    let mut groups = vec![(value & 0x7f) as u8];
+    value >>= 7;
+    while value > 0 {
+        groups.push(0x80 | (value & 0x7f) as u8);
+        value >>= 7;
+    }
+    out.extend(groups.iter().rev());
+}
+
+/// A request PDU with NULL values. For GETBULK the two integers after the
+/// request id are non-repeaters and max-repetitions.
+pub fn request_pdu(tag: u8, request_id: i32, fields: (i64, i64), oids: &[Vec<u32>]) -> Vec<u8> {
+    let varbinds: Vec<Vec<u8>> = oids
+        .iter()
+        .map(|o| sequence(&[oid(o), tlv(NULL, &[])]))
+        .collect();
+    tlv(
+        tag,
+        &[
+            integer(request_id as i64),
+            integer(fields.0),
+            integer(fields.1),
+            sequence(&varbinds),
+        ]
+        .concat(),
+    )
+}
+
+/// Sequential reader over concatenated TLVs
+pub struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self { buf, pos: 0 }
+    }
+
+    /// Next element's tag and contents
+    pub fn read(&mut self) -> Option<(u8, &'a [u8])> {
+        let tag = *self.buf.get(self.pos)?;
+        let first = *self.buf.get(self.pos + 1)? as usize;
+        let mut header = 2;
+        let len = if first & 0x80 == 0 {
+            first
+        } else {
+            let count = first & 0x7f;
+            if count == 0 || count > 4 {
+                return None;
+            }
+            header += count;
+            self.buf
+                .get(self.pos + 2..self.pos + 2 + count)?
+                .iter()
+                .fold(0usize, |acc, b| (acc << 8) | *b as usize)
+        };
+        let start = self.pos + header;
+        let content = self.buf.get(start..start.checked_add(len)?)?;
+        self.pos = start + len;
+        Some((tag, content))
+    }
+
+    /// Next element, which must have `tag`
+    pub fn expect(&mut self, tag: u8) -> Option<&'a [u8]> {
+        let (found, content) = self.read()?;
+        (found == tag).then_some(content)
+    }
+
+    pub fn integer(&mut self) -> Option<i64> {
+        decode_integer(self.expect(INTEGER)?)
+    }
+}
+
+fn decode_integer(content: &[u8]) -> Option<i64> {
+    if content.is_empty() || content.len() > 8 {
+        return None;
+    }
+    let negative = content[0] & 0x80 != 0;
+    Some(
+        content
+            .iter()
+            .fold(if negative { -1i64 } else { 0 }, |acc, b| {
+                (acc << 8) | *b as i64
+            }),
+    )
+}
+
+fn decode_unsigned(content: &[u8]) -> Option<u64> {
+    // Unsigned types may carry a leading zero byte
+    if content.is_empty() || content.len() > 9 {
+        return None;
+    }
+    Some(content.iter().fold(0u64, |acc, b| (acc << 8) | *b as u64))
+}
+
+fn decode_oid(content: &[u8]) -> Option<Vec<u32>> {
+    let mut arcs = Vec::new();
+    let mut value: u32 = 0;
+    for byte in content {
+        value = value.checked_mul(128)? | (*byte & 0x7f) as u32;
+        if byte & 0x80 == 0 {
+            if arcs.is_empty() {
+                let first = (value / 40).min(2);
+                arcs.push(first);
+                arcs.push(value - first * 40);
+            } else {
+                arcs.push(value);
+            }
+            value = 0;
+        }
+    }
+    Some(arcs)
+}
+
+fn decode_value(tag: u8, content: &[u8]) -> Value {
+    let decoded = match tag {
+        INTEGER => decode_integer(content).map(Value::Integer),
+        OCTET_STRING => Some(Value::OctetString(content.to_vec())),
+        NULL => Some(Value::Null),
+        OID => decode_oid(content).map(Value::Oid),
+        IP_ADDRESS => <[u8; 4]>::try_from(content).ok().map(Value::IpAddress),
+        COUNTER32 | GAUGE32 | COUNTER64 => decode_unsigned(content).map(Value::Unsigned),
+        TIMETICKS => decode_unsigned(content).map(|v| Value::TimeTicks(v as u32)),
+        NO_SUCH_OBJECT => Some(Value::NoSuchObject),
+        NO_SUCH_INSTANCE => Some(Value::NoSuchInstance),
+        END_OF_MIB_VIEW => Some(Value::EndOfMibView),
+        _ => None,
+    };
+    decoded.unwrap_or(Value::Other)
+}
+
+/// Decode a PDU element (tag included)
+pub fn parse_pdu(tag: u8, content: &[u8]) -> Option<Pdu> {
+    let mut fields = Reader::new(content);
+    let request_id = fields.integer()? as i32;
+    let error_status = fields.integer()?;
+    let _error_index = fields.integer()?;
+    let mut list = Reader::new(fields.expect(SEQUENCE)?);
+
+    let mut varbinds = Vec::new();
+    while let Some((SEQUENCE, varbind)) = list.read() {
+        let mut parts = Reader::new(varbind);
+        let name = decode_oid(parts.expect(OID)?)?;
+        let (value_tag, value) = parts.read()?;
+        varbinds.push((name, decode_value(value_tag, value)));
+    }
+
+    Some(Pdu {
+        tag,
+        request_id,
+        error_status,
+        varbinds,
+    })
+}
+
+/// Parse a dotted OID ("1.3.6.1.2.1.1.5.0")
+#[cfg(test)]
+pub fn parse_oid(text: &str) -> Option<Vec<u32>> {
+    text.trim_start_matches('.')
+        .split('.')
+        .map(|arc| arc.parse().ok())
+        .collect()
+}
+
+pub fn format_oid(arcs: &[u32]) -> String {
+    arcs.iter()
+        .map(|a| a.to_string())
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_integer_encoding() {
+        assert_eq!(integer(0), vec![0x02, 0x01, 0x00]);
+        assert_eq!(integer(127), vec![0x02, 0x01, 0x7f]);
+        assert_eq!(integer(128), vec![0x02, 0x02, 0x00, 0x80]);
+        assert_eq!(integer(-1), vec![0x02, 0x01, 0xff]);
+        assert_eq!(integer(-129), vec![0x02, 0x02, 0xff, 0x7f]);
+        for value in [
+            0,
+            1,
+            255,
+            65_536,
+            -1,
+            -129,
+            i32::MAX as i64,
+            i32::MIN as i64,
+        ] {
+            let encoded = integer(value);
+            assert_eq!(Reader::new(&encoded).integer(), Some(value));
+        }
+    }
+
+    #[test]
+    fn test_oid_round_trip() {
+        let sys_object_id = parse_oid("1.3.6.1.4.1.9.1.2494").unwrap();
+        let encoded = oid(&sys_object_id);
+        assert_eq!(
+            encoded,
+            vec![0x06, 0x09, 0x2b, 6, 1, 4, 1, 9, 1, 0x93, 0x3e]
+        );
+        let content = Reader::new(&encoded).expect(OID).unwrap();
+        assert_eq!(decode_oid(content).unwrap(), sys_object_id);
+        assert_eq!(format_oid(&sys_object_id), "1.3.6.1.4.1.9.1.2494");
+    }
+
+    #[test]
+    fn test_parse_response_pdu() {
+        let varbinds = sequence(&[
+            sequence(&[
+                oid(&parse_oid("1.3.6.1.2.1.1.5.0").unwrap()),
+                octet_string(b"core-sw1"),
+            ]),
+            sequence(&[
+                oid(&parse_oid("1.3.6.1.2.1.1.3.0").unwrap()),
+                tlv(TIMETICKS, &[0x00, 0x9c, 0x40, 0x00]),
+            ]),
+            sequence(&[
+                oid(&parse_oid("1.3.6.1.2.1.1.6.0").unwrap()),
+                tlv(NO_SUCH_INSTANCE, &[]),
+            ]),
+        ]);
+        let pdu = tlv(
+            RESPONSE,
+            &[integer(42), integer(0), integer(0), varbinds].concat(),
+        );
+
+        let (tag, content) = Reader::new(&pdu).read().unwrap();
+        let parsed = parse_pdu(tag, content).unwrap();
+        assert_eq!(parsed.request_id, 42);
+        assert_eq!(parsed.varbinds.len(), 3);
+        assert_eq!(parsed.varbinds[0].1.as_text().as_deref(), Some("core-sw1"));
+        assert_eq!(parsed.varbinds[1].1, Value::TimeTicks(10_240_000));
+        assert!(parsed.varbinds[2].1.is_exception());
+    }
+
+    #[test]
+    fn test_long_length() {
+        let content = vec![0x41; 300];
+        let encoded = octet_string(&content);
+        assert_eq!(&encoded[..4], &[0x04, 0x82, 0x01, 0x2c]);
+        assert_eq!(
+            Reader::new(&encoded).expect(OCTET_STRING),
+            Some(&content[..])
+        );
+    }
+}
@@ -0,0 +1,143 @@
+//! Hashes and the block cipher needed by the SNMPv3 user-based security
+//! model, from the RustCrypto crates: MD5, SHA-1 and SHA-256 for HMAC
+//! authentication (RFC 3414, RFC 7860) and AES-128 in CFB mode for privacy
+//! (RFC 3826).
+
+use aes::Aes128;
+use cfb_mode::cipher::{AsyncStreamCipher, KeyIvInit};
+use hmac::digest::KeyInit;
+use hmac::{Hmac, Mac};
+use md5::Md5;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+/// Hash function used for authentication and key localization
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hash {
+    Md5,
+    Sha1,
+    Sha256,
+}
+
+impl Hash {
+    pub fn digest(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Hash::Md5 => Md5::digest(data).to_vec(),
+            Hash::Sha1 => Sha1::digest(data).to_vec(),
+            Hash::Sha256 => Sha256::digest(data).to_vec(),
+        }
+    }
+
+    /// HMAC (RFC 2104) over `data`
+    pub fn hmac(self, key: &[u8], data: &[u8]) -> Vec<u8> {
+        match self {
+            Hash::Md5 => hmac::<Hmac<Md5>>(key, data),
+            Hash::Sha1 => hmac::<Hmac<Sha1>>(key, data),
+            Hash::Sha256 => hmac::<Hmac<Sha256>>(key, data),
+        }
+    }
+
+    /// Check an HMAC truncated to its leading bytes, in constant time
+    pub fn verify_hmac(self, key: &[u8], data: &[u8], tag: &[u8]) -> bool {
+        match self {
+            Hash::Md5 => verify_hmac::<Hmac<Md5>>(key, data, tag),
+            Hash::Sha1 => verify_hmac::<Hmac<Sha1>>(key, data, tag),
+            Hash::Sha256 => verify_hmac::<Hmac<Sha256>>(key, data, tag),
+        }
+    }
+}
+
+fn keyed<M: Mac + KeyInit>(key: &[u8], data: &[u8]) -> M {
+    let mut mac = <M as KeyInit>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac
+}
+
+fn hmac<M: Mac + KeyInit>(key: &[u8], data: &[u8]) -> Vec<u8> {
+    keyed::<M>(key, data).finalize().into_bytes().to_vec()
+}
+
+fn verify_hmac<M: Mac + KeyInit>(key: &[u8], data: &[u8], tag: &[u8]) -> bool {
+    keyed::<M>(key, data).verify_truncated_left(tag).is_ok()
+}
+
+/// AES-128 in CFB-128 mode
+pub fn aes128_cfb(key: &[u8; 16], iv: &[u8; 16], data: &[u8], decrypt: bool) -> Vec<u8> {
+    let mut out = data.to_vec();
+    if decrypt {
+        cfb_mode::Decryptor::<Aes128>::new(key.into(), iv.into()).decrypt(&mut out);
+    } else {
+        cfb_mode::Encryptor::<Aes128>::new(key.into(), iv.into()).encrypt(&mut out);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn test_hashes() {
+        assert_eq!(
+            hex(&Hash::Md5.digest(b"The quick brown fox jumps over the lazy dog")),
+            "9e107d9d372bb6826bd81d3542a419d6"
+        );
+        assert_eq!(
+            hex(&Hash::Sha1.digest(b"abc")),
+            "a9993e364706816aba3e25717850c26c9cd0d89d"
+        );
+        assert_eq!(
+            hex(&Hash::Sha256.digest(b"abc")),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_hmac() {
+        // RFC 2202 test case 2 and RFC 4231 test case 2
+        let data = b"what do ya want for nothing?";
+        assert_eq!(
+            hex(&Hash::Md5.hmac(b"Jefe", data)),
+            "750c783e6ab0b503eaa86e310a5db738"
+        );
+        assert_eq!(
+            hex(&Hash::Sha1.hmac(b"Jefe", data)),
+            "effcdf6ae5eb2fa2d27416d5f184df9c259a7c79"
+        );
+        let sha256 = Hash::Sha256.hmac(b"Jefe", data);
+        assert_eq!(
+            hex(&sha256),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+
+        // USM carries the first 24 bytes of an HMAC-SHA-256
+        assert!(Hash::Sha256.verify_hmac(b"Jefe", data, &sha256[..24]));
+        assert!(!Hash::Sha256.verify_hmac(b"Joe", data, &sha256[..24]));
+        assert!(!Hash::Sha256.verify_hmac(b"Jefe", data, &[]));
+    }
+
+    #[test]
+    fn test_aes128_cfb() {
+        // NIST SP 800-38A F.3.13, first two blocks plus a partial one
+        let key = [
+            0x2b, 0x7e, 0x15, 0x16, 0x28, 0xae, 0xd2, 0xa6, 0xab, 0xf7, 0x15, 0x88, 0x09, 0xcf,
+            0x4f, 0x3c,
+        ];
+        let iv: [u8; 16] = std::array::from_fn(|i| i as u8);
+        let plain = [
+            0x6b, 0xc1, 0xbe, 0xe2, 0x2e, 0x40, 0x9f, 0x96, 0xe9, 0x3d, 0x7e, 0x11, 0x73, 0x93,
+            0x17, 0x2a, 0xae, 0x2d, 0x8a, 0x57, 0x1e, 0x03, 0xac, 0x9c, 0x9e, 0xb7, 0x6f, 0xac,
+            0x45, 0xaf, 0x8e, 0x51, 0x30, 0xc8,
+        ];
+        let encrypted = aes128_cfb(&key, &iv, &plain, false);
+        assert_eq!(
+            hex(&encrypted[..32]),
+            "3b3fd92eb72dad20333449f8e83cfb4ac8a64537a0b3a93fcde3cdad9f1ce58b"
+        );
+        assert_eq!(aes128_cfb(&key, &iv, &encrypted, true), plain);
+    }
+}
@@ -0,0 +1,885 @@
+//! SNMP enrichment
+//!
+//! Asks each device for its system group (sysName, sysDescr, sysObjectID,
+//! sysLocation and sysUpTime), over SNMPv3 when a user is configured and
+//! then SNMPv2c with each community in turn. Switches, routers and printers
+//! are also asked for their interface table. The sysObjectID enterprise
+//! number identifies who built the agent; the classification rules match on
+//! it and rank it above the MAC vendor.
+
+mod ber;
+mod crypto;
+mod usm;
+
+use super::classify::Ruleset;
+use super::config::SnmpConfig;
+use super::{Device, HostnameSource, is_scan_cancelled};
+use ber::{Pdu, Reader, Value};
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+use tokio::net::UdpSocket;
+
+const SNMP_PORT: u16 = 161;
+/// msgVersion of SNMPv2c
+const VERSION_2C: i64 = 1;
+/// Rows read per interface table column per GETBULK
+const MAX_REPETITIONS: i64 = 16;
+/// Stop walking interface tables beyond this many rows
+const MAX_INTERFACES: usize = 512;
+
+const SYS_DESCR: &[u32] = &[1, 3, 6, 1, 2, 1, 1, 1, 0];
+const SYS_OBJECT_ID: &[u32] = &[1, 3, 6, 1, 2, 1, 1, 2, 0];
+const SYS_UPTIME: &[u32] = &[1, 3, 6, 1, 2, 1, 1, 3, 0];
+const SYS_NAME: &[u32] = &[1, 3, 6, 1, 2, 1, 1, 5, 0];
+const SYS_LOCATION: &[u32] = &[1, 3, 6, 1, 2, 1, 1, 6, 0];
+
+/// Interface table columns walked together, from IF-MIB
+const IF_COLUMNS: [&[u32]; 7] = [
+    &[1, 3, 6, 1, 2, 1, 2, 2, 1, 2],      // ifDescr
+    &[1, 3, 6, 1, 2, 1, 2, 2, 1, 5],      // ifSpeed
+    &[1, 3, 6, 1, 2, 1, 2, 2, 1, 6],      // ifPhysAddress
+    &[1, 3, 6, 1, 2, 1, 2, 2, 1, 8],      // ifOperStatus
+    &[1, 3, 6, 1, 2, 1, 31, 1, 1, 1, 1],  // ifName
+    &[1, 3, 6, 1, 2, 1, 31, 1, 1, 1, 15], // ifHighSpeed
+    &[1, 3, 6, 1, 2, 1, 31, 1, 1, 1, 18], // ifAlias
+];
+
+/// What a device's SNMP agent told us
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnmpInfo {
+    /// Protocol version that answered ("v2c" or "v3")
+    pub version: String,
+    pub sys_name: Option<String>,
+    pub sys_descr: Option<String>,
+    /// Dotted OID naming the vendor and model (e.g. "1.3.6.1.4.1.9.1.2494")
+    pub sys_object_id: Option<String>,
+    pub sys_location: Option<String>,
+    pub uptime_seconds: Option<u64>,
+    /// Interface table, read from network devices and printers only
+    #[serde(default)]
+    pub interfaces: Vec<SnmpInterface>,
+}
+
+/// One row of the interface table
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnmpInterface {
+    pub index: u32,
+    /// ifName (e.g. "Gi1/0/1")
+    pub name: Option<String>,
+    /// ifDescr
+    pub description: Option<String>,
+    /// ifAlias, the administrator's label
+    pub alias: Option<String>,
+    pub mac: Option<String>,
+    pub speed_mbps: Option<u64>,
+    /// ifOperStatus ("up", "down", "dormant", ...)
+    pub oper_status: Option<String>,
+}
+
+/// Query every device's SNMP agent and record what answered.
+pub async fn collect(devices: &mut [Device], config: &SnmpConfig, rules: &Ruleset) {
+    // Password hashing is the slow part of SNMPv3; do it once per scan
+    let keys = config.v3.as_ref().map(usm::UserKeys::new);
+
+    let work: Vec<(usize, SocketAddr, Option<String>)> = devices
+        .iter()
+        .enumerate()
+        .filter_map(|(i, d)| {
+            let ip: IpAddr = d.primary_ip().parse().ok()?;
+            Some((i, SocketAddr::new(ip, SNMP_PORT), d.device_type.clone()))
+        })
+        .collect();
+
+    tracing::info!(
+        "Querying SNMP on {} devices (concurrency {}, {}ms timeout)",
+        work.len(),
+        config.concurrency,
+        config.timeout_ms
+    );
+
+    let keys = keys.as_ref();
+    let results: Vec<(usize, Option<SnmpInfo>)> = stream::iter(work)
+        .map(|(i, agent, device_type)| async move {
+            if is_scan_cancelled() {
+                return (i, None);
+            }
+            (
+                i,
+                query_device(agent, device_type.as_deref(), config, keys, rules).await,
+            )
+        })
+        .buffer_unordered(config.concurrency.max(1))
+        .collect()
+        .await;
+
+    let mut answered = 0;
+    for (i, info) in results {
+        if let Some(info) = info {
+            apply_snmp_info(&mut devices[i], info);
+            answered += 1;
+        }
+    }
+    tracing::info!("{} devices answered SNMP", answered);
+}
+
+/// Record `info` on the device; sysName fills in a missing hostname.
+fn apply_snmp_info(device: &mut Device, info: SnmpInfo) {
+    if device.hostname.is_none()
+        && let Some(name) = &info.sys_name
+    {
+        device.hostname = Some(name.clone());
+        device.hostname_source = Some(HostnameSource::Snmp);
+    }
+    device.snmp = Some(info);
+}
+
+/// Try the configured credentials in turn until one gets an answer.
+async fn query_device(
+    agent: SocketAddr,
+    device_type: Option<&str>,
+    config: &SnmpConfig,
+    keys: Option<&usm::UserKeys>,
+    rules: &Ruleset,
+) -> Option<SnmpInfo> {
+    let bind: SocketAddr = match agent {
+        SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+        SocketAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+    };
+    let socket = UdpSocket::bind(bind).await.ok()?;
+    socket.connect(agent).await.ok()?;
+
+    let mut session = Session {
+        socket,
+        timeout: Duration::from_millis(config.timeout_ms),
+        retries: config.retries,
+        security: Security::Community(String::new()),
+        next_id: super::dns::next_query_id() as i32,
+    };
+
+    if let Some(keys) = keys {
+        match session.discover_engine().await {
+            Some(engine) => {
+                let user = keys.localize(&engine.id);
+                session.security = Security::V3 { user, engine };
+                if let Some(info) = session.read(device_type, rules).await {
+                    return Some(info);
+                }
+                tracing::debug!("{}: SNMPv3 request not answered", agent);
+            }
+            None => tracing::debug!("{}: no SNMPv3 engine", agent),
+        }
+    }
+
+    for community in &config.communities {
+        if is_scan_cancelled() {
+            return None;
+        }
+        session.security = Security::Community(community.clone());
+        if let Some(info) = session.read(device_type, rules).await {
+            return Some(info);
+        }
+    }
+    None
+}
+
+enum Security {
+    Community(String),
+    V3 {
+        user: usm::User,
+        engine: usm::Engine,
+    },
+}
+
+/// Requests to one agent with one set of credentials
+struct Session {
+    socket: UdpSocket,
+    timeout: Duration,
+    retries: u32,
+    security: Security,
+    next_id: i32,
+}
+
+/// What came back for a request
+enum Reply {
+    Response(Pdu),
+    /// The agent's clock moved on; retry with the engine it reported
+    NotInTimeWindow(usm::Engine),
+}
+
+impl Session {
+    /// Read the system group, and the interface table of network devices
+    /// and printers.
+    async fn read(&mut self, device_type: Option<&str>, rules: &Ruleset) -> Option<SnmpInfo> {
+        let oids: Vec<Vec<u32>> = [SYS_DESCR, SYS_OBJECT_ID, SYS_UPTIME, SYS_NAME, SYS_LOCATION]
+            .iter()
+            .map(|oid| oid.to_vec())
+            .collect();
+        let pdu = self.request(ber::GET_REQUEST, (0, 0), &oids).await?;
+        if pdu.error_status != 0 {
+            return None;
+        }
+
+        let mut info = SnmpInfo {
+            version: match self.security {
+                Security::Community(_) => "v2c",
+                Security::V3 { .. } => "v3",
+            }
+            .to_string(),
+            ..Default::default()
+        };
+        for (name, value) in &pdu.varbinds {
+            match name.as_slice() {
+                SYS_DESCR => info.sys_descr = value.as_text(),
+                SYS_NAME => info.sys_name = value.as_text(),
+                SYS_LOCATION => info.sys_location = value.as_text(),
+                SYS_UPTIME => info.uptime_seconds = value.as_u64().map(|ticks| ticks / 100),
+                SYS_OBJECT_ID => {
+                    if let Value::Oid(arcs) = value {
+                        info.sys_object_id = Some(ber::format_oid(arcs));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // What the rules make of the sysObjectID alone beats the NIC vendor
+        let by_agent = Device {
+            snmp: Some(info.clone()),
+            ..Default::default()
+        };
+        let by_agent = rules.classify(&by_agent).map(|v| v.device_type);
+        let device_type = by_agent.as_deref().or(device_type);
+        if matches!(device_type, Some("network_device" | "printer")) {
+            info.interfaces = self.interfaces().await;
+        }
+        Some(info)
+    }
+
+    /// Walk the interface table columns together with GETBULK.
+    async fn interfaces(&mut self) -> Vec<SnmpInterface> {
+        let mut rows: BTreeMap<u32, SnmpInterface> = BTreeMap::new();
+        // Last OID read in each column; None once the column is done
+        let mut cursors: Vec<Option<Vec<u32>>> = IF_COLUMNS
+            .iter()
+            .map(|column| Some(column.to_vec()))
+            .collect();
+
+        loop {
+            let active: Vec<usize> = (0..IF_COLUMNS.len())
+                .filter(|c| cursors[*c].is_some())
+                .collect();
+            if active.is_empty() || is_scan_cancelled() {
+                break;
+            }
+            let oids: Vec<Vec<u32>> = active.iter().filter_map(|c| cursors[*c].clone()).collect();
+            let Some(pdu) = self
+                .request(ber::GET_BULK, (0, MAX_REPETITIONS), &oids)
+                .await
+            else {
+                break;
+            };
+            if pdu.error_status != 0 {
+                break;
+            }
+
+            let mut advanced = vec![false; IF_COLUMNS.len()];
+            // Varbinds repeat the requested columns in order, row by row
+            for (k, (name, value)) in pdu.varbinds.into_iter().enumerate() {
+                let column = active[k % active.len()];
+                let Some(cursor) = &cursors[column] else {
+                    continue;
+                };
+                let prefix = IF_COLUMNS[column];
+                let in_column = name.len() == prefix.len() + 1 && name.starts_with(prefix);
+                // An agent that does not move forward would loop forever
+                if !in_column || value.is_exception() || name <= *cursor {
+                    cursors[column] = None;
+                    continue;
+                }
+                let index = name[prefix.len()];
+                if rows.len() >= MAX_INTERFACES && !rows.contains_key(&index) {
+                    cursors[column] = None;
+                    continue;
+                }
+                let row = rows.entry(index).or_insert_with(|| SnmpInterface {
+                    index,
+                    ..Default::default()
+                });
+                set_interface_column(row, column, &value);
+                cursors[column] = Some(name);
+                advanced[column] = true;
+            }
+            for column in active {
+                if !advanced[column] {
+                    cursors[column] = None;
+                }
+            }
+        }
+        rows.into_values().collect()
+    }
+
+    /// Engine ID, boots and time of a v3 agent, from the report it sends
+    /// back for an unauthenticated request.
+    async fn discover_engine(&mut self) -> Option<usm::Engine> {
+        let id = self.next_request_id();
+        let pdu = ber::request_pdu(ber::GET_REQUEST, id, (0, 0), &[]);
+        let message = usm::encode(id as i64, &usm::User::discovery(), None, &pdu);
+        exchange(
+            &self.socket,
+            &message,
+            self.timeout,
+            self.retries,
+            |reply| {
+                let reply = usm::decode(reply, &usm::User::discovery())?;
+                (reply.msg_id == id as i64
+                    && reply.pdu.tag == ber::REPORT
+                    && !reply.engine.id.is_empty())
+                .then_some(reply.engine)
+            },
+        )
+        .await
+    }
+
+    /// Send one request and wait for its response.
+    async fn request(&mut self, tag: u8, fields: (i64, i64), oids: &[Vec<u32>]) -> Option<Pdu> {
+        // One retry after the agent corrects our idea of its clock
+        for _ in 0..2 {
+            let id = self.next_request_id();
+            let pdu = ber::request_pdu(tag, id, fields, oids);
+            let reply = match &self.security {
+                Security::Community(community) => {
+                    let message = ber::sequence(&[
+                        ber::integer(VERSION_2C),
+                        ber::octet_string(community.as_bytes()),
+                        pdu,
+                    ]);
+                    exchange(
+                        &self.socket,
+                        &message,
+                        self.timeout,
+                        self.retries,
+                        |reply| parse_v2c_response(id, reply).map(Reply::Response),
+                    )
+                    .await?
+                }
+                Security::V3 { user, engine } => {
+                    let message = usm::encode(id as i64, user, Some(engine), &pdu);
+                    exchange(
+                        &self.socket,
+                        &message,
+                        self.timeout,
+                        self.retries,
+                        |reply| parse_v3_response(id, user, reply),
+                    )
+                    .await?
+                }
+            };
+
+            match reply {
+                Reply::Response(pdu) => return Some(pdu),
+                Reply::NotInTimeWindow(reported) => {
+                    if let Security::V3 { engine, .. } = &mut self.security {
+                        *engine = reported;
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    fn next_request_id(&mut self) -> i32 {
+        self.next_id = self.next_id.wrapping_add(1) & i32::MAX;
+        self.next_id
+    }
+}
+
+fn parse_v2c_response(id: i32, reply: &[u8]) -> Option<Pdu> {
+    let mut message = Reader::new(Reader::new(reply).expect(ber::SEQUENCE)?);
+    if message.integer()? != VERSION_2C {
+        return None;
+    }
+    let _community = message.expect(ber::OCTET_STRING)?;
+    let (tag, content) = message.read()?;
+    let pdu = ber::parse_pdu(tag, content)?;
+    (pdu.tag == ber::RESPONSE && pdu.request_id == id).then_some(pdu)
+}
+
+fn parse_v3_response(id: i32, user: &usm::User, reply: &[u8]) -> Option<Reply> {
+    let message = usm::decode(reply, user)?;
+    if message.msg_id != id as i64 {
+        return None;
+    }
+    match message.pdu.tag {
+        ber::RESPONSE => Some(Reply::Response(message.pdu)),
+        ber::REPORT => {
+            let reason = message
+                .pdu
+                .varbinds
+                .first()
+                .map(|(name, _)| ber::format_oid(name));
+            tracing::debug!("SNMPv3 report: {:?}", reason);
+            (reason.as_deref() == Some(usm::NOT_IN_TIME_WINDOWS))
+                .then_some(Reply::NotInTimeWindow(message.engine))
+        }
+        _ => None,
+    }
+}
+
+/// Send `packet` up to `retries + 1` times, waiting `timeout` for a reply
+/// that `accept` takes. An ICMP port unreachable ends the exchange early.
+async fn exchange<T>(
+    socket: &UdpSocket,
+    packet: &[u8],
+    timeout: Duration,
+    retries: u32,
+    accept: impl Fn(&[u8]) -> Option<T>,
+) -> Option<T> {
+    let mut buf = vec![0u8; 65535];
+    for _ in 0..=retries {
+        socket.send(packet).await.ok()?;
+        let deadline = tokio::time::Instant::now() + timeout;
+        while let Ok(received) = tokio::time::timeout_at(deadline, socket.recv(&mut buf)).await {
+            // Connection refused: no agent on the port
+            let len = received.ok()?;
+            if let Some(answer) = accept(&buf[..len]) {
+                return Some(answer);
+            }
+        }
+    }
+    None
+}
+
+fn set_interface_column(row: &mut SnmpInterface, column: usize, value: &Value) {
+    match column {
+        0 => row.description = value.as_text(),
+        // ifHighSpeed is preferred; ifSpeed tops out at 4.3 Gb/s
+        1 if row.speed_mbps.is_none() => {
+            row.speed_mbps = value
+                .as_u64()
+                .filter(|bps| *bps > 0)
+                .map(|bps| bps / 1_000_000);
+        }
+        2 => {
+            if let Value::OctetString(bytes) = value
+                && bytes.len() == 6
+                && bytes.iter().any(|b| *b != 0)
+            {
+                row.mac = Some(
+                    bytes
+                        .iter()
+                        .map(|b| format!("{:02x}", b))
+                        .collect::<Vec<_>>()
+                        .join(":"),
+                );
+            }
+        }
+        3 => {
+            row.oper_status = value
+                .as_u64()
+                .and_then(|status| match status {
+                    1 => Some("up"),
+                    2 => Some("down"),
+                    3 => Some("testing"),
+                    4 => Some("unknown"),
+                    5 => Some("dormant"),
+                    6 => Some("not_present"),
+                    7 => Some("lower_layer_down"),
+                    _ => None,
+                })
+                .map(String::from)
+        }
+        4 => row.name = value.as_text(),
+        5 => {
+            if let Some(mbps) = value.as_u64().filter(|mbps| *mbps > 0) {
+                row.speed_mbps = Some(mbps);
+            }
+        }
+        6 => row.alias = value.as_text(),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::config::{SnmpAuthProtocol, SnmpPrivacyProtocol, SnmpV3Config};
+    use std::ops::Bound;
+    use std::time::Instant;
+
+    type Mib = BTreeMap<Vec<u32>, Vec<u8>>;
+
+    fn mib() -> Mib {
+        let mut mib = Mib::new();
+        let mut add = |oid: &str, value: Vec<u8>| {
+            mib.insert(ber::parse_oid(oid).unwrap(), value);
+        };
+        add(
+            "1.3.6.1.2.1.1.1.0",
+            ber::octet_string(b"Cisco IOS Software, C2960X"),
+        );
+        add(
+            "1.3.6.1.2.1.1.2.0",
+            ber::oid(&[1, 3, 6, 1, 4, 1, 9, 1, 1208]),
+        );
+        // TimeTicks
+        add(
+            "1.3.6.1.2.1.1.3.0",
+            ber::tlv(0x43, &[0x01, 0x00, 0x00, 0x00]),
+        );
+        add("1.3.6.1.2.1.1.5.0", ber::octet_string(b"core-sw1"));
+        for i in 1..=5u8 {
+            let index = 10100 + i as u32;
+            add(
+                &format!("1.3.6.1.2.1.2.2.1.2.{}", index),
+                ber::octet_string(format!("GigabitEthernet1/0/{}", i).as_bytes()),
+            );
+            // Gauge32
+            add(
+                &format!("1.3.6.1.2.1.2.2.1.5.{}", index),
+                ber::tlv(0x42, &[0x3b, 0x9a, 0xca, 0x00]),
+            );
+            add(
+                &format!("1.3.6.1.2.1.2.2.1.6.{}", index),
+                ber::octet_string(&[0x00, 0x1b, 0x54, 0x11, 0x22, i]),
+            );
+            add(
+                &format!("1.3.6.1.2.1.2.2.1.8.{}", index),
+                ber::integer(if i == 5 { 2 } else { 1 }),
+            );
+            add(
+                &format!("1.3.6.1.2.1.31.1.1.1.1.{}", index),
+                ber::octet_string(format!("Gi1/0/{}", i).as_bytes()),
+            );
+        }
+        add(
+            "1.3.6.1.2.1.31.1.1.1.18.10101",
+            ber::octet_string(b"uplink"),
+        );
+        mib
+    }
+
+    /// Answer GET and GETBULK from `mib`, with at most three rows per
+    /// GETBULK so that walks need several requests
+    fn respond(mib: &Mib, request: &Pdu) -> Vec<u8> {
+        let varbind = |name: &[u32], value: Vec<u8>| ber::sequence(&[ber::oid(name), value]);
+        let mut varbinds = Vec::new();
+        if request.tag == ber::GET_BULK {
+            let mut cursors: Vec<Vec<u32>> =
+                request.varbinds.iter().map(|(n, _)| n.clone()).collect();
+            for _ in 0..3 {
+                for cursor in cursors.iter_mut() {
+                    match mib
+                        .range((Bound::Excluded(cursor.clone()), Bound::Unbounded))
+                        .next()
+                    {
+                        Some((name, value)) => {
+                            varbinds.push(varbind(name, value.clone()));
+                            *cursor = name.clone();
+                        }
+                        // endOfMibView
+                        None => varbinds.push(varbind(cursor, ber::tlv(0x82, &[]))),
+                    }
+                }
+            }
+        } else {
+            for (name, _) in &request.varbinds {
+                // noSuchObject
+                let value = mib
+                    .get(name)
+                    .cloned()
+                    .unwrap_or_else(|| ber::tlv(0x80, &[]));
+                varbinds.push(varbind(name, value));
+            }
+        }
+        ber::tlv(
+            ber::RESPONSE,
+            &[
+                ber::integer(request.request_id as i64),
+                ber::integer(0),
+                ber::integer(0),
+                ber::sequence(&varbinds),
+            ]
+            .concat(),
+        )
+    }
+
+    async fn v2c_agent(community: &'static str, mib: Mib) -> SocketAddr {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = socket.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut buf = [0u8; 2048];
+            while let Ok((len, from)) = socket.recv_from(&mut buf).await {
+                let mut message =
+                    Reader::new(Reader::new(&buf[..len]).expect(ber::SEQUENCE).unwrap());
+                assert_eq!(message.integer(), Some(VERSION_2C));
+                // Wrong communities are dropped without a reply
+                if message.expect(ber::OCTET_STRING).unwrap() != community.as_bytes() {
+                    continue;
+                }
+                let (tag, content) = message.read().unwrap();
+                let request = ber::parse_pdu(tag, content).unwrap();
+                let reply = ber::sequence(&[
+                    ber::integer(VERSION_2C),
+                    ber::octet_string(community.as_bytes()),
+                    respond(&mib, &request),
+                ]);
+                let _ = socket.send_to(&reply, from).await;
+            }
+        });
+        addr
+    }
+
+    async fn v3_agent(config: SnmpV3Config) -> SocketAddr {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = socket.local_addr().unwrap();
+        let mib = mib();
+        let engine = usm::Engine {
+            id: b"\x80\x00\x00\x09\x04core-sw1".to_vec(),
+            boots: 3,
+            time: 86_400,
+            learned: Instant::now(),
+        };
+        let user = usm::UserKeys::new(&config).localize(&engine.id);
+        tokio::spawn(async move {
+            let mut buf = [0u8; 2048];
+            while let Ok((len, from)) = socket.recv_from(&mut buf).await {
+                // Messages that fail authentication are dropped
+                let Some(request) = usm::decode(&buf[..len], &user) else {
+                    continue;
+                };
+                let reply = if request.engine.id.is_empty() {
+                    // usmStatsUnknownEngineIDs report for discovery
+                    let report = ber::tlv(
+                        ber::REPORT,
+                        &[
+                            ber::integer(request.pdu.request_id as i64),
+                            ber::integer(0),
+                            ber::integer(0),
+                            ber::sequence(&[ber::sequence(&[
+                                ber::oid(&ber::parse_oid("1.3.6.1.6.3.15.1.1.4.0").unwrap()),
+                                ber::tlv(0x41, &[1]),
+                            ])]),
+                        ]
+                        .concat(),
+                    );
+                    usm::encode(
+                        request.msg_id,
+                        &usm::User::discovery(),
+                        Some(&engine),
+                        &report,
+                    )
+                } else {
+                    usm::encode(
+                        request.msg_id,
+                        &user,
+                        Some(&engine),
+                        &respond(&mib, &request.pdu),
+                    )
+                };
+                let _ = socket.send_to(&reply, from).await;
+            }
+        });
+        addr
+    }
+
+    fn test_config() -> SnmpConfig {
+        SnmpConfig {
+            enabled: true,
+            communities: vec!["private".to_string(), "public".to_string()],
+            timeout_ms: 200,
+            retries: 0,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_query_v2c_with_interface_walk() {
+        let agent = v2c_agent("public", mib()).await;
+        let info = query_device(agent, None, &test_config(), None, &Ruleset::builtin())
+            .await
+            .unwrap();
+
+        assert_eq!(info.version, "v2c");
+        assert_eq!(info.sys_name.as_deref(), Some("core-sw1"));
+        assert_eq!(info.sys_object_id.as_deref(), Some("1.3.6.1.4.1.9.1.1208"));
+        assert_eq!(info.uptime_seconds, Some(167_772));
+        assert_eq!(info.sys_location, None);
+
+        // Cisco's enterprise number makes it a network device, so the
+        // interface table is read even without a device type
+        assert_eq!(info.interfaces.len(), 5);
+        let uplink = &info.interfaces[0];
+        assert_eq!(uplink.index, 10101);
+        assert_eq!(uplink.name.as_deref(), Some("Gi1/0/1"));
+        assert_eq!(uplink.description.as_deref(), Some("GigabitEthernet1/0/1"));
+        assert_eq!(uplink.alias.as_deref(), Some("uplink"));
+        assert_eq!(uplink.mac.as_deref(), Some("00:1b:54:11:22:01"));
+        assert_eq!(uplink.speed_mbps, Some(1000));
+        assert_eq!(uplink.oper_status.as_deref(), Some("up"));
+        assert_eq!(info.interfaces[4].oper_status.as_deref(), Some("down"));
+
+        let mut device = Device {
+            addresses: vec!["192.0.2.2".to_string()],
+            device_type: Some("computer".to_string()),
+            ..Default::default()
+        };
+        apply_snmp_info(&mut device, info);
+        Ruleset::builtin().apply(std::slice::from_mut(&mut device));
+        assert_eq!(device.device_type.as_deref(), Some("network_device"));
+        assert_eq!(device.classification.unwrap().rule, "snmp-network");
+        assert_eq!(device.hostname.as_deref(), Some("core-sw1"));
+        assert_eq!(device.hostname_source, Some(HostnameSource::Snmp));
+    }
+
+    #[tokio::test]
+    async fn test_query_v3_auth_priv() {
+        let v3 = SnmpV3Config {
+            username: "monitor".to_string(),
+            auth_protocol: SnmpAuthProtocol::Sha,
+            auth_password: Some("auth-secret".to_string()),
+            privacy_protocol: SnmpPrivacyProtocol::Aes,
+            privacy_password: Some("priv-secret".to_string()),
+        };
+        let agent = v3_agent(v3.clone()).await;
+        let keys = usm::UserKeys::new(&v3);
+        let info = query_device(
+            agent,
+            Some("printer"),
+            &test_config(),
+            Some(&keys),
+            &Ruleset::builtin(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(info.version, "v3");
+        assert_eq!(
+            info.sys_descr.as_deref(),
+            Some("Cisco IOS Software, C2960X")
+        );
+        assert_eq!(info.interfaces.len(), 5);
+
+        // A wrong password gets no answer, and the agent speaks no v2c
+        let wrong = usm::UserKeys::new(&SnmpV3Config {
+            auth_password: Some("not-the-secret".to_string()),
+            ..v3
+        });
+        assert!(
+            query_device(
+                agent,
+                None,
+                &test_config(),
+                Some(&wrong),
+                &Ruleset::builtin()
+            )
+            .await
+            .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_query_v3_auth_no_priv() {
+        // The agent's user has no privacy key, so it can only read
+        // requests that are authenticated but not encrypted
+        let v3 = SnmpV3Config {
+            username: "monitor".to_string(),
+            auth_protocol: SnmpAuthProtocol::Sha256,
+            auth_password: Some("auth-secret".to_string()),
+            privacy_protocol: SnmpPrivacyProtocol::Aes,
+            privacy_password: None,
+        };
+        let agent = v3_agent(v3.clone()).await;
+        let keys = usm::UserKeys::new(&v3);
+        let info = query_device(
+            agent,
+            None,
+            &test_config(),
+            Some(&keys),
+            &Ruleset::builtin(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(info.version, "v3");
+        assert_eq!(info.sys_name.as_deref(), Some("core-sw1"));
+        assert_eq!(info.interfaces.len(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_query_wrong_credentials() {
+        // No configured community matches
+        let agent = v2c_agent("s3cret", mib()).await;
+        assert!(
+            query_device(agent, None, &test_config(), None, &Ruleset::builtin())
+                .await
+                .is_none()
+        );
+
+        // Right password, wrong hash: the agent drops the request, and
+        // the v2c fallback finds no agent either
+        let v3 = SnmpV3Config {
+            username: "monitor".to_string(),
+            auth_protocol: SnmpAuthProtocol::Md5,
+            auth_password: Some("auth-secret".to_string()),
+            privacy_protocol: SnmpPrivacyProtocol::Aes,
+            privacy_password: None,
+        };
+        let agent = v3_agent(v3.clone()).await;
+        let wrong = usm::UserKeys::new(&SnmpV3Config {
+            auth_protocol: SnmpAuthProtocol::Sha,
+            ..v3.clone()
+        });
+        assert!(
+            query_device(
+                agent,
+                None,
+                &test_config(),
+                Some(&wrong),
+                &Ruleset::builtin()
+            )
+            .await
+            .is_none()
+        );
+
+        // Privacy the agent's user does not have
+        let encrypting = usm::UserKeys::new(&SnmpV3Config {
+            privacy_password: Some("priv-secret".to_string()),
+            ..v3
+        });
+        assert!(
+            query_device(
+                agent,
+                None,
+                &test_config(),
+                Some(&encrypting),
+                &Ruleset::builtin()
+            )
+            .await
+            .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_interface_walk_stops_at_row_cap() {
+        let mut mib = mib();
+        for index in 20_001..=20_600u32 {
+            mib.insert(
+                [IF_COLUMNS[0], &[index]].concat(),
+                ber::octet_string(format!("port{}", index).as_bytes()),
+            );
+        }
+        let agent = v2c_agent("public", mib).await;
+        let info = query_device(agent, None, &test_config(), None, &Ruleset::builtin())
+            .await
+            .unwrap();
+
+        assert_eq!(info.interfaces.len(), MAX_INTERFACES);
+        assert_eq!(info.interfaces[0].name.as_deref(), Some("Gi1/0/1"));
+        let last = info.interfaces.last().unwrap();
+        assert_eq!(last.index, 20_000 + (MAX_INTERFACES - 5) as u32);
+        assert_eq!(last.name, None);
+    }
+}
@@ -0,0 +1,395 @@
+//! SNMPv3 messages with the user-based security model (RFC 3414)
+//!
+//! Covers engine discovery, HMAC authentication of requests and responses,
+//! and AES-128 privacy (RFC 3826).
+
+use super::ber::{self, Pdu, Reader};
+use super::crypto::{self, Hash};
+use crate::scanner::config::{SnmpAuthProtocol, SnmpPrivacyProtocol, SnmpV3Config};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+const VERSION_3: i64 = 3;
+const SECURITY_MODEL_USM: i64 = 3;
+const MAX_MESSAGE_SIZE: i64 = 65507;
+const FLAG_AUTH: u8 = 0x01;
+const FLAG_PRIV: u8 = 0x02;
+const FLAG_REPORTABLE: u8 = 0x04;
+/// Password bytes hashed for the user key (RFC 3414 A.2)
+const PASSWORD_EXPANSION: usize = 1024 * 1024;
+
+/// usmStatsNotInTimeWindows, reported when our engine time is stale
+pub const NOT_IN_TIME_WINDOWS: &str = "1.3.6.1.6.3.15.1.1.2.0";
+
+/// The authoritative engine of an agent, from discovery or a response
+#[derive(Debug, Clone)]
+pub struct Engine {
+    pub id: Vec<u8>,
+    pub boots: i64,
+    pub time: i64,
+    /// When `time` was learned, to keep the clock moving
+    pub learned: Instant,
+}
+
+impl Engine {
+    fn current_time(&self) -> i64 {
+        self.time + self.learned.elapsed().as_secs() as i64
+    }
+}
+
+/// A user's password-derived keys, computed once per scan
+pub struct UserKeys {
+    name: String,
+    hash: Hash,
+    auth: Option<Vec<u8>>,
+    privacy: Option<Vec<u8>>,
+}
+
+impl UserKeys {
+    /// Hash the passwords in `config`. This is slow by design (RFC 3414
+    /// A.2 hashes a megabyte per password), so do it once, not per agent.
+    pub fn new(config: &SnmpV3Config) -> Self {
+        let hash = match config.auth_protocol {
+            SnmpAuthProtocol::Md5 => Hash::Md5,
+            SnmpAuthProtocol::Sha => Hash::Sha1,
+            SnmpAuthProtocol::Sha256 => Hash::Sha256,
+        };
+        let auth = config
+            .auth_password
+            .as_deref()
+            .map(|password| password_key(hash, password));
+        let privacy = match (&auth, config.privacy_protocol, &config.privacy_password) {
+            (Some(_), SnmpPrivacyProtocol::Aes, Some(password)) => {
+                Some(password_key(hash, password))
+            }
+            _ => None,
+        };
+        Self {
+            name: config.username.clone(),
+            hash,
+            auth,
+            privacy,
+        }
+    }
+
+    /// Bind the keys to one agent's engine
+    pub fn localize(&self, engine_id: &[u8]) -> User {
+        User {
+            name: self.name.clone(),
+            auth: self
+                .auth
+                .as_ref()
+                .map(|key| (self.hash, localize(self.hash, key, engine_id))),
+            privacy: self.privacy.as_ref().and_then(|key| {
+                localize(self.hash, key, engine_id)
+                    .get(..16)
+                    .and_then(|k| k.try_into().ok())
+            }),
+        }
+    }
+}
+
+/// A user's keys localized to one engine
+pub struct User {
+    name: String,
+    auth: Option<(Hash, Vec<u8>)>,
+    privacy: Option<[u8; 16]>,
+}
+
+impl User {
+    /// Unauthenticated user for engine discovery
+    pub fn discovery() -> Self {
+        Self {
+            name: String::new(),
+            auth: None,
+            privacy: None,
+        }
+    }
+}
+
+/// RFC 3414 A.2: hash 1 MB of the repeated password
+fn password_key(hash: Hash, password: &str) -> Vec<u8> {
+    let password = password.as_bytes();
+    let expanded: Vec<u8> = if password.is_empty() {
+        Vec::new()
+    } else {
+        password
+            .iter()
+            .cycle()
+            .take(PASSWORD_EXPANSION)
+            .copied()
+            .collect()
+    };
+    hash.digest(&expanded)
+}
+
+/// Bind a password key to an engine ID
+fn localize(hash: Hash, key: &[u8], engine_id: &[u8]) -> Vec<u8> {
+    hash.digest(&[key, engine_id, key].concat())
+}
+
+/// Bytes of the truncated HMAC carried in msgAuthenticationParameters
+fn auth_param_len(hash: Hash) -> usize {
+    match hash {
+        Hash::Md5 | Hash::Sha1 => 12,
+        Hash::Sha256 => 24,
+    }
+}
+
+/// A decoded SNMPv3 message
+#[derive(Debug)]
+pub struct Message {
+    pub msg_id: i64,
+    pub engine: Engine,
+    pub pdu: Pdu,
+}
+
+/// Encode a request. `engine` is `None` for discovery.
+pub fn encode(msg_id: i64, user: &User, engine: Option<&Engine>, pdu: &[u8]) -> Vec<u8> {
+    let (engine_id, boots, time) = match engine {
+        Some(e) => (e.id.as_slice(), e.boots, e.current_time()),
+        None => (&[][..], 0, 0),
+    };
+
+    let mut flags = FLAG_REPORTABLE;
+    let scoped = ber::sequence(&[
+        ber::octet_string(engine_id),
+        ber::octet_string(b""),
+        pdu.to_vec(),
+    ]);
+    let (data, priv_params) = match &user.privacy {
+        Some(key) => {
+            flags |= FLAG_PRIV;
+            let salt = next_salt().to_be_bytes();
+            let encrypted = crypto::aes128_cfb(key, &aes_iv(boots, time, &salt), &scoped, false);
+            (ber::octet_string(&encrypted), salt.to_vec())
+        }
+        None => (scoped, Vec::new()),
+    };
+    let auth_placeholder = match &user.auth {
+        Some((hash, _)) => {
+            flags |= FLAG_AUTH;
+            vec![0u8; auth_param_len(*hash)]
+        }
+        None => Vec::new(),
+    };
+
+    let username = ber::octet_string(user.name.as_bytes());
+    let auth_field = ber::octet_string(&auth_placeholder);
+    let security = ber::sequence(&[
+        ber::octet_string(engine_id),
+        ber::integer(boots),
+        ber::integer(time),
+        username.clone(),
+        auth_field.clone(),
+        ber::octet_string(&priv_params),
+    ]);
+    let mut message = ber::sequence(&[
+        ber::integer(VERSION_3),
+        ber::sequence(&[
+            ber::integer(msg_id),
+            ber::integer(MAX_MESSAGE_SIZE),
+            ber::octet_string(&[flags]),
+            ber::integer(SECURITY_MODEL_USM),
+        ]),
+        ber::octet_string(&security),
+        data,
+    ]);
+
+    if let Some((hash, key)) = &user.auth {
+        // The placeholder follows the user name; the HMAC is computed over
+        // the whole message with it zeroed
+        let marker = [username, auth_field].concat();
+        if let Some(at) = find(&message, &marker) {
+            let start = at + marker.len() - auth_placeholder.len();
+            let digest = hash.hmac(key, &message);
+            message[start..start + auth_placeholder.len()]
+                .copy_from_slice(&digest[..auth_placeholder.len()]);
+        }
+    }
+    message
+}
+
+/// Decode a response or report, checking its HMAC and decrypting it when
+/// `user` has keys.
+pub fn decode(buf: &[u8], user: &User) -> Option<Message> {
+    let mut outer = Reader::new(Reader::new(buf).expect(ber::SEQUENCE)?);
+    if outer.integer()? != VERSION_3 {
+        return None;
+    }
+    let mut global = Reader::new(outer.expect(ber::SEQUENCE)?);
+    let msg_id = global.integer()?;
+    let _max_size = global.integer()?;
+    let flags = *global.expect(ber::OCTET_STRING)?.first()?;
+
+    let mut security =
+        Reader::new(Reader::new(outer.expect(ber::OCTET_STRING)?).expect(ber::SEQUENCE)?);
+    let engine_id = security.expect(ber::OCTET_STRING)?.to_vec();
+    let boots = security.integer()?;
+    let time = security.integer()?;
+    let _username = security.expect(ber::OCTET_STRING)?;
+    let auth_params = security.expect(ber::OCTET_STRING)?;
+    let priv_params = security.expect(ber::OCTET_STRING)?;
+
+    if flags & FLAG_AUTH != 0 {
+        let (hash, key) = user.auth.as_ref()?;
+        let start = auth_params.as_ptr() as usize - buf.as_ptr() as usize;
+        let mut zeroed = buf.to_vec();
+        zeroed[start..start + auth_params.len()].fill(0);
+        if auth_params.len() != auth_param_len(*hash)
+            || !hash.verify_hmac(key, &zeroed, auth_params)
+        {
+            tracing::debug!("SNMPv3 response failed authentication");
+            return None;
+        }
+    }
+
+    let (tag, data) = outer.read()?;
+    let decrypted;
+    let scoped = if flags & FLAG_PRIV != 0 {
+        let key = user.privacy.as_ref()?;
+        if tag != ber::OCTET_STRING {
+            return None;
+        }
+        let salt: [u8; 8] = priv_params.try_into().ok()?;
+        decrypted = crypto::aes128_cfb(key, &aes_iv(boots, time, &salt), data, true);
+        Reader::new(&decrypted).expect(ber::SEQUENCE)?
+    } else if tag == ber::SEQUENCE {
+        data
+    } else {
+        return None;
+    };
+
+    let mut scoped = Reader::new(scoped);
+    let _context_engine = scoped.expect(ber::OCTET_STRING)?;
+    let _context_name = scoped.expect(ber::OCTET_STRING)?;
+    let (pdu_tag, pdu) = scoped.read()?;
+
+    Some(Message {
+        msg_id,
+        engine: Engine {
+            id: engine_id,
+            boots,
+            time,
+            learned: Instant::now(),
+        },
+        pdu: ber::parse_pdu(pdu_tag, pdu)?,
+    })
+}
+
+/// RFC 3826 3.1.2.1: boots, time and the 64-bit salt
+fn aes_iv(boots: i64, time: i64, salt: &[u8; 8]) -> [u8; 16] {
+    let mut iv = [0u8; 16];
+    iv[..4].copy_from_slice(&(boots as u32).to_be_bytes());
+    iv[4..8].copy_from_slice(&(time as u32).to_be_bytes());
+    iv[8..].copy_from_slice(salt);
+    iv
+}
+
+/// Salt for AES privacy; must not repeat under one key
+fn next_salt() -> u64 {
+    static SALT: std::sync::OnceLock<AtomicU64> = std::sync::OnceLock::new();
+    SALT.get_or_init(|| {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or_default();
+        AtomicU64::new(nanos ^ ((std::process::id() as u64) << 32))
+    })
+    .fetch_add(1, Ordering::Relaxed)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn config(auth: SnmpAuthProtocol, privacy: SnmpPrivacyProtocol) -> SnmpV3Config {
+        SnmpV3Config {
+            username: "monitor".to_string(),
+            auth_protocol: auth,
+            auth_password: Some("maplesyrup".to_string()),
+            privacy_protocol: privacy,
+            privacy_password: (privacy != SnmpPrivacyProtocol::None)
+                .then(|| "privpassword".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_localize_key() {
+        // RFC 3414 A.3.1 and A.3.2
+        let engine_id = [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2];
+        assert_eq!(
+            hex(&localize(
+                Hash::Md5,
+                &password_key(Hash::Md5, "maplesyrup"),
+                &engine_id
+            )),
+            "526f5eed9fcce26f8964c2930787d82b"
+        );
+        assert_eq!(
+            hex(&localize(
+                Hash::Sha1,
+                &password_key(Hash::Sha1, "maplesyrup"),
+                &engine_id
+            )),
+            "6695febc9288e36282235fc7151f128497b38f3f"
+        );
+    }
+
+    #[test]
+    fn test_encode_decode_auth_priv() {
+        for auth in [
+            SnmpAuthProtocol::Md5,
+            SnmpAuthProtocol::Sha,
+            SnmpAuthProtocol::Sha256,
+        ] {
+            let engine = Engine {
+                id: b"\x80\x00\x1f\x88\x80agent-1".to_vec(),
+                boots: 7,
+                time: 1200,
+                learned: Instant::now(),
+            };
+            let user = UserKeys::new(&config(auth, SnmpPrivacyProtocol::Aes)).localize(&engine.id);
+            let pdu = ber::request_pdu(
+                ber::GET_REQUEST,
+                99,
+                (0, 0),
+                &[ber::parse_oid("1.3.6.1.2.1.1.5.0").unwrap()],
+            );
+
+            let message = encode(5, &user, Some(&engine), &pdu);
+            // The PDU is not visible on the wire
+            assert!(find(&message, &pdu).is_none());
+
+            let decoded = decode(&message, &user).expect("authenticated and decrypted");
+            assert_eq!(decoded.msg_id, 5);
+            assert_eq!(decoded.engine.boots, 7);
+            assert_eq!(decoded.pdu.request_id, 99);
+
+            // A flipped bit fails authentication
+            let mut tampered = message.clone();
+            let last = tampered.len() - 1;
+            tampered[last] ^= 1;
+            assert!(decode(&tampered, &user).is_none());
+        }
+    }
+
+    #[test]
+    fn test_discovery_request() {
+        let pdu = ber::request_pdu(ber::GET_REQUEST, 1, (0, 0), &[]);
+        let message = encode(1, &User::discovery(), None, &pdu);
+        let decoded = decode(&message, &User::discovery()).unwrap();
+        assert!(decoded.engine.id.is_empty());
+        assert!(decoded.pdu.varbinds.is_empty());
+    }
+}
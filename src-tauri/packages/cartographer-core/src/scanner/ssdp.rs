@@ -0,0 +1,462 @@
+//! SSDP / UPnP discovery
+//!
+//! Multicasts an `M-SEARCH` for `ssdp:all`, collects the `LOCATION` of each
+//! responder and fetches its device description XML for the friendly name,
+//! manufacturer, model and serial number. Descriptions are only fetched
+//! from responders inside the scan targets and not in `[scan] exclude`.
+
+use super::config::SsdpConfig;
+use super::is_scan_cancelled;
+use super::mdns::multicast_socket;
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::time::{Duration, Instant};
+use tokio::net::UdpSocket;
+
+const SSDP_GROUP: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(239, 255, 255, 250), 1900);
+/// UPnP Device Architecture 1.1: the multicast TTL should default to 2
+const SSDP_TTL: u32 = 2;
+/// Seconds responders may wait before answering
+const MX_SECONDS: u64 = 2;
+/// Description documents larger than this are not read
+const MAX_DESCRIPTION_BYTES: usize = 256 * 1024;
+/// Descriptions fetched in parallel
+const FETCH_CONCURRENCY: usize = 16;
+
+/// Root device details from a UPnP device description
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpnpDevice {
+    /// Name set by the user or vendor (e.g. "Living Room TV")
+    pub friendly_name: Option<String>,
+    pub manufacturer: Option<String>,
+    pub model_name: Option<String>,
+    pub model_number: Option<String>,
+    pub serial_number: Option<String>,
+    /// UPnP device type (e.g. "urn:schemas-upnp-org:device:MediaRenderer:1")
+    pub device_type: Option<String>,
+    /// SSDP `SERVER` header (e.g. "Linux/4.9 UPnP/1.0 Roku/11.5")
+    pub server: Option<String>,
+}
+
+impl UpnpDevice {
+    /// "modelName modelNumber", without repeating a number already in the name
+    pub fn model(&self) -> Option<String> {
+        let name = self.model_name.as_deref()?;
+        match self.model_number.as_deref() {
+            Some(number) if !number.is_empty() && !name.contains(number) => {
+                Some(format!("{} {}", name, number))
+            }
+            _ => Some(name.to_string()),
+        }
+    }
+}
+
+/// What a responder said in its M-SEARCH replies
+#[derive(Debug, Default)]
+struct Responder {
+    locations: BTreeSet<String>,
+    server: Option<String>,
+}
+
+/// Search on every local IPv4 address and return the description of each
+/// responder `in_scope` accepts, keyed by its address.
+pub async fn discover(
+    local_ips: &[Ipv4Addr],
+    config: &SsdpConfig,
+    in_scope: impl Fn(IpAddr) -> bool,
+) -> HashMap<IpAddr, UpnpDevice> {
+    let window = Duration::from_millis(config.window_ms);
+    let searches: Vec<_> = local_ips
+        .iter()
+        .filter_map(|ip| match multicast_socket(*ip, SSDP_TTL) {
+            Ok(socket) => Some(search_on(socket, SocketAddr::V4(SSDP_GROUP), window)),
+            Err(e) => {
+                tracing::warn!("SSDP search on {} unavailable: {:#}", ip, e);
+                None
+            }
+        })
+        .collect();
+
+    let mut responders: HashMap<IpAddr, Responder> = HashMap::new();
+    for found in futures::future::join_all(searches).await {
+        for (address, responder) in found {
+            let entry = responders.entry(address).or_default();
+            entry.locations.extend(responder.locations);
+            entry.server = entry.server.take().or(responder.server);
+        }
+    }
+
+    let devices = describe_all(responders, config, in_scope).await;
+    tracing::info!("SSDP: {} UPnP devices described", devices.len());
+    devices
+}
+
+/// Send M-SEARCH to `dest` and collect replies until `window` has passed.
+async fn search_on(
+    socket: UdpSocket,
+    dest: SocketAddr,
+    window: Duration,
+) -> HashMap<IpAddr, Responder> {
+    let request = format!(
+        "M-SEARCH * HTTP/1.1\r\nHOST: {}\r\nMAN: \"ssdp:discover\"\r\nMX: {}\r\nST: ssdp:all\r\nUSER-AGENT: cartographer-agent UPnP/1.1\r\n\r\n",
+        SSDP_GROUP, MX_SECONDS
+    );
+    // Sent twice: SSDP runs over UDP and replies to a lost search never come
+    for _ in 0..2 {
+        if let Err(e) = socket.send_to(request.as_bytes(), dest).await {
+            tracing::debug!("SSDP send to {} failed: {}", dest, e);
+        }
+    }
+
+    let deadline = Instant::now() + window;
+    let mut responders: HashMap<IpAddr, Responder> = HashMap::new();
+    let mut buf = vec![0u8; 4096];
+
+    loop {
+        let now = Instant::now();
+        if now >= deadline || is_scan_cancelled() {
+            break;
+        }
+        let wait = (deadline - now).min(Duration::from_millis(250));
+        let (len, source) = match tokio::time::timeout(wait, socket.recv_from(&mut buf)).await {
+            Ok(Ok(received)) => received,
+            Ok(Err(e)) => {
+                tracing::debug!("SSDP receive failed: {}", e);
+                continue;
+            }
+            Err(_) => continue,
+        };
+
+        let Some(headers) = parse_search_response(&buf[..len]) else {
+            continue;
+        };
+        let responder = responders.entry(source.ip()).or_default();
+        if let Some(location) = headers.get("location") {
+            responder.locations.insert(location.clone());
+        }
+        if responder.server.is_none() {
+            responder.server = headers.get("server").cloned();
+        }
+    }
+
+    responders
+}
+
+/// Headers of an M-SEARCH reply, names lowercased
+fn parse_search_response(packet: &[u8]) -> Option<HashMap<String, String>> {
+    let text = String::from_utf8_lossy(packet);
+    let mut lines = text.lines();
+    let status = lines.next()?;
+    if !status.starts_with("HTTP/") || !status.contains(" 200") {
+        return None;
+    }
+
+    Some(
+        lines
+            .filter_map(|line| {
+                let (name, value) = line.split_once(':')?;
+                Some((name.trim().to_ascii_lowercase(), value.trim().to_string()))
+            })
+            .collect(),
+    )
+}
+
+/// Fetch a description for each responder `in_scope` accepts, trying its
+/// locations in turn.
+async fn describe_all(
+    responders: HashMap<IpAddr, Responder>,
+    config: &SsdpConfig,
+    in_scope: impl Fn(IpAddr) -> bool,
+) -> HashMap<IpAddr, UpnpDevice> {
+    let responders: Vec<_> = responders
+        .into_iter()
+        .filter(|(address, _)| {
+            let allowed = in_scope(*address);
+            if !allowed {
+                tracing::debug!(
+                    "SSDP: not describing {} (excluded or outside the targets)",
+                    address
+                );
+            }
+            allowed
+        })
+        .collect();
+    if responders.is_empty() {
+        return HashMap::new();
+    }
+
+    let client = match reqwest::Client::builder()
+        .timeout(Duration::from_millis(config.fetch_timeout_ms))
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+    {
+        Ok(client) => client,
+        Err(e) => {
+            tracing::warn!("SSDP: failed to create HTTP client: {}", e);
+            return HashMap::new();
+        }
+    };
+
+    stream::iter(responders)
+        .map(|(address, responder)| {
+            let client = &client;
+            async move {
+                let mut device = UpnpDevice::default();
+                for location in &responder.locations {
+                    if is_scan_cancelled() {
+                        break;
+                    }
+                    match fetch_description(client, address, location).await {
+                        Some(described) => {
+                            device = described;
+                            break;
+                        }
+                        None => continue,
+                    }
+                }
+                device.server = responder.server;
+                (address, device)
+            }
+        })
+        .buffer_unordered(FETCH_CONCURRENCY)
+        .filter(|(_, device)| {
+            let described = device.friendly_name.is_some() || device.server.is_some();
+            async move { described }
+        })
+        .collect()
+        .await
+}
+
+/// GET a description document. Only locations on the responder itself are
+/// fetched, so a reply cannot point the agent at another host.
+async fn fetch_description(
+    client: &reqwest::Client,
+    address: IpAddr,
+    location: &str,
+) -> Option<UpnpDevice> {
+    let url = reqwest::Url::parse(location).ok()?;
+    let host = url
+        .host_str()?
+        .trim_start_matches('[')
+        .trim_end_matches(']');
+    if url.scheme() != "http" || host.parse::<IpAddr>().ok()? != address {
+        tracing::debug!("SSDP: ignoring location {} from {}", location, address);
+        return None;
+    }
+
+    let mut response = match client.get(url).send().await {
+        Ok(response) if response.status().is_success() => response,
+        Ok(response) => {
+            tracing::debug!("SSDP: {} returned {}", location, response.status());
+            return None;
+        }
+        Err(e) => {
+            tracing::debug!("SSDP: fetching {} failed: {}", location, e);
+            return None;
+        }
+    };
+
+    let mut body = Vec::new();
+    while let Ok(Some(chunk)) = response.chunk().await {
+        body.extend_from_slice(&chunk);
+        if body.len() > MAX_DESCRIPTION_BYTES {
+            tracing::debug!("SSDP: description at {} is too large", location);
+            return None;
+        }
+    }
+
+    parse_description(&String::from_utf8_lossy(&body))
+}
+
+/// Pull the root device's fields out of a description document.
+fn parse_description(xml: &str) -> Option<UpnpDevice> {
+    // Embedded devices follow in <deviceList>; stop before them so their
+    // names do not shadow the root device's
+    let start = xml.find("<device>").or_else(|| xml.find("<device "))?;
+    let root = &xml[start..];
+    let root = &root[..root.find("<deviceList").unwrap_or(root.len())];
+
+    let device = UpnpDevice {
+        friendly_name: element_text(root, "friendlyName"),
+        manufacturer: element_text(root, "manufacturer"),
+        model_name: element_text(root, "modelName"),
+        model_number: element_text(root, "modelNumber"),
+        serial_number: element_text(root, "serialNumber"),
+        device_type: element_text(root, "deviceType"),
+        server: None,
+    };
+    (device.friendly_name.is_some() || device.model_name.is_some()).then_some(device)
+}
+
+/// Text of the first `<tag>` element, unescaped and trimmed
+fn element_text(xml: &str, tag: &str) -> Option<String> {
+    let open = format!("<{}>", tag);
+    let close = format!("</{}>", tag);
+    let start = xml.find(&open)? + open.len();
+    let end = start + xml[start..].find(&close)?;
+    let text = unescape_xml(xml[start..end].trim());
+    (!text.is_empty()).then_some(text)
+}
+
+fn unescape_xml(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let Some(semi) = rest.find(';') else {
+            break;
+        };
+        let entity = &rest[1..semi];
+        let decoded = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                .and_then(char::from_u32),
+        };
+        match decoded {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[semi + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    const ROUTER_DESCRIPTION: &str = r#"<?xml version="1.0"?>
+<root xmlns="urn:schemas-upnp-org:device-1-0">
+  <specVersion><major>1</major><minor>0</minor></specVersion>
+  <device>
+    <deviceType>urn:schemas-upnp-org:device:InternetGatewayDevice:1</deviceType>
+    <friendlyName>Home Router &amp; AP</friendlyName>
+    <manufacturer>NETGEAR, Inc.</manufacturer>
+    <modelName>Nighthawk</modelName>
+    <modelNumber>R7000</modelNumber>
+    <serialNumber>4CR1234567890</serialNumber>
+    <deviceList>
+      <device>
+        <deviceType>urn:schemas-upnp-org:device:WANDevice:1</deviceType>
+        <friendlyName>WANDevice</friendlyName>
+      </device>
+    </deviceList>
+  </device>
+</root>"#;
+
+    #[test]
+    fn test_parse_description() {
+        let device = parse_description(ROUTER_DESCRIPTION).expect("root device");
+        assert_eq!(device.friendly_name.as_deref(), Some("Home Router & AP"));
+        assert_eq!(device.manufacturer.as_deref(), Some("NETGEAR, Inc."));
+        assert_eq!(device.model().as_deref(), Some("Nighthawk R7000"));
+        assert_eq!(device.serial_number.as_deref(), Some("4CR1234567890"));
+        assert_eq!(
+            device.device_type.as_deref(),
+            Some("urn:schemas-upnp-org:device:InternetGatewayDevice:1")
+        );
+
+        assert_eq!(parse_description("<html>not upnp</html>"), None);
+        assert_eq!(
+            unescape_xml("Caf&#233; &#x26; Bar &bogus"),
+            "Café & Bar &bogus"
+        );
+    }
+
+    #[test]
+    fn test_parse_search_response() {
+        let reply = b"HTTP/1.1 200 OK\r\nCACHE-CONTROL: max-age=1800\r\nLocation: http://192.168.1.1:5000/rootDesc.xml\r\nSERVER: Linux/3.14 UPnP/1.0 MiniUPnPd/2.1\r\nST: upnp:rootdevice\r\n\r\n";
+        let headers = parse_search_response(reply).unwrap();
+        assert_eq!(
+            headers.get("location").map(String::as_str),
+            Some("http://192.168.1.1:5000/rootDesc.xml")
+        );
+        assert_eq!(
+            headers.get("server").map(String::as_str),
+            Some("Linux/3.14 UPnP/1.0 MiniUPnPd/2.1")
+        );
+
+        assert_eq!(parse_search_response(b"NOTIFY * HTTP/1.1\r\n\r\n"), None);
+    }
+
+    #[tokio::test]
+    async fn test_search_and_describe() {
+        let http = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let http_port = http.local_addr().unwrap().port();
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let fetched = fetches.clone();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = http.accept().await {
+                fetched.fetch_add(1, Ordering::SeqCst);
+                let _ = stream.read(&mut [0u8; 1024]).await;
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/xml\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    ROUTER_DESCRIPTION.len(),
+                    ROUTER_DESCRIPTION
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+
+        let responder = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let dest = responder.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut buf = [0u8; 1024];
+            while let Ok((len, from)) = responder.recv_from(&mut buf).await {
+                if !buf[..len].starts_with(b"M-SEARCH") {
+                    continue;
+                }
+                let reply = format!(
+                    "HTTP/1.1 200 OK\r\nLOCATION: http://127.0.0.1:{}/rootDesc.xml\r\nSERVER: MiniUPnPd/2.1\r\nST: upnp:rootdevice\r\n\r\n",
+                    http_port
+                );
+                let _ = responder.send_to(reply.as_bytes(), from).await;
+                // A second root device on the same host pointing elsewhere is ignored
+                let _ = responder
+                    .send_to(
+                        b"HTTP/1.1 200 OK\r\nLOCATION: http://192.0.2.99/desc.xml\r\n\r\n",
+                        from,
+                    )
+                    .await;
+            }
+        });
+
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let responders = search_on(socket, dest, Duration::from_millis(300)).await;
+        let described = describe_all(responders, &SsdpConfig::default(), |_| true).await;
+
+        let device = &described[&"127.0.0.1".parse::<IpAddr>().unwrap()];
+        assert_eq!(device.friendly_name.as_deref(), Some("Home Router & AP"));
+        assert_eq!(device.server.as_deref(), Some("MiniUPnPd/2.1"));
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+
+        // Excluded or out-of-target responders are never fetched from
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let responders = search_on(socket, dest, Duration::from_millis(300)).await;
+        assert!(!responders.is_empty());
+        let described = describe_all(responders, &SsdpConfig::default(), |_| false).await;
+        assert!(described.is_empty());
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+    }
+}
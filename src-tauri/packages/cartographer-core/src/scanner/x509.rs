@@ -0,0 +1,180 @@
+//! Summary of a TLS service's X.509 certificate
+//!
+//! Only the fields shown in the inventory are kept: subject, issuer,
+//! validity and subject alternative names. Decoding is left to
+//! `x509-parser`; a certificate it rejects is not reported.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::net::{Ipv4Addr, Ipv6Addr};
+use x509_parser::certificate::X509Certificate;
+use x509_parser::extensions::GeneralName;
+use x509_parser::prelude::FromDer;
+use x509_parser::time::ASN1Time;
+use x509_parser::x509::X509Name;
+
+/// Certificate presented by a TLS service
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TlsCertificate {
+    /// Subject distinguished name (e.g. "C=US, O=Example, CN=host")
+    pub subject: String,
+    /// Issuer distinguished name
+    pub issuer: String,
+    /// DNS names, IP addresses and emails from the subjectAltName extension
+    pub subject_alt_names: Vec<String>,
+    pub not_before: Option<DateTime<Utc>>,
+    pub not_after: Option<DateTime<Utc>>,
+}
+
+impl TlsCertificate {
+    /// Whole days until the certificate expires (negative once expired)
+    pub fn days_until_expiry(&self, now: DateTime<Utc>) -> Option<i64> {
+        self.not_after.map(|t| (t - now).num_days())
+    }
+
+    /// Whether the certificate is expired or expires within `days`
+    pub fn expires_within(&self, days: i64, now: DateTime<Utc>) -> bool {
+        self.days_until_expiry(now).is_some_and(|d| d < days)
+    }
+
+    /// Subject and issuer are the same
+    pub fn is_self_signed(&self) -> bool {
+        self.subject == self.issuer
+    }
+}
+
+/// Decode a DER-encoded certificate
+pub fn parse_certificate(der: &[u8]) -> Option<TlsCertificate> {
+    let (_, cert) = X509Certificate::from_der(der).ok()?;
+    let subject_alt_names = match cert.subject_alternative_name().ok()? {
+        Some(san) => san
+            .value
+            .general_names
+            .iter()
+            .filter_map(format_general_name)
+            .collect(),
+        None => Vec::new(),
+    };
+    let validity = cert.validity();
+
+    Some(TlsCertificate {
+        subject: format_name(cert.subject()),
+        issuer: format_name(cert.issuer()),
+        subject_alt_names,
+        not_before: to_utc(validity.not_before),
+        not_after: to_utc(validity.not_after),
+    })
+}
+
+fn to_utc(time: ASN1Time) -> Option<DateTime<Utc>> {
+    DateTime::from_timestamp(time.timestamp(), 0)
+}
+
+/// Format a distinguished name as "C=US, O=Example, CN=host", in
+/// certificate order.
+fn format_name(name: &X509Name) -> String {
+    name.iter_attributes()
+        .map(|attribute| {
+            let value = match attribute.as_str() {
+                Ok(value) => value.to_string(),
+                Err(_) => String::from_utf8_lossy(attribute.attr_value().data).into_owned(),
+            };
+            let oid = attribute.attr_type().to_id_string();
+            format!("{}={}", attribute_name(&oid), value.trim())
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Short name of a distinguished name attribute, or its dotted OID
+fn attribute_name(oid: &str) -> &str {
+    match oid {
+        "2.5.4.3" => "CN",
+        "2.5.4.5" => "serialNumber",
+        "2.5.4.6" => "C",
+        "2.5.4.7" => "L",
+        "2.5.4.8" => "ST",
+        "2.5.4.10" => "O",
+        "2.5.4.11" => "OU",
+        "1.2.840.113549.1.9.1" => "emailAddress",
+        _ => oid,
+    }
+}
+
+/// DNS names, emails and IP addresses; other kinds of name are skipped
+fn format_general_name(name: &GeneralName) -> Option<String> {
+    match name {
+        GeneralName::DNSName(name) | GeneralName::RFC822Name(name) => Some(name.to_string()),
+        GeneralName::IPAddress(octets) => {
+            if let Ok(octets) = <[u8; 4]>::try_from(*octets) {
+                Some(Ipv4Addr::from(octets).to_string())
+            } else if let Ok(octets) = <[u8; 16]>::try_from(*octets) {
+                Some(Ipv6Addr::from(octets).to_string())
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    const PRINTER_CERT: &[u8] = include_bytes!("../../testdata/printer-cert.der");
+    const LEGACY_CERT: &[u8] = include_bytes!("../../testdata/legacy-cert.der");
+
+    #[test]
+    fn test_parse_certificate() {
+        let cert = parse_certificate(PRINTER_CERT).expect("valid certificate");
+        assert_eq!(cert.subject, "C=US, O=Example Labs, CN=printer.example.lan");
+        assert!(cert.is_self_signed());
+        assert_eq!(
+            cert.subject_alt_names,
+            vec!["printer.example.lan", "printer", "192.168.1.20"]
+        );
+        assert_eq!(
+            cert.not_before,
+            Some(Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap())
+        );
+        assert_eq!(
+            cert.not_after,
+            Some(Utc.with_ymd_and_hms(2035, 1, 1, 0, 0, 0).unwrap())
+        );
+
+        let now = Utc.with_ymd_and_hms(2034, 12, 10, 0, 0, 0).unwrap();
+        assert_eq!(cert.days_until_expiry(now), Some(22));
+        assert!(cert.expires_within(30, now));
+        assert!(!cert.expires_within(14, now));
+
+        // Truncated input is rejected, not misread
+        assert_eq!(parse_certificate(&PRINTER_CERT[..200]), None);
+    }
+
+    #[test]
+    fn test_parse_names_and_times() {
+        // UTCTime from last century, GeneralizedTime from 2050 on
+        let cert = parse_certificate(LEGACY_CERT).expect("valid certificate");
+        assert_eq!(
+            cert.not_before,
+            Some(Utc.with_ymd_and_hms(1999, 12, 31, 23, 59, 59).unwrap())
+        );
+        assert_eq!(
+            cert.not_after,
+            Some(Utc.with_ymd_and_hms(2050, 1, 1, 0, 0, 0).unwrap())
+        );
+
+        // 1.2.840.113549.1.9.1 is named, 2.5.4.97 (organizationIdentifier) is not
+        assert_eq!(
+            cert.subject,
+            "emailAddress=ops@example.lan, 2.5.4.97=VATUS-123, CN=legacy.example.lan"
+        );
+        assert_eq!(
+            cert.subject_alt_names,
+            vec!["2001:db8::20", "ops@example.lan"]
+        );
+    }
+}
@@ -1,6 +0,0 @@
-mod credentials;
-mod device_flow;
-
-pub use credentials::{check_auth, load_credentials, logout};
-pub use device_flow::{start_login, poll_for_login, request_login_url, LoginFlowStarted, LoginUrlEvent};
-
@@ -1,3 +1,4 @@
+use cartographer_core::{auth, cloud, scanner};
 use clap::{Parser, Subcommand};
 
 #[derive(Parser)]
@@ -35,12 +36,15 @@ pub async fn run_cli() {
             println!("Starting login flow...");
             // Use auth module directly since CLI doesn't have AppHandle for events
             // Print URL to stdout instead
-            let emit_url = |event: crate::auth::LoginUrlEvent| {
+            let emit_url = |event: auth::LoginUrlEvent| {
                 println!("Please visit: {}", event.verification_url);
                 println!("Code: {}", event.user_code);
             };
-            match crate::auth::start_login(Some(emit_url)).await {
+            match auth::start_login(Some(emit_url)).await {
                 Ok(status) => {
+                    if let Some(ref settings) = status.agent_settings {
+                        crate::scheduler::apply_agent_settings(settings);
+                    }
                     if status.authenticated {
                         println!("✓ Successfully signed in as: {}",
                             status.user_email.unwrap_or_else(|| "Unknown".to_string()));
@@ -58,7 +62,7 @@ pub async fn run_cli() {
             println!("Scanning network...");
             // Use scanner directly since CLI doesn't have AppHandle for events
             // Print progress to stdout instead
-            let progress_callback: Box<dyn Fn(crate::scanner::ScanProgress) + Send + Sync> =
+            let progress_callback: Box<dyn Fn(scanner::ScanProgress) + Send + Sync> =
                 Box::new(|progress| {
                     // Print progress to console
                     if let Some(pct) = progress.percent {
@@ -68,22 +72,22 @@ pub async fn run_cli() {
                     }
                 });
 
-            match crate::scanner::scan_network_with_progress(Some(progress_callback)).await {
+            match scanner::scan_network_with_progress(Some(progress_callback)).await {
                 Ok(scan_result) => {
                     println!("✓ Found {} devices", scan_result.devices.len());
                     for device in &scan_result.devices {
                         let hostname = device.hostname.as_deref().unwrap_or("-");
                         println!(
                             "  - {} ({:.1}ms) {}",
-                            device.ip,
+                            device.primary_ip(),
                             device.response_time_ms.unwrap_or(0.0),
                             hostname
                         );
                     }
                     // Upload to cloud if authenticated
-                    if let Ok(status) = crate::auth::check_auth().await {
+                    if let Ok(status) = auth::check_auth().await {
                         if status.authenticated {
-                            let client = crate::cloud::CloudClient::new();
+                            let client = cloud::CloudClient::new();
                             if let Err(e) = client.upload_scan_result(&scan_result).await {
                                 eprintln!("⚠ Failed to upload: {}", e);
                             } else {
@@ -139,7 +143,7 @@ pub async fn run_cli() {
                 // Start background daemon
                 loop {
                     tokio::time::sleep(tokio::time::Duration::from_secs(300)).await;
-                    if let Err(e) = crate::scanner::scan_network().await {
+                    if let Err(e) = scanner::scan_network().await {
                         eprintln!("Scan error: {}", e);
                     }
                 }
@@ -1,9 +0,0 @@
-mod client;
-pub mod commands;
-pub mod config;
-
-pub use client::CloudClient;
-pub use client::TokenVerifyResult;
-pub use commands::{ClaimResponse, PendingCommand, PollResponse, ResultReport, ResultResponse};
-pub use config::{load_cloud_config, CloudEndpointConfig, ConfigSource};
-
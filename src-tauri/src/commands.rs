@@ -1,13 +1,19 @@
-use crate::auth::{check_auth, logout as auth_logout, poll_for_login, request_login_url, start_login, LoginFlowStarted, LoginUrlEvent};
-use crate::cloud::CloudClient;
-use crate::scanner::{
-    check_device_reachable, get_arp_table_ips, scan_network_with_progress, Device, ScanProgress, ScanStage,
+use cartographer_core::auth::{
+    check_auth, delete_credentials, poll_for_login, request_login_url, start_login, LoginUrlEvent,
+};
+use cartographer_core::cloud::{CloudClient, DeviceHealthResult};
+use cartographer_core::scanner::{
+    check_device_reachable, clear_scan_cancel, get_arp_table_ips, request_scan_cancel,
+    scan_network_with_progress, Device, ScanProgress, ScanStage,
 };
 use crate::scheduler::{
-    clear_scan_cancel, ensure_background_scanning, get_known_devices, get_last_scan_time,
-    is_scanning, merge_devices_preserving_health, persist_state, record_scan_time, request_scan_cancel,
-    reset_scan_state, set_scan_interval as scheduler_set_scan_interval, stop_background_scanning,
-    trigger_immediate_scan, update_known_devices, DeviceHealthResult,
+    apply_agent_settings, clear_known_devices, clear_upload_queue, emit_device_changes,
+    ensure_background_scanning, get_known_devices, get_last_scan_time, is_scanning,
+    merge_devices_preserving_health, notifications_enabled, persist_state, record_health_results,
+    record_scan_time, reset_scan_state, send_or_queue,
+    set_notifications_enabled as scheduler_set_notifications_enabled,
+    set_scan_interval as scheduler_set_scan_interval, stop_background_scanning,
+    trigger_immediate_scan,
 };
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
@@ -78,6 +84,9 @@ pub async fn start_login_flow(app: AppHandle) -> Result<AgentStatus, String> {
 
     match start_login(Some(emit_url)).await {
         Ok(status) => {
+            if let Some(ref settings) = status.agent_settings {
+                apply_agent_settings(settings);
+            }
             // Start background scanning if authenticated
             if status.authenticated {
                 tracing::info!("Login successful, starting background scanning");
@@ -131,6 +140,9 @@ pub async fn request_login() -> Result<LoginFlowResponse, String> {
 pub async fn complete_login(device_code: String, expires_in: u64, poll_interval: u64) -> Result<AgentStatus, String> {
     match poll_for_login(&device_code, expires_in, poll_interval).await {
         Ok(status) => {
+            if let Some(ref settings) = status.agent_settings {
+                apply_agent_settings(settings);
+            }
             // Start background scanning if authenticated
             if status.authenticated {
                 tracing::info!("Login successful, starting background scanning");
@@ -159,10 +171,13 @@ pub async fn logout() -> Result<(), String> {
     stop_background_scanning().await;
 
     // Delete credentials
-    auth_logout().await.map_err(|e| e.to_string())?;
+    delete_credentials().await.map_err(|e| e.to_string())?;
 
-    // Clear in-memory devices
-    update_known_devices(Vec::new()).await;
+    // Clear known devices and the inventory file
+    clear_known_devices().await;
+
+    // Drop uploads still queued for the old network
+    clear_upload_queue().await;
 
     // Reset scan state (last scan time, scanning flags) so reconnecting starts fresh
     reset_scan_state();
@@ -217,16 +232,19 @@ pub async fn scan_network(app: AppHandle) -> Result<ScanResultResponse, String>
         .map_err(|e| format!("{}", e))?;
 
     tracing::info!(
-        "Scan complete, found {} devices (gateway: {:?})",
+        "Scan complete, found {} devices (gateways: {:?})",
         scan_result.devices.len(),
-        scan_result.network_info.gateway_ip
+        scan_result.network_info.gateway_ips()
     );
 
     // Record scan time
     record_scan_time();
 
     // Merge new devices with existing ones, preserving health data from previous health checks
-    merge_devices_preserving_health(scan_result.devices.clone(), &scan_result.network_info.subnet).await;
+    let changes =
+        merge_devices_preserving_health(scan_result.devices.clone(), &scan_result.network_info)
+            .await;
+    emit_device_changes(&changes);
 
     // Persist to disk
     persist_state().await;
@@ -241,14 +259,9 @@ pub async fn scan_network(app: AppHandle) -> Result<ScanResultResponse, String>
                 status.network_name.as_deref().unwrap_or("Unknown")
             );
             let client = get_cloud_client().await;
-            match client.upload_scan_result(&scan_result).await {
-                Ok(_) => {
-                    tracing::info!("Scan results synced to cloud");
-                    synced = true;
-                }
-                Err(e) => {
-                    tracing::warn!("Failed to upload scan to cloud: {}", e);
-                }
+            synced = send_or_queue(&client, CloudClient::scan_upload(&scan_result)).await;
+            if synced {
+                tracing::info!("Scan results synced to cloud");
             }
         }
         Ok(_) => {
@@ -302,7 +315,7 @@ pub fn get_app_version() -> String {
 
 #[tauri::command]
 pub async fn get_network_info() -> Result<String, String> {
-    crate::scanner::get_network_info().await.map_err(|e| e.to_string())
+    cartographer_core::scanner::get_network_info().await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -322,15 +335,14 @@ pub async fn get_start_at_login() -> Result<bool, String> {
 }
 
 #[tauri::command]
-pub async fn set_notifications_enabled(_enabled: bool) -> Result<(), String> {
-    // Store in config file
-    Ok(())
+pub async fn set_notifications_enabled(enabled: bool) -> Result<(), String> {
+    scheduler_set_notifications_enabled(enabled);
+    crate::persistence::save_notifications_enabled(enabled).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 pub async fn get_notifications_enabled() -> Result<bool, String> {
-    // Read from config file
-    Ok(true)
+    Ok(notifications_enabled())
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -363,7 +375,7 @@ pub async fn run_health_check() -> Result<HealthCheckStatus, String> {
     // Update the devices in-place to ensure all devices get their response_time_ms updated
     let mut health_results = Vec::new();
     for device in &mut devices {
-        let result = check_device_reachable(&device.ip, &arp_ips).await;
+        let result = check_device_reachable(device.primary_ip(), &arp_ips).await;
         let reachable = result.is_ok();
         let response_time = if reachable {
             result.ok()
@@ -375,7 +387,7 @@ pub async fn run_health_check() -> Result<HealthCheckStatus, String> {
         device.response_time_ms = response_time;
         
         health_results.push(DeviceHealthResult {
-            ip: device.ip.clone(),
+            ip: device.primary_ip().to_string(),
             reachable,
             response_time_ms: response_time,
         });
@@ -390,8 +402,9 @@ pub async fn run_health_check() -> Result<HealthCheckStatus, String> {
         unreachable_count
     );
 
-    // Update in-memory known devices with the updated health data
-    update_known_devices(devices.clone()).await;
+    // Record the updated health data in the inventory
+    let changes = record_health_results(&health_results).await;
+    emit_device_changes(&changes);
 
     // Persist to disk
     persist_state().await;
@@ -401,14 +414,9 @@ pub async fn run_health_check() -> Result<HealthCheckStatus, String> {
     match check_auth().await {
         Ok(status) if status.authenticated => {
             let client = get_cloud_client().await;
-            match client.upload_health_check(&health_results).await {
-                Ok(_) => {
-                    tracing::info!("Health check results synced to cloud");
-                    synced = true;
-                }
-                Err(e) => {
-                    tracing::warn!("Failed to upload health check to cloud: {}", e);
-                }
+            synced = send_or_queue(&client, CloudClient::health_upload(&health_results)).await;
+            if synced {
+                tracing::info!("Health check results synced to cloud");
             }
         }
         Ok(_) => {
@@ -424,7 +432,7 @@ pub async fn run_health_check() -> Result<HealthCheckStatus, String> {
         healthy_devices: healthy_count,
         unreachable_devices: unreachable_count,
         synced_to_cloud: synced,
-        devices,
+        devices: get_known_devices().await,
     })
 }
 
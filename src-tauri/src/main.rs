@@ -3,32 +3,19 @@
 
 //! Cartographer Desktop Agent
 //!
-//! This is the GUI desktop agent built with Tauri. It uses local implementations
-//! of auth, cloud, and scanner modules that are integrated with Tauri's event system.
-//!
-//! For the lightweight CLI-only agent (used on headless Linux servers), see
-//! `packages/cartographer-cli` which uses `packages/cartographer-core` for
-//! shared functionality without any GUI dependencies.
-//!
-//! The core library (`cartographer-core`) can be used here for shared types
-//! when needed, but the main functionality uses local Tauri-integrated modules.
+//! This is the GUI desktop agent built with Tauri. Network scanning, auth and
+//! cloud sync come from `cartographer-core`, shared with the lightweight CLI
+//! agent in `packages/cartographer-cli`. This crate only adds the Tauri glue:
+//! commands, the background scheduler, persistence, tray and updater.
 
-mod auth;
 mod cli;
-mod cloud;
 mod commands;
 mod persistence;
 mod platform;
-mod scanner;
 mod scheduler;
 mod tray;
 mod updater;
 
-// Re-export core types for compatibility with CLI
-// These can be used when sharing data structures between desktop and CLI agents
-#[allow(unused_imports)]
-use cartographer_core as core;
-
 use tauri::Manager;
 use tracing::info;
 
@@ -50,7 +37,7 @@ fn main() {
     tracing_subscriber::fmt()
         .with_env_filter(
             tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "cartographer_agent=info".into()),
+                .unwrap_or_else(|_| "cartographer_agent=info,cartographer_core=info".into()),
         )
         .init();
 
@@ -1,8 +1,9 @@
 //! Persistence module for saving and loading agent state.
 //!
-//! Stores scan data, device lists, and settings to survive app restarts.
+//! Stores scan times and settings to survive app restarts. Devices live in
+//! the core inventory (`cartographer_core::inventory`).
 
-use crate::scanner::Device;
+use cartographer_core::scanner::Device;
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
@@ -14,12 +15,17 @@ fn default_automatic_full_scan_min_interval_seconds() -> u64 {
     DEFAULT_AUTOMATIC_FULL_SCAN_MIN_INTERVAL_SECONDS
 }
 
+fn default_notifications_enabled() -> bool {
+    true
+}
+
 /// Persisted agent state
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AgentState {
     /// Last scan timestamp (Unix seconds)
     pub last_scan_time: u64,
-    /// Known devices from last scan
+    /// Devices saved before the inventory existed; moved into it on startup
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub devices: Vec<Device>,
     /// Scan interval in minutes
     pub scan_interval_minutes: u64,
@@ -37,6 +43,9 @@ pub struct AgentState {
     /// Whether the app should start hidden after a restart (e.g., after background update)
     #[serde(default)]
     pub restart_hidden: bool,
+    /// Whether device change alerts (`[notifications]` in config.toml) are sent
+    #[serde(default = "default_notifications_enabled")]
+    pub notifications_enabled: bool,
 }
 
 impl Default for AgentState {
@@ -51,6 +60,7 @@ impl Default for AgentState {
                 DEFAULT_AUTOMATIC_FULL_SCAN_MIN_INTERVAL_SECONDS,
             silent_update_version: None,
             restart_hidden: false,
+            notifications_enabled: true,
         }
     }
 }
@@ -82,8 +92,7 @@ pub fn load_state() -> Result<AgentState> {
         serde_json::from_str(&content).context("Failed to parse state file")?;
 
     tracing::info!(
-        "Loaded state: {} devices, last scan: {}",
-        state.devices.len(),
+        "Loaded state: last scan: {}",
         if state.last_scan_time > 0 {
             chrono::DateTime::from_timestamp(state.last_scan_time as i64, 0)
                 .map(|dt| dt.to_rfc3339())
@@ -104,19 +113,28 @@ pub fn save_state(state: &AgentState) -> Result<()> {
 
     std::fs::write(&path, content).context("Failed to write state file")?;
 
-    tracing::debug!("Saved state: {} devices", state.devices.len());
+    tracing::debug!("Saved state");
 
     Ok(())
 }
 
-/// Update just the devices and scan time
-pub fn save_scan_results(devices: &[Device], scan_time: u64) -> Result<()> {
+/// Update just the scan time
+pub fn save_last_scan_time(scan_time: u64) -> Result<()> {
     let mut state = load_state().unwrap_or_default();
-    state.devices = devices.to_vec();
     state.last_scan_time = scan_time;
     save_state(&state)
 }
 
+/// Remove the devices of an older agent once the inventory has them
+pub fn clear_legacy_devices() -> Result<()> {
+    let mut state = load_state().unwrap_or_default();
+    if state.devices.is_empty() {
+        return Ok(());
+    }
+    state.devices.clear();
+    save_state(&state)
+}
+
 /// Update just the last automatic scan timestamp
 pub fn save_last_automatic_scan_time(scan_time: u64) -> Result<()> {
     let mut state = load_state().unwrap_or_default();
@@ -138,31 +156,18 @@ pub fn save_health_check_interval_seconds(seconds: u64) -> Result<()> {
     save_state(&state)
 }
 
+/// Persist whether device change alerts are sent
+pub fn save_notifications_enabled(enabled: bool) -> Result<()> {
+    let mut state = load_state().unwrap_or_default();
+    state.notifications_enabled = enabled;
+    save_state(&state)
+}
+
 /// Get the stored last scan time
 pub fn get_stored_last_scan_time() -> u64 {
     load_state().map(|s| s.last_scan_time).unwrap_or(0)
 }
 
-/// Get the stored devices
-pub fn get_stored_devices() -> Vec<Device> {
-    load_state().map(|s| s.devices).unwrap_or_default()
-}
-
-/// Update device health data (response times) after a health check
-/// Returns the updated devices
-pub fn update_device_health(health_results: &[(String, Option<f64>)]) -> Result<Vec<Device>> {
-    let mut state = load_state().unwrap_or_default();
-
-    for (ip, response_time) in health_results {
-        if let Some(device) = state.devices.iter_mut().find(|d| &d.ip == ip) {
-            device.response_time_ms = *response_time;
-        }
-    }
-
-    save_state(&state)?;
-    Ok(state.devices)
-}
-
 /// Clear all persisted state (devices, scan times, etc.)
 /// Called during logout to remove all local data
 pub fn clear_state() -> Result<()> {
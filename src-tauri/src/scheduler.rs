@@ -1,8 +1,16 @@
-use crate::auth::check_auth;
-use crate::cloud::{CloudClient, ResultReport};
+use cartographer_core::auth::{check_auth, AgentSettings};
+use cartographer_core::cloud::{
+    load_outbox_config, CloudClient, Delivery, DeviceHealthResult, Outbox, ResultReport, Upload,
+};
+use cartographer_core::inventory::{ChangeEvent, Inventory};
+use cartographer_core::notifications::{load_notifier_or_disabled, Notifier};
+use cartographer_core::scanner::{
+    check_device_reachable, clear_scan_cancel, get_arp_table_ips, load_validated_scan_config,
+    scan_network_with_progress, start_dhcp_listener, stop_dhcp_listener, Device, NetworkInfo,
+    ScanProgress,
+};
 use crate::commands::SCAN_PROGRESS_EVENT;
 use crate::persistence;
-use crate::scanner::{check_device_reachable, get_arp_table_ips, scan_network_with_progress, Device, ScanProgress};
 use serde::Serialize;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Arc, OnceLock};
@@ -13,6 +21,8 @@ use tokio_util::sync::CancellationToken;
 
 /// Event name for health check progress updates
 pub const HEALTH_CHECK_PROGRESS_EVENT: &str = "health-check-progress";
+/// Event name for device changes (new, offline, back, IP/MAC/hostname changed)
+pub const DEVICE_CHANGES_EVENT: &str = "device-changes";
 const DEFAULT_AUTOMATIC_FULL_SCAN_MIN_INTERVAL_SECONDS: u64 = 2 * 60 * 60;
 
 #[derive(Debug, Clone, Serialize)]
@@ -51,11 +61,17 @@ static SCANNING_IN_PROGRESS: AtomicBool = AtomicBool::new(false);
 // Track if a health check is currently in progress
 static HEALTH_CHECK_IN_PROGRESS: AtomicBool = AtomicBool::new(false);
 
-// Flag to request scan cancellation
-static SCAN_CANCEL_REQUESTED: AtomicBool = AtomicBool::new(false);
+// Whether device change alerts are sent (Preferences toggle)
+static NOTIFICATIONS_ENABLED: AtomicBool = AtomicBool::new(true);
 
-// Cached list of known devices for health checks
-static KNOWN_DEVICES: Mutex<Vec<Device>> = Mutex::const_new(Vec::new());
+// Uploads that failed, replayed in order once the cloud is reachable again
+static OUTBOX: OnceLock<Mutex<Outbox>> = OnceLock::new();
+
+// Alert sinks from the [notifications] section of config.toml
+static NOTIFIER: OnceLock<Arc<Notifier>> = OnceLock::new();
+
+// Known devices (online and offline) with their history, saved across restarts
+static INVENTORY: OnceLock<Mutex<Inventory>> = OnceLock::new();
 
 // Last scan timestamp (Unix timestamp in seconds)
 static LAST_SCAN_TIME: AtomicU64 = AtomicU64::new(0);
@@ -79,6 +95,52 @@ fn get_shared_cloud_client() -> Arc<CloudClient> {
     SHARED_CLOUD_CLIENT.get_or_init(|| Arc::new(CloudClient::new())).clone()
 }
 
+fn get_inventory() -> &'static Mutex<Inventory> {
+    INVENTORY.get_or_init(|| Mutex::new(Inventory::open_or_in_memory()))
+}
+
+fn get_outbox() -> &'static Mutex<Outbox> {
+    OUTBOX.get_or_init(|| Mutex::new(Outbox::open(load_outbox_config())))
+}
+
+/// Send a scan or health check upload, queueing it in the outbox when the
+/// cloud cannot be reached (older queued uploads are replayed first).
+/// Returns whether it reached the cloud now.
+pub async fn send_or_queue(client: &CloudClient, upload: anyhow::Result<Upload>) -> bool {
+    let upload = match upload {
+        Ok(upload) => upload,
+        Err(e) => {
+            tracing::warn!("Failed to prepare upload: {:#}", e);
+            return false;
+        }
+    };
+    let kind = upload.kind;
+    match get_outbox().lock().await.send(client, upload).await {
+        Ok(Delivery::Sent) => true,
+        Ok(Delivery::Queued(depth)) => {
+            tracing::info!("Queued {} for upload ({} waiting)", kind, depth);
+            false
+        }
+        Err(e) => {
+            tracing::warn!("Failed to upload {}: {:#}", kind, e);
+            false
+        }
+    }
+}
+
+/// Drop queued uploads (called on logout: they belong to the old network)
+pub async fn clear_upload_queue() {
+    if let Err(e) = get_outbox().lock().await.clear() {
+        tracing::warn!("Failed to clear upload queue: {:#}", e);
+    }
+}
+
+fn get_notifier() -> Arc<Notifier> {
+    NOTIFIER
+        .get_or_init(|| Arc::new(load_notifier_or_disabled()))
+        .clone()
+}
+
 pub fn init(app: AppHandle) {
     APP_HANDLE.set(app).ok();
 
@@ -96,12 +158,23 @@ pub fn init(app: AppHandle) {
             state.automatic_full_scan_min_interval_seconds,
             Ordering::Relaxed,
         );
-        // Load devices into memory (spawn async task)
+        NOTIFICATIONS_ENABLED.store(state.notifications_enabled, Ordering::Relaxed);
+        // Move devices saved by older versions into the inventory (spawn async task)
         if !state.devices.is_empty() {
             let devices = state.devices;
             tauri::async_runtime::spawn(async move {
-                let mut known = KNOWN_DEVICES.lock().await;
-                *known = devices;
+                let mut inventory = get_inventory().lock().await;
+                if inventory.is_empty() {
+                    tracing::info!("Importing {} saved devices into the inventory", devices.len());
+                    inventory.import(devices, chrono::Utc::now());
+                    if let Err(e) = inventory.save() {
+                        tracing::warn!("Failed to save imported devices: {:#}", e);
+                        return;
+                    }
+                }
+                if let Err(e) = persistence::clear_legacy_devices() {
+                    tracing::warn!("Failed to clear legacy devices from state: {}", e);
+                }
             });
         }
     }
@@ -179,178 +252,97 @@ pub fn set_automatic_full_scan_min_interval_seconds(seconds: u64) {
     );
 }
 
-/// Update the list of known devices (called after successful scans)
-pub async fn update_known_devices(devices: Vec<Device>) {
-    let mut known = KNOWN_DEVICES.lock().await;
-    *known = devices;
-}
-
-/// Normalize a MAC address to lowercase colon-separated format (e.g. "aa:bb:cc:dd:ee:ff").
-fn normalize_mac(mac: &str) -> String {
-    let hex: String = mac
-        .chars()
-        .filter(|c| c.is_ascii_hexdigit())
-        .collect::<String>()
-        .to_lowercase();
-    hex.as_bytes()
-        .chunks(2)
-        .map(|chunk| std::str::from_utf8(chunk).unwrap_or(""))
-        .collect::<Vec<&str>>()
-        .join(":")
+/// Apply account-level settings received from the cloud at login
+pub fn apply_agent_settings(settings: &AgentSettings) {
+    if let Some(seconds) = settings.automatic_full_scan_min_interval_seconds {
+        set_automatic_full_scan_min_interval_seconds(seconds);
+    }
+    if let Some(seconds) = settings.health_poll_interval_seconds {
+        set_health_check_interval(seconds);
+    }
 }
 
-/// Merge new devices with existing ones, preserving health data from previous health checks.
-/// Uses dual-key matching (IP first, then MAC) to correctly identify devices even when
-/// IP or MAC changes (DHCP churn, NIC replacement, etc.).
-/// - If the new device has no response_time_ms, preserve the old one
-/// - If the new device has response_time_ms, use the new value
-/// - If IP match found but MAC differs, update the MAC
-/// - If no IP match but MAC matches, treat as same device with IP change
-/// Devices not matched by either key are kept but marked as offline (response_time_ms = None),
-/// but only if they are within the target subnet. Out-of-subnet devices are dropped to avoid
-/// retaining stale entries from other interfaces (VPN, containers, virtual adapters).
-pub async fn merge_devices_preserving_health(new_devices: Vec<Device>, subnet: &str) {
-    let mut known = KNOWN_DEVICES.lock().await;
-
-    // Parse subnet for filtering stale offline devices from other interfaces
-    let subnet_network: Option<ipnetwork::IpNetwork> = match subnet.parse() {
-        Ok(net) => Some(net),
-        Err(e) => {
-            tracing::warn!("Could not parse subnet '{}' for merge filtering: {}", subnet, e);
-            None
-        }
-    };
-
-    // Create maps for dual-key lookup
-    let old_device_map: std::collections::HashMap<String, Device> =
-        known.iter().map(|d| (d.ip.clone(), d.clone())).collect();
-
-    let old_device_map_by_mac: std::collections::HashMap<String, Device> = known
+/// Record health check results in the inventory (response times, online/offline).
+/// Returns the devices that went offline or came back.
+pub async fn record_health_results(results: &[DeviceHealthResult]) -> Vec<ChangeEvent> {
+    let results: Vec<(String, Option<f64>)> = results
         .iter()
-        .filter_map(|d| {
-            d.mac
-                .as_ref()
-                .map(|m| (normalize_mac(m), d.clone()))
-        })
+        .map(|r| (r.ip.clone(), r.response_time_ms.filter(|_| r.reachable)))
         .collect();
+    get_inventory()
+        .lock()
+        .await
+        .record_health(&results, chrono::Utc::now())
+}
 
-    // Track which old device IPs were matched (by either IP or MAC)
-    let mut matched_old_ips: std::collections::HashSet<String> =
-        std::collections::HashSet::new();
-
-    // Merge: for each new device, try IP match first, then MAC match
-    let mut merged: Vec<Device> = new_devices
-        .into_iter()
-        .map(|mut new_device| {
-            // Try IP match first
-            if let Some(old_device) = old_device_map.get(&new_device.ip) {
-                matched_old_ips.insert(old_device.ip.clone());
-
-                // Preserve health data if new device doesn't have it
-                if new_device.response_time_ms.is_none()
-                    || new_device.response_time_ms == Some(0.0)
-                {
-                    if old_device.response_time_ms.is_some() {
-                        new_device.response_time_ms = old_device.response_time_ms;
-                    }
-                }
-                // Preserve hostname if new doesn't have one
-                if new_device.hostname.is_none() && old_device.hostname.is_some() {
-                    new_device.hostname = old_device.hostname.clone();
-                }
+/// Send device changes to the UI and to the configured alert sinks
+pub fn emit_device_changes(changes: &[ChangeEvent]) {
+    if changes.is_empty() {
+        return;
+    }
+    for change in changes {
+        tracing::info!("Device change: {}", change);
+    }
+    if let Some(app) = get_app_handle()
+        && let Err(e) = app.emit(DEVICE_CHANGES_EVENT, changes)
+    {
+        tracing::warn!("Failed to emit device changes event: {}", e);
+    }
 
-                // Update MAC if it changed (e.g. NIC replacement)
-                if let Some(ref new_mac) = new_device.mac {
-                    let new_mac_norm = normalize_mac(new_mac);
-                    let old_mac_norm = old_device.mac.as_ref().map(|m| normalize_mac(m));
-                    if old_mac_norm.as_deref() != Some(&new_mac_norm) {
-                        tracing::info!(
-                            "Device {} MAC changed from {:?} to {}",
-                            new_device.ip,
-                            old_device.mac,
-                            new_mac
-                        );
-                    }
-                }
-            } else if let Some(ref new_mac) = new_device.mac {
-                // No IP match — try MAC match (DHCP churn: same device, new IP)
-                let new_mac_norm = normalize_mac(new_mac);
-                if let Some(old_device) = old_device_map_by_mac.get(&new_mac_norm) {
-                    matched_old_ips.insert(old_device.ip.clone());
-                    tracing::info!(
-                        "Device MAC {} moved from IP {} to {}",
-                        new_mac,
-                        old_device.ip,
-                        new_device.ip
-                    );
+    // Alerts go out in the background so scans and health checks don't wait on sinks
+    let notifier = get_notifier();
+    if notifications_enabled() && notifier.is_active() {
+        let changes = changes.to_vec();
+        tauri::async_runtime::spawn(async move {
+            notifier.notify(&changes).await;
+        });
+    }
+}
 
-                    // Preserve health data
-                    if new_device.response_time_ms.is_none()
-                        || new_device.response_time_ms == Some(0.0)
-                    {
-                        if old_device.response_time_ms.is_some() {
-                            new_device.response_time_ms = old_device.response_time_ms;
-                        }
-                    }
-                    // Preserve hostname if new doesn't have one
-                    if new_device.hostname.is_none() && old_device.hostname.is_some() {
-                        new_device.hostname = old_device.hostname.clone();
-                    }
-                    // Preserve vendor if new doesn't have one
-                    if new_device.vendor.is_none() && old_device.vendor.is_some() {
-                        new_device.vendor = old_device.vendor.clone();
-                    }
-                    // Preserve device_type if new doesn't have one
-                    if new_device.device_type.is_none() && old_device.device_type.is_some() {
-                        new_device.device_type = old_device.device_type.clone();
-                    }
-                }
-            }
-            new_device
-        })
-        .collect();
+/// Turn device change alerts on or off
+pub fn set_notifications_enabled(enabled: bool) {
+    NOTIFICATIONS_ENABLED.store(enabled, Ordering::Relaxed);
+    tracing::info!("Device change alerts {}", if enabled { "enabled" } else { "disabled" });
+}
 
-    // Add old devices that weren't matched by either IP or MAC, marking them as offline.
-    // Only retain offline devices that are within the target subnet to avoid keeping
-    // stale entries from other interfaces (VPN, containers, virtual adapters).
-    for old_device in old_device_map.values() {
-        if !matched_old_ips.contains(&old_device.ip) {
-            // Drop out-of-subnet devices instead of keeping them as offline
-            if let Some(ref network) = subnet_network {
-                if let Ok(ip) = old_device.ip.parse::<std::net::IpAddr>() {
-                    if !network.contains(ip) {
-                        tracing::info!(
-                            "Dropping out-of-subnet device {} (not in {})",
-                            old_device.ip,
-                            subnet
-                        );
-                        continue;
-                    }
-                }
-            }
+pub fn notifications_enabled() -> bool {
+    NOTIFICATIONS_ENABLED.load(Ordering::Relaxed)
+}
 
-            tracing::info!(
-                "Device {} not found in scan, marking as offline",
-                old_device.ip
-            );
-            let offline_device = Device {
-                ip: old_device.ip.clone(),
-                mac: old_device.mac.clone(),
-                response_time_ms: None, // Mark as offline
-                hostname: old_device.hostname.clone(),
-                vendor: old_device.vendor.clone(),
-                device_type: old_device.device_type.clone(),
-            };
-            merged.push(offline_device);
-        }
+/// Forget all known devices (called on logout)
+pub async fn clear_known_devices() {
+    if let Err(e) = get_inventory().lock().await.clear() {
+        tracing::warn!("Failed to clear device inventory: {:#}", e);
     }
+}
 
-    *known = merged;
+/// Merge new devices into the inventory, preserving health data from previous health checks.
+/// Devices are matched by IP, then MAC, then (for randomized MACs) hostname, DHCP client id or
+/// mDNS name; unmatched known devices are marked offline if they are within a scanned subnet and
+/// dropped otherwise. Returns what changed. See `Inventory::merge`. Nothing is merged if the
+/// `[scan]` section became invalid since the scan started.
+pub async fn merge_devices_preserving_health(
+    new_devices: Vec<Device>,
+    network_info: &NetworkInfo,
+) -> Vec<ChangeEvent> {
+    let randomized_macs = match load_validated_scan_config() {
+        Ok(config) => config.randomized_macs,
+        Err(e) => {
+            tracing::warn!("Not updating the device inventory: {:#}", e);
+            return Vec::new();
+        }
+    };
+    get_inventory().lock().await.merge(
+        new_devices,
+        network_info,
+        &randomized_macs,
+        chrono::Utc::now(),
+    )
 }
 
 /// Get current known devices
 pub async fn get_known_devices() -> Vec<Device> {
-    KNOWN_DEVICES.lock().await.clone()
+    get_inventory().lock().await.devices()
 }
 
 /// Record that a scan just completed and persist to disk
@@ -395,12 +387,14 @@ fn record_automatic_scan_time(now: u64) {
     }
 }
 
-/// Persist current state to disk (call after scans)
+/// Persist current state to disk (call after scans and health checks)
 pub async fn persist_state() {
-    let devices = get_known_devices().await;
-    let scan_time = LAST_SCAN_TIME.load(Ordering::Relaxed);
+    if let Err(e) = get_inventory().lock().await.save() {
+        tracing::warn!("Failed to save device inventory: {:#}", e);
+    }
 
-    if let Err(e) = persistence::save_scan_results(&devices, scan_time) {
+    let scan_time = LAST_SCAN_TIME.load(Ordering::Relaxed);
+    if let Err(e) = persistence::save_last_scan_time(scan_time) {
         tracing::warn!("Failed to persist state: {}", e);
     }
 }
@@ -442,6 +436,7 @@ pub async fn stop_background_scanning() {
     if let Some(token) = token_guard.take() {
         tracing::info!("Stopping background scanning tasks");
         token.cancel();
+        stop_dhcp_listener();
         // Reset the running flag so tasks can be restarted on next login
         BACKGROUND_RUNNING.store(false, Ordering::SeqCst);
     }
@@ -458,26 +453,10 @@ pub fn reset_scan_state() {
     );
     SCANNING_IN_PROGRESS.store(false, Ordering::SeqCst);
     HEALTH_CHECK_IN_PROGRESS.store(false, Ordering::SeqCst);
-    SCAN_CANCEL_REQUESTED.store(false, Ordering::SeqCst);
+    clear_scan_cancel();
     tracing::info!("Reset all scan state (last scan time, scanning flags)");
 }
 
-/// Request cancellation of the current scan
-pub fn request_scan_cancel() {
-    SCAN_CANCEL_REQUESTED.store(true, Ordering::SeqCst);
-    tracing::info!("Scan cancellation requested");
-}
-
-/// Check if scan cancellation has been requested
-pub fn is_scan_cancelled() -> bool {
-    SCAN_CANCEL_REQUESTED.load(Ordering::Relaxed)
-}
-
-/// Clear the cancellation flag (call when starting a new scan)
-pub fn clear_scan_cancel() {
-    SCAN_CANCEL_REQUESTED.store(false, Ordering::SeqCst);
-}
-
 /// Helper to run a single scan and upload
 async fn run_scan_and_upload(app: &AppHandle) {
     let now = current_unix_seconds();
@@ -507,16 +486,19 @@ async fn run_scan_and_upload(app: &AppHandle) {
         Ok(scan_result) => {
             let device_count = scan_result.devices.len();
             tracing::info!(
-                "Scan found {} devices (gateway: {:?})",
+                "Scan found {} devices (gateways: {:?})",
                 device_count,
-                scan_result.network_info.gateway_ip
+                scan_result.network_info.gateway_ips()
             );
 
             // Record scan time
             record_scan_time();
 
             // Merge new devices with existing ones, preserving health data
-            merge_devices_preserving_health(scan_result.devices.clone(), &scan_result.network_info.subnet).await;
+            let changes =
+                merge_devices_preserving_health(scan_result.devices.clone(), &scan_result.network_info)
+                    .await;
+            emit_device_changes(&changes);
 
             // Persist to disk
             persist_state().await;
@@ -529,9 +511,8 @@ async fn run_scan_and_upload(app: &AppHandle) {
                         status.user_email.as_deref().unwrap_or("unknown")
                     );
                     let client = get_shared_cloud_client();
-                    if let Err(e) = client.upload_scan_result(&scan_result).await {
-                        tracing::warn!("Failed to upload scan to cloud: {}", e);
-                    } else {
+                    let upload = CloudClient::scan_upload(&scan_result);
+                    if send_or_queue(&client, upload).await {
                         tracing::info!("Scan synced to cloud");
                     }
                 }
@@ -583,7 +564,7 @@ async fn run_health_checks_and_upload(app: &AppHandle) {
 
     let mut health_results = Vec::new();
     for device in &mut devices {
-        let result = check_device_reachable(&device.ip, &arp_ips).await;
+        let result = check_device_reachable(device.primary_ip(), &arp_ips).await;
         let reachable = result.is_ok();
         let response_time = if reachable { result.ok() } else { None };
         
@@ -591,14 +572,15 @@ async fn run_health_checks_and_upload(app: &AppHandle) {
         device.response_time_ms = response_time;
         
         health_results.push(DeviceHealthResult {
-            ip: device.ip.clone(),
+            ip: device.primary_ip().to_string(),
             reachable,
             response_time_ms: response_time,
         });
     }
 
-    // Update in-memory devices with updated health data
-    update_known_devices(devices).await;
+    // Record the updated health data in the inventory
+    let changes = record_health_results(&health_results).await;
+    emit_device_changes(&changes);
 
     // Persist to disk
     persist_state().await;
@@ -607,9 +589,7 @@ async fn run_health_checks_and_upload(app: &AppHandle) {
     match check_auth().await {
         Ok(status) if status.authenticated => {
             let client = get_shared_cloud_client();
-            if let Err(e) = client.upload_health_check(&health_results).await {
-                tracing::debug!("Failed to upload health check: {}", e);
-            }
+            send_or_queue(&client, CloudClient::health_upload(&health_results)).await;
         }
         Ok(_) => {
             tracing::debug!("Not authenticated, skipping health check cloud sync");
@@ -686,7 +666,7 @@ async fn run_health_checks_with_progress(app: &AppHandle) {
     let mut healthy_count = 0;
 
     for (i, device) in devices.iter_mut().enumerate() {
-        let result = check_device_reachable(&device.ip, &arp_ips).await;
+        let result = check_device_reachable(device.primary_ip(), &arp_ips).await;
         let reachable = result.is_ok();
         let response_time = if reachable { result.ok() } else { None };
 
@@ -698,7 +678,7 @@ async fn run_health_checks_with_progress(app: &AppHandle) {
         device.response_time_ms = response_time;
 
         health_results.push(DeviceHealthResult {
-            ip: device.ip.clone(),
+            ip: device.primary_ip().to_string(),
             reachable,
             response_time_ms: response_time,
         });
@@ -708,7 +688,7 @@ async fn run_health_checks_with_progress(app: &AppHandle) {
             HEALTH_CHECK_PROGRESS_EVENT,
             HealthCheckProgress {
                 stage: HealthCheckStage::CheckingDevices,
-                message: format!("Checking {}...", device.ip),
+                message: format!("Checking {}...", device.primary_ip()),
                 total_devices: total,
                 checked_devices: i + 1,
                 healthy_devices: healthy_count,
@@ -717,8 +697,9 @@ async fn run_health_checks_with_progress(app: &AppHandle) {
         );
     }
 
-    // Update in-memory devices with updated health data
-    update_known_devices(devices).await;
+    // Record the updated health data in the inventory
+    let changes = record_health_results(&health_results).await;
+    emit_device_changes(&changes);
 
     // Persist to disk
     persist_state().await;
@@ -741,14 +722,9 @@ async fn run_health_checks_with_progress(app: &AppHandle) {
     match check_auth().await {
         Ok(status) if status.authenticated => {
             let client = get_shared_cloud_client();
-            match client.upload_health_check(&health_results).await {
-                Ok(_) => {
-                    tracing::debug!("Health check results synced to cloud");
-                    synced = true;
-                }
-                Err(e) => {
-                    tracing::debug!("Failed to upload health check: {}", e);
-                }
+            synced = send_or_queue(&client, CloudClient::health_upload(&health_results)).await;
+            if synced {
+                tracing::debug!("Health check results synced to cloud");
             }
         }
         Ok(_) => {
@@ -813,6 +789,9 @@ pub async fn start_background_scanning(app: AppHandle) {
         *token_guard = Some(cancel_token.clone());
     }
 
+    // Hear DHCP requests between scans; each scan attaches the fingerprints
+    start_dhcp_listener();
+
     let app_scan = app.clone();
     let scan_cancel_token = cancel_token.clone();
 
@@ -906,13 +885,6 @@ pub async fn start_background_scanning(app: AppHandle) {
     });
 }
 
-#[derive(Debug, Clone, serde::Serialize)]
-pub struct DeviceHealthResult {
-    pub ip: String,
-    pub reachable: bool,
-    pub response_time_ms: Option<f64>,
-}
-
 // =============================================================================
 // Cloud command poll loop
 // =============================================================================
@@ -942,11 +914,10 @@ pub enum CloudCommandStage {
 ///
 /// - Waits for credentials; retries every 10s if not authenticated.
 /// - Uses 30s long-poll; the server responds immediately on new commands.
-/// - Exponential backoff on errors (1s -> 2s -> 4s -> ... -> 30s max), resets on success.
+/// - Backs off on errors with the client's retry policy (`[cloud.retry]`), resets on success.
 async fn run_command_poll_loop(app: AppHandle, cancel_token: CancellationToken) {
     let client = get_shared_cloud_client();
-    let mut backoff_secs: u64 = 1;
-    const MAX_BACKOFF: u64 = 30;
+    let mut failures: u32 = 0;
     const POLL_TIMEOUT: u64 = 30;
 
     loop {
@@ -956,7 +927,7 @@ async fn run_command_poll_loop(app: AppHandle, cancel_token: CancellationToken)
         }
 
         // Load credentials
-        let creds = match crate::auth::load_credentials().await {
+        let creds = match cartographer_core::auth::load_credentials().await {
             Ok(Some(c)) => c,
             _ => {
                 tracing::debug!("Not authenticated, waiting before retrying command poll");
@@ -975,7 +946,7 @@ async fn run_command_poll_loop(app: AppHandle, cancel_token: CancellationToken)
 
         match poll_result {
             Ok(poll_response) => {
-                backoff_secs = 1; // Reset backoff on success
+                failures = 0; // Reset backoff on success
 
                 for pending_cmd in poll_response.commands {
                     let cmd_id = pending_cmd.id;
@@ -1056,15 +1027,13 @@ async fn run_command_poll_loop(app: AppHandle, cancel_token: CancellationToken)
                 }
             }
             Err(e) => {
-                tracing::debug!(
-                    "Command poll error (backoff {}s): {}",
-                    backoff_secs, e
-                );
+                let backoff = client.retry_config().backoff_delay(failures);
+                tracing::debug!("Command poll error (backoff {:?}): {}", backoff, e);
                 tokio::select! {
                     _ = cancel_token.cancelled() => return,
-                    _ = tokio::time::sleep(Duration::from_secs(backoff_secs)) => {},
+                    _ = tokio::time::sleep(backoff) => {},
                 }
-                backoff_secs = (backoff_secs * 2).min(MAX_BACKOFF);
+                failures = failures.saturating_add(1);
             }
         }
     }
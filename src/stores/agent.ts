@@ -4,14 +4,179 @@ import { invoke } from '@tauri-apps/api/core'
 import { listen, type UnlistenFn } from '@tauri-apps/api/event'
 
 export interface Device {
-  ip: string
+  /** All IPv4/IPv6 addresses; the first one is the primary address */
+  addresses: string[]
   mac?: string
+  /** The MAC is locally administered (randomized for privacy) and may change */
+  randomizedMac?: boolean
   responseTimeMs?: number
+  /** ISO 8601 timestamp of the last scan that found the device */
+  lastSeen?: string
   hostname?: string
+  /** Resolver that produced the hostname */
+  hostnameSource?: HostnameSource
   /** Device vendor/manufacturer from MAC OUI lookup */
   vendor?: string
-  /** Inferred device type based on vendor (e.g., "router", "apple", "nas", "iot") */
+  /** Device type set by the classification rules (e.g., "network_device", "apple", "nas", "iot") */
   deviceType?: string
+  /** Rule that set the device type, and the conditions it matched */
+  classification?: Classification
+  /** Tags of every classification rule the device matched */
+  tags?: string[]
+  /** Interface the device was seen on */
+  interface?: string
+  /** State of the device's kernel neighbor table entry (Linux) */
+  neighborState?: NeighborState
+  /** Scanned subnet the device belongs to (e.g., "192.168.1.0/24") */
+  subnet?: string
+  /** Open TCP ports found by the optional port scan */
+  openPorts?: OpenPort[]
+  /** Banners and TLS certificates read from open ports */
+  services?: ServiceInfo[]
+  /** Model name advertised over mDNS or UPnP (e.g., "HP LaserJet M404dn") */
+  model?: string
+  /** Services advertised over mDNS / DNS-SD */
+  advertisedServices?: AdvertisedService[]
+  /** UPnP device description found over SSDP */
+  upnp?: UpnpDevice
+  /** System group and interfaces read over SNMP */
+  snmp?: SnmpInfo
+  /** Latest DHCP request heard from the device's MAC address */
+  dhcp?: DhcpFingerprint
+  /** IP TTL of the ICMP echo reply */
+  ttl?: number
+  /** SYN-ACK window and options from the port scan */
+  tcpFingerprint?: TcpFingerprint
+  /** Operating system guessed from the TTL, TCP handshake and other hints */
+  osGuess?: OsGuess
+}
+
+export type NeighborState =
+  | 'incomplete'
+  | 'reachable'
+  | 'stale'
+  | 'delay'
+  | 'probe'
+  | 'failed'
+  | 'noarp'
+  | 'permanent'
+  | 'unknown'
+
+export interface Classification {
+  rule: string
+  source: 'builtin' | 'user'
+  priority: number
+  /** Conditions of the rule that matched (e.g., "port 9100 open") */
+  matched: string[]
+}
+
+export interface TcpFingerprint {
+  port: number
+  /** Receive window advertised in the SYN-ACK */
+  window?: number
+  windowScale?: number
+  sack: boolean
+  timestamps: boolean
+}
+
+export interface OsGuess {
+  family: OsFamily
+  /** 0 to 1 */
+  confidence: number
+  /** Signals that supported the guess (e.g., "ttl 128", "dhcp") */
+  evidence: string[]
+}
+
+export interface UpnpDevice {
+  friendlyName?: string
+  manufacturer?: string
+  modelName?: string
+  modelNumber?: string
+  serialNumber?: string
+  /** UPnP device type (e.g., "urn:schemas-upnp-org:device:MediaRenderer:1") */
+  deviceType?: string
+  /** SSDP `SERVER` header */
+  server?: string
+}
+
+export interface SnmpInfo {
+  /** Protocol version that answered ("v2c" or "v3") */
+  version: string
+  sysName?: string
+  sysDescr?: string
+  /** Dotted OID naming the vendor and model */
+  sysObjectId?: string
+  sysLocation?: string
+  uptimeSeconds?: number
+  /** Read from network devices and printers only */
+  interfaces: SnmpInterface[]
+}
+
+export interface SnmpInterface {
+  index: number
+  /** ifName (e.g., "Gi1/0/1") */
+  name?: string
+  description?: string
+  /** Administrator's label */
+  alias?: string
+  mac?: string
+  speedMbps?: number
+  /** "up", "down", "dormant", ... */
+  operStatus?: string
+}
+
+export interface DhcpFingerprint {
+  mac: string
+  /** Option 12, or the client FQDN when absent */
+  hostname?: string
+  /** Option 60 (e.g., "MSFT 5.0", "android-dhcp-13") */
+  vendorClass?: string
+  /** Option 61 in hex; survives MAC randomization when it is a DUID */
+  clientId?: string
+  /** Option 55, in the order the client sent it */
+  parameterList: number[]
+  requestedIp?: string
+  osFamily?: OsFamily
+  /** ISO 8601 timestamp */
+  lastSeen: string
+}
+
+export type OsFamily = 'windows' | 'macos' | 'ios' | 'android' | 'linux'
+
+export type HostnameSource = 'local' | 'hosts' | 'dns' | 'mdns' | 'upnp' | 'netbios' | 'llmnr' | 'snmp' | 'dhcp'
+
+export interface AdvertisedService {
+  /** Instance name (e.g., "Office Printer") */
+  name: string
+  /** Service type (e.g., "_ipp._tcp") */
+  serviceType: string
+  port: number
+  txt: Record<string, string>
+}
+
+export interface OpenPort {
+  port: number
+  /** Service usually found on the port (e.g., "ssh") */
+  service?: string
+}
+
+export interface ServiceInfo {
+  port: number
+  /** SSH identification string */
+  banner?: string
+  /** HTTP `Server` header */
+  httpServer?: string
+  /** HTML page title */
+  httpTitle?: string
+  tlsCertificate?: TlsCertificate
+}
+
+export interface TlsCertificate {
+  subject: string
+  issuer: string
+  subjectAltNames: string[]
+  notBefore?: string
+  notAfter?: string
 }
 
 export interface AgentStatus {
@@ -30,7 +195,14 @@ export type ScanStage =
   | 'detecting_network'
   | 'reading_arp'
   | 'ping_sweep'
+  | 'arp_sweep'
+  | 'browsing_mdns'
+  | 'ssdp_discovery'
   | 'resolving_hostnames'
+  | 'snmp_query'
+  | 'lldp_capture'
+  | 'port_scan'
+  | 'service_detection'
   | 'complete'
   | 'failed'
 
@@ -54,6 +226,33 @@ export interface HealthCheckProgress {
   syncedToCloud?: boolean
 }
 
+export type DeviceChangeKind =
+  | 'new'
+  | 'offline'
+  | 'back'
+  | 'ip_changed'
+  | 'mac_changed'
+  | 'hostname_changed'
+
+/** A change to a device found by a scan or health check */
+export interface DeviceChange {
+  /** ISO 8601 timestamp */
+  at: string
+  kind: DeviceChangeKind
+  /** Inventory id of the device */
+  deviceId: string
+  /** Primary IP address after the change */
+  ip: string
+  hostname?: string
+  mac?: string
+  vendor?: string
+  /** Classification tags of the device */
+  tags?: string[]
+  /** Old and new value of a changed IP, MAC or hostname */
+  previous?: string
+  current?: string
+}
+
 export type CloudCommandStage = 'received' | 'executing' | 'completed' | 'failed'
 
 export interface CloudCommandEvent {
@@ -88,12 +287,15 @@ export const useAgentStore = defineStore('agent', () => {
   const scanProgress = ref<ScanProgress | null>(null)
   const healthCheckProgress = ref<HealthCheckProgress | null>(null)
   const cloudCommand = ref<CloudCommandEvent | null>(null)
+  /** Device changes received since the app started, newest first */
+  const deviceChanges = ref<DeviceChange[]>([])
 
   // Event listener cleanup
   let progressUnlisten: UnlistenFn | null = null
   let healthUnlisten: UnlistenFn | null = null
   let scanCompleteUnlisten: UnlistenFn | null = null
   let cloudCommandUnlisten: UnlistenFn | null = null
+  let deviceChangesUnlisten: UnlistenFn | null = null
 
   const isAuthenticated = computed(() => status.value.authenticated)
 
@@ -154,6 +356,11 @@ export const useAgentStore = defineStore('agent', () => {
       }
     })
 
+    // Listen for device changes (new, offline, back, IP/MAC/hostname changed)
+    deviceChangesUnlisten = await listen<DeviceChange[]>('device-changes', (event) => {
+      deviceChanges.value = [...event.payload.reverse(), ...deviceChanges.value].slice(0, 200)
+    })
+
     // Listen for cloud command events (remote scan triggers from cloud UI)
     cloudCommandUnlisten = await listen<CloudCommandEvent>('cloud-command', (event) => {
       cloudCommand.value = event.payload
@@ -184,6 +391,10 @@ export const useAgentStore = defineStore('agent', () => {
       cloudCommandUnlisten()
       cloudCommandUnlisten = null
     }
+    if (deviceChangesUnlisten) {
+      deviceChangesUnlisten()
+      deviceChangesUnlisten = null
+    }
   }
 
   async function checkAuth() {
@@ -254,6 +465,7 @@ export const useAgentStore = defineStore('agent', () => {
       // Clear all local state so reconnecting starts with a clean slate
       status.value = { authenticated: false }
       devices.value = []
+      deviceChanges.value = []
       scanning.value = false
       scanProgress.value = null
       healthCheckProgress.value = null
@@ -339,6 +551,7 @@ export const useAgentStore = defineStore('agent', () => {
     scanProgress,
     healthCheckProgress,
     cloudCommand,
+    deviceChanges,
     isAuthenticated,
     checkAuth,
     login,